@@ -1,17 +1,46 @@
 use clap::Parser;
 use reqwest::Client;
+use secure_disk_erasure::core::{advanced::AdvancedWipeEngine, pipeline::WipePipeline};
+use secure_disk_erasure::core::methods::MethodRegistry;
+use secure_disk_erasure::core::safety::{AllowList, SafetyOptions};
+use secure_disk_erasure::core::progress::{progress_channel, ProgressEvent};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser)]
 struct Args {
     #[arg(long, default_value = "http://localhost:8080")] server: String,
     #[arg(long, default_value = "station-001")] station_id: String,
+    /// Device to wipe after registering
+    #[arg(long, requires = "allow_list")] device: Option<PathBuf>,
+    /// File of serial numbers, WWNs or device paths the agent may wipe unattended
+    #[arg(long)] allow_list: Option<PathBuf>,
+    /// Wipe method name
+    #[arg(long, default_value = "full")] method: String,
+    /// Seconds between progress uploads
+    #[arg(long, default_value_t = 5)] progress_interval: u64,
 }
 
 #[derive(Serialize, Deserialize)]
 struct Register { station_id: String }
 
+#[derive(Serialize)]
+struct ProgressUpload<'a> { station_id: &'a str, progress: &'a ProgressEvent }
+
+#[derive(Serialize)]
+struct ResultUpload<'a> {
+    station_id: &'a str,
+    device: &'a PathBuf,
+    method: &'a str,
+    success: bool,
+    verification_passed: bool,
+    bytes_written: u64,
+    duration_seconds: u64,
+    errors: Vec<String>,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::registry()
@@ -26,5 +55,99 @@ async fn main() -> anyhow::Result<()> {
     let resp = client.post(&url).json(&Register { station_id: args.station_id.clone() }).send().await?;
     tracing::info!("registered: status={}", resp.status());
 
+    if let Some(device) = &args.device {
+        run_wipe(&client, &args, device).await?;
+    }
+
+    Ok(())
+}
+
+/// Wipe a local device, uploading the latest progress event every `progress_interval` seconds
+async fn run_wipe(client: &Client, args: &Args, device_path: &PathBuf) -> anyhow::Result<()> {
+    let method = MethodRegistry::builtin().get(&args.method).cloned()
+        .ok_or_else(|| anyhow::anyhow!("unknown wipe method: {}", args.method))?;
+
+    // Nobody is at the station to type a confirmation, so the allow-list stands in for it
+    let pipeline = WipePipeline::system();
+    let device = pipeline.find(device_path)?;
+    let safety = SafetyOptions {
+        assume_yes: true,
+        allow_list: args.allow_list.as_deref().map(AllowList::load).transpose()?,
+        allow_in_use: false,
+    };
+    let clearance = pipeline.preflight(&device, &safety)?;
+
+    let progress_url = format!("{}/v1/progress", args.server);
+    let (reporter, mut receiver) = progress_channel();
+    let engine = AdvancedWipeEngine::new().with_progress(reporter);
+    let mut ticker = tokio::time::interval(Duration::from_secs(args.progress_interval.max(1)));
+    let mut latest: Option<ProgressEvent> = None;
+    let mut uploaded = true;
+
+    let result = {
+        // The pipeline drops the engine when done, which closes the progress stream
+        let wipe = pipeline.wipe(engine, &device, &method, clearance, None);
+        tokio::pin!(wipe);
+
+        loop {
+            tokio::select! {
+                result = &mut wipe => break result,
+                Some(event) = receiver.recv() => {
+                    latest = Some(event);
+                    uploaded = false;
+                }
+                _ = ticker.tick() => {
+                    if let (Some(event), false) = (&latest, uploaded) {
+                        upload_progress(client, &progress_url, &args.station_id, event).await;
+                        uploaded = true;
+                    }
+                }
+            }
+        }
+    };
+
+    // Flush events sent after the last tick
+    while let Ok(event) = receiver.try_recv() {
+        latest = Some(event);
+        uploaded = false;
+    }
+    if let (Some(event), false) = (&latest, uploaded) {
+        upload_progress(client, &progress_url, &args.station_id, event).await;
+    }
+
+    let upload = match &result {
+        Ok(wipe) => ResultUpload {
+            station_id: &args.station_id,
+            device: device_path,
+            method: &method.name,
+            success: true,
+            verification_passed: wipe.verification_passed,
+            bytes_written: wipe.bytes_written,
+            duration_seconds: wipe.duration_seconds,
+            errors: wipe.errors.clone(),
+        },
+        Err(e) => ResultUpload {
+            station_id: &args.station_id,
+            device: device_path,
+            method: &method.name,
+            success: false,
+            verification_passed: false,
+            bytes_written: 0,
+            duration_seconds: 0,
+            errors: vec![e.to_string()],
+        },
+    };
+    let resp = client.post(format!("{}/v1/results", args.server)).json(&upload).send().await?;
+    tracing::info!("result uploaded: status={}", resp.status());
+
+    result?;
     Ok(())
 }
+
+async fn upload_progress(client: &Client, url: &str, station_id: &str, event: &ProgressEvent) {
+    let upload = ProgressUpload { station_id, progress: event };
+    // A lost progress update must not interrupt the wipe
+    if let Err(e) = client.post(url).json(&upload).send().await {
+        tracing::warn!("progress upload failed: {}", e);
+    }
+}
@@ -1,7 +1,11 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use secure_disk_erasure::core::{device_manager::DeviceManager, advanced::AdvancedWipeEngine, EraseMode};
+use secure_disk_erasure::core::advanced::AdvancedWipeEngine;
+use secure_disk_erasure::core::methods::{MethodRegistry, WipeMethod};
+use secure_disk_erasure::core::pipeline::WipePipeline;
+use secure_disk_erasure::core::progress::progress_channel;
+use secure_disk_erasure::core::safety::{self, SafetyOptions};
 use secure_disk_erasure::certificates::{enhanced::EnhancedCertificateGenerator, verifier::CertificateVerifier};
 use secure_disk_erasure::crypto::generate_key_pair;
 use secure_disk_erasure::error::Result;
@@ -56,40 +60,66 @@ async fn get_system_info() -> Result<SystemInfo, String> {
 
 #[tauri::command]
 async fn list_devices(detailed: bool) -> Result<Vec<secure_disk_erasure::core::StorageDevice>, String> {
-    let mut device_manager = DeviceManager::new();
-    device_manager.scan_devices().await
-        .map_err(|e| e.to_string())?;
-    
-    Ok(device_manager.get_devices().clone())
+    WipePipeline::system().devices()
+        .map_err(|e| e.to_string())
+}
+
+fn load_methods(methods_path: Option<String>) -> Result<MethodRegistry, String> {
+    let mut registry = MethodRegistry::builtin();
+    if let Some(path) = methods_path {
+        registry.load_path(&PathBuf::from(path))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(registry)
+}
+
+#[tauri::command]
+async fn list_methods(methods_path: Option<String>) -> Result<Vec<WipeMethod>, String> {
+    let registry = load_methods(methods_path)?;
+    Ok(registry.methods().cloned().collect())
 }
 
 #[tauri::command]
 async fn wipe_device(
+    window: tauri::Window,
     device: String,
     mode: String,
+    methods_path: Option<String>,
     certificate: bool,
     verify: bool,
+    confirmation: String,
 ) -> Result<WipeResult, String> {
-    // Parse erase mode
-    let erase_mode = match mode.to_lowercase().as_str() {
-        "quick" => EraseMode::Quick,
-        "full" => EraseMode::Full,
-        "advanced" => EraseMode::Advanced,
-        _ => return Err("Invalid erase mode".to_string()),
-    };
+    // Resolve the wipe method by name
+    let registry = load_methods(methods_path)?;
+    let method = registry.get(&mode)
+        .cloned()
+        .ok_or_else(|| format!("Unknown wipe method: {}", mode))?;
 
     // Find the device
-    let mut device_manager = DeviceManager::new();
-    device_manager.scan_devices().await
+    let pipeline = WipePipeline::system();
+    let target_device = pipeline.find(&PathBuf::from(&device))
         .map_err(|e| e.to_string())?;
-    
-    let target_device = device_manager.find_device(&PathBuf::from(&device))
-        .ok_or_else(|| "Device not found".to_string())?;
 
-    // Perform wipe operation
-    let wipe_engine = AdvancedWipeEngine::new();
-    let result = wipe_engine.secure_erase_with_verification(target_device, erase_mode).await
+    // Refuse devices the running system depends on; the operator retypes the serial number
+    let clearance = pipeline.preflight(&target_device, &SafetyOptions::default())
         .map_err(|e| e.to_string())?;
+    if let Some(expected) = &clearance.confirm_with {
+        safety::confirm(expected, &confirmation).map_err(|e| e.to_string())?;
+    }
+
+    // Forward engine progress to the frontend as `wipe-progress` events
+    let (reporter, mut receiver) = progress_channel();
+    let progress_task = tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            let _ = window.emit("wipe-progress", &event);
+        }
+    });
+
+    // Perform wipe operation; the pipeline drops the engine, ending the progress stream
+    let wipe_engine = AdvancedWipeEngine::new().with_progress(reporter);
+    let result = pipeline.wipe(wipe_engine, &target_device, &method, clearance, None).await;
+    let _ = progress_task.await;
+    let result = result.map_err(|e| e.to_string())?;
 
     let mut certificate_path = None;
 
@@ -190,6 +220,7 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             get_system_info,
             list_devices,
+            list_methods,
             wipe_device,
             verify_certificate,
             generate_keys,
@@ -1,5 +1,6 @@
 // Main JavaScript for Secure Disk Erasure Tool GUI
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/api/dialog';
 import { writeTextFile, readTextFile } from '@tauri-apps/api/fs';
 
@@ -17,6 +18,7 @@ class SecureDiskErasureGUI {
     async init() {
         this.setupEventListeners();
         await this.loadSystemInfo();
+        await this.loadMethods();
         await this.loadDevices();
         this.setupTabNavigation();
     }
@@ -106,6 +108,19 @@ class SecureDiskErasureGUI {
         }
     }
 
+    async loadMethods() {
+        try {
+            const methods = await invoke('list_methods', { methodsPath: null });
+            const select = document.getElementById('wipe-mode');
+            const current = select.value;
+            select.innerHTML = methods.map(method =>
+                `<option value="${method.name}" ${method.name === current ? 'selected' : ''}>${method.name} - ${method.description}</option>`
+            ).join('');
+        } catch (error) {
+            console.error('Failed to load wipe methods:', error);
+        }
+    }
+
     async loadDevices() {
         try {
             this.showLoading('devices-container');
@@ -201,6 +216,7 @@ class SecureDiskErasureGUI {
             case 'HDD': return 'secondary';
             case 'SSD': return 'primary';
             case 'NVMe': return 'success';
+            case 'SAS': return 'info';
             case 'USB': return 'warning';
             default: return 'dark';
         }
@@ -254,6 +270,9 @@ class SecureDiskErasureGUI {
         `;
 
         document.getElementById('wipe-confirmation-details').innerHTML = detailsHtml;
+        const expected = (device.serial || '').trim() || device.path;
+        document.getElementById('wipe-confirmation-expected').textContent = expected;
+        document.getElementById('wipe-confirmation-input').value = '';
         
         const modal = new bootstrap.Modal(document.getElementById('confirmWipeModal'));
         modal.show();
@@ -264,6 +283,7 @@ class SecureDiskErasureGUI {
         const wipeMode = document.getElementById('wipe-mode').value;
         const generateCertificate = document.getElementById('generate-certificate').checked;
         const verifyAfterWipe = document.getElementById('verify-after-wipe').checked;
+        const confirmation = document.getElementById('wipe-confirmation-input').value;
 
         // Close modal
         const modal = bootstrap.Modal.getInstance(document.getElementById('confirmWipeModal'));
@@ -277,12 +297,20 @@ class SecureDiskErasureGUI {
         document.getElementById('start-wipe').disabled = true;
         document.getElementById('cancel-wipe').disabled = false;
 
+        this.wipeStartedAt = Date.now();
+        this.lastProgressPhase = null;
+        const unlisten = await listen('wipe-progress', (event) => {
+            this.updateProgress(event.payload);
+        });
+
         try {
             const result = await invoke('wipe_device', {
                 device: devicePath,
                 mode: wipeMode,
+                methodsPath: null,
                 certificate: generateCertificate,
-                verify: verifyAfterWipe
+                verify: verifyAfterWipe,
+                confirmation
             });
 
             this.showAlert('Wipe operation completed successfully!', 'success');
@@ -296,12 +324,45 @@ class SecureDiskErasureGUI {
             this.showAlert(`Wipe operation failed: ${error}`, 'danger');
             this.logOperation(`Wipe operation failed: ${error}`);
         } finally {
+            unlisten();
             this.isWiping = false;
             document.getElementById('start-wipe').disabled = false;
             document.getElementById('cancel-wipe').disabled = true;
         }
     }
 
+    updateProgress(progress) {
+        const percentage = progress.bytes_total > 0
+            ? (progress.bytes_done / progress.bytes_total) * 100
+            : 0;
+        const phaseKey = `${progress.phase}:${progress.pass}`;
+
+        if (phaseKey !== this.lastProgressPhase) {
+            this.lastProgressPhase = phaseKey;
+            if (progress.phase === 'overwrite') {
+                this.logOperation(`Pass ${progress.pass}/${progress.total_passes}: ${progress.detail}`);
+            } else if (progress.phase === 'verify') {
+                this.logOperation(`Verifying: ${progress.detail}`);
+            } else {
+                this.logOperation(`Hardware command: ${progress.detail}`);
+            }
+        }
+
+        document.getElementById('progress-bar').style.width = `${percentage.toFixed(1)}%`;
+        document.getElementById('progress-percentage').textContent = `${percentage.toFixed(1)}%`;
+        document.getElementById('elapsed-time').textContent = this.formatDuration((Date.now() - this.wipeStartedAt) / 1000);
+        document.getElementById('bytes-written').textContent = this.formatBytes(progress.bytes_done);
+        document.getElementById('current-speed').textContent = `${this.formatBytes(progress.throughput_bytes_per_sec)}/s`;
+        document.getElementById('estimated-remaining').textContent = progress.eta_seconds === null
+            ? '--:--:--'
+            : this.formatDuration(progress.eta_seconds);
+    }
+
+    formatDuration(seconds) {
+        const pad = (value) => String(Math.floor(value)).padStart(2, '0');
+        return `${pad(seconds / 3600)}:${pad((seconds / 60) % 60)}:${pad(seconds % 60)}`;
+    }
+
     async verifyCertificate() {
         const certificateFile = document.getElementById('certificate-file').files[0];
         const publicKeyFile = document.getElementById('public-key-file').files[0];
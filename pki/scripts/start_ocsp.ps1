@@ -9,18 +9,25 @@ $IntDir = Join-Path $BaseDir 'intermediate'
 
 Push-Location $IntDir
 
+# The responder certificate is issued by the Intermediate CA so clients accept its responses
 if (-not (Test-Path (Join-Path 'private' 'ocsp.key.pem'))) {
+  New-Item -ItemType Directory -Force -Path 'csr' | Out-Null
   & openssl genrsa -out (Join-Path 'private' 'ocsp.key.pem') 4096
-  & openssl req -new -key (Join-Path 'private' 'ocsp.key.pem') -subj '/CN=OCSP Responder' -out (Join-Path 'csr' 'ocsp.csr.pem')
-  & openssl x509 -req -in (Join-Path 'csr' 'ocsp.csr.pem') -signkey (Join-Path 'private' 'ocsp.key.pem') -days 365 -out (Join-Path 'certs' 'ocsp.cert.pem')
+  & openssl req -config openssl.cnf -new -sha256 -key (Join-Path 'private' 'ocsp.key.pem') -subj '/CN=OCSP Responder' -out (Join-Path 'csr' 'ocsp.csr.pem')
+  & openssl ca -batch -config openssl.cnf -extensions ocsp -days 365 -notext -md sha256 `
+    -in (Join-Path 'csr' 'ocsp.csr.pem') -out (Join-Path 'certs' 'ocsp.cert.pem')
 }
 
+# openssl ocsp listens on every interface; Host is the address in the
+# authorityInfoAccess OCSP URI of station certificates
+Write-Host "OCSP responder: http://${Host}:$Port"
 & openssl ocsp `
   -index (Join-Path '.' 'index.txt') `
-  -port "$Host:$Port" `
+  -port $Port `
   -rsigner (Join-Path 'certs' 'ocsp.cert.pem') `
   -rkey (Join-Path 'private' 'ocsp.key.pem') `
   -CA (Join-Path 'certs' 'ca-chain.pem') `
+  -ndays 1 `
   -text
 
 Pop-Location
@@ -9,19 +9,26 @@ INT_DIR="$BASE_DIR/intermediate"
 
 pushd "$INT_DIR" >/dev/null
 
-# Create OCSP responder key/cert if missing (self-signed for demo)
+# Create OCSP responder key/cert if missing, issued by the Intermediate CA so
+# clients accept its responses
 if [ ! -f private/ocsp.key.pem ]; then
+  mkdir -p csr
   openssl genrsa -out private/ocsp.key.pem 4096
-  openssl req -new -key private/ocsp.key.pem -subj "/CN=OCSP Responder" -out csr/ocsp.csr.pem
-  openssl x509 -req -in csr/ocsp.csr.pem -signkey private/ocsp.key.pem -days 365 -out certs/ocsp.cert.pem
+  openssl req -config openssl.cnf -new -sha256 -key private/ocsp.key.pem -subj "/CN=OCSP Responder" -out csr/ocsp.csr.pem
+  openssl ca -batch -config openssl.cnf -extensions ocsp -days 365 -notext -md sha256 \
+    -in csr/ocsp.csr.pem -out certs/ocsp.cert.pem
 fi
 
+# openssl ocsp listens on every interface; HOST is the address in the
+# authorityInfoAccess OCSP URI of station certificates
+echo "OCSP responder: http://$HOST:$PORT"
 openssl ocsp \
   -index index.txt \
-  -port "$HOST:$PORT" \
+  -port "$PORT" \
   -rsigner certs/ocsp.cert.pem \
   -rkey private/ocsp.key.pem \
   -CA certs/ca-chain.pem \
+  -ndays 1 \
   -text
 
 popd >/dev/null
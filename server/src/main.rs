@@ -17,6 +17,7 @@ async fn main() {
         .route("/health", get(|| async { Json(Health { status: "ok" }) }))
         .route("/v1/stations/register", post(dummy))
         .route("/v1/jobs", post(dummy))
+        .route("/v1/progress", post(dummy))
         .route("/v1/results", post(dummy));
 
     let addr: SocketAddr = "0.0.0.0:8080".parse().unwrap();
@@ -0,0 +1,176 @@
+use crate::error::{Result, SecureEraseError};
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use std::fmt::Write;
+
+/// Largest integer a JSON number keeps exactly, per I-JSON (RFC 7493)
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// RFC 8785 (JCS) text of a serializable value
+pub fn to_canonical<T: Serialize>(value: &T) -> Result<String> {
+    canonicalize(&serde_json::to_value(value)?)
+}
+
+/// RFC 8785 (JCS) text of a JSON value.
+///
+/// Members are sorted by their UTF-16 code units, numbers are printed the way
+/// ECMAScript prints them and strings escape only what JSON requires, so the
+/// same document always yields the same bytes however it was formatted.
+pub fn canonicalize(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    write_value(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<()> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(flag) => out.push_str(if *flag { "true" } else { "false" }),
+        Value::Number(number) => out.push_str(&format_number(number)?),
+        Value::String(text) => write_string(text, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(members) => write_object(members, out)?,
+    }
+    Ok(())
+}
+
+fn write_object(members: &Map<String, Value>, out: &mut String) -> Result<()> {
+    let mut members: Vec<(&String, &Value)> = members.iter().collect();
+    members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+    out.push('{');
+    for (index, (name, value)) in members.into_iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        write_string(name, out);
+        out.push(':');
+        write_value(value, out)?;
+    }
+    out.push('}');
+    Ok(())
+}
+
+fn write_string(text: &str, out: &mut String) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// A number as ECMAScript's `Number.prototype.toString` prints it.
+///
+/// Integers beyond 2^53 are refused rather than rounded, so two different
+/// values can never share a canonical form.
+fn format_number(number: &Number) -> Result<String> {
+    if let Some(integer) = number.as_u64() {
+        if integer > MAX_SAFE_INTEGER {
+            return Err(out_of_range(number));
+        }
+    } else if let Some(integer) = number.as_i64() {
+        if integer.unsigned_abs() > MAX_SAFE_INTEGER {
+            return Err(out_of_range(number));
+        }
+    }
+    let value = number.as_f64().filter(|value| value.is_finite()).ok_or_else(|| out_of_range(number))?;
+    if value == 0.0 {
+        return Ok("0".to_string());
+    }
+
+    // Shortest round-trip digits d1..dk with value = 0.d1..dk * 10^n
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap_or(0) + 1;
+
+    let mut out = if value < 0.0 { "-".to_string() } else { String::new() };
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let _ = write!(out, "e{}{}", if n > 0 { "+" } else { "-" }, (n - 1).abs());
+    }
+    Ok(out)
+}
+
+fn out_of_range(number: &Number) -> SecureEraseError {
+    SecureEraseError::CertificateGenerationFailed(format!("{} cannot be represented exactly in canonical JSON", number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(json: &str) -> String {
+        canonicalize(&serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_rfc8785_example() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            canonical(input),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn test_members_sort_by_utf16_code_units() {
+        let input = r#"{"\u20ac": 1, "\r": 2, "\ufb33": 3, "1": 4, "\ud83d\ude00": 5, "\u0080": 6, "\u00f6": 7}"#;
+        assert_eq!(canonical(input), "{\"\\r\":2,\"1\":4,\"\u{80}\":6,\"ö\":7,\"€\":1,\"😀\":5,\"\u{fb33}\":3}");
+        assert_eq!(canonical(r#"{"b": {"d": 1, "c": 2}, "a": []}"#), r#"{"a":[],"b":{"c":2,"d":1}}"#);
+    }
+
+    #[test]
+    fn test_ecmascript_number_formatting() {
+        let cases = [
+            ("0", "0"), ("-0.0", "0"), ("1", "1"), ("-1", "-1"), ("4.5", "4.5"),
+            ("0.000001", "0.000001"), ("1e-7", "1e-7"), ("123e18", "123000000000000000000"),
+            ("1e21", "1e+21"), ("9007199254740991", "9007199254740991"),
+            ("5e-324", "5e-324"), ("1.7976931348623157e308", "1.7976931348623157e+308"),
+            ("-1.5e-10", "-1.5e-10"), ("12.75", "12.75"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(canonical(input), expected, "formatting {}", input);
+        }
+        assert!(canonicalize(&Value::from(u64::MAX)).is_err());
+        assert!(canonicalize(&Value::from(-(1i64 << 60))).is_err());
+    }
+}
@@ -1,15 +1,25 @@
 use crate::error::{Result, SecureEraseError};
-use crate::core::WipeResult;
-use crate::crypto::{sign_data, load_signing_key, load_verifying_key, hash_data, verify_signature};
+use crate::core::{LbaRange, SanitizationStatus, WipeResult};
+use crate::core::methods::WipeMethod;
+use crate::core::discard::ReadAfterDiscard;
+use crate::core::opal::{self, LockingState, SecuritySubsystem, SedErase};
+use crate::certificates::signing::{self, Schema, SignatureEnvelope};
+use crate::crypto::{load_private_key, key_fingerprint};
+use crate::crypto::ocsp::{OcspCertStatus, OcspClient};
+use crate::crypto::x509::{certificates_to_pem, StationChain};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::fs;
 use std::time::{SystemTime, UNIX_EPOCH};
 use log::{info, warn, error};
 
+/// Bytes in the megabyte used for speed figures
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
 /// Enhanced digital certificate for wipe operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnhancedWipeCertificate {
+    /// Schema version, see `signing::Schema`
     pub version: String,
     pub certificate_id: String,
     pub timestamp: u64,
@@ -19,7 +29,7 @@ pub struct EnhancedWipeCertificate {
     pub verification: EnhancedVerificationInfo,
     pub compliance: ComplianceInfo,
     pub pki: PKIInfo,
-    pub signature: String,
+    pub signature: Option<SignatureEnvelope>,
     pub metadata: CertificateMetadata,
 }
 
@@ -54,7 +64,14 @@ pub struct HiddenAreaInfo {
     pub start_lba: u64,
     pub size: u64,
     pub description: String,
+    /// Set only when an overwrite pass covered the area after it was made accessible
     pub wiped: bool,
+    #[serde(default)]
+    pub restored: bool,
+    #[serde(default)]
+    pub permanent: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
 }
 
 /// Device capabilities
@@ -70,6 +87,7 @@ pub struct DeviceCapabilities {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnhancedWipeDetails {
     pub mode: String,
+    pub method: Option<WipeMethod>,
     pub start_time: u64,
     pub end_time: u64,
     pub duration_seconds: u64,
@@ -79,6 +97,87 @@ pub struct EnhancedWipeDetails {
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
     pub performance_metrics: PerformanceMetrics,
+    /// Whether the wipe was interrupted and resumed from a checkpoint
+    #[serde(default)]
+    pub interrupted: bool,
+    #[serde(default)]
+    pub interruptions: Vec<InterruptionInfo>,
+    /// Complete, partial (some blocks unwritable) or failed
+    #[serde(default)]
+    pub sanitization_status: Option<SanitizationStatus>,
+    /// Blocks that could not be overwritten and still hold their old contents
+    #[serde(default)]
+    pub unwritable_ranges: Vec<LbaRange>,
+    #[serde(default)]
+    pub unwritable_bytes: u64,
+    /// Measured record of every overwrite pass
+    #[serde(default)]
+    pub passes: Vec<PassInfo>,
+    /// Hardware sanitize commands that completed on the device
+    #[serde(default)]
+    pub hardware_commands: Vec<HardwareCommandInfo>,
+    /// Whole-device discards, stated as secure or best-effort
+    #[serde(default)]
+    pub discards: Vec<DiscardInfo>,
+    /// TCG cryptographic erases of a self-encrypting drive
+    #[serde(default)]
+    pub crypto_erases: Vec<CryptoEraseInfo>,
+}
+
+/// A TCG cryptographic erase and the drive's locking state around it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CryptoEraseInfo {
+    pub method: SedErase,
+    pub subsystem: SecuritySubsystem,
+    pub locking_before: LockingState,
+    pub locking_after: Option<LockingState>,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+/// A whole-device discard and what reading it back showed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscardInfo {
+    /// "secure" (BLKSECDISCARD) or "best-effort" (BLKDISCARD)
+    pub mode: String,
+    pub bytes_discarded: u64,
+    pub read_after_discard: ReadAfterDiscard,
+    pub blocks_checked: u64,
+    pub blocks_zeroed: u64,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+/// A hardware sanitize command as issued to the device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardwareCommandInfo {
+    pub command: String,
+    pub issued: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+/// Measured figures of one overwrite pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassInfo {
+    pub pass_number: u32,
+    pub pattern: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub bytes_written: u64,
+    pub throughput_mbps: f64,
+    pub retries: u32,
+    pub unwritable_ranges: usize,
+    pub errors: Vec<String>,
+}
+
+/// A point at which the wipe stopped and was resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterruptionInfo {
+    pub pass_number: u32,
+    pub offset: u64,
+    pub checkpointed_at: u64,
+    pub resumed_at: u64,
 }
 
 /// Performance metrics
@@ -93,12 +192,19 @@ pub struct PerformanceMetrics {
 /// Enhanced verification information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnhancedVerificationInfo {
-    pub hash: String,
-    pub algorithm: String,
     pub verification_method: String,
     pub sample_count: u32,
     pub verification_ratio: f64,
     pub forensic_tools_used: Vec<String>,
+    /// Pattern the read-back was compared against
+    #[serde(default)]
+    pub expected_pattern: Option<String>,
+    #[serde(default)]
+    pub bytes_checked: u64,
+    #[serde(default)]
+    pub mismatch_count: u64,
+    #[serde(default)]
+    pub mismatched_ranges: Vec<LbaRange>,
 }
 
 /// PKI information for certificate verification
@@ -106,7 +212,14 @@ pub struct EnhancedVerificationInfo {
 pub struct PKIInfo {
     pub ocsp_url: Option<String>,
     pub crl_url: Option<String>,
+    /// Intermediate certificates between the station certificate and its root
     pub ca_chain_pem: Option<String>,
+    /// X.509 certificate of the key that signed this certificate
+    #[serde(default)]
+    pub station_certificate_pem: Option<String>,
+    /// Base64 DER OCSP response for the station certificate, fetched when the certificate was signed
+    #[serde(default)]
+    pub ocsp_response: Option<String>,
 }
 
 /// Compliance information
@@ -143,6 +256,11 @@ pub struct EnhancedCertificateGenerator {
     pub ocsp_url: Option<String>,
     pub crl_url: Option<String>,
     pub ca_chain_pem: Option<String>,
+    pub station_chain: Option<StationChain>,
+    /// Fetches the OCSP response stapled into certificates signed under `station_chain`
+    pub ocsp_stapling: Option<OcspClient>,
+    /// Passphrase of an encrypted signing key
+    pub key_passphrase: Option<String>,
 }
 
 impl EnhancedCertificateGenerator {
@@ -158,12 +276,24 @@ impl EnhancedCertificateGenerator {
             ocsp_url: None,
             crl_url: None,
             ca_chain_pem: None,
+            station_chain: None,
+            ocsp_stapling: None,
+            key_passphrase: None,
         }
     }
     
     pub fn with_ocsp_url(mut self, url: String) -> Self { self.ocsp_url = Some(url); self }
     pub fn with_crl_url(mut self, url: String) -> Self { self.crl_url = Some(url); self }
     pub fn with_ca_chain_pem(mut self, pem: String) -> Self { self.ca_chain_pem = Some(pem); self }
+    pub fn with_key_passphrase(mut self, passphrase: String) -> Self { self.key_passphrase = Some(passphrase); self }
+    
+    /// Embed the signing key's X.509 certificate and its intermediates.
+    ///
+    /// The OCSP and CRL locations default to the ones the station certificate names.
+    pub fn with_station_chain(mut self, chain: StationChain) -> Self { self.station_chain = Some(chain); self }
+    
+    /// Staple the station certificate's OCSP status so the certificate can be verified offline
+    pub fn with_ocsp_stapling(mut self, client: OcspClient) -> Self { self.ocsp_stapling = Some(client); self }
     
     /// Generate an enhanced certificate for a wipe operation
     pub async fn generate_enhanced_certificate(
@@ -176,7 +306,14 @@ impl EnhancedCertificateGenerator {
         info!("Generating enhanced wipe certificate...");
         
         // Load signing key
-        let signing_key = load_signing_key(private_key_path).await?;
+        let signing_key = load_private_key(private_key_path, self.key_passphrase.as_deref()).await?;
+        if let Some(chain) = &self.station_chain {
+            if chain.public_key()? != signing_key.public_key() {
+                return Err(SecureEraseError::CertificateGenerationFailed(format!(
+                    "{} is not the key of the station certificate", private_key_path.display()
+                )));
+            }
+        }
         
         // Create certificate data
         let timestamp = SystemTime::now()
@@ -196,18 +333,19 @@ impl EnhancedCertificateGenerator {
             serial: wipe_result.device.serial.clone(),
             firmware_version: self.get_firmware_version(&wipe_result.device).await.ok(),
             interface_type: self.get_interface_type(&wipe_result.device).await.ok(),
-            hidden_areas: self.convert_hidden_areas(&wipe_result.device),
+            hidden_areas: self.convert_hidden_areas(wipe_result),
             capabilities: DeviceCapabilities {
                 supports_secure_erase: wipe_result.device.supports_secure_erase,
                 supports_trim: wipe_result.device.supports_trim,
-                supports_crypto_erase: self.check_crypto_erase_support(&wipe_result.device).await.unwrap_or(false),
+                supports_crypto_erase: self.check_crypto_erase_support(wipe_result).await.unwrap_or(false),
                 supports_format_unit: self.check_format_unit_support(&wipe_result.device).await.unwrap_or(false),
             },
         };
         
         // Enhanced wipe details
         let wipe_details = EnhancedWipeDetails {
-            mode: format!("{:?}", wipe_result.mode),
+            mode: wipe_result.mode.method_name().to_string(),
+            method: wipe_result.method.clone(),
             start_time: wipe_result.start_time
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
@@ -218,21 +356,90 @@ impl EnhancedCertificateGenerator {
                 .as_secs(),
             duration_seconds: wipe_result.duration_seconds,
             bytes_written: wipe_result.bytes_written,
-            passes_completed: self.calculate_passes_completed(&wipe_result.mode),
+            passes_completed: self.calculate_passes_completed(wipe_result),
             verification_passed: wipe_result.verification_passed,
             errors: wipe_result.errors.clone(),
             warnings: self.generate_warnings(&wipe_result).await,
             performance_metrics: self.calculate_performance_metrics(wipe_result).await,
+            interrupted: !wipe_result.interruptions.is_empty(),
+            interruptions: wipe_result.interruptions.iter().map(|i| InterruptionInfo {
+                pass_number: i.pass_number,
+                offset: i.offset,
+                checkpointed_at: i.checkpointed_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                resumed_at: i.resumed_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            }).collect(),
+            sanitization_status: Some(wipe_result.sanitization_status()),
+            unwritable_ranges: wipe_result.unwritable_ranges.clone(),
+            unwritable_bytes: wipe_result.unwritable_bytes,
+            passes: wipe_result.passes.iter().map(|pass| PassInfo {
+                pass_number: pass.pass_number,
+                pattern: pass.pattern.describe(),
+                started_at: pass.started_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                finished_at: pass.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                bytes_written: pass.stats.bytes_written,
+                throughput_mbps: pass.throughput_bytes_per_sec / BYTES_PER_MB,
+                retries: pass.stats.retries,
+                unwritable_ranges: pass.stats.unwritable_ranges.len(),
+                errors: pass.stats.errors.clone(),
+            }).collect(),
+            hardware_commands: wipe_result.hardware_commands.iter().map(|record| HardwareCommandInfo {
+                command: format!("{:?}", record.command),
+                issued: record.issued.clone(),
+                started_at: record.started_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                finished_at: record.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            }).collect(),
+            crypto_erases: wipe_result.crypto_erases.iter().map(|record| CryptoEraseInfo {
+                method: record.method,
+                subsystem: record.subsystem,
+                locking_before: record.locking_before,
+                locking_after: record.locking_after,
+                started_at: record.started_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                finished_at: record.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            }).collect(),
+            discards: wipe_result.discards.iter().map(|report| DiscardInfo {
+                mode: if report.secure { "secure" } else { "best-effort" }.to_string(),
+                bytes_discarded: report.bytes_discarded,
+                read_after_discard: report.read_after_discard,
+                blocks_checked: report.blocks_checked,
+                blocks_zeroed: report.blocks_zeroed,
+                started_at: report.started_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                finished_at: report.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            }).collect(),
         };
         
         // Enhanced verification info
-        let verification_info = EnhancedVerificationInfo {
-            hash: "".to_string(),
-            algorithm: "SHA-256".to_string(),
-            verification_method: "Random Sector Sampling".to_string(),
-            sample_count: 100,
-            verification_ratio: if wipe_result.verification_passed { 1.0 } else { 0.0 },
-            forensic_tools_used: vec!["Internal Verification".to_string()],
+        let verification_info = match &wipe_result.verification {
+            Some(report) => EnhancedVerificationInfo {
+                verification_method: report.mode.describe(),
+                sample_count: report.blocks_checked.min(u32::MAX as u64) as u32,
+                verification_ratio: report.match_ratio(),
+                forensic_tools_used: vec!["Internal Verification".to_string()],
+                expected_pattern: Some(report.expected_pattern.describe()),
+                bytes_checked: report.bytes_checked,
+                mismatch_count: report.mismatched_blocks,
+                mismatched_ranges: report.mismatched_ranges.clone(),
+            },
+            // A read-back has nothing to compare against once no pattern describes the media
+            None if wipe_result.expected_contents.pattern().is_none() => EnhancedVerificationInfo {
+                verification_method: format!("Not applicable: the media holds {}", wipe_result.expected_contents.describe()),
+                sample_count: 0,
+                verification_ratio: 0.0,
+                forensic_tools_used: Vec::new(),
+                expected_pattern: None,
+                bytes_checked: 0,
+                mismatch_count: 0,
+                mismatched_ranges: Vec::new(),
+            },
+            None => EnhancedVerificationInfo {
+                verification_method: "Not performed".to_string(),
+                sample_count: 0,
+                verification_ratio: if wipe_result.verification_passed { 1.0 } else { 0.0 },
+                forensic_tools_used: Vec::new(),
+                expected_pattern: None,
+                bytes_checked: 0,
+                mismatch_count: 0,
+                mismatched_ranges: Vec::new(),
+            },
         };
         
         // Compliance info
@@ -242,16 +449,30 @@ impl EnhancedCertificateGenerator {
                 "DoD 5220.22-M".to_string(),
                 "ISO/IEC 27040:2015".to_string(),
             ],
-            compliance_level: self.determine_compliance_level(&wipe_result.mode),
+            compliance_level: self.determine_compliance_level(wipe_result),
             audit_trail: self.generate_audit_trail(wipe_result).await,
         };
         
         // PKI info
-        let pki = PKIInfo {
-            ocsp_url: self.ocsp_url.clone(),
-            crl_url: self.crl_url.clone(),
-            ca_chain_pem: self.ca_chain_pem.clone(),
+        let mut pki = match &self.station_chain {
+            Some(chain) => PKIInfo {
+                ocsp_url: self.ocsp_url.clone().or(chain.ocsp_url()?),
+                crl_url: self.crl_url.clone().or(chain.crl_url()?),
+                ca_chain_pem: self.ca_chain_pem.clone().or_else(|| Some(certificates_to_pem(&chain.intermediates))),
+                station_certificate_pem: Some(certificates_to_pem(std::slice::from_ref(&chain.leaf))),
+                ocsp_response: None,
+            },
+            None => PKIInfo {
+                ocsp_url: self.ocsp_url.clone(),
+                crl_url: self.crl_url.clone(),
+                ca_chain_pem: self.ca_chain_pem.clone(),
+                station_certificate_pem: None,
+                ocsp_response: None,
+            },
         };
+        if let (Some(chain), Some(client)) = (&self.station_chain, &self.ocsp_stapling) {
+            pki.ocsp_response = self.staple_ocsp_response(chain, client, pki.ocsp_url.as_deref()).await?;
+        }
         
         // Certificate metadata
         let metadata = CertificateMetadata {
@@ -264,28 +485,25 @@ impl EnhancedCertificateGenerator {
         
         // Create certificate without signature
         let mut certificate = EnhancedWipeCertificate {
-            version: "2.0".to_string(),
+            version: Schema::Enhanced.version().to_string(),
             certificate_id,
             timestamp,
-            issuer: self.issuer_info.clone(),
+            issuer: CertificateIssuer {
+                public_key_fingerprint: key_fingerprint(&signing_key.public_key()),
+                ..self.issuer_info.clone()
+            },
             device_info,
             wipe_details,
             verification: verification_info,
             compliance: compliance_info,
             pki,
             metadata,
-            signature: "".to_string(),
+            signature: None,
         };
         
-        // Generate hash and signature
-        let certificate_data = serde_json::to_string(&certificate)?;
-        let hash = hash_data(certificate_data.as_bytes()).await?;
-        let signature = sign_data(certificate_data.as_bytes(), &signing_key).await?;
-        
-        // Update certificate with hash and signature
-        certificate.verification.hash = hash;
-        certificate.signature = hex::encode(signature.to_bytes());
+        // Everything but the signature envelope is signed, the QR code included
         certificate.metadata.qr_code_data = Some(self.generate_qr_code_data(&certificate).await?);
+        certificate.signature = Some(signing::sign(&certificate, &signing_key).await?);
         
         // Save JSON certificate
         let json_data = serde_json::to_string_pretty(&certificate)?;
@@ -297,6 +515,24 @@ impl EnhancedCertificateGenerator {
         Ok(())
     }
 
+    /// Base64 OCSP response for the station certificate; a responder that cannot be
+    /// reached only costs the staple, but a revoked station may not sign
+    async fn staple_ocsp_response(&self, chain: &StationChain, client: &OcspClient, url: Option<&str>) -> Result<Option<String>> {
+        use base64::Engine;
+        let (Some(url), Some(issuer)) = (url, chain.issuer()?) else {
+            warn!("Station certificate has no OCSP responder or issuer; not stapling OCSP status");
+            return Ok(None);
+        };
+        let response = match client.status(url, &chain.leaf, issuer).await {
+            Ok(response) => response,
+            Err(e) => { warn!("Not stapling OCSP status: {}", e); return Ok(None); }
+        };
+        if let OcspCertStatus::Revoked(_) = response.status {
+            return Err(SecureEraseError::CertificateGenerationFailed(format!("{} reports the station certificate as revoked", url)));
+        }
+        Ok(Some(base64::engine::general_purpose::STANDARD.encode(&response.der)))
+    }
+
     async fn get_firmware_version(&self, _device: &crate::core::StorageDevice) -> Result<String> {
         Ok("Unknown".to_string())
     }
@@ -305,32 +541,42 @@ impl EnhancedCertificateGenerator {
         Ok("Unknown".to_string())
     }
     
-    fn convert_hidden_areas(&self, device: &crate::core::StorageDevice) -> Vec<HiddenAreaInfo> {
-        device.hidden_areas.iter().map(|area| {
+    fn convert_hidden_areas(&self, wipe_result: &WipeResult) -> Vec<HiddenAreaInfo> {
+        wipe_result.hidden_areas.iter().map(|status| {
             HiddenAreaInfo {
-                area_type: format!("{:?}", area.area_type),
-                start_lba: area.start_lba,
-                size: area.size,
-                description: area.description.clone(),
-                wiped: true,
+                area_type: format!("{:?}", status.area.area_type),
+                start_lba: status.area.start_lba,
+                size: status.area.size,
+                description: status.area.description.clone(),
+                wiped: status.wiped,
+                restored: status.restored,
+                permanent: status.permanent,
+                note: status.note.clone(),
             }
         }).collect()
     }
     
-    async fn check_crypto_erase_support(&self, device: &crate::core::StorageDevice) -> Result<bool> {
-        Ok(matches!(device.device_type, crate::core::DeviceType::NVMe))
+    /// A crypto erase that ran, or Level 0 discovery reporting an SED with media encryption
+    async fn check_crypto_erase_support(&self, wipe_result: &WipeResult) -> Result<bool> {
+        if wipe_result.crypto_erases.iter().any(|record| record.locking_before.media_encryption) {
+            return Ok(true);
+        }
+        let device = wipe_result.device.clone();
+        let discovered = tokio::task::spawn_blocking(move || opal::discover_device(&device))
+            .await
+            .map_err(|e| SecureEraseError::CertificateGenerationFailed(e.to_string()))?;
+        match discovered {
+            Ok(discovery) => Ok(discovery.supports_crypto_erase()),
+            Err(_) => Ok(matches!(wipe_result.device.device_type, crate::core::DeviceType::NVMe)),
+        }
     }
     
     async fn check_format_unit_support(&self, _device: &crate::core::StorageDevice) -> Result<bool> {
         Ok(true)
     }
     
-    fn calculate_passes_completed(&self, mode: &crate::core::EraseMode) -> u32 {
-        match mode {
-            crate::core::EraseMode::Quick => 1,
-            crate::core::EraseMode::Full => 3,
-            crate::core::EraseMode::Advanced => 7,
-        }
+    fn calculate_passes_completed(&self, wipe_result: &WipeResult) -> u32 {
+        wipe_result.passes.len() as u32
     }
     
     async fn generate_warnings(&self, wipe_result: &WipeResult) -> Vec<String> {
@@ -341,27 +587,60 @@ impl EnhancedCertificateGenerator {
         if !wipe_result.errors.is_empty() {
             warnings.push("Errors occurred during wipe operation".to_string());
         }
+        if !wipe_result.unwritable_ranges.is_empty() {
+            warnings.push(format!(
+                "Partial sanitization: {} bytes in {} LBA range(s) could not be overwritten",
+                wipe_result.unwritable_bytes, wipe_result.unwritable_ranges.len()
+            ));
+        }
+        for status in wipe_result.hidden_areas.iter().filter(|status| !status.wiped) {
+            warnings.push(format!(
+                "{} ({} sectors from LBA {}) was not wiped",
+                status.area.description, status.area.size, status.area.start_lba
+            ));
+        }
+        for report in wipe_result.discards.iter().filter(|report| !report.secure) {
+            warnings.push(format!(
+                "Discard was best-effort: the device may keep discarded data readable internally ({}/{} sampled blocks read back as zeroes)",
+                report.blocks_zeroed, report.blocks_checked
+            ));
+        }
+        if !wipe_result.interruptions.is_empty() {
+            warnings.push(format!("Wipe was interrupted and resumed {} time(s) from a checkpoint", wipe_result.interruptions.len()));
+        }
+        let in_use: Vec<String> = wipe_result.safety_overrides.iter()
+            .filter_map(|safety_override| safety_override.hazard.as_ref().map(|hazard| hazard.describe()))
+            .collect();
+        if !in_use.is_empty() {
+            warnings.push(format!("Device was wiped while in use: it {}", in_use.join("; ")));
+        }
         if wipe_result.device.size > 2 * 1024 * 1024 * 1024 * 1024 { warnings.push("Large device - extended verification recommended".to_string()); }
         warnings
     }
     
     async fn calculate_performance_metrics(&self, wipe_result: &WipeResult) -> PerformanceMetrics {
-        let size_gb = wipe_result.bytes_written as f64 / (1024.0 * 1024.0 * 1024.0);
-        let duration_hours = wipe_result.duration_seconds as f64 / 3600.0;
-        let average_speed_mbps = if duration_hours > 0.0 { (size_gb * 1024.0) / duration_hours } else { 0.0 };
+        // Only time spent writing counts, so hardware commands and verification do not skew the figures
+        let bytes: u64 = wipe_result.passes.iter().map(|pass| pass.stats.bytes_written).sum();
+        let seconds: f64 = wipe_result.passes.iter().map(|pass| pass.stats.write_seconds).sum();
+        let average = if seconds > 0.0 { bytes as f64 / seconds } else { 0.0 };
+        let peak = wipe_result.passes.iter().map(|pass| pass.stats.peak_bytes_per_sec).fold(0.0, f64::max);
         PerformanceMetrics {
-            average_speed_mbps,
-            peak_speed_mbps: average_speed_mbps * 1.5,
-            sectors_per_second: if wipe_result.duration_seconds > 0 { wipe_result.bytes_written / 512 / wipe_result.duration_seconds } else { 0 },
-            retry_count: 0,
+            average_speed_mbps: average / BYTES_PER_MB,
+            peak_speed_mbps: peak / BYTES_PER_MB,
+            sectors_per_second: (average / 512.0) as u64,
+            retry_count: wipe_result.passes.iter().map(|pass| pass.stats.retries).sum(),
         }
     }
     
-    fn determine_compliance_level(&self, mode: &crate::core::EraseMode) -> String {
-        match mode {
+    fn determine_compliance_level(&self, wipe_result: &WipeResult) -> String {
+        if let Some(level) = wipe_result.method.as_ref().and_then(|m| m.compliance_level.clone()) {
+            return level;
+        }
+        match wipe_result.mode {
             crate::core::EraseMode::Quick => "Basic".to_string(),
             crate::core::EraseMode::Full => "Standard".to_string(),
             crate::core::EraseMode::Advanced => "High".to_string(),
+            crate::core::EraseMode::Custom(_) => "Custom".to_string(),
         }
     }
     
@@ -369,9 +648,64 @@ impl EnhancedCertificateGenerator {
         let mut audit_trail = Vec::new();
         let start_time = wipe_result.start_time.duration_since(UNIX_EPOCH).unwrap().as_secs();
         let end_time = wipe_result.end_time.duration_since(UNIX_EPOCH).unwrap().as_secs();
-        audit_trail.push(AuditEntry { timestamp: start_time, action: "Wipe Operation Started".to_string(), result: "Success".to_string(), details: Some(format!("Mode: {:?}", wipe_result.mode)) });
-        audit_trail.push(AuditEntry { timestamp: end_time, action: "Wipe Operation Completed".to_string(), result: if wipe_result.verification_passed { "Success" } else { "Failed" }.to_string(), details: Some(format!("Bytes written: {}", wipe_result.bytes_written)) });
-        audit_trail.push(AuditEntry { timestamp: end_time, action: "Verification Performed".to_string(), result: if wipe_result.verification_passed { "Passed" } else { "Failed" }.to_string(), details: None });
+        for safety_override in &wipe_result.safety_overrides {
+            let at = safety_override.at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            let operator = safety_override.operator.as_deref().unwrap_or("unknown operator");
+            audit_trail.push(AuditEntry { timestamp: at, action: "Safety Check Overridden".to_string(), result: "Overridden".to_string(), details: Some(format!("{} (by {})", safety_override.description, operator)) });
+        }
+        audit_trail.push(AuditEntry { timestamp: start_time, action: "Wipe Operation Started".to_string(), result: "Success".to_string(), details: Some(format!("Method: {}", wipe_result.mode.method_name())) });
+        for interruption in &wipe_result.interruptions {
+            let checkpointed_at = interruption.checkpointed_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            let resumed_at = interruption.resumed_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            audit_trail.push(AuditEntry { timestamp: checkpointed_at, action: "Wipe Operation Interrupted".to_string(), result: "Checkpointed".to_string(), details: Some(format!("Pass {} at offset {}", interruption.pass_number, interruption.offset)) });
+            audit_trail.push(AuditEntry { timestamp: resumed_at, action: "Wipe Operation Resumed".to_string(), result: "Success".to_string(), details: Some(format!("Pass {} from offset {}", interruption.pass_number, interruption.offset)) });
+        }
+        for status in &wipe_result.hidden_areas {
+            if let Some(attempted_at) = status.attempted_at {
+                let attempted_at = attempted_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+                audit_trail.push(AuditEntry { timestamp: attempted_at, action: "Hidden Area Restored".to_string(), result: if status.restored { "Success" } else { "Failed" }.to_string(), details: Some(format!("{}: {} sectors from LBA {} ({})", status.area.description, status.area.size, status.area.start_lba, if status.permanent { "permanent" } else { "temporary" })) });
+            }
+        }
+        for record in &wipe_result.hardware_commands {
+            let finished_at = record.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            audit_trail.push(AuditEntry { timestamp: finished_at, action: "Hardware Command Issued".to_string(), result: "Success".to_string(), details: Some(record.issued.clone()) });
+        }
+        for record in &wipe_result.crypto_erases {
+            for exchange in &record.exchanges {
+                let at = exchange.at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+                audit_trail.push(AuditEntry { timestamp: at, action: "TCG Method Call".to_string(), result: exchange.status.clone(), details: Some(exchange.call.clone()) });
+            }
+            let finished_at = record.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            audit_trail.push(AuditEntry { timestamp: finished_at, action: "Cryptographic Erase Completed".to_string(), result: "Success".to_string(), details: Some(format!("{:?} on {:?} drive", record.method, record.subsystem)) });
+        }
+        for report in &wipe_result.discards {
+            let finished_at = report.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            audit_trail.push(AuditEntry { timestamp: finished_at, action: "Discard Issued".to_string(), result: if report.secure { "Secure" } else { "Best-effort" }.to_string(), details: Some(report.describe()) });
+        }
+        for pass in &wipe_result.passes {
+            let started_at = pass.started_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            let finished_at = pass.finished_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            audit_trail.push(AuditEntry { timestamp: started_at, action: format!("Pass {} Started", pass.pass_number), result: "Success".to_string(), details: Some(pass.pattern.describe()) });
+            if !pass.stats.unwritable_ranges.is_empty() {
+                let ranges: Vec<String> = pass.stats.unwritable_ranges.iter().map(|r| format!("{}+{}", r.start_lba, r.count)).collect();
+                audit_trail.push(AuditEntry { timestamp: finished_at, action: "Unwritable Blocks Skipped".to_string(), result: "Skipped".to_string(), details: Some(format!("Pass {}: LBA {}", pass.pass_number, ranges.join(", "))) });
+            }
+            audit_trail.push(AuditEntry { timestamp: finished_at, action: format!("Pass {} Completed", pass.pass_number), result: if pass.stats.errors.is_empty() { "Success" } else { "Errors" }.to_string(), details: Some(format!("{} bytes at {:.1} MB/s, {} retries", pass.stats.bytes_written, pass.throughput_bytes_per_sec / BYTES_PER_MB, pass.stats.retries)) });
+        }
+        let completed = match wipe_result.sanitization_status() {
+            SanitizationStatus::Complete => "Success",
+            SanitizationStatus::Partial => "Partial",
+            SanitizationStatus::Failed => "Failed",
+        };
+        audit_trail.push(AuditEntry { timestamp: end_time, action: "Wipe Operation Completed".to_string(), result: completed.to_string(), details: Some(format!("Bytes written: {}", wipe_result.bytes_written)) });
+        let verified = if wipe_result.verification.is_none() && wipe_result.expected_contents.pattern().is_none() {
+            "Not applicable"
+        } else if wipe_result.verification_passed {
+            "Passed"
+        } else {
+            "Failed"
+        };
+        audit_trail.push(AuditEntry { timestamp: end_time, action: "Verification Performed".to_string(), result: verified.to_string(), details: wipe_result.verification.as_ref().map(|r| format!("{}: {} bytes checked, {} mismatched blocks", r.mode.describe(), r.bytes_checked, r.mismatched_blocks)) });
         audit_trail
     }
     
@@ -382,7 +716,7 @@ impl EnhancedCertificateGenerator {
             "device": certificate.device_info.name,
             "mode": certificate.wipe_details.mode,
             "verified": certificate.wipe_details.verification_passed,
-            "hash": certificate.verification.hash,
+            "key": certificate.issuer.public_key_fingerprint,
             "ocsp": certificate.pki.ocsp_url,
         });
         Ok(qr_data.to_string())
@@ -399,26 +733,46 @@ impl EnhancedCertificateGenerator {
         let (doc, page1, layer1) = PdfDocument::new("Secure Disk Erasure Certificate", Mm(210.0), Mm(297.0), "Layer 1");
         let current_layer = doc.get_page(page1).get_layer(layer1);
         
-        let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
-        current_layer.set_font(&font, 24.0);
-        current_layer.begin_text_section();
-        current_layer.write_text("Secure Disk Erasure Certificate", &font, 24.0, Mm(20.0), Mm(270.0), &[]);
-        current_layer.end_text_section();
+        let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)
+            .map_err(|e| SecureEraseError::CertificateGenerationFailed(e.to_string()))?;
+        current_layer.use_text("Secure Disk Erasure Certificate", 24.0, Mm(20.0), Mm(270.0), &font);
         
-        current_layer.set_font(&font, 12.0);
-        current_layer.begin_text_section();
-        current_layer.write_text(&format!("Certificate ID: {}", certificate.certificate_id), &font, 12.0, Mm(20.0), Mm(250.0), &[]);
-        current_layer.end_text_section();
+        current_layer.use_text(format!("Certificate ID: {}", certificate.certificate_id), 12.0, Mm(20.0), Mm(250.0), &font);
         
-        current_layer.write_text("Verification:", &font, 14.0, Mm(20.0), Mm(50.0), &[]);
-        current_layer.write_text(&format!("Hash: {}", &certificate.verification.hash[..std::cmp::min(32, certificate.verification.hash.len())]), &font, 10.0, Mm(30.0), Mm(40.0), &[]);
-        current_layer.write_text(&format!("Signature: {}", &certificate.signature[..std::cmp::min(32, certificate.signature.len())]), &font, 10.0, Mm(30.0), Mm(30.0), &[]);
+        let sanitization = match certificate.wipe_details.sanitization_status {
+            Some(SanitizationStatus::Partial) => format!(
+                "Sanitization: PARTIAL - {} bytes in {} LBA range(s) not overwritten",
+                certificate.wipe_details.unwritable_bytes, certificate.wipe_details.unwritable_ranges.len()
+            ),
+            Some(SanitizationStatus::Complete) => "Sanitization: COMPLETE".to_string(),
+            Some(SanitizationStatus::Failed) => "Sanitization: FAILED".to_string(),
+            None => format!("Sanitization: {}", if certificate.wipe_details.verification_passed { "COMPLETE" } else { "FAILED" }),
+        };
+        current_layer.use_text(sanitization, 12.0, Mm(20.0), Mm(240.0), &font);
+        let hardware_lines = certificate.wipe_details.hardware_commands.iter()
+            .map(|record| format!("Hardware command: {}", record.issued))
+            .chain(certificate.wipe_details.crypto_erases.iter().map(|erase| format!(
+                "Cryptographic erase: {:?} ({:?})", erase.method, erase.subsystem
+            )))
+            .chain(certificate.wipe_details.discards.iter().map(|discard| format!(
+                "Discard: {} ({} bytes, {}/{} sampled blocks zeroed)",
+                discard.mode, discard.bytes_discarded, discard.blocks_zeroed, discard.blocks_checked
+            )));
+        for (index, line) in hardware_lines.enumerate() {
+            current_layer.use_text(line, 10.0, Mm(20.0), Mm(232.0 - 6.0 * index as f32), &font);
+        }
+        
+        current_layer.use_text("Verification:", 14.0, Mm(20.0), Mm(50.0), &font);
+        if let Some(envelope) = &certificate.signature {
+            current_layer.use_text(format!("Payload SHA-256: {}", &envelope.payload_sha256[..std::cmp::min(32, envelope.payload_sha256.len())]), 10.0, Mm(30.0), Mm(40.0), &font);
+            current_layer.use_text(format!("Signature: {}", &envelope.value[..std::cmp::min(32, envelope.value.len())]), 10.0, Mm(30.0), Mm(30.0), &font);
+        }
         if let Some(ocsp) = &certificate.pki.ocsp_url {
-            current_layer.write_text(&format!("OCSP: {}", ocsp), &font, 10.0, Mm(30.0), Mm(20.0), &[]);
+            current_layer.use_text(format!("OCSP: {}", ocsp), 10.0, Mm(30.0), Mm(20.0), &font);
         }
         
-        current_layer.end_text_section();
-        doc.save(&mut BufWriter::new(fs::File::create(pdf_path)?))?;
+        doc.save(&mut BufWriter::new(fs::File::create(pdf_path)?))
+            .map_err(|e| SecureEraseError::CertificateGenerationFailed(e.to_string()))?;
         Ok(())
     }
 }
@@ -1,24 +1,29 @@
 use crate::error::{Result, SecureEraseError};
 use crate::core::WipeResult;
-use crate::crypto::{sign_data, load_signing_key, hash_data};
+use crate::crypto::{load_private_key, load_public_key, key_fingerprint};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::fs;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+pub mod canonical;
 pub mod enhanced;
+pub mod signing;
 pub mod verifier;
 
+use signing::{Schema, SignatureEnvelope};
+
 /// Digital certificate for wipe operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WipeCertificate {
+    /// Schema version, see `signing::Schema`
     pub version: String,
     pub certificate_id: String,
     pub timestamp: u64,
     pub device_info: DeviceInfo,
     pub wipe_details: WipeDetails,
     pub verification: VerificationInfo,
-    pub signature: String,
+    pub signature: Option<SignatureEnvelope>,
 }
 
 /// Device information in certificate
@@ -47,8 +52,6 @@ pub struct WipeDetails {
 /// Verification information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationInfo {
-    pub hash: String,
-    pub algorithm: String,
     pub public_key_fingerprint: String,
 }
 
@@ -57,10 +60,9 @@ pub async fn generate_certificate(
     wipe_result: &WipeResult,
     json_path: &Path,
     pdf_path: &Path,
+    private_key_path: &Path,
 ) -> Result<()> {
-    // Load signing key (in production, this should be from secure storage)
-    let signing_key_path = Path::new("private_key.pem");
-    let signing_key = load_signing_key(signing_key_path).await?;
+    let signing_key = load_private_key(private_key_path, None).await?;
     
     // Create certificate data
     let timestamp = SystemTime::now()
@@ -80,7 +82,7 @@ pub async fn generate_certificate(
     };
     
     let wipe_details = WipeDetails {
-        mode: format!("{:?}", wipe_result.mode),
+        mode: wipe_result.mode.method_name().to_string(),
         start_time: wipe_result.start_time
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -95,39 +97,18 @@ pub async fn generate_certificate(
         errors: wipe_result.errors.clone(),
     };
     
-    // Create verification info
-    let certificate_data = serde_json::to_string(&WipeCertificate {
-        version: "1.0".to_string(),
-        certificate_id: certificate_id.clone(),
-        timestamp,
-        device_info: device_info.clone(),
-        wipe_details: wipe_details.clone(),
-        verification: VerificationInfo {
-            hash: "".to_string(), // Will be filled after signing
-            algorithm: "SHA-256".to_string(),
-            public_key_fingerprint: "".to_string(), // Will be filled
-        },
-        signature: "".to_string(), // Will be filled after signing
-    })?;
-    
-    // Generate hash and signature
-    let hash = hash_data(certificate_data.as_bytes()).await?;
-    let signature = sign_data(certificate_data.as_bytes(), &signing_key).await?;
-    
-    // Create final certificate
-    let certificate = WipeCertificate {
-        version: "1.0".to_string(),
+    let mut certificate = WipeCertificate {
+        version: Schema::Basic.version().to_string(),
         certificate_id,
         timestamp,
         device_info,
         wipe_details,
         verification: VerificationInfo {
-            hash,
-            algorithm: "SHA-256".to_string(),
-            public_key_fingerprint: "ED25519".to_string(), // Simplified for now
+            public_key_fingerprint: key_fingerprint(&signing_key.public_key()),
         },
-        signature: hex::encode(signature.to_bytes()),
+        signature: None,
     };
+    certificate.signature = Some(signing::sign(&certificate, &signing_key).await?);
     
     // Save JSON certificate
     let json_data = serde_json::to_string_pretty(&certificate)?;
@@ -148,83 +129,65 @@ async fn generate_pdf_certificate(certificate: &WipeCertificate, pdf_path: &Path
     let current_layer = doc.get_page(page1).get_layer(layer1);
     
     // Title
-    let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
-    current_layer.set_font(&font, 24.0);
-    current_layer.begin_text_section();
-    current_layer.write_text("Secure Disk Erasure Certificate", &font, 24.0, Mm(20.0), Mm(270.0), &[]);
-    current_layer.end_text_section();
+    let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)
+        .map_err(|e| SecureEraseError::CertificateGenerationFailed(e.to_string()))?;
+    current_layer.use_text("Secure Disk Erasure Certificate", 24.0, Mm(20.0), Mm(270.0), &font);
     
     // Certificate ID
-    current_layer.set_font(&font, 12.0);
-    current_layer.begin_text_section();
-    current_layer.write_text(&format!("Certificate ID: {}", certificate.certificate_id), &font, 12.0, Mm(20.0), Mm(250.0), &[]);
-    current_layer.end_text_section();
+    current_layer.use_text(format!("Certificate ID: {}", certificate.certificate_id), 12.0, Mm(20.0), Mm(250.0), &font);
     
     // Timestamp
-    current_layer.write_text(&format!("Generated: {}", chrono::DateTime::from_timestamp(certificate.timestamp as i64, 0).unwrap().format("%Y-%m-%d %H:%M:%S UTC")), &font, 12.0, Mm(20.0), Mm(240.0), &[]);
+    current_layer.use_text(format!("Generated: {}", chrono::DateTime::from_timestamp(certificate.timestamp as i64, 0).unwrap().format("%Y-%m-%d %H:%M:%S UTC")), 12.0, Mm(20.0), Mm(240.0), &font);
     
     // Device Information
-    current_layer.write_text("Device Information:", &font, 14.0, Mm(20.0), Mm(220.0), &[]);
-    current_layer.write_text(&format!("Device: {}", certificate.device_info.name), &font, 12.0, Mm(30.0), Mm(210.0), &[]);
-    current_layer.write_text(&format!("Path: {}", certificate.device_info.path), &font, 12.0, Mm(30.0), Mm(200.0), &[]);
-    current_layer.write_text(&format!("Size: {} GB", certificate.device_info.size / (1024 * 1024 * 1024)), &font, 12.0, Mm(30.0), Mm(190.0), &[]);
-    current_layer.write_text(&format!("Type: {}", certificate.device_info.device_type), &font, 12.0, Mm(30.0), Mm(180.0), &[]);
+    current_layer.use_text("Device Information:", 14.0, Mm(20.0), Mm(220.0), &font);
+    current_layer.use_text(format!("Device: {}", certificate.device_info.name), 12.0, Mm(30.0), Mm(210.0), &font);
+    current_layer.use_text(format!("Path: {}", certificate.device_info.path), 12.0, Mm(30.0), Mm(200.0), &font);
+    current_layer.use_text(format!("Size: {} GB", certificate.device_info.size / (1024 * 1024 * 1024)), 12.0, Mm(30.0), Mm(190.0), &font);
+    current_layer.use_text(format!("Type: {}", certificate.device_info.device_type), 12.0, Mm(30.0), Mm(180.0), &font);
     
     if let Some(model) = &certificate.device_info.model {
-        current_layer.write_text(&format!("Model: {}", model), &font, 12.0, Mm(30.0), Mm(170.0), &[]);
+        current_layer.use_text(format!("Model: {}", model), 12.0, Mm(30.0), Mm(170.0), &font);
     }
     
     // Wipe Details
-    current_layer.write_text("Wipe Details:", &font, 14.0, Mm(20.0), Mm(150.0), &[]);
-    current_layer.write_text(&format!("Mode: {}", certificate.wipe_details.mode), &font, 12.0, Mm(30.0), Mm(140.0), &[]);
-    current_layer.write_text(&format!("Duration: {} seconds", certificate.wipe_details.duration_seconds), &font, 12.0, Mm(30.0), Mm(130.0), &[]);
-    current_layer.write_text(&format!("Bytes Written: {} GB", certificate.wipe_details.bytes_written / (1024 * 1024 * 1024)), &font, 12.0, Mm(30.0), Mm(120.0), &[]);
-    current_layer.write_text(&format!("Verification: {}", if certificate.wipe_details.verification_passed { "PASSED" } else { "FAILED" }), &font, 12.0, Mm(30.0), Mm(110.0), &[]);
+    current_layer.use_text("Wipe Details:", 14.0, Mm(20.0), Mm(150.0), &font);
+    current_layer.use_text(format!("Mode: {}", certificate.wipe_details.mode), 12.0, Mm(30.0), Mm(140.0), &font);
+    current_layer.use_text(format!("Duration: {} seconds", certificate.wipe_details.duration_seconds), 12.0, Mm(30.0), Mm(130.0), &font);
+    current_layer.use_text(format!("Bytes Written: {} GB", certificate.wipe_details.bytes_written / (1024 * 1024 * 1024)), 12.0, Mm(30.0), Mm(120.0), &font);
+    current_layer.use_text(format!("Verification: {}", if certificate.wipe_details.verification_passed { "PASSED" } else { "FAILED" }), 12.0, Mm(30.0), Mm(110.0), &font);
     
     // Verification Info
-    current_layer.write_text("Verification:", &font, 14.0, Mm(20.0), Mm(90.0), &[]);
-    current_layer.write_text(&format!("Hash: {}", certificate.verification.hash), &font, 10.0, Mm(30.0), Mm(80.0), &[]);
-    current_layer.write_text(&format!("Algorithm: {}", certificate.verification.algorithm), &font, 12.0, Mm(30.0), Mm(70.0), &[]);
-    current_layer.write_text(&format!("Signature: {}", &certificate.signature[..32]), &font, 10.0, Mm(30.0), Mm(60.0), &[]);
+    current_layer.use_text("Verification:", 14.0, Mm(20.0), Mm(90.0), &font);
+    if let Some(envelope) = &certificate.signature {
+        current_layer.use_text(format!("Payload SHA-256: {}", envelope.payload_sha256), 10.0, Mm(30.0), Mm(80.0), &font);
+        current_layer.use_text(format!("Algorithm: {} over {}", envelope.algorithm, envelope.canonicalization), 12.0, Mm(30.0), Mm(70.0), &font);
+        current_layer.use_text(format!("Signature: {}", &envelope.value[..std::cmp::min(32, envelope.value.len())]), 10.0, Mm(30.0), Mm(60.0), &font);
+    }
     
     // Footer
-    current_layer.write_text("This certificate provides cryptographic proof of secure data erasure.", &font, 10.0, Mm(20.0), Mm(30.0), &[]);
-    current_layer.write_text("The signature can be verified using the corresponding public key.", &font, 10.0, Mm(20.0), Mm(20.0), &[]);
+    current_layer.use_text("This certificate provides cryptographic proof of secure data erasure.", 10.0, Mm(20.0), Mm(30.0), &font);
+    current_layer.use_text("The signature can be verified using the corresponding public key.", 10.0, Mm(20.0), Mm(20.0), &font);
     
-    current_layer.end_text_section();
     
     // Save PDF
-    doc.save(&mut BufWriter::new(fs::File::create(pdf_path)?))?;
+    doc.save(&mut BufWriter::new(fs::File::create(pdf_path)?))
+        .map_err(|e| SecureEraseError::CertificateGenerationFailed(e.to_string()))?;
     
     Ok(())
 }
 
-/// Verify a wipe certificate
+/// Verify a wipe certificate of any schema against a public key
 pub async fn verify_certificate(
     certificate_path: &Path,
     public_key_path: Option<&Path>,
 ) -> Result<bool> {
-    let certificate_data = fs::read(certificate_path)?;
-    let certificate: WipeCertificate = serde_json::from_slice(&certificate_data)?;
+    let certificate: serde_json::Value = serde_json::from_slice(&fs::read(certificate_path)?)?;
+    Schema::of(&certificate)?;
     
     // Load public key
     let public_key_path = public_key_path.unwrap_or(Path::new("public_key.pem"));
-    let verifying_key = crate::crypto::load_verifying_key(public_key_path).await?;
-    
-    // Verify signature
-    let signature_bytes = hex::decode(&certificate.signature)
-        .map_err(|_| SecureEraseError::CertificateVerificationFailed("Invalid signature format".to_string()))?;
-    
-    let signature = ed25519_dalek::Signature::from_bytes(&signature_bytes)
-        .map_err(|_| SecureEraseError::CertificateVerificationFailed("Invalid signature".to_string()))?;
-    
-    // Create data to verify (certificate without signature)
-    let mut cert_copy = certificate.clone();
-    cert_copy.signature = "".to_string();
-    let data_to_verify = serde_json::to_string(&cert_copy)?;
-    
-    // Verify signature
-    let is_valid = crate::crypto::verify_signature(data_to_verify.as_bytes(), &signature, &verifying_key).await?;
+    let verifying_key = load_public_key(public_key_path).await?;
     
-    Ok(is_valid)
+    Ok(signing::verify(&certificate, &verifying_key).await?.is_valid())
 }
@@ -0,0 +1,180 @@
+use crate::error::{Result, SecureEraseError};
+use crate::certificates::canonical;
+use crate::crypto::{hash_data, key_fingerprint, PrivateKey, PublicKey, SignatureAlgorithm};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Member of a certificate that holds its `SignatureEnvelope`
+pub const SIGNATURE_MEMBER: &str = "signature";
+pub const CANONICALIZATION: &str = "RFC8785";
+
+/// Certificate types and the schema version each is written with.
+///
+/// The major part of the `version` member names the type; versions before
+/// these were signed over `serde_json::to_string` of the struct and cannot
+/// be checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schema {
+    /// `WipeCertificate`
+    Basic,
+    /// `EnhancedWipeCertificate`
+    Enhanced,
+}
+
+impl Schema {
+    pub fn version(&self) -> &'static str {
+        match self {
+            Schema::Basic => "1.1",
+            Schema::Enhanced => "2.1",
+        }
+    }
+
+    /// The schema a certificate declares in its `version` member
+    pub fn of(certificate: &Value) -> Result<Self> {
+        let version = certificate.get("version").and_then(Value::as_str)
+            .ok_or_else(|| SecureEraseError::CertificateVerificationFailed("certificate declares no schema version".to_string()))?;
+        match version {
+            "1.1" => Ok(Schema::Basic),
+            "2.1" => Ok(Schema::Enhanced),
+            "1.0" | "2.0" => Err(SecureEraseError::CertificateVerificationFailed(format!(
+                "schema version {} predates canonical signing payloads and its signature cannot be checked; re-issue the certificate", version
+            ))),
+            other => Err(SecureEraseError::CertificateVerificationFailed(format!("unsupported certificate schema version {}", other))),
+        }
+    }
+}
+
+/// Detached signature over a certificate's canonical payload.
+///
+/// It is stored in the certificate's `signature` member, which is the only
+/// member the payload leaves out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignatureEnvelope {
+    /// `SignatureAlgorithm::name` of the signing key
+    pub algorithm: String,
+    pub canonicalization: String,
+    /// SHA-256 of the canonical payload, hex
+    pub payload_sha256: String,
+    /// SHA-256 of the signing public key, hex
+    pub key_fingerprint: String,
+    /// Signature over the canonical payload, hex
+    pub value: String,
+}
+
+/// Outcome of checking a certificate against its envelope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureCheck {
+    pub payload_hash_valid: bool,
+    pub signature_valid: bool,
+}
+
+impl SignatureCheck {
+    pub fn is_valid(&self) -> bool {
+        self.payload_hash_valid && self.signature_valid
+    }
+}
+
+/// The bytes a certificate is signed over: every member but `signature`, in RFC 8785 form
+pub fn payload(certificate: &Value) -> Result<Vec<u8>> {
+    let mut certificate = certificate.clone();
+    let members = certificate.as_object_mut()
+        .ok_or_else(|| SecureEraseError::CertificateVerificationFailed("certificate is not a JSON object".to_string()))?;
+    members.remove(SIGNATURE_MEMBER);
+    Ok(canonical::canonicalize(&certificate)?.into_bytes())
+}
+
+/// Sign a certificate of any schema
+pub async fn sign<T: Serialize>(certificate: &T, signing_key: &PrivateKey) -> Result<SignatureEnvelope> {
+    let payload = payload(&serde_json::to_value(certificate)?)?;
+    let public_key = signing_key.public_key();
+    Ok(SignatureEnvelope {
+        algorithm: public_key.algorithm.name().to_string(),
+        canonicalization: CANONICALIZATION.to_string(),
+        payload_sha256: hash_data(&payload).await?,
+        key_fingerprint: key_fingerprint(&public_key),
+        value: hex::encode(signing_key.sign(&payload)?),
+    })
+}
+
+/// Check a certificate, as read from its JSON file, against its own envelope.
+///
+/// Verifying the JSON rather than a parsed struct keeps members this build
+/// does not know about inside the signed payload.
+pub async fn verify(certificate: &Value, verifying_key: &PublicKey) -> Result<SignatureCheck> {
+    let envelope: SignatureEnvelope = match certificate.get(SIGNATURE_MEMBER) {
+        Some(envelope) if !envelope.is_null() => serde_json::from_value(envelope.clone())?,
+        _ => return Err(SecureEraseError::CertificateVerificationFailed("certificate is not signed".to_string())),
+    };
+    if SignatureAlgorithm::from_name(&envelope.algorithm).is_none() || envelope.canonicalization != CANONICALIZATION {
+        return Err(SecureEraseError::CertificateVerificationFailed(format!(
+            "unsupported signature: {} over {}", envelope.algorithm, envelope.canonicalization
+        )));
+    }
+    let signature = hex::decode(&envelope.value)
+        .map_err(|_| SecureEraseError::CertificateVerificationFailed("Invalid signature format".to_string()))?;
+
+    // A key of another algorithm than the envelope names never verifies
+    let payload = payload(certificate)?;
+    Ok(SignatureCheck {
+        payload_hash_valid: hash_data(&payload).await? == envelope.payload_sha256,
+        signature_valid: verifying_key.algorithm.name() == envelope.algorithm && verifying_key.verify(&payload, &signature),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_signature_survives_reformatting_but_not_edits() {
+        let signing_key = PrivateKey::Ed25519(SigningKey::from_bytes(&[7u8; 32]));
+        let mut certificate = json!({
+            "version": "1.1",
+            "certificate_id": "WIPE_0000000000000001",
+            "wipe_details": { "bytes_written": 1048576, "throughput_mbps": 412.5, "errors": [] },
+            "signature": null,
+        });
+        certificate["signature"] = serde_json::to_value(sign(&certificate, &signing_key).await.unwrap()).unwrap();
+
+        // Pretty printing and member order do not change the payload
+        let reordered: Value = serde_json::from_str(&serde_json::to_string_pretty(&certificate).unwrap()).unwrap();
+        let check = verify(&reordered, &signing_key.public_key()).await.unwrap();
+        assert!(check.is_valid());
+
+        let mut tampered = certificate.clone();
+        tampered["wipe_details"]["bytes_written"] = json!(1048577);
+        let check = verify(&tampered, &signing_key.public_key()).await.unwrap();
+        assert!(!check.payload_hash_valid && !check.signature_valid);
+
+        let other_key = SigningKey::from_bytes(&[8u8; 32]).verifying_key().into();
+        assert!(!verify(&certificate, &other_key).await.unwrap().signature_valid);
+
+        certificate["signature"] = Value::Null;
+        assert!(verify(&certificate, &signing_key.public_key()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_station_keys_sign_with_their_own_algorithm() {
+        let station_key = crate::crypto::load_private_key(std::path::Path::new("tests/fixtures/pki/station-001.key.pem"), None).await.unwrap();
+        let mut certificate = json!({ "version": "2.1", "certificate_id": "WIPE_0000000000000002", "signature": null });
+        let envelope = sign(&certificate, &station_key).await.unwrap();
+        assert_eq!(envelope.algorithm, "RSA-PKCS1-SHA256");
+        certificate["signature"] = serde_json::to_value(envelope).unwrap();
+        assert!(verify(&certificate, &station_key.public_key()).await.unwrap().is_valid());
+
+        // An Ed25519 key never verifies an RSA envelope
+        let ed25519_key = SigningKey::from_bytes(&[7u8; 32]).verifying_key().into();
+        assert!(!verify(&certificate, &ed25519_key).await.unwrap().signature_valid);
+    }
+
+    #[test]
+    fn test_schema_versions() {
+        assert_eq!(Schema::of(&json!({"version": Schema::Basic.version()})).unwrap(), Schema::Basic);
+        assert_eq!(Schema::of(&json!({"version": Schema::Enhanced.version()})).unwrap(), Schema::Enhanced);
+        assert!(Schema::of(&json!({"version": "1.0"})).is_err());
+        assert!(Schema::of(&json!({"version": "3.0"})).is_err());
+        assert!(Schema::of(&json!({})).is_err());
+    }
+}
@@ -1,16 +1,39 @@
-use crate::error::{Result, SecureEraseError};
-use crate::crypto::{load_verifying_key, verify_signature, hash_data};
+use crate::error::Result;
+use crate::crypto::{load_public_key, PublicKey};
+use crate::crypto::crl::RevocationList;
+use crate::crypto::ocsp::{OcspCertStatus, OcspClient, OcspResponse};
+use crate::crypto::x509::{parse_certificate, pem_certificates, StationChain, TrustAnchors, ValidatedPath};
 use crate::certificates::{WipeCertificate, enhanced::EnhancedWipeCertificate};
-use std::path::Path;
+use crate::certificates::signing::{self, Schema};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
 use std::fs;
 use log::{info, warn, error};
 
 /// Certificate verifier for validating wipe certificates
 pub struct CertificateVerifier {
     pub public_key_path: Option<String>,
+    /// PEM file of the CA certificates station certificates must chain to
+    pub trust_anchors_path: Option<String>,
     pub verification_level: VerificationLevel,
     pub enable_ocsp: bool,
     pub enable_crl: bool,
+    /// Local CRL to check station certificates against
+    pub crl_path: Option<String>,
+    /// Directory of CRLs downloaded from `PKIInfo.crl_url`, named as at the end of the URL
+    pub crl_cache_dir: Option<String>,
+    /// Directory OCSP responses are cached in between verifications
+    pub ocsp_cache_dir: Option<String>,
+    /// Ask the responder at `PKIInfo.ocsp_url` when there is no stapled or cached response
+    pub ocsp_online: bool,
+}
+
+/// Station certificate a certificate was signed under
+struct Station {
+    chain: StationChain,
+    public_key: PublicKey,
+    /// Path to a trust anchor, if one was found
+    path: Option<ValidatedPath>,
 }
 
 /// Verification levels
@@ -26,113 +49,235 @@ impl CertificateVerifier {
     pub fn new() -> Self {
         Self {
             public_key_path: None,
+            trust_anchors_path: None,
             verification_level: VerificationLevel::Standard,
             enable_ocsp: true,
             enable_crl: true,
+            crl_path: None,
+            crl_cache_dir: None,
+            ocsp_cache_dir: None,
+            ocsp_online: true,
         }
     }
     
     pub fn with_public_key(mut self, public_key_path: String) -> Self { self.public_key_path = Some(public_key_path); self }
+    pub fn with_trust_anchors(mut self, trust_anchors_path: String) -> Self { self.trust_anchors_path = Some(trust_anchors_path); self }
     pub fn with_verification_level(mut self, level: VerificationLevel) -> Self { self.verification_level = level; self }
     pub fn with_ocsp(mut self, enabled: bool) -> Self { self.enable_ocsp = enabled; self }
     pub fn with_crl(mut self, enabled: bool) -> Self { self.enable_crl = enabled; self }
+    pub fn with_crl_file(mut self, crl_path: String) -> Self { self.crl_path = Some(crl_path); self }
+    pub fn with_crl_cache(mut self, crl_cache_dir: String) -> Self { self.crl_cache_dir = Some(crl_cache_dir); self }
+    pub fn with_ocsp_cache(mut self, ocsp_cache_dir: String) -> Self { self.ocsp_cache_dir = Some(ocsp_cache_dir); self }
+    pub fn with_ocsp_online(mut self, online: bool) -> Self { self.ocsp_online = online; self }
     
     pub async fn verify_certificate(&self, certificate_path: &Path) -> Result<VerificationResult> {
         info!("Verifying certificate: {}", certificate_path.display());
-        let certificate_data = fs::read(certificate_path)?;
-        if let Ok(enhanced_cert) = serde_json::from_slice::<EnhancedWipeCertificate>(&certificate_data) {
-            return self.verify_enhanced_certificate(&enhanced_cert).await;
+        let certificate: Value = serde_json::from_slice(&fs::read(certificate_path)?)?;
+        let mut result = VerificationResult::default();
+        let schema = match Schema::of(&certificate) { Ok(schema) => schema, Err(e) => { result.errors.push(e.to_string()); return Ok(result); } };
+        match schema {
+            Schema::Basic => self.verify_basic_certificate(&certificate, &serde_json::from_value(certificate.clone())?, result).await,
+            Schema::Enhanced => self.verify_enhanced_certificate(&certificate, &serde_json::from_value(certificate.clone())?, result).await,
         }
-        let certificate: WipeCertificate = serde_json::from_slice(&certificate_data)?;
-        self.verify_basic_certificate(&certificate).await
     }
     
-    async fn verify_basic_certificate(&self, certificate: &WipeCertificate) -> Result<VerificationResult> {
-        let mut result = VerificationResult::default();
-        let public_key_path = self.public_key_path.as_ref().map(|p| Path::new(p)).unwrap_or(Path::new("public_key.pem"));
-        let verifying_key = match load_verifying_key(public_key_path).await { Ok(key) => key, Err(e) => { result.errors.push(format!("Failed to load public key: {}", e)); return Ok(result); } };
-        match self.verify_signature_basic(certificate, &verifying_key).await { Ok(valid) => { result.signature_valid = valid; if !valid { result.errors.push("Invalid signature".to_string()); } }, Err(e) => { result.errors.push(format!("Signature verification failed: {}", e)); } }
-        match self.verify_hash_basic(certificate).await { Ok(valid) => { result.hash_valid = valid; if !valid { result.warnings.push("Hash verification failed".to_string()); } }, Err(e) => { result.warnings.push(format!("Hash verification error: {}", e)); } }
-        result.compliance_valid = certificate.verification_passed && certificate.errors.is_empty();
+    async fn verify_basic_certificate(&self, json: &Value, certificate: &WipeCertificate, mut result: VerificationResult) -> Result<VerificationResult> {
+        if !self.verify_signature(json, None, &mut result).await { return Ok(result); }
+        result.compliance_valid = certificate.wipe_details.verification_passed && certificate.wipe_details.errors.is_empty();
         result.is_valid = result.signature_valid && result.hash_valid;
         Ok(result)
     }
     
-    async fn verify_enhanced_certificate(&self, certificate: &EnhancedWipeCertificate) -> Result<VerificationResult> {
-        let mut result = VerificationResult::default();
-        let public_key_path = self.public_key_path.as_ref().map(|p| Path::new(p)).unwrap_or(Path::new("public_key.pem"));
-        let verifying_key = match load_verifying_key(public_key_path).await { Ok(key) => key, Err(e) => { result.errors.push(format!("Failed to load public key: {}", e)); return Ok(result); } };
-        match self.verify_signature_enhanced(certificate, &verifying_key).await { Ok(valid) => { result.signature_valid = valid; if !valid { result.errors.push("Invalid signature".to_string()); } }, Err(e) => { result.errors.push(format!("Signature verification failed: {}", e)); } }
-        match self.verify_hash_enhanced(certificate).await { Ok(valid) => { result.hash_valid = valid; if !valid { result.warnings.push("Hash verification failed".to_string()); } }, Err(e) => { result.warnings.push(format!("Hash verification error: {}", e)); } }
+    async fn verify_enhanced_certificate(&self, json: &Value, certificate: &EnhancedWipeCertificate, mut result: VerificationResult) -> Result<VerificationResult> {
+        // A certificate signed by a station key is checked with the key of its embedded station certificate
+        let station = match &certificate.pki.station_certificate_pem {
+            Some(pem) => match self.validate_station_chain(certificate, pem, &mut result) { Some(station) => Some(station), None => return Ok(result) },
+            None => None,
+        };
+        if !self.verify_signature(json, station.as_ref().map(|s| s.public_key.clone()), &mut result).await { return Ok(result); }
         result.compliance_valid = self.check_compliance_enhanced(certificate);
-        if self.enable_ocsp { self.check_ocsp_status(certificate, &mut result).await; }
-        if self.enable_crl { self.check_crl_status(certificate, &mut result).await; }
-        result.is_valid = result.signature_valid && result.hash_valid && result.compliance_valid;
+        if let Some(station) = &station {
+            if self.enable_ocsp { self.check_ocsp_status(certificate, station, &mut result).await; }
+            if self.enable_crl { self.check_crl_status(certificate, station, &mut result); }
+        }
+        result.is_valid = result.signature_valid && result.hash_valid && result.compliance_valid
+            && (result.chain_valid || station.is_none()) && !result.revoked;
         Ok(result)
     }
     
-    async fn verify_signature_basic(&self, certificate: &WipeCertificate, verifying_key: &ed25519_dalek::VerifyingKey) -> Result<bool> {
-        let signature_bytes = hex::decode(&certificate.signature).map_err(|_| SecureEraseError::CertificateVerificationFailed("Invalid signature format".to_string()))?;
-        let signature = ed25519_dalek::Signature::from_bytes(&signature_bytes).map_err(|_| SecureEraseError::CertificateVerificationFailed("Invalid signature".to_string()))?;
-        let mut cert_copy = certificate.clone();
-        cert_copy.verification.hash = "".to_string();
-        cert_copy.signature = "".to_string();
-        let data_to_verify = serde_json::to_string(&cert_copy)?;
-        verify_signature(data_to_verify.as_bytes(), &signature, verifying_key).await
-    }
-    
-    async fn verify_signature_enhanced(&self, certificate: &EnhancedWipeCertificate, verifying_key: &ed25519_dalek::VerifyingKey) -> Result<bool> {
-        let signature_bytes = hex::decode(&certificate.signature).map_err(|_| SecureEraseError::CertificateVerificationFailed("Invalid signature format".to_string()))?;
-        let signature = ed25519_dalek::Signature::from_bytes(&signature_bytes).map_err(|_| SecureEraseError::CertificateVerificationFailed("Invalid signature".to_string()))?;
-        let mut cert_copy = certificate.clone();
-        cert_copy.verification.hash = "".to_string();
-        cert_copy.signature = "".to_string();
-        let data_to_verify = serde_json::to_string(&cert_copy)?;
-        verify_signature(data_to_verify.as_bytes(), &signature, verifying_key).await
-    }
-    
-    async fn verify_hash_basic(&self, certificate: &WipeCertificate) -> Result<bool> {
-        let mut cert_copy = certificate.clone();
-        cert_copy.verification.hash = "".to_string();
-        cert_copy.signature = "".to_string();
-        let data_to_hash = serde_json::to_string(&cert_copy)?;
-        let calculated_hash = hash_data(data_to_hash.as_bytes()).await?;
-        Ok(calculated_hash == certificate.verification.hash)
+    /// Build the path from the station certificate to a trust anchor as of the certificate's timestamp.
+    ///
+    /// Returns the station when its certificate can be read, even if its path
+    /// does not validate, so the signature is still reported on.
+    fn validate_station_chain(&self, certificate: &EnhancedWipeCertificate, station_pem: &str, result: &mut VerificationResult) -> Option<Station> {
+        let chain = StationChain::from_pem(station_pem).and_then(|chain| Ok(StationChain {
+            intermediates: match &certificate.pki.ca_chain_pem { Some(pem) => pem_certificates(pem)?, None => Vec::new() },
+            ..chain
+        }));
+        let (chain, public_key) = match chain.and_then(|chain| { let key = chain.public_key()?; Ok((chain, key)) }) {
+            Ok(station) => station,
+            Err(e) => { result.errors.push(format!("Invalid station certificate: {}", e)); return None; }
+        };
+        result.verification_details.station_certificate = parse_certificate(&chain.leaf).ok().map(|leaf| leaf.subject().to_string());
+        
+        let mut station = Station { chain, public_key, path: None };
+        let Some(anchors_path) = &self.trust_anchors_path else {
+            result.errors.push("Certificate is signed by a station certificate but no trust anchor is configured".to_string());
+            return Some(station);
+        };
+        let anchors = match TrustAnchors::load(Path::new(anchors_path)) { Ok(anchors) => anchors, Err(e) => { result.errors.push(format!("Failed to load trust anchors: {}", e)); return Some(station); } };
+        match anchors.validate(&station.chain, certificate.timestamp as i64) {
+            Ok(path) => {
+                result.chain_valid = true;
+                result.verification_details.certificate_path = path.subjects.clone();
+                station.path = Some(path);
+            }
+            Err(e) => result.errors.push(e.to_string()),
+        }
+        Some(station)
     }
     
-    async fn verify_hash_enhanced(&self, certificate: &EnhancedWipeCertificate) -> Result<bool> {
-        let mut cert_copy = certificate.clone();
-        cert_copy.verification.hash = "".to_string();
-        cert_copy.signature = "".to_string();
-        let data_to_hash = serde_json::to_string(&cert_copy)?;
-        let calculated_hash = hash_data(data_to_hash.as_bytes()).await?;
-        Ok(calculated_hash == certificate.verification.hash)
+    /// Check the certificate against its signature envelope with `key`, or the configured
+    /// public key if none is given; false if that key could not be loaded
+    async fn verify_signature(&self, json: &Value, key: Option<PublicKey>, result: &mut VerificationResult) -> bool {
+        let verifying_key = match key {
+            Some(key) => key,
+            None => {
+                let public_key_path = self.public_key_path.as_ref().map(|p| Path::new(p)).unwrap_or(Path::new("public_key.pem"));
+                match load_public_key(public_key_path).await { Ok(key) => key, Err(e) => { result.errors.push(format!("Failed to load public key: {}", e)); return false; } }
+            }
+        };
+        match signing::verify(json, &verifying_key).await {
+            Ok(check) => {
+                result.signature_valid = check.signature_valid;
+                result.hash_valid = check.payload_hash_valid;
+                if !check.signature_valid { result.errors.push("Invalid signature".to_string()); }
+                if !check.payload_hash_valid { result.warnings.push("Hash verification failed".to_string()); }
+            }
+            Err(e) => { result.errors.push(format!("Signature verification failed: {}", e)); }
+        }
+        true
     }
     
     fn check_compliance_enhanced(&self, certificate: &EnhancedWipeCertificate) -> bool {
         certificate.wipe_details.verification_passed && certificate.wipe_details.errors.is_empty() && !certificate.compliance.standards.is_empty()
     }
 
-    async fn check_ocsp_status(&self, certificate: &EnhancedWipeCertificate, result: &mut VerificationResult) {
-        if let Some(url) = &certificate.pki.ocsp_url {
-            // Placeholder: perform OCSP request for the station cert (future)
-            result.verification_details.ocsp_checked = true;
+    /// Check the station certificate's OCSP status: the stapled response if there is one,
+    /// else a cached response or, when online, the responder at `PKIInfo.ocsp_url`.
+    ///
+    /// A stapled response that does not verify is an error; a responder that
+    /// cannot be asked only leaves revocation unchecked.
+    async fn check_ocsp_status(&self, certificate: &EnhancedWipeCertificate, station: &Station, result: &mut VerificationResult) {
+        if certificate.pki.ocsp_response.is_none() && certificate.pki.ocsp_url.is_none() { return; }
+        let Some(issuer) = station.path.as_ref().and_then(|path| path.certificates.get(1)) else {
+            result.warnings.push("OCSP not checked: the station certificate has no trusted issuer".to_string());
+            return;
+        };
+        let signed_at = certificate.timestamp as i64;
+        let response = match (&certificate.pki.ocsp_response, &certificate.pki.ocsp_url) {
+            (Some(stapled), _) => {
+                use base64::Engine;
+                let response = base64::engine::general_purpose::STANDARD.decode(stapled)
+                    .map_err(|e| e.to_string())
+                    .and_then(|der| OcspResponse::verify(&der, &station.chain.leaf, issuer, None).map_err(|e| e.to_string()));
+                match response {
+                    Ok(response) => response,
+                    Err(e) => { result.errors.push(format!("Stapled OCSP response is invalid: {}", e)); return; }
+                }
+            }
+            (None, Some(url)) => {
+                let client = OcspClient { cache_dir: self.ocsp_cache_dir.as_ref().map(PathBuf::from) };
+                let response = if self.ocsp_online {
+                    client.status(url, &station.chain.leaf, issuer).await.map_err(|e| e.to_string())
+                } else {
+                    client.cached(&station.chain.leaf, issuer).ok_or_else(|| format!("no cached response from {} and verifying offline", url))
+                };
+                match response {
+                    Ok(response) => response,
+                    Err(e) => { result.warnings.push(format!("OCSP not checked: {}", e)); return; }
+                }
+            }
+            (None, None) => return,
+        };
+        
+        let details = &mut result.verification_details;
+        details.ocsp_checked = true;
+        details.ocsp_stapled = certificate.pki.ocsp_response.is_some();
+        details.ocsp_status = Some(response.status.describe().to_string());
+        details.ocsp_this_update = Some(response.this_update as u64);
+        details.ocsp_next_update = response.next_update.map(|time| time as u64);
+        match response.status {
+            OcspCertStatus::Revoked(revoked_at) if revoked_at <= signed_at => {
+                result.revoked = true;
+                result.errors.push(format!("OCSP responder reports the station certificate revoked on {}, before the wipe was signed", format_time(revoked_at)));
+            }
+            OcspCertStatus::Revoked(revoked_at) => {
+                result.warnings.push(format!("OCSP responder reports the station certificate revoked on {}, after the wipe was signed", format_time(revoked_at)));
+            }
+            OcspCertStatus::Unknown => result.warnings.push(format!("OCSP responder {} does not know the station certificate", response.responder)),
+            OcspCertStatus::Good => {}
+        }
+        if details.ocsp_stapled && !response.is_fresh(signed_at) {
+            result.warnings.push(format!("Stapled OCSP response from {} was stale when the wipe was signed", format_time(response.this_update)));
         }
     }
 
-    async fn check_crl_status(&self, certificate: &EnhancedWipeCertificate, result: &mut VerificationResult) {
-        if let Some(url) = &certificate.pki.crl_url {
-            // Placeholder: fetch CRL and check revocation (future)
-            result.verification_details.crl_checked = true;
+    /// Check the station certificate against the configured CRL, or the cached copy of `PKIInfo.crl_url`.
+    ///
+    /// The CRL must be signed by the CA that issued the station certificate,
+    /// which is only known once its path to a trust anchor validated.
+    fn check_crl_status(&self, certificate: &EnhancedWipeCertificate, station: &Station, result: &mut VerificationResult) {
+        let crl_path = match (&self.crl_path, &certificate.pki.crl_url, &self.crl_cache_dir) {
+            (Some(path), _, _) => Path::new(path).to_path_buf(),
+            (None, Some(url), Some(dir)) => Path::new(dir).join(url.trim_end_matches('/').rsplit('/').next().unwrap_or_default()),
+            (None, Some(url), None) => { result.warnings.push(format!("Revocation not checked: no local copy of the CRL at {}", url)); return; }
+            (None, None, _) => return,
+        };
+        let Some(issuer) = station.path.as_ref().and_then(|path| path.certificates.get(1)) else {
+            result.warnings.push("Revocation not checked: the station certificate has no trusted issuer".to_string());
+            return;
+        };
+        let status = match RevocationList::load(&crl_path).and_then(|crl| crl.status(&station.chain.leaf, issuer)) {
+            Ok(status) => status,
+            Err(e) => { result.errors.push(format!("CRL check failed for {}: {}", crl_path.display(), e)); return; }
+        };
+        
+        let details = &mut result.verification_details;
+        details.crl_checked = true;
+        details.crl_this_update = Some(status.this_update as u64);
+        details.crl_next_update = status.next_update.map(|time| time as u64);
+        let signed_at = certificate.timestamp as i64;
+        if status.revoked_as_of(signed_at) {
+            result.revoked = true;
+            result.errors.push(format!("Station certificate was revoked on {}, before the wipe was signed", format_time(status.revoked_at.unwrap_or_default())));
+        } else if let Some(revoked_at) = status.revoked_at {
+            result.warnings.push(format!("Station certificate was revoked on {}, after the wipe was signed", format_time(revoked_at)));
+        }
+        if status.this_update < signed_at {
+            result.warnings.push(format!("CRL was issued on {}, before the wipe; later revocations are not covered", format_time(status.this_update)));
+        }
+        if status.next_update.is_some_and(|next_update| next_update < chrono::Utc::now().timestamp()) {
+            result.warnings.push(format!("CRL expired on {}; fetch a current copy", format_time(status.next_update.unwrap_or_default())));
         }
     }
 }
 
+fn format_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct VerificationResult {
     pub is_valid: bool,
     pub signature_valid: bool,
     pub hash_valid: bool,
+    /// The station certificate chains to a trust anchor
+    pub chain_valid: bool,
+    /// A CRL or OCSP response reports the station certificate revoked when the wipe was signed
+    pub revoked: bool,
     pub compliance_valid: bool,
     pub warnings: Vec<String>,
     pub errors: Vec<String>,
@@ -147,6 +292,22 @@ pub struct VerificationDetails {
     pub verification_ratio: f64,
     pub ocsp_checked: bool,
     pub crl_checked: bool,
+    /// The OCSP response checked was stapled into the certificate
+    pub ocsp_stapled: bool,
+    /// Certificate status the OCSP responder reported
+    pub ocsp_status: Option<String>,
+    /// thisUpdate of the OCSP response checked, Unix seconds
+    pub ocsp_this_update: Option<u64>,
+    /// nextUpdate of the OCSP response checked, Unix seconds
+    pub ocsp_next_update: Option<u64>,
+    /// thisUpdate of the CRL checked, Unix seconds
+    pub crl_this_update: Option<u64>,
+    /// nextUpdate of the CRL checked, Unix seconds
+    pub crl_next_update: Option<u64>,
+    /// Subject of the station certificate the certificate was signed under
+    pub station_certificate: Option<String>,
+    /// Subjects from the station certificate to its trust anchor
+    pub certificate_path: Vec<String>,
 }
 
 impl VerificationResult {
@@ -158,8 +319,27 @@ impl VerificationResult {
         println!("  Signature: {}", if self.signature_valid { "✓ Valid" } else { "✗ Invalid" });
         println!("  Hash: {}", if self.hash_valid { "✓ Valid" } else { "✗ Invalid" });
         println!("  Compliance: {}", if self.compliance_valid { "✓ Valid" } else { "✗ Invalid" });
+        if let Some(station) = &self.verification_details.station_certificate {
+            if self.chain_valid { println!("  Certificate Chain: ✓ {}", self.verification_details.certificate_path.join(" → ")); } else { println!("  Certificate Chain: ✗ {} not trusted", station); }
+        }
         println!("  OCSP Checked: {}", if self.verification_details.ocsp_checked { "Yes" } else { "No" });
+        if let Some(status) = &self.verification_details.ocsp_status {
+            println!("  OCSP Status: {}{}", status, if self.verification_details.ocsp_stapled { " (stapled)" } else { "" });
+        }
+        if let Some(this_update) = self.verification_details.ocsp_this_update {
+            println!("  OCSP Updated: {}", format_time(this_update as i64));
+        }
+        if let Some(next_update) = self.verification_details.ocsp_next_update {
+            println!("  OCSP Next Update: {}", format_time(next_update as i64));
+        }
         println!("  CRL Checked: {}", if self.verification_details.crl_checked { "Yes" } else { "No" });
+        if let Some(this_update) = self.verification_details.crl_this_update {
+            println!("  CRL Issued: {}", format_time(this_update as i64));
+        }
+        if let Some(next_update) = self.verification_details.crl_next_update {
+            println!("  CRL Next Update: {}", format_time(next_update as i64));
+        }
+        if self.revoked { println!("  Revocation: ✗ Revoked"); }
         if !self.warnings.is_empty() { println!("\nWarnings:"); for w in &self.warnings { println!("  ⚠ {}", w); } }
         if !self.errors.is_empty() { println!("\nErrors:"); for e in &self.errors { println!("  ✗ {}", e); } }
         println!("\nVerification Details:");
@@ -1,16 +1,46 @@
 use crate::error::{Result, SecureEraseError};
-use crate::core::{StorageDevice, DeviceType, EraseMode, WipeResult, device_manager::DeviceManager, advanced::AdvancedWipeEngine};
+use crate::core::{StorageDevice, WipeResult, advanced::AdvancedWipeEngine};
+use crate::core::methods::{MethodRegistry, WipeMethod};
+use crate::core::batch::{self, BatchJob, BatchOptions, BatchStatus, BatchSummary};
+use crate::core::checkpoint::{CancellationToken, Checkpoint};
+use crate::core::hidden_areas::HiddenAreaRestore;
+use crate::core::pipeline::WipePipeline;
+use crate::core::plan::{PlanFormat, WipePlan};
+use crate::core::progress::{progress_channel, ProgressEvent, ProgressReceiver, WipePhase};
+use crate::core::safety::{self, SafetyOptions};
+use crate::utils::{ProgressBar, Utils};
 use crate::certificates::{enhanced::EnhancedCertificateGenerator, verifier::CertificateVerifier};
-use std::path::PathBuf;
+use crate::crypto::{KeyFormat, SignatureAlgorithm};
+use crate::crypto::ocsp::OcspClient;
+use crate::crypto::x509::StationChain;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use log::{info, warn, error};
 
-/// List available storage devices
-pub async fn list_devices(detailed: bool) -> Result<()> {
+/// How wipe certificates are signed
+#[derive(Debug, Clone)]
+pub struct CertificateSigning {
+    pub signing_key: PathBuf,
+    /// PEM file with the signing key's X.509 certificate followed by its CA chain
+    pub station_certificate: Option<PathBuf>,
+    /// Staple the station certificate's OCSP status into each certificate
+    pub staple_ocsp: bool,
+    /// Passphrase of an encrypted `signing_key`
+    pub passphrase: Option<String>,
+}
+
+/// List available storage devices, saving them to `record` if given
+pub async fn list_devices(pipeline: &WipePipeline, detailed: bool, record: Option<PathBuf>) -> Result<()> {
     info!("Scanning for available storage devices...");
     
-    let mut device_manager = DeviceManager::new();
-    device_manager.scan_devices().await?;
-    let devices = device_manager.get_devices();
+    let devices = match &record {
+        Some(path) => pipeline.record(path)?,
+        None => pipeline.devices()?,
+    };
+    if let Some(path) = &record {
+        println!("Recorded {} device(s) to {}", devices.len(), path.display());
+    }
     
     if devices.is_empty() {
         println!("No storage devices found.");
@@ -35,11 +65,53 @@ pub async fn list_devices(detailed: bool) -> Result<()> {
             }
             println!("   Secure Erase: {}", device.supports_secure_erase);
             println!("   TRIM Support: {}", device.supports_trim);
-            
+
+            let attributes = &device.attributes;
+            if attributes.logical_block_size > 0 {
+                println!("   Block Size: {} logical / {} physical", attributes.logical_block_size, attributes.physical_block_size);
+            }
+            if let Some(transport) = &attributes.transport {
+                println!("   Transport: {}{}", transport, if attributes.removable { " (removable)" } else { "" });
+            }
+            if let Some(wwn) = &attributes.wwn {
+                println!("   WWN: {}", wwn);
+            }
+            if let Some(firmware) = &attributes.firmware {
+                println!("   Firmware: {}", firmware);
+            }
+            if attributes.offline {
+                println!("   Offline: taken offline by the operating system");
+            }
+
+            let layout = &device.layout;
+            if !layout.system_volumes.is_empty() {
+                println!("   System Volumes: {}", layout.system_volumes.join(", "));
+            }
+            for mountpoint in &layout.mountpoints {
+                println!("   Mounted at: {}", mountpoint.display());
+            }
+            if !layout.partitions.is_empty() {
+                println!("   Partitions: {}", layout.partitions.len());
+                for partition in &layout.partitions {
+                    let mountpoints: Vec<String> = partition.mountpoints.iter().map(|m| m.display().to_string()).collect();
+                    println!("     - {}: {} MB from sector {}{}", partition.path.display(), partition.size / (1024 * 1024),
+                             partition.start_sector,
+                             if mountpoints.is_empty() { String::new() } else { format!(", mounted at {}", mountpoints.join(", ")) });
+                }
+            }
+            let holders = layout.all_holders();
+            if !holders.is_empty() {
+                println!("   Used by:");
+                for holder in holders {
+                    println!("     - {} ({:?}{})", holder.path.display(), holder.kind,
+                             holder.dm_name.as_ref().map(|name| format!(", {}", name)).unwrap_or_default());
+                }
+            }
+
             if !device.hidden_areas.is_empty() {
                 println!("   Hidden Areas: {}", device.hidden_areas.len());
                 for area in &device.hidden_areas {
-                    println!("     - {}: {} sectors", area.description, area.size);
+                    println!("     - {}: {} sectors from LBA {}", area.description, area.size, area.start_lba);
                 }
             }
         }
@@ -50,46 +122,140 @@ pub async fn list_devices(detailed: bool) -> Result<()> {
 }
 
 /// Securely erase a storage device
+#[allow(clippy::too_many_arguments)]
 pub async fn wipe_device(
+    pipeline: &WipePipeline,
     device_path: PathBuf,
     mode_str: String,
-    generate_certificate: bool,
+    methods_path: Option<PathBuf>,
+    restore_hidden: Option<HiddenAreaRestore>,
+    sed_credential: Option<String>,
+    safety: SafetyOptions,
+    dry_run: Option<PlanFormat>,
+    certificate: Option<CertificateSigning>,
     output_dir: PathBuf,
+    resume: bool,
+    bandwidth_limit: Option<u64>,
 ) -> Result<()> {
     info!("Starting secure erase operation...");
     
-    // Parse erase mode
-    let mode = match mode_str.to_lowercase().as_str() {
-        "quick" => EraseMode::Quick,
-        "full" => EraseMode::Full,
-        "advanced" => EraseMode::Advanced,
-        _ => return Err(SecureEraseError::InvalidEraseMode(mode_str)),
+    let device = pipeline.find(&device_path)?;
+    
+    // Resolve the wipe method, from the journal when resuming
+    let journal_path = Checkpoint::journal_path(&output_dir, &device);
+    let checkpoint = if resume {
+        let checkpoint = Checkpoint::load(&journal_path)?;
+        checkpoint.ensure_same_device(&device)?;
+        Some(checkpoint)
+    } else {
+        if journal_path.exists() {
+            warn!("Found journal of an interrupted wipe at {}; starting over (use --resume to continue it)",
+                  journal_path.display());
+        }
+        None
+    };
+    let method = match &checkpoint {
+        Some(checkpoint) => checkpoint.method.clone(),
+        None => resolve_method(&mode_str, methods_path.as_deref(), restore_hidden)?,
     };
     
-    // Find the device
-    let mut device_manager = DeviceManager::new();
-    device_manager.scan_devices().await?;
+    let mut wipe_engine = AdvancedWipeEngine::new().with_journal(journal_path.clone());
+    if let Some(limit) = bandwidth_limit {
+        wipe_engine = wipe_engine.with_bandwidth_limit(limit);
+    }
+    if let Some(credential) = sed_credential {
+        wipe_engine = wipe_engine.with_sed_credential(credential);
+    }
     
-    let device = device_manager.find_device(&device_path)
-        .ok_or_else(|| SecureEraseError::DeviceNotFound(device_path.display().to_string()))?;
+    if let Some(format) = dry_run {
+        return print_plans(pipeline, &wipe_engine, std::slice::from_ref(&device), &method, format);
+    }
     
     // Confirm the operation
     println!("WARNING: This operation will permanently destroy all data on the device!");
     println!("Device: {} ({})", device.name, device_path.display());
     println!("Size: {} GB", device.size / (1024 * 1024 * 1024));
-    println!("Mode: {:?}", mode);
+    println!("Method: {} - {}", method.name, method.description);
+    if let Some(checkpoint) = &checkpoint {
+        let pass = checkpoint.current_pass.as_ref()
+            .map(|current| (current.pass_number, current.offset))
+            .unwrap_or((checkpoint.passes.len() as u32 + 1, 0));
+        println!("Resuming at pass {} from {}", pass.0, Utils::format_bytes(pass.1));
+    }
     println!();
     
+    // Refuse devices the running system depends on, then have the operator confirm
+    let clearance = pipeline.preflight(&device, &safety)?;
+    if let Some(expected) = &clearance.confirm_with {
+        confirm_interactively(&device, expected)?;
+    }
+    
     // Perform the wipe
     info!("Starting wipe operation on device: {}", device_path.display());
-    let wipe_engine = AdvancedWipeEngine::new();
-    let result = wipe_engine.secure_erase_with_verification(device, mode).await?;
+    let (reporter, receiver) = progress_channel();
+    let progress_task = tokio::spawn(render_progress(receiver));
+    
+    // Ctrl-C stops at the next chunk boundary and leaves the journal behind
+    let cancellation = CancellationToken::new();
+    let ctrl_c_token = cancellation.clone();
+    let ctrl_c_task = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c_token.cancel();
+        }
+    });
+    
+    let wipe_engine = wipe_engine
+        .with_progress(reporter)
+        .with_cancellation(cancellation);
+    // The pipeline drops the engine when done, which closes the progress stream
+    let result = pipeline.wipe(wipe_engine, &device, &method, clearance, checkpoint).await;
+    ctrl_c_task.abort();
+    let _ = progress_task.await;
+    
+    let result = match result {
+        Err(SecureEraseError::Cancelled) => {
+            println!();
+            println!("Wipe interrupted. Progress saved to {}", journal_path.display());
+            println!("Run the same command with --resume to continue.");
+            return Err(SecureEraseError::Cancelled);
+        }
+        other => other?,
+    };
     
     // Display results
     println!("Wipe operation completed!");
     println!("Duration: {} seconds", result.duration_seconds);
     println!("Bytes written: {} GB", result.bytes_written / (1024 * 1024 * 1024));
+    for pass in &result.passes {
+        println!("  Pass {}: {} written in {} at {}/s ({} retries)",
+                 pass.pass_number,
+                 Utils::format_bytes(pass.stats.bytes_written),
+                 Utils::format_duration(pass.stats.write_seconds as u64),
+                 Utils::format_bytes(pass.throughput_bytes_per_sec as u64),
+                 pass.stats.retries);
+    }
     println!("Verification: {}", if result.verification_passed { "PASSED" } else { "FAILED" });
+    if !result.unwritable_ranges.is_empty() {
+        println!("Sanitization: PARTIAL ({} in {} unwritable LBA range(s) not overwritten)",
+                 Utils::format_bytes(result.unwritable_bytes), result.unwritable_ranges.len());
+    }
+    if !result.interruptions.is_empty() {
+        println!("Interrupted and resumed {} time(s)", result.interruptions.len());
+    }
+    for status in &result.hidden_areas {
+        let outcome = if status.wiped {
+            "wiped"
+        } else if status.restored {
+            "restored but NOT wiped"
+        } else {
+            "NOT wiped"
+        };
+        println!("{}: {} sectors from LBA {} {}", status.area.description, status.area.size, status.area.start_lba, outcome);
+    }
+    for record in &result.crypto_erases {
+        println!("Cryptographic erase: {:?} on {:?} drive ({} TCG method calls)",
+                 record.method, record.subsystem, record.exchanges.len());
+    }
     
     if !result.errors.is_empty() {
         warn!("Errors encountered during wipe:");
@@ -99,19 +265,8 @@ pub async fn wipe_device(
     }
     
     // Generate certificate if requested
-    if generate_certificate {
-        info!("Generating enhanced wipe certificate...");
-        let cert_path = output_dir.join("wipe_certificate.json");
-        let pdf_path = output_dir.join("wipe_certificate.pdf");
-        let private_key_path = PathBuf::from("private_key.pem");
-        
-        // Create enhanced certificate generator
-        let cert_generator = EnhancedCertificateGenerator::new(
-            "Secure Disk Erasure Tool".to_string(),
-            "Your Organization".to_string()
-        );
-        
-        cert_generator.generate_enhanced_certificate(&result, &cert_path, &pdf_path, &private_key_path).await?;
+    if let Some(signing) = &certificate {
+        let (cert_path, pdf_path) = write_certificate(&result, &output_dir, "wipe_certificate", signing).await?;
         
         println!("Enhanced certificate generated:");
         println!("  JSON: {}", cert_path.display());
@@ -121,10 +276,318 @@ pub async fn wipe_device(
     Ok(())
 }
 
+/// Print what wiping `devices` with `method` would do, without touching them.
+///
+/// JSON output is a single plan for one device and an array for several.
+fn print_plans(pipeline: &WipePipeline, engine: &AdvancedWipeEngine, devices: &[StorageDevice], method: &WipeMethod, format: PlanFormat) -> Result<()> {
+    let plans: Vec<WipePlan> = devices.iter()
+        .map(|device| pipeline.plan(engine, device, method))
+        .collect();
+    
+    let output = match (format, plans.as_slice()) {
+        (_, [plan]) => plan.render(format)?,
+        (PlanFormat::Json, _) => serde_json::to_string_pretty(&plans)?,
+        (PlanFormat::Text, _) => plans.iter().map(WipePlan::to_text).collect::<Vec<_>>().join("\n\n"),
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+/// Securely erase several devices at once.
+///
+/// Targets are the given paths plus every device matching `selector`. Each
+/// device keeps its own journal and certificate; a summary of the whole batch
+/// is written to `batch_summary.json` in the output directory.
+#[allow(clippy::too_many_arguments)]
+pub async fn wipe_devices(
+    pipeline: &WipePipeline,
+    device_paths: Vec<PathBuf>,
+    selector: Option<String>,
+    mode_str: String,
+    methods_path: Option<PathBuf>,
+    restore_hidden: Option<HiddenAreaRestore>,
+    sed_credential: Option<String>,
+    safety: SafetyOptions,
+    dry_run: Option<PlanFormat>,
+    certificate: Option<CertificateSigning>,
+    output_dir: PathBuf,
+    resume: bool,
+    options: BatchOptions,
+) -> Result<()> {
+    info!("Starting batch secure erase operation...");
+    
+    // Named devices first, then the ones matching the selector, without duplicates
+    let devices = pipeline.select(&device_paths, selector.as_deref())?;
+    
+    let method = resolve_method(&mode_str, methods_path.as_deref(), restore_hidden)?;
+    
+    // Every device's engine starts from these settings
+    let configured_engine = || {
+        let engine = AdvancedWipeEngine::new();
+        match &sed_credential {
+            Some(credential) => engine.with_sed_credential(credential.clone()),
+            None => engine,
+        }
+    };
+    
+    if let Some(format) = dry_run {
+        return print_plans(pipeline, &configured_engine(), &devices, &method, format);
+    }
+    
+    println!("WARNING: This operation will permanently destroy all data on {} device(s)!", devices.len());
+    for device in &devices {
+        println!("  {} ({}, {} GB)", device.path.display(), device.name, device.size / (1024 * 1024 * 1024));
+    }
+    println!("Method: {} - {}", method.name, method.description);
+    println!("Concurrency: {} device(s) at a time", options.max_concurrent);
+    if let Some(limit) = options.bandwidth_limit {
+        println!("Bandwidth limit: {}/s per device", Utils::format_bytes(limit));
+    }
+    println!();
+    
+    // Every device has to pass before any of them is touched
+    let clearances = devices.iter()
+        .map(|device| pipeline.preflight(device, &safety))
+        .collect::<Result<Vec<_>>>()?;
+    for (device, clearance) in devices.iter().zip(&clearances) {
+        if let Some(expected) = &clearance.confirm_with {
+            confirm_interactively(device, expected)?;
+        }
+    }
+    
+    let (reporter, receiver) = progress_channel();
+    let progress_task = tokio::spawn(render_batch_progress(receiver));
+    
+    let cancellation = CancellationToken::new();
+    let ctrl_c_token = cancellation.clone();
+    let ctrl_c_task = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c_token.cancel();
+        }
+    });
+    
+    let mut jobs = Vec::with_capacity(devices.len());
+    for (device, clearance) in devices.into_iter().zip(clearances) {
+        let journal_path = Checkpoint::journal_path(&output_dir, &device);
+        let checkpoint = if journal_path.exists() {
+            if resume {
+                let checkpoint = Checkpoint::load(&journal_path)?;
+                checkpoint.ensure_same_device(&device)?;
+                println!("Resuming {} from its journal", device.path.display());
+                Some(checkpoint)
+            } else {
+                warn!("Found journal of an interrupted wipe at {}; starting over (use --resume to continue it)",
+                      journal_path.display());
+                None
+            }
+        } else {
+            None
+        };
+        let engine = configured_engine()
+            .with_progress(reporter.clone())
+            .with_cancellation(cancellation.clone())
+            .with_journal(journal_path)
+            .with_safety_overrides(clearance.overrides);
+        jobs.push(BatchJob { device, engine, resume: checkpoint });
+    }
+    // The engines hold the remaining senders, so the stream ends with the batch
+    drop(reporter);
+    
+    let started_at = SystemTime::now();
+    let outcomes = batch::run_batch(jobs, &method, &options).await;
+    ctrl_c_task.abort();
+    let _ = progress_task.await;
+    
+    let mut summary = BatchSummary::new(&method, started_at, &outcomes);
+    if let Some(signing) = &certificate {
+        // Certificates are generated one at a time once all wipes are done
+        for (outcome, entry) in outcomes.iter().zip(summary.devices.iter_mut()) {
+            if let Some(result) = &outcome.result {
+                let stem = format!("wipe_certificate_{}", outcome.device.file_id());
+                match write_certificate(result, &output_dir, &stem, signing).await {
+                    Ok((cert_path, _)) => entry.certificate_path = Some(cert_path),
+                    Err(e) => error!("Certificate generation failed for {}: {}", outcome.device.path.display(), e),
+                }
+            }
+        }
+    }
+    
+    std::fs::create_dir_all(&output_dir)?;
+    let summary_path = output_dir.join("batch_summary.json");
+    std::fs::write(&summary_path, serde_json::to_string_pretty(&summary)?)?;
+    
+    println!();
+    println!("Batch wipe finished: {} succeeded, {} failed, {} cancelled",
+             summary.succeeded, summary.failed, summary.cancelled);
+    for entry in &summary.devices {
+        let status = match entry.status {
+            BatchStatus::Succeeded => "OK",
+            BatchStatus::Failed => "FAILED",
+            BatchStatus::Cancelled => "CANCELLED",
+        };
+        print!("  {:<24} {:<9}", entry.path.display(), status);
+        if let Some(error) = &entry.error {
+            print!(" {}", error);
+        } else if let Some(cert_path) = &entry.certificate_path {
+            print!(" {}", cert_path.display());
+        }
+        println!();
+    }
+    println!("Summary: {}", summary_path.display());
+    
+    if summary.cancelled > 0 {
+        println!("Run the same command with --resume to continue the interrupted wipes.");
+        return Err(SecureEraseError::Cancelled);
+    }
+    if summary.failed > 0 {
+        return Err(SecureEraseError::WipeFailed(
+            format!("{} of {} device(s) failed", summary.failed, summary.total)
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Have the operator retype the serial number (or path) of a device
+fn confirm_interactively(device: &StorageDevice, expected: &str) -> Result<()> {
+    use std::io::Write;
+    
+    let what = if *expected == *device.path.to_string_lossy() { "device path" } else { "serial number" };
+    print!("Type the {} of {} ({}) to confirm: ", what, device.path.display(), expected);
+    std::io::stdout().flush()?;
+    let mut typed = String::new();
+    std::io::stdin().read_line(&mut typed)?;
+    safety::confirm(expected, &typed)
+}
+
+/// Look up a wipe method by name, loading extra definitions first
+fn resolve_method(
+    mode_str: &str,
+    methods_path: Option<&Path>,
+    restore_hidden: Option<HiddenAreaRestore>,
+) -> Result<WipeMethod> {
+    let mut registry = MethodRegistry::builtin();
+    if let Some(path) = methods_path {
+        registry.load_path(path)?;
+    }
+    let method = registry.get(mode_str)
+        .ok_or_else(|| SecureEraseError::InvalidEraseMode(
+            format!("{} (available: {})", mode_str, registry.names().join(", "))
+        ))?;
+    Ok(match restore_hidden {
+        Some(mode) => method.with_hidden_area_restore(mode),
+        None => method.clone(),
+    })
+}
+
+/// Write the JSON and PDF certificates for a wipe as `<stem>.json` and `<stem>.pdf`
+async fn write_certificate(result: &WipeResult, output_dir: &Path, stem: &str, signing: &CertificateSigning) -> Result<(PathBuf, PathBuf)> {
+    info!("Generating enhanced wipe certificate...");
+    let cert_path = output_dir.join(format!("{}.json", stem));
+    let pdf_path = output_dir.join(format!("{}.pdf", stem));
+    
+    // Create enhanced certificate generator
+    let mut cert_generator = EnhancedCertificateGenerator::new(
+        "Secure Disk Erasure Tool".to_string(),
+        "Your Organization".to_string()
+    );
+    if let Some(path) = &signing.station_certificate {
+        cert_generator = cert_generator.with_station_chain(StationChain::from_pem(&std::fs::read_to_string(path)?)?);
+    }
+    if signing.staple_ocsp {
+        cert_generator = cert_generator.with_ocsp_stapling(OcspClient::new());
+    }
+    if let Some(passphrase) = &signing.passphrase {
+        cert_generator = cert_generator.with_key_passphrase(passphrase.clone());
+    }
+    
+    cert_generator.generate_enhanced_certificate(result, &cert_path, &pdf_path, &signing.signing_key).await?;
+    Ok((cert_path, pdf_path))
+}
+
+/// Draw progress events as a terminal progress bar, one bar per phase
+async fn render_progress(mut receiver: ProgressReceiver) {
+    let mut bar: Option<(WipePhase, u32, ProgressBar)> = None;
+    
+    while let Some(event) = receiver.recv().await {
+        let same_phase = matches!(&bar, Some((phase, pass, _)) if *phase == event.phase && *pass == event.pass);
+        if !same_phase {
+            if let Some((_, _, previous)) = bar.take() {
+                previous.finish();
+            }
+            let label = match event.phase {
+                WipePhase::Overwrite => format!("Pass {}/{}: {}", event.pass, event.total_passes, event.detail),
+                WipePhase::Verify => format!("Verify: {}", event.detail),
+                WipePhase::HardwareCommand => format!("Hardware command: {}", event.detail),
+            };
+            println!("{}", label);
+            bar = Some((event.phase, event.pass, Utils::create_progress_bar(event.bytes_total)));
+        }
+        
+        if let Some((_, _, progress_bar)) = bar.as_mut() {
+            let eta = event.eta_seconds
+                .map(Utils::format_duration)
+                .unwrap_or_else(|| "--:--:--".to_string());
+            progress_bar.set_message(format!(
+                "{} / {} at {}/s, ETA {}",
+                Utils::format_bytes(event.bytes_done),
+                Utils::format_bytes(event.bytes_total),
+                Utils::format_bytes(event.throughput_bytes_per_sec as u64),
+                eta
+            ));
+            progress_bar.update(event.bytes_done);
+        }
+    }
+    
+    if let Some((_, _, progress_bar)) = bar {
+        progress_bar.finish();
+    }
+}
+
+/// Print one status line per device whenever its phase changes or it gains another 10%
+async fn render_batch_progress(mut receiver: ProgressReceiver) {
+    let mut shown: HashMap<PathBuf, (WipePhase, u32, u64)> = HashMap::new();
+    
+    while let Some(event) = receiver.recv().await {
+        let step = (event.percent() / 10.0) as u64;
+        let last = shown.get(&event.device);
+        if last.is_some_and(|(phase, pass, shown_step)| {
+            *phase == event.phase && *pass == event.pass && *shown_step >= step
+        }) {
+            continue;
+        }
+        shown.insert(event.device.clone(), (event.phase, event.pass, step));
+        println!("[{}] {}", event.device.display(), describe_progress(&event));
+    }
+}
+
+fn describe_progress(event: &ProgressEvent) -> String {
+    let phase = match event.phase {
+        WipePhase::Overwrite => format!("Pass {}/{}", event.pass, event.total_passes),
+        WipePhase::Verify => "Verify".to_string(),
+        WipePhase::HardwareCommand => format!("Hardware command: {}", event.detail),
+    };
+    let eta = event.eta_seconds
+        .map(Utils::format_duration)
+        .unwrap_or_else(|| "--:--:--".to_string());
+    format!(
+        "{}: {:.0}% at {}/s, ETA {}",
+        phase,
+        event.percent(),
+        Utils::format_bytes(event.throughput_bytes_per_sec as u64),
+        eta
+    )
+}
+
 /// Verify a wipe certificate
 pub async fn verify_certificate(
     certificate_path: PathBuf,
     public_key_path: Option<PathBuf>,
+    trust_anchor_path: Option<PathBuf>,
+    crl_path: Option<PathBuf>,
+    crl_cache_dir: Option<PathBuf>,
+    ocsp_cache_dir: Option<PathBuf>,
+    offline: bool,
 ) -> Result<()> {
     info!("Verifying certificate: {}", certificate_path.display());
     
@@ -134,6 +597,19 @@ pub async fn verify_certificate(
     if let Some(key_path) = public_key_path {
         verifier = verifier.with_public_key(key_path.to_string_lossy().to_string());
     }
+    if let Some(anchor_path) = trust_anchor_path {
+        verifier = verifier.with_trust_anchors(anchor_path.to_string_lossy().to_string());
+    }
+    if let Some(crl_path) = crl_path {
+        verifier = verifier.with_crl_file(crl_path.to_string_lossy().to_string());
+    }
+    if let Some(cache_dir) = crl_cache_dir {
+        verifier = verifier.with_crl_cache(cache_dir.to_string_lossy().to_string());
+    }
+    if let Some(cache_dir) = ocsp_cache_dir {
+        verifier = verifier.with_ocsp_cache(cache_dir.to_string_lossy().to_string());
+    }
+    verifier = verifier.with_ocsp_online(!offline);
     
     // Perform verification
     let verification_result = verifier.verify_certificate(&certificate_path).await?;
@@ -150,14 +626,14 @@ pub async fn verify_certificate(
     Ok(())
 }
 
-/// Generate signing key pair
-pub async fn generate_keys(output_dir: PathBuf) -> Result<()> {
-    info!("Generating Ed25519 key pair...");
+/// Generate signing key pair, encrypting the private key if a passphrase is given
+pub async fn generate_keys(output_dir: PathBuf, algorithm: SignatureAlgorithm, format: KeyFormat, passphrase: Option<String>) -> Result<()> {
+    info!("Generating {} key pair...", algorithm.name());
     
-    let (private_key_path, public_key_path) = crate::crypto::generate_key_pair(&output_dir).await?;
+    let (private_key_path, public_key_path) = crate::crypto::generate_key_pair(&output_dir, algorithm, format, passphrase.as_deref()).await?;
     
     println!("Key pair generated successfully:");
-    println!("  Private key: {}", private_key_path.display());
+    println!("  Private key: {}{}", private_key_path.display(), if passphrase.is_some() { " (encrypted)" } else { "" });
     println!("  Public key: {}", public_key_path.display());
     println!();
     println!("IMPORTANT: Keep your private key secure and never share it!");
@@ -1,16 +1,224 @@
 use crate::error::{Result, SecureEraseError};
-use crate::core::{StorageDevice, DeviceType, EraseMode, WipeResult, HiddenArea, HiddenAreaType};
+use crate::core::{StorageDevice, DeviceType, EraseMode, WipeResult, HardwareCommandRecord, PassRecord, PassStats, LbaRange};
+use crate::core::ata::{self, AtaDevice};
+use crate::core::block_io::{BlockDevice, OverwriteOutcome, RetryPolicy, DEFAULT_BUFFER_SIZE};
+use crate::core::methods::{ExpectedContents, HardwareCommand, MethodRegistry, WipeMethod, WipeStep};
+use crate::core::nvme::{AdminCommand, FormatErase, NvmeDevice, NvmeIoctl, SanitizeAction};
+use crate::core::opal::{self, CryptoEraseRecord, OpalDevice, SedErase};
+use crate::core::scsi::{self, ScsiDevice};
+use crate::core::verification::{self, VerificationMode, VerificationReport};
+use crate::core::sg_io::SgIoDevice;
+use crate::core::progress::{ProgressReporter, ProgressTracker, WipePhase};
+use crate::core::checkpoint::{CancellationToken, Checkpoint, FallbackTaken, PassProgress};
+use crate::core::discard::{self, DiscardReport};
+use crate::core::hidden_areas::{self, HiddenAreaRestore};
+use crate::core::plan::WipePlan;
+use crate::core::safety::SafetyOverride;
+use crate::utils::Utils;
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use std::time::SystemTime;
-use std::process::Command;
+use std::time::{Duration, Instant, SystemTime};
 use log::{info, warn, error};
 use serde::{Deserialize, Serialize};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// Source of the data written during a single overwrite pass.
+///
+/// Every source is a pure function of the device offset, so verification can
+/// regenerate the expected contents of any range from the recorded pass.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PatternSource {
+    /// A single repeated byte
+    Fixed { byte: u8 },
+    /// A repeating multi-byte pattern
+    Repeating { bytes: Vec<u8> },
+    /// ChaCha20 keystream generated from a recorded seed
+    Random {
+        #[serde(with = "seed_hex")]
+        seed: [u8; 32],
+    },
+}
+
+impl PatternSource {
+    /// Random stream with a fresh seed from the OS CSPRNG
+    pub fn random() -> Self {
+        let mut seed = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut seed);
+        PatternSource::Random { seed }
+    }
+    
+    /// Fill `buf` with the data this source produces at `offset`
+    pub fn fill(&self, offset: u64, buf: &mut [u8]) {
+        match self {
+            PatternSource::Fixed { byte } => buf.fill(*byte),
+            PatternSource::Repeating { bytes } => fill_repeating(bytes, offset, buf),
+            PatternSource::Random { seed } => {
+                // The keystream is addressed in 32-bit words
+                let mut rng = ChaCha20Rng::from_seed(*seed);
+                rng.set_word_pos((offset / 4) as u128);
+                let skip = (offset % 4) as usize;
+                if skip > 0 {
+                    let mut head = [0u8; 4];
+                    rng.fill_bytes(&mut head);
+                    let take = std::cmp::min(4 - skip, buf.len());
+                    buf[..take].copy_from_slice(&head[skip..skip + take]);
+                    rng.fill_bytes(&mut buf[take..]);
+                } else {
+                    rng.fill_bytes(buf);
+                }
+            }
+        }
+    }
+    
+    /// Short human-readable description for logs and certificates
+    pub fn describe(&self) -> String {
+        match self {
+            PatternSource::Fixed { byte } => format!("0x{:02X}", byte),
+            PatternSource::Repeating { bytes } => format!("0x{}", hex::encode_upper(bytes)),
+            PatternSource::Random { .. } => "ChaCha20 random".to_string(),
+        }
+    }
+}
+
+/// Serialize pattern seeds as hex strings
+mod seed_hex {
+    use serde::{Deserialize, Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(seed: &[u8; 32], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(seed))
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<[u8; 32], D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = hex::decode(&encoded).map_err(serde::de::Error::custom)?;
+        bytes.try_into().map_err(|_| serde::de::Error::custom("seed must be 32 bytes"))
+    }
+}
+
+/// Named overwrite pass schedules
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PassSchedule {
+    /// Single pass of zeros
+    #[serde(rename = "zero")]
+    Zero,
+    /// Single pass of random data
+    #[serde(rename = "random")]
+    Random,
+    /// DoD 5220.22-M: zeros, ones, random
+    #[serde(rename = "dod-3")]
+    Dod3Pass,
+    /// DoD 5220.22-M ECE: the 3-pass sequence, a random pass, then the 3-pass sequence again
+    #[serde(rename = "dod-7")]
+    Dod7Pass,
+    /// Peter Gutmann's 35-pass method
+    #[serde(rename = "gutmann")]
+    Gutmann,
+}
+
+impl PassSchedule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PassSchedule::Zero => "zero",
+            PassSchedule::Random => "random",
+            PassSchedule::Dod3Pass => "dod-3",
+            PassSchedule::Dod7Pass => "dod-7",
+            PassSchedule::Gutmann => "gutmann",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "zero" => Some(PassSchedule::Zero),
+            "random" => Some(PassSchedule::Random),
+            "dod-3" | "dod3" => Some(PassSchedule::Dod3Pass),
+            "dod-7" | "dod7" => Some(PassSchedule::Dod7Pass),
+            "gutmann" => Some(PassSchedule::Gutmann),
+            _ => None,
+        }
+    }
+    
+    /// Expand the schedule into concrete passes, seeding each random pass freshly
+    pub fn passes(&self) -> Vec<PatternSource> {
+        let fixed = |byte| PatternSource::Fixed { byte };
+        match self {
+            PassSchedule::Zero => vec![fixed(0x00)],
+            PassSchedule::Random => vec![PatternSource::random()],
+            PassSchedule::Dod3Pass => vec![fixed(0x00), fixed(0xFF), PatternSource::random()],
+            PassSchedule::Dod7Pass => vec![
+                fixed(0x00), fixed(0xFF), PatternSource::random(),
+                PatternSource::random(),
+                fixed(0x00), fixed(0xFF), PatternSource::random(),
+            ],
+            PassSchedule::Gutmann => {
+                let mut passes: Vec<PatternSource> = (0..4).map(|_| PatternSource::random()).collect();
+                passes.extend(GUTMANN_PATTERNS.iter().map(|bytes| {
+                    if bytes.len() == 1 {
+                        fixed(bytes[0])
+                    } else {
+                        PatternSource::Repeating { bytes: bytes.to_vec() }
+                    }
+                }));
+                passes.extend((0..4).map(|_| PatternSource::random()));
+                passes
+            }
+        }
+    }
+}
+
+/// Deterministic passes 5-31 of the Gutmann method
+const GUTMANN_PATTERNS: [&[u8]; 27] = [
+    &[0x55], &[0xAA],
+    &[0x92, 0x49, 0x24], &[0x49, 0x24, 0x92], &[0x24, 0x92, 0x49],
+    &[0x00], &[0x11], &[0x22], &[0x33], &[0x44], &[0x55], &[0x66], &[0x77],
+    &[0x88], &[0x99], &[0xAA], &[0xBB], &[0xCC], &[0xDD], &[0xEE], &[0xFF],
+    &[0x92, 0x49, 0x24], &[0x49, 0x24, 0x92], &[0x24, 0x92, 0x49],
+    &[0x6D, 0xB6, 0xDB], &[0xB6, 0xDB, 0x6D], &[0xDB, 0x6D, 0xB6],
+];
+
+/// How often the journal is refreshed during an overwrite pass
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A hardware command as it ran on the device
+struct IssuedCommand {
+    /// The command exactly as sent
+    issued: String,
+    /// Whether an ATA Security Erase ran in enhanced mode
+    enhanced: Option<bool>,
+    contents: ExpectedContents,
+}
+
+impl IssuedCommand {
+    /// A command whose effect on the media does not depend on how the device ran it
+    fn new(command: &HardwareCommand, issued: String) -> Self {
+        Self { issued, enhanced: None, contents: command.expected_contents() }
+    }
+}
 
 /// Enhanced wipe engine with advanced features
 pub struct AdvancedWipeEngine {
     pub verify_after_wipe: bool,
     pub generate_hash: bool,
-    pub max_retries: u32,
+    /// How failed chunk writes are retried and when they are skipped
+    pub retry_policy: RetryPolicy,
+    pub buffer_size: usize,
+    /// Read-back mode for verify steps that do not name their own
+    pub verification_mode: VerificationMode,
+    /// Destination for progress events, if anyone is listening
+    pub progress: Option<ProgressReporter>,
+    /// Checked between chunks and steps; a cancelled wipe can be resumed from its journal
+    pub cancellation: Option<CancellationToken>,
+    /// Where the checkpoint journal is written; no journal is kept when unset
+    pub journal_path: Option<PathBuf>,
+    /// Upper bound on overwrite throughput in bytes per second
+    pub write_bandwidth_limit: Option<u64>,
+    /// PSID or admin password for crypto erase steps on self-encrypting drives
+    pub sed_credential: Option<String>,
+    /// Pre-flight safety checks bypassed for this wipe, copied into its result
+    pub safety_overrides: Vec<SafetyOverride>,
+    /// Resolve and log the plan but refuse to touch the device
+    pub dry_run: bool,
 }
 
 impl AdvancedWipeEngine {
@@ -18,65 +226,165 @@ impl AdvancedWipeEngine {
         Self {
             verify_after_wipe: true,
             generate_hash: true,
-            max_retries: 3,
+            retry_policy: RetryPolicy::default(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            verification_mode: VerificationMode::default(),
+            progress: None,
+            cancellation: None,
+            journal_path: None,
+            write_bandwidth_limit: None,
+            sed_credential: None,
+            safety_overrides: Vec::new(),
+            dry_run: false,
         }
     }
     
-    /// Perform secure erase with verification
+    /// Stream progress events for every phase of the wipe to `reporter`
+    pub fn with_progress(mut self, reporter: ProgressReporter) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
+    
+    /// Stop the wipe when `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+    
+    /// Keep a checkpoint journal at `path` so an interrupted wipe can be resumed
+    pub fn with_journal(mut self, path: PathBuf) -> Self {
+        self.journal_path = Some(path);
+        self
+    }
+    
+    /// Retry and skip failed writes according to `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+    
+    /// Throttle overwrite passes to at most `bytes_per_sec`
+    pub fn with_bandwidth_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.write_bandwidth_limit = Some(bytes_per_sec).filter(|limit| *limit > 0);
+        self
+    }
+    
+    /// Authenticate crypto erase steps with `credential` (the PSID for a PSID revert)
+    pub fn with_sed_credential(mut self, credential: String) -> Self {
+        self.sed_credential = Some(credential);
+        self
+    }
+    
+    /// Never write to the device: wipes only log their plan and fail with `DryRun`
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    
+    /// Everything `method` would do to `device`, without any device IO
+    pub fn plan(&self, device: &StorageDevice, method: &WipeMethod) -> WipePlan {
+        WipePlan::build(device, method, &self.verification_mode, &|command| self.supports_hardware_command(device, command))
+    }
+    
+    /// Record safety checks the operator bypassed in the result and certificate
+    pub fn with_safety_overrides(mut self, overrides: Vec<SafetyOverride>) -> Self {
+        self.safety_overrides = overrides;
+        self
+    }
+    
+    /// Perform secure erase with verification using the built-in method for `mode`
     pub async fn secure_erase_with_verification(
         &self,
         device: &StorageDevice,
         mode: EraseMode,
     ) -> Result<WipeResult> {
-        info!("Starting secure erase with verification for device: {}", device.path.display());
-        
-        let start_time = SystemTime::now();
+        let method = MethodRegistry::builtin().resolve(&mode)?;
+        self.secure_erase_with_method(device, &method).await
+    }
+    
+    /// Perform secure erase with verification using a named wipe method
+    pub async fn secure_erase_with_method(
+        &self,
+        device: &StorageDevice,
+        method: &WipeMethod,
+    ) -> Result<WipeResult> {
+        info!("Starting secure erase with method '{}' for device: {}", method.name, device.path.display());
+        self.run_wipe(device, Checkpoint::new(device, method)).await
+    }
+    
+    /// Continue an interrupted wipe from its checkpoint journal
+    pub async fn resume_wipe(&self, device: &StorageDevice, mut checkpoint: Checkpoint) -> Result<WipeResult> {
+        checkpoint.ensure_same_device(device)?;
+        info!("Resuming method '{}' for device: {}", checkpoint.method.name, device.path.display());
+        checkpoint.record_resume();
+        self.run_wipe(device, checkpoint).await
+    }
+    
+    async fn run_wipe(&self, device: &StorageDevice, initial: Checkpoint) -> Result<WipeResult> {
+        if self.dry_run {
+            info!("{}", self.plan(device, &initial.method).to_text());
+            return Err(SecureEraseError::DryRun(device.path.display().to_string()));
+        }
+        let method = initial.method.clone();
+        let mode = EraseMode::from_method_name(&method.name);
+        let start_time = initial.started_at;
         let mut errors = Vec::new();
         
-        // Attempt wipe operation with retries
-        let mut wipe_successful = false;
-        for attempt in 1..=self.max_retries {
-            info!("Wipe attempt {} of {}", attempt, self.max_retries);
-            
-            match self.perform_wipe_operation(device, &mode).await {
-                Ok(_) => {
-                    wipe_successful = true;
-                    break;
-                }
-                Err(e) => {
-                    let error_msg = format!("Attempt {} failed: {}", attempt, e);
-                    error!("{}", error_msg);
-                    errors.push(error_msg);
-                    
-                    if attempt < self.max_retries {
-                        info!("Retrying in 5 seconds...");
-                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                    }
-                }
+        // Write errors are retried per chunk, so a failure here is final;
+        // the journal is kept so the wipe can still be resumed
+        let mut state = initial;
+        self.save_checkpoint(&mut state)?;
+        let step_verification = match self.perform_wipe_operation(device, &method, &mut state).await {
+            Ok(verified) => verified,
+            Err(SecureEraseError::Cancelled) => {
+                warn!("Wipe cancelled at pass {}", state.passes.len() + 1);
+                return Err(SecureEraseError::Cancelled);
             }
-        }
+            Err(e) => {
+                error!("Wipe failed: {}", e);
+                return Err(e);
+            }
+        };
         
-        if !wipe_successful {
-            return Err(SecureEraseError::WipeFailed(
-                format!("All {} wipe attempts failed", self.max_retries)
-            ));
+        for pass in &state.passes {
+            let unwritable = &pass.stats.unwritable_ranges;
+            if !unwritable.is_empty() {
+                let blocks: u64 = unwritable.iter().map(|range| range.count).sum();
+                errors.push(format!(
+                    "Pass {}: {} unwritable block(s) in {} range(s) skipped",
+                    pass.pass_number, blocks, unwritable.len()
+                ));
+            }
         }
+        let unwritable_ranges = unwritable_ranges(&state.passes);
+        let unwritable_bytes = unwritable_ranges.iter().map(|range| range.count).sum::<u64>()
+            * state.logical_block_size as u64;
+        
+        let sector_size = if state.logical_block_size > 0 { state.logical_block_size } else { 512 };
+        let hidden_areas = hidden_areas::account(device, &state.hidden_areas, &state.passes, sector_size);
         
         let end_time = SystemTime::now();
         let duration = end_time.duration_since(start_time)
             .map_err(|e| SecureEraseError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
         
-        // Verify the wipe if requested
-        let verification_passed = if self.verify_after_wipe {
-            match self.verify_device_wipe(device).await {
-                Ok(verified) => verified,
+        // Verify the wipe if the method did not already do so
+        let verification = if step_verification.is_some() {
+            step_verification
+        } else if self.verify_after_wipe {
+            match self.verify_device_wipe(device, &state, &self.verification_mode).await {
+                Ok(report) => report,
+                Err(SecureEraseError::Cancelled) => return Err(SecureEraseError::Cancelled),
                 Err(e) => {
                     errors.push(format!("Verification failed: {}", e));
-                    false
+                    None
                 }
             }
         } else {
-            true
+            None
+        };
+        let verification_passed = match &verification {
+            Some(report) => report.passed,
+            None => !self.verify_after_wipe || state.expected_contents.pattern().is_none(),
         };
         
         // Generate hash if requested
@@ -86,264 +394,543 @@ impl AdvancedWipeEngine {
             None
         };
         
+        // The journal is only needed while the wipe is incomplete
+        if let Some(path) = &self.journal_path {
+            Checkpoint::remove(path);
+        }
+        
         Ok(WipeResult {
             device: device.clone(),
             mode,
+            method: Some(method),
             start_time,
             end_time,
             duration_seconds: duration.as_secs(),
-            bytes_written: device.size,
+            bytes_written: state.passes.iter().map(|pass| pass.stats.bytes_written).sum(),
             verification_passed,
+            verification,
+            expected_contents: state.expected_contents,
+            unwritable_ranges,
+            unwritable_bytes,
+            hidden_areas,
+            hardware_commands: state.hardware_commands,
+            discards: state.discards,
+            crypto_erases: state.crypto_erases,
+            safety_overrides: self.safety_overrides.clone(),
             errors,
+            passes: state.passes,
+            interruptions: state.interruptions,
         })
     }
     
-    /// Perform the actual wipe operation
+    /// Run the steps of a wipe method, returning the report of any verify step.
+    ///
+    /// Steps and passes already recorded in `state` are skipped, so the same
+    /// walk serves fresh and resumed wipes.
     async fn perform_wipe_operation(
         &self,
         device: &StorageDevice,
-        mode: &EraseMode,
-    ) -> Result<()> {
-        match device.device_type {
-            DeviceType::HDD => self.wipe_hdd(device, mode).await,
-            DeviceType::SSD => self.wipe_ssd(device, mode).await,
-            DeviceType::NVMe => self.wipe_nvme(device, mode).await,
-            DeviceType::USB => self.wipe_usb(device, mode).await,
-            DeviceType::Unknown => self.wipe_generic(device, mode).await,
-        }
-    }
-    
-    /// Wipe HDD with appropriate method
-    async fn wipe_hdd(&self, device: &StorageDevice, mode: &EraseMode) -> Result<()> {
-        info!("Wiping HDD: {}", device.path.display());
+        method: &WipeMethod,
+        state: &mut Checkpoint,
+    ) -> Result<Option<VerificationReport>> {
+        info!("Wiping {:?} device {} with method '{}'", device.device_type, device.path.display(), method.name);
         
-        match mode {
-            EraseMode::Quick => {
-                // Single pass with zeros
-                self.overwrite_device(device, &[0u8; 1024]).await?;
-            }
-            EraseMode::Full => {
-                // Multiple passes with random data
-                self.multi_pass_wipe(device, 3).await?;
-            }
-            EraseMode::Advanced => {
-                // Try hardware secure erase first
-                if device.supports_secure_erase {
-                    self.hardware_secure_erase(device).await?;
-                } else {
-                    // Fall back to multi-pass
-                    self.multi_pass_wipe(device, 7).await?;
-                }
-            }
-        }
+        let mut pending: VecDeque<&WipeStep> = method.steps_for(&device.device_type).iter().collect();
+        let mut verification: Option<VerificationReport> = None;
+        let mut step_index = 0u32;
+        let mut next_pass = 1u32;
         
-        Ok(())
-    }
-    
-    /// Wipe SSD with TRIM and secure erase
-    async fn wipe_ssd(&self, device: &StorageDevice, mode: &EraseMode) -> Result<()> {
-        info!("Wiping SSD: {}", device.path.display());
-        
-        match mode {
-            EraseMode::Quick => {
-                // TRIM all blocks
-                if device.supports_trim {
-                    self.trim_device(device).await?;
-                } else {
-                    self.overwrite_device(device, &[0u8; 1024]).await?;
+        while let Some(step) = pending.pop_front() {
+            self.check_cancelled()?;
+            let completed = step_index < state.steps_completed;
+            step_index += 1;
+            
+            match step {
+                WipeStep::RestoreHiddenAreas { mode } => {
+                    if !completed {
+                        let statuses = self.restore_hidden_areas(device, *mode).await?;
+                        let size = BlockDevice::open_for_read(&device.path, device.size)?.size;
+                        state.record_hidden_areas(statuses, size);
+                    }
                 }
-            }
-            EraseMode::Full => {
-                // TRIM + overwrite
-                if device.supports_trim {
-                    self.trim_device(device).await?;
+                WipeStep::Overwrite { pattern } => {
+                    let pass = next_pass;
+                    next_pass += 1;
+                    if !completed {
+                        let total = pass + queued_passes(&pending);
+                        self.run_pass(device, pass, total, pattern.resolve(), state).await?;
+                        state.expected_contents = last_pass_contents(state);
+                    }
                 }
-                self.overwrite_device(device, &[0xFFu8; 1024]).await?;
-            }
-            EraseMode::Advanced => {
-                // Hardware secure erase
-                if device.supports_secure_erase {
-                    self.hardware_secure_erase(device).await?;
-                } else {
-                    // TRIM + multiple passes
+                WipeStep::Schedule { schedule } => {
+                    let patterns = schedule.passes();
+                    let first = next_pass;
+                    next_pass += patterns.len() as u32;
+                    if !completed {
+                        info!("Performing {}-pass {} wipe", patterns.len(), schedule.name());
+                        let total = next_pass - 1 + queued_passes(&pending);
+                        for (index, pattern) in patterns.into_iter().enumerate() {
+                            self.run_pass(device, first + index as u32, total, pattern, state).await?;
+                        }
+                        state.expected_contents = last_pass_contents(state);
+                    }
+                }
+                WipeStep::HardwareCommand { command, fallback } => {
+                    if state.fell_back(step_index) {
+                        info!("{:?} was refused before the interruption, continuing its fallback steps", command);
+                        queue_fallback(&mut pending, fallback);
+                    } else if self.supports_hardware_command(device, command) {
+                        if !completed {
+                            let mut tracker = self.tracker(device, WipePhase::HardwareCommand, format!("{:?}", command));
+                            tracker.emit_now(0);
+                            let started_at = SystemTime::now();
+                            match self.run_hardware_command(device, command).await {
+                                Ok(outcome) => {
+                                    tracker.emit_now(device.size);
+                                    state.hardware_commands.push(HardwareCommandRecord {
+                                        command: *command,
+                                        issued: outcome.issued,
+                                        enhanced: outcome.enhanced,
+                                        started_at,
+                                        finished_at: SystemTime::now(),
+                                    });
+                                    state.expected_contents = outcome.contents;
+                                }
+                                // Frozen security or a missing capability only shows once the device is asked
+                                Err(e @ (SecureEraseError::DeviceFrozen(_) | SecureEraseError::SecureEraseNotSupported))
+                                    if !fallback.is_empty() =>
+                                {
+                                    warn!("{:?} not possible ({}); running fallback steps", command, e);
+                                    state.fallbacks.push(FallbackTaken { step: step_index, reason: e.to_string() });
+                                    queue_fallback(&mut pending, fallback);
+                                }
+                                Err(e) => return Err(e),
+                            }
+                        }
+                    } else if !fallback.is_empty() {
+                        info!("{:?} not supported by device, running fallback steps", command);
+                        queue_fallback(&mut pending, fallback);
+                    } else {
+                        return Err(SecureEraseError::SecureEraseNotSupported);
+                    }
+                }
+                WipeStep::CryptoErase { method, fallback } => {
+                    if state.fell_back(step_index) {
+                        info!("Crypto erase was refused before the interruption, continuing its fallback steps");
+                        queue_fallback(&mut pending, fallback);
+                    } else if !completed {
+                        let mut tracker = self.tracker(device, WipePhase::HardwareCommand, format!("TCG crypto erase ({:?})", method));
+                        tracker.emit_now(0);
+                        match self.sed_crypto_erase(device, *method).await {
+                            Ok(record) => {
+                                tracker.emit_now(device.size);
+                                state.crypto_erases.push(record);
+                                state.expected_contents = ExpectedContents::Ciphertext;
+                            }
+                            Err(e @ SecureEraseError::SecureEraseNotSupported) if !fallback.is_empty() => {
+                                info!("Device is not a self-encrypting drive, running fallback steps");
+                                state.fallbacks.push(FallbackTaken { step: step_index, reason: e.to_string() });
+                                queue_fallback(&mut pending, fallback);
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+                WipeStep::Trim { fallback } => {
                     if device.supports_trim {
-                        self.trim_device(device).await?;
+                        if !completed {
+                            let mut tracker = self.tracker(device, WipePhase::HardwareCommand, "TRIM".to_string());
+                            tracker.emit_now(0);
+                            let report = self.trim_device(device).await?;
+                            state.expected_contents = report.read_after_discard.expected_contents();
+                            state.discards.push(report);
+                            tracker.emit_now(device.size);
+                        }
+                    } else if !fallback.is_empty() {
+                        info!("TRIM not supported by device, running fallback steps");
+                        queue_fallback(&mut pending, fallback);
+                    } else {
+                        return Err(SecureEraseError::SecureEraseNotSupported);
+                    }
+                }
+                WipeStep::Verify { mode } => {
+                    if !completed {
+                        let mode = mode.as_ref().unwrap_or(&self.verification_mode);
+                        let report = self.verify_device_wipe(device, state, mode).await?;
+                        // Keep the first failing report if the method verifies more than once
+                        if report.is_some() && verification.as_ref().is_none_or(|previous| previous.passed) {
+                            verification = report;
+                        }
                     }
-                    self.multi_pass_wipe(device, 3).await?;
                 }
             }
+            
+            if !completed {
+                state.steps_completed = step_index;
+                self.save_checkpoint(state)?;
+            }
         }
         
-        Ok(())
+        Ok(verification)
     }
     
-    /// Wipe NVMe device
-    async fn wipe_nvme(&self, device: &StorageDevice, mode: &EraseMode) -> Result<()> {
-        info!("Wiping NVMe: {}", device.path.display());
-        
-        match mode {
-            EraseMode::Quick => {
-                // Format with secure erase
-                self.nvme_format(device, true).await?;
-            }
-            EraseMode::Full => {
-                // Format + overwrite
-                self.nvme_format(device, true).await?;
-                self.overwrite_device(device, &[0xFFu8; 1024]).await?;
-            }
-            EraseMode::Advanced => {
-                // Cryptographic erase
-                self.nvme_crypto_erase(device).await?;
-            }
+    /// Run one overwrite pass, continuing from the journal if it was interrupted
+    async fn run_pass(
+        &self,
+        device: &StorageDevice,
+        pass: u32,
+        total_passes: u32,
+        pattern: PatternSource,
+        state: &mut Checkpoint,
+    ) -> Result<()> {
+        if pass <= state.passes.len() as u32 {
+            // Finished before the interruption
+            return Ok(());
         }
         
-        Ok(())
+        // An interrupted pass must continue with the data stream it started with
+        let progress = match state.current_pass.take() {
+            Some(current) if current.pass_number == pass => current,
+            _ => PassProgress {
+                pass_number: pass,
+                pattern,
+                offset: 0,
+                started_at: SystemTime::now(),
+                stats: PassStats::default(),
+            },
+        };
+        info!("Pass {} of {}: writing {}", pass, total_passes, progress.pattern.describe());
+        
+        let pattern = progress.pattern.clone();
+        state.current_pass = Some(progress);
+        let outcome = self.overwrite_device(device, total_passes, state).await?;
+        
+        // Figures from before an interruption were kept in the journal
+        let current = state.current_pass.take()
+            .ok_or_else(|| SecureEraseError::WipeFailed("No pass in progress".to_string()))?;
+        let stats = current.stats.merged(&outcome.stats);
+        if !stats.unwritable_ranges.is_empty() {
+            warn!("Pass {} skipped {} unwritable range(s)", pass, stats.unwritable_ranges.len());
+        }
+        info!("Pass {} wrote {} at {}/s", pass,
+              Utils::format_bytes(stats.bytes_written), Utils::format_bytes(stats.throughput_bytes_per_sec() as u64));
+        state.logical_block_size = outcome.logical_block_size;
+        state.passes.push(PassRecord {
+            pass_number: pass,
+            pattern,
+            started_at: current.started_at,
+            finished_at: SystemTime::now(),
+            throughput_bytes_per_sec: stats.throughput_bytes_per_sec(),
+            stats,
+        });
+        self.save_checkpoint(state)
     }
     
-    /// Wipe USB device
-    async fn wipe_usb(&self, device: &StorageDevice, mode: &EraseMode) -> Result<()> {
-        info!("Wiping USB: {}", device.path.display());
-        
-        // USB devices typically don't support hardware secure erase
-        match mode {
-            EraseMode::Quick => {
-                self.overwrite_device(device, &[0u8; 1024]).await?;
-            }
-            EraseMode::Full => {
-                self.multi_pass_wipe(device, 3).await?;
-            }
-            EraseMode::Advanced => {
-                self.multi_pass_wipe(device, 7).await?;
-            }
+    /// Write the journal, if one is kept
+    fn save_checkpoint(&self, state: &mut Checkpoint) -> Result<()> {
+        match &self.journal_path {
+            Some(path) => state.save(path),
+            None => Ok(()),
         }
-        
-        Ok(())
     }
     
-    /// Generic wipe for unknown device types
-    async fn wipe_generic(&self, device: &StorageDevice, mode: &EraseMode) -> Result<()> {
-        info!("Wiping unknown device: {}", device.path.display());
-        
-        // Use conservative approach for unknown devices
-        match mode {
-            EraseMode::Quick => {
-                self.overwrite_device(device, &[0u8; 1024]).await?;
-            }
-            EraseMode::Full => {
-                self.multi_pass_wipe(device, 3).await?;
-            }
-            EraseMode::Advanced => {
-                self.multi_pass_wipe(device, 5).await?;
-            }
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(SecureEraseError::Cancelled),
+            _ => Ok(()),
         }
-        
-        Ok(())
     }
     
-    /// Overwrite device with specific pattern
-    async fn overwrite_device(&self, device: &StorageDevice, pattern: &[u8]) -> Result<()> {
-        info!("Overwriting device with pattern of {} bytes", pattern.len());
-        
-        // This would implement actual device overwriting
-        // For now, simulate the operation
-        let sectors = device.size / 512; // Assuming 512-byte sectors
-        let sectors_per_chunk = 1024 / 512;
-        
-        for sector in (0..sectors).step_by(sectors_per_chunk as usize) {
-            // Simulate writing pattern to sector
-            tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
+    /// Progress tracker for one phase covering the whole device
+    fn tracker(&self, device: &StorageDevice, phase: WipePhase, detail: String) -> ProgressTracker {
+        ProgressTracker::new(self.progress.clone(), device.path.clone(), phase, device.size, detail)
+    }
+    
+    /// Check whether a device can execute a hardware sanitize command
+    fn supports_hardware_command(&self, device: &StorageDevice, command: &HardwareCommand) -> bool {
+        match command {
+            HardwareCommand::AtaSecureErase => device.supports_secure_erase,
+            HardwareCommand::NvmeFormat
+            | HardwareCommand::NvmeCryptoErase
+            | HardwareCommand::NvmeSanitizeBlockErase
+            | HardwareCommand::NvmeSanitizeCryptoErase
+            | HardwareCommand::NvmeSanitizeOverwrite => matches!(device.device_type, DeviceType::NVMe),
+            HardwareCommand::ScsiSanitizeOverwrite
+            | HardwareCommand::ScsiSanitizeBlockErase
+            | HardwareCommand::ScsiSanitizeCryptoErase
+            | HardwareCommand::ScsiFormatUnit => matches!(device.device_type, DeviceType::SAS),
         }
-        
-        Ok(())
     }
     
-    /// Multi-pass wipe with different patterns
-    async fn multi_pass_wipe(&self, device: &StorageDevice, passes: u32) -> Result<()> {
-        info!("Performing {}-pass wipe", passes);
+    /// Dispatch a hardware sanitize command, returning the command as issued
+    async fn run_hardware_command(&self, device: &StorageDevice, command: &HardwareCommand) -> Result<IssuedCommand> {
+        let issued = match command {
+            HardwareCommand::AtaSecureErase => return self.hardware_secure_erase(device).await,
+            HardwareCommand::NvmeFormat => self.nvme_format(device, FormatErase::UserData).await?,
+            HardwareCommand::NvmeCryptoErase => self.nvme_crypto_erase(device).await?,
+            HardwareCommand::NvmeSanitizeBlockErase => self.nvme_sanitize(device, SanitizeAction::BlockErase).await?,
+            HardwareCommand::NvmeSanitizeCryptoErase => self.nvme_sanitize(device, SanitizeAction::CryptoErase).await?,
+            HardwareCommand::NvmeSanitizeOverwrite => self.nvme_sanitize(device, SanitizeAction::Overwrite).await?,
+            HardwareCommand::ScsiSanitizeOverwrite => self.scsi_sanitize(device, scsi::SanitizeAction::Overwrite).await?,
+            HardwareCommand::ScsiSanitizeBlockErase => self.scsi_sanitize(device, scsi::SanitizeAction::BlockErase).await?,
+            HardwareCommand::ScsiSanitizeCryptoErase => self.scsi_sanitize(device, scsi::SanitizeAction::CryptoErase).await?,
+            HardwareCommand::ScsiFormatUnit => self.scsi_format_unit(device).await?,
+        };
+        let mut outcome = IssuedCommand::new(command, issued);
+        if matches!(command, HardwareCommand::NvmeFormat | HardwareCommand::NvmeSanitizeBlockErase) {
+            // Both leave every block deallocated; DLFEAT says what those read back as
+            let target = device.clone();
+            outcome.contents = tokio::task::spawn_blocking(move || discard::read_after_discard(&target))
+                .await
+                .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))?
+                .expected_contents();
+        }
+        Ok(outcome)
+    }
+    
+    /// Overwrite the device with the pass in `state.current_pass`, starting at its
+    /// committed offset. The journal is refreshed periodically and on cancellation.
+    async fn overwrite_device(
+        &self,
+        device: &StorageDevice,
+        total_passes: u32,
+        state: &Checkpoint,
+    ) -> Result<OverwriteOutcome> {
+        let current = state.current_pass.clone()
+            .ok_or_else(|| SecureEraseError::WipeFailed("No pass in progress".to_string()))?;
+        info!("Overwriting device with pattern {}", current.pattern.describe());
         
-        let patterns = vec![
-            vec![0x00u8; 1024], // All zeros
-            vec![0xFFu8; 1024], // All ones
-            vec![0xAAu8; 1024], // Alternating pattern
-            vec![0x55u8; 1024], // Alternating pattern
-        ];
+        let path = device.path.clone();
+        let size_hint = device.size;
+        let buffer_size = self.buffer_size;
+        let policy = self.retry_policy.clone();
+        let reporter = self.progress.clone();
+        let cancellation = self.cancellation.clone();
+        let bandwidth_limit = self.write_bandwidth_limit;
+        let mut journal = self.journal_path.clone().map(|journal_path| (journal_path, state.clone()));
         
-        for pass in 1..=passes {
-            let pattern = &patterns[(pass as usize - 1) % patterns.len()];
-            info!("Pass {} of {}: writing pattern", pass, passes);
-            self.overwrite_device(device, pattern).await?;
-        }
+        // Direct IO blocks the calling thread, so keep it off the async runtime
+        let outcome = tokio::task::spawn_blocking(move || {
+            let mut target = BlockDevice::open_for_write(&path, size_hint)?;
+            let mut tracker = ProgressTracker::new(
+                reporter, path.clone(), WipePhase::Overwrite, target.size, current.pattern.describe(),
+            ).with_pass(current.pass_number, total_passes).resumed_from(current.offset);
+            tracker.emit_now(current.offset);
+            let mut last_checkpoint = Instant::now();
+            let pass_started = Instant::now();
+            
+            target.overwrite_with_progress(
+                buffer_size,
+                current.offset,
+                &policy,
+                |offset, buf| current.pattern.fill(offset, buf),
+                |written, stats| {
+                    tracker.update(written);
+                    let cancelled = cancellation.as_ref().is_some_and(|token| token.is_cancelled());
+                    if let Some((journal_path, checkpoint)) = journal.as_mut() {
+                        // Writes are synchronous, so everything below `written` is committed
+                        if cancelled || last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                            if let Some(pass) = checkpoint.current_pass.as_mut() {
+                                pass.offset = written;
+                                pass.stats = current.stats.merged(stats);
+                            }
+                            checkpoint.save(journal_path)?;
+                            last_checkpoint = Instant::now();
+                        }
+                    }
+                    if cancelled {
+                        return Err(SecureEraseError::Cancelled);
+                    }
+                    if let Some(limit) = bandwidth_limit {
+                        // Sleep until the elapsed time catches up with the allowed rate
+                        let allowed = Duration::from_secs_f64((written - current.offset) as f64 / limit as f64);
+                        if let Some(ahead) = allowed.checked_sub(pass_started.elapsed()) {
+                            std::thread::sleep(ahead);
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(format!("Overwrite task failed: {}", e)))??;
         
-        // Final pass with zeros
-        self.overwrite_device(device, &[0x00u8; 1024]).await?;
+        info!("Overwrite complete: {} bytes written", outcome.stats.bytes_written);
+        Ok(outcome)
+    }
+    
+    /// Make HPA/DCO sectors addressable so the following passes overwrite them
+    async fn restore_hidden_areas(
+        &self,
+        device: &StorageDevice,
+        mode: HiddenAreaRestore,
+    ) -> Result<Vec<hidden_areas::HiddenAreaStatus>> {
+        if device.hidden_areas.is_empty() {
+            info!("No hidden areas to restore on {}", device.path.display());
+            return Ok(Vec::new());
+        }
         
-        Ok(())
+        let mut tracker = self.tracker(device, WipePhase::HardwareCommand, format!("Restore hidden areas ({:?})", mode));
+        tracker.emit_now(0);
+        let target = device.clone();
+        let statuses = tokio::task::spawn_blocking(move || hidden_areas::restore(&target, mode))
+            .await
+            .map_err(|e| SecureEraseError::HiddenAreaAccessFailed(e.to_string()))?;
+        tracker.emit_now(device.size);
+        Ok(statuses)
     }
     
-    /// Hardware secure erase
-    async fn hardware_secure_erase(&self, device: &StorageDevice) -> Result<()> {
+    /// ATA Security Erase over SG_IO, enhanced when the drive supports it
+    async fn hardware_secure_erase(&self, device: &StorageDevice) -> Result<IssuedCommand> {
         info!("Attempting hardware secure erase");
         
-        // Platform-specific implementation would go here
-        // This is a placeholder
+        let path = device.path.clone();
+        let report = tokio::task::spawn_blocking(move || {
+            let mut ata = AtaDevice::new(SgIoDevice::open(&path)?);
+            ata::secure_erase(&mut ata, true)
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))??;
         
-        Ok(())
+        info!("{} security erase finished in {} seconds",
+              if report.enhanced { "Enhanced" } else { "Normal" }, report.elapsed_seconds);
+        Ok(IssuedCommand {
+            issued: format!(
+                "ATA SECURITY ERASE UNIT (F4h), {} erase, timeout {} seconds",
+                if report.enhanced { "enhanced" } else { "normal" }, report.timeout_seconds
+            ),
+            enhanced: Some(report.enhanced),
+            contents: report.expected_contents(),
+        })
     }
     
-    /// TRIM device (for SSDs)
-    async fn trim_device(&self, device: &StorageDevice) -> Result<()> {
-        info!("TRIMming device: {}", device.path.display());
+    /// TCG Opal/Enterprise cryptographic erase over the device's security protocol
+    async fn sed_crypto_erase(&self, device: &StorageDevice, method: SedErase) -> Result<CryptoEraseRecord> {
+        info!("TCG cryptographic erase: {:?}", method);
         
-        // Platform-specific TRIM implementation
-        // This is a placeholder
-        
-        Ok(())
+        let target = device.clone();
+        let credential = self.sed_credential.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut opal = OpalDevice::new(opal::open_transport(&target)?)?;
+            let credential = credential.ok_or_else(|| SecureEraseError::WipeFailed(
+                "A crypto erase step needs the drive's PSID or admin password (--sed-credential-file)".to_string()
+            ))?;
+            opal.crypto_erase(method, credential.as_bytes())
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))?
     }
     
-    /// NVMe format with secure erase
-    async fn nvme_format(&self, device: &StorageDevice, secure: bool) -> Result<()> {
-        info!("NVMe format with secure erase: {}", secure);
+    /// Discard every block of the device (TRIM for SSDs)
+    async fn trim_device(&self, device: &StorageDevice) -> Result<DiscardReport> {
+        info!("TRIMming device: {}", device.path.display());
         
-        // NVMe-specific formatting
-        // This is a placeholder
+        let target = device.clone();
+        tokio::task::spawn_blocking(move || discard::discard(&target))
+            .await
+            .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))?
+    }
+    
+    /// Format NVM with the given Secure Erase Setting
+    async fn nvme_format(&self, device: &StorageDevice, erase: FormatErase) -> Result<String> {
+        info!("NVMe format with secure erase: {:?}", erase);
         
-        Ok(())
+        let path = device.path.clone();
+        let issued = tokio::task::spawn_blocking(move || {
+            NvmeDevice::new(NvmeIoctl::open(&path)?).format(erase)
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))??;
+        Ok(format!("NVMe Format NVM (SES={}): {}", erase as u32, issued.describe()))
     }
     
-    /// NVMe cryptographic erase
-    async fn nvme_crypto_erase(&self, device: &StorageDevice) -> Result<()> {
+    /// NVMe cryptographic erase, by sanitize or by format
+    async fn nvme_crypto_erase(&self, device: &StorageDevice) -> Result<String> {
         info!("NVMe cryptographic erase");
         
-        // NVMe cryptographic erase implementation
-        // This is a placeholder
+        let path = device.path.clone();
+        let mut tracker = self.tracker(device, WipePhase::HardwareCommand, "NVMe crypto erase".to_string());
+        let size = device.size;
+        let issued = tokio::task::spawn_blocking(move || {
+            NvmeDevice::new(NvmeIoctl::open(&path)?).crypto_erase(|fraction| tracker.update((fraction * size as f64) as u64))
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))??;
+        Ok(format!("NVMe {}: {}", nvme_command_name(&issued), issued.describe()))
+    }
+    
+    /// NVMe Sanitize, waiting for the controller to finish
+    async fn nvme_sanitize(&self, device: &StorageDevice, action: SanitizeAction) -> Result<String> {
+        info!("NVMe sanitize: {:?}", action);
         
-        Ok(())
+        let path = device.path.clone();
+        let mut tracker = self.tracker(device, WipePhase::HardwareCommand, format!("NVMe sanitize ({:?})", action));
+        let size = device.size;
+        let issued = tokio::task::spawn_blocking(move || {
+            NvmeDevice::new(NvmeIoctl::open(&path)?).sanitize(action, |fraction| tracker.update((fraction * size as f64) as u64))
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))??;
+        Ok(format!("NVMe {}: {}", nvme_command_name(&issued), issued.describe()))
     }
     
-    /// Verify device wipe
-    async fn verify_device_wipe(&self, device: &StorageDevice) -> Result<bool> {
-        info!("Verifying device wipe: {}", device.path.display());
+    /// SCSI SANITIZE, waiting for the device to finish
+    async fn scsi_sanitize(&self, device: &StorageDevice, action: scsi::SanitizeAction) -> Result<String> {
+        info!("SCSI sanitize: {:?}", action);
         
-        // Sample random sectors and verify they contain expected data
-        let sample_count = 100;
-        let mut verified_sectors = 0;
+        let path = device.path.clone();
+        let mut tracker = self.tracker(device, WipePhase::HardwareCommand, format!("SCSI sanitize ({:?})", action));
+        let size = device.size;
+        let cdb = tokio::task::spawn_blocking(move || {
+            ScsiDevice::new(SgIoDevice::open(&path)?).sanitize(action, |fraction| tracker.update((fraction * size as f64) as u64))
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))??;
+        Ok(format!("SCSI SANITIZE (48h) {:?}: CDB {}", action, scsi::describe_cdb(&cdb)))
+    }
+    
+    /// SCSI FORMAT UNIT with security initialize
+    async fn scsi_format_unit(&self, device: &StorageDevice) -> Result<String> {
+        info!("SCSI format unit with security initialize");
         
-        for _ in 0..sample_count {
-            // Sample a random sector
-            let sector = rand::random::<u64>() % (device.size / 512);
-            
-            // Read sector and verify it's wiped
-            // This is a placeholder - actual implementation would read the sector
-            verified_sectors += 1;
-        }
+        let path = device.path.clone();
+        let mut tracker = self.tracker(device, WipePhase::HardwareCommand, "SCSI format unit".to_string());
+        let size = device.size;
+        let cdb = tokio::task::spawn_blocking(move || {
+            ScsiDevice::new(SgIoDevice::open(&path)?).format_unit(|fraction| tracker.update((fraction * size as f64) as u64))
+        })
+        .await
+        .map_err(|e| SecureEraseError::WipeFailed(e.to_string()))??;
+        Ok(format!("SCSI FORMAT UNIT (04h) with security initialize: CDB {}", scsi::describe_cdb(&cdb)))
+    }
+    
+    /// Read the device back and compare it with the contents the completed steps
+    /// left behind; `None` when no pattern describes the media
+    async fn verify_device_wipe(
+        &self,
+        device: &StorageDevice,
+        state: &Checkpoint,
+        mode: &VerificationMode,
+    ) -> Result<Option<VerificationReport>> {
+        let expected = match state.expected_contents.pattern() {
+            Some(pattern) => pattern.clone(),
+            None => {
+                info!("Pattern verification does not apply: the media holds {}", state.expected_contents.describe());
+                return Ok(None);
+            }
+        };
         
-        let verification_ratio = verified_sectors as f64 / sample_count as f64;
-        let passed = verification_ratio >= 0.95; // 95% threshold
+        // Blocks that could not be written are reported separately, not as mismatches
+        let excluded = unwritable_ranges(&state.passes);
+        let report = verification::verify_device(
+            device, mode, &expected, &excluded, self.progress.clone(), self.cancellation.clone(),
+        ).await?;
         
-        info!("Verification result: {}/{} sectors verified ({:.1}%)", 
-              verified_sectors, sample_count, verification_ratio * 100.0);
+        info!("Verification result: {}/{} blocks matched ({:.1}%)",
+              report.blocks_checked - report.mismatched_blocks, report.blocks_checked,
+              report.match_ratio() * 100.0);
         
-        Ok(passed)
+        Ok(Some(report))
     }
     
     /// Generate hash of device for verification
@@ -356,3 +943,283 @@ impl AdvancedWipeEngine {
         Ok("device_hash_placeholder".to_string())
     }
 }
+
+/// Union of the blocks any pass had to skip
+fn unwritable_ranges(passes: &[PassRecord]) -> Vec<LbaRange> {
+    LbaRange::merge(passes.iter().flat_map(|pass| pass.stats.unwritable_ranges.iter().cloned()).collect())
+}
+
+/// Readable name of an NVMe sanitize-type admin command
+fn nvme_command_name(command: &AdminCommand) -> String {
+    match command.opcode {
+        0x80 => format!("Format NVM (SES={})", (command.cdw10 >> 9) & 0x7),
+        0x84 => format!("Sanitize (SANACT={})", command.cdw10 & 0x7),
+        opcode => format!("admin command 0x{:02x}", opcode),
+    }
+}
+
+/// Contents left by the overwrite pass that just finished
+fn last_pass_contents(state: &Checkpoint) -> ExpectedContents {
+    state.passes.last()
+        .map(|pass| ExpectedContents::Pattern(pass.pattern.clone()))
+        .unwrap_or_default()
+}
+
+/// Run `fallback` next, ahead of the steps already queued
+fn queue_fallback<'a>(pending: &mut VecDeque<&'a WipeStep>, fallback: &'a [WipeStep]) {
+    for fallback_step in fallback.iter().rev() {
+        pending.push_front(fallback_step);
+    }
+}
+
+/// Overwrite passes still queued behind the current step
+fn queued_passes(pending: &VecDeque<&WipeStep>) -> u32 {
+    pending.iter().map(|step| step.pass_count()).sum()
+}
+
+/// Fill a buffer with a repeating pattern, keeping it in phase with the device offset
+fn fill_repeating(pattern: &[u8], offset: u64, buf: &mut [u8]) {
+    if pattern.is_empty() {
+        buf.fill(0);
+        return;
+    }
+    let mut index = (offset % pattern.len() as u64) as usize;
+    for byte in buf.iter_mut() {
+        *byte = pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{HiddenArea, HiddenAreaType, SanitizationStatus};
+    use tempfile::TempDir;
+
+    const SIZE: usize = 1024 * 1024;
+
+    fn image_device(dir: &TempDir) -> StorageDevice {
+        let path = dir.path().join("disk.img");
+        std::fs::write(&path, vec![0x33u8; SIZE]).unwrap();
+        StorageDevice {
+            path,
+            name: "Disk Image".to_string(),
+            size: SIZE as u64,
+            device_type: DeviceType::HDD,
+            model: None,
+            serial: Some("IMG-0001".to_string()),
+            supports_secure_erase: true,
+            supports_trim: false,
+            hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: Default::default(),
+        }
+    }
+
+    /// Resume a wipe that was interrupted halfway through the second pass of
+    /// the fallback of its first step, which the device had refused
+    async fn resume_in_fallback(dir: &TempDir, device: &StorageDevice, steps: &str, refusal: SecureEraseError) -> WipeResult {
+        let mut registry = MethodRegistry::new();
+        registry.load_toml(&format!("[[method]]\nname = \"fallback\"\nsteps = [{}]\n", steps)).unwrap();
+        let method = registry.get("fallback").unwrap().clone();
+        let journal_path = Checkpoint::journal_path(dir.path(), device);
+
+        let mut state = Checkpoint::new(device, &method);
+        state.fallbacks.push(FallbackTaken { step: 1, reason: refusal.to_string() });
+        state.steps_completed = 2;
+        state.passes.push(PassRecord {
+            pass_number: 1,
+            pattern: PatternSource::Fixed { byte: 0x11 },
+            started_at: SystemTime::now(),
+            finished_at: SystemTime::now(),
+            throughput_bytes_per_sec: 0.0,
+            stats: PassStats { bytes_written: SIZE as u64, ..Default::default() },
+        });
+        state.current_pass = Some(PassProgress {
+            pass_number: 2,
+            pattern: PatternSource::Fixed { byte: 0x22 },
+            offset: SIZE as u64 / 2,
+            started_at: SystemTime::now(),
+            stats: PassStats { bytes_written: SIZE as u64 / 2, ..Default::default() },
+        });
+        state.save(&journal_path).unwrap();
+
+        let mut engine = AdvancedWipeEngine::new().with_journal(journal_path.clone());
+        engine.verify_after_wipe = false;
+        let result = engine.resume_wipe(device, Checkpoint::load(&journal_path).unwrap()).await.unwrap();
+        assert!(!journal_path.exists());
+        result
+    }
+
+    #[tokio::test]
+    async fn test_resume_continues_hardware_command_fallback() {
+        let dir = TempDir::new().unwrap();
+        let device = image_device(&dir);
+        let steps = r#"{ type = "hardware_command", command = "ata_secure_erase", fallback = [
+            { type = "overwrite", pattern = { kind = "fixed", byte = 17 } },
+            { type = "overwrite", pattern = { kind = "fixed", byte = 34 } },
+        ] }"#;
+        let result = resume_in_fallback(&dir, &device, steps, SecureEraseError::DeviceFrozen("security frozen".to_string())).await;
+
+        // The secure erase is not retried; the second fallback pass picks up where it stopped
+        assert!(result.hardware_commands.is_empty());
+        assert_eq!(result.passes.len(), 2);
+        assert_eq!(result.passes[1].stats.bytes_written, SIZE as u64);
+        let data = std::fs::read(&device.path).unwrap();
+        assert!(data[..SIZE / 2].iter().all(|&b| b == 0x33));
+        assert!(data[SIZE / 2..].iter().all(|&b| b == 0x22));
+    }
+
+    #[tokio::test]
+    async fn test_resume_continues_crypto_erase_fallback() {
+        let dir = TempDir::new().unwrap();
+        let device = image_device(&dir);
+        let steps = r#"{ type = "crypto_erase", method = "revert_sp", fallback = [
+            { type = "overwrite", pattern = { kind = "fixed", byte = 17 } },
+            { type = "overwrite", pattern = { kind = "fixed", byte = 34 } },
+        ] }"#;
+        let result = resume_in_fallback(&dir, &device, steps, SecureEraseError::SecureEraseNotSupported).await;
+
+        assert!(result.crypto_erases.is_empty());
+        assert_eq!(result.passes.len(), 2);
+        let data = std::fs::read(&device.path).unwrap();
+        assert!(data[SIZE / 2..].iter().all(|&b| b == 0x22));
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_permanent_hidden_area_restore() {
+        let dir = TempDir::new().unwrap();
+        let device = image_device(&dir);
+        let hpa = HiddenArea {
+            area_type: HiddenAreaType::HPA,
+            start_lba: SIZE as u64 / 1024,
+            size: SIZE as u64 / 1024,
+            description: "Host Protected Area".to_string(),
+        };
+        let before_restore = StorageDevice { size: SIZE as u64 / 2, hidden_areas: vec![hpa.clone()], ..device.clone() };
+        let mut registry = MethodRegistry::new();
+        registry.load_toml(r#"
+            [[method]]
+            name = "restore-then-overwrite"
+            steps = [
+                { type = "restore_hidden_areas", mode = "permanent" },
+                { type = "overwrite", pattern = { kind = "fixed", byte = 17 } },
+            ]
+        "#).unwrap();
+        let method = registry.get("restore-then-overwrite").unwrap().clone();
+        let journal_path = Checkpoint::journal_path(dir.path(), &device);
+
+        // The HPA was removed for good just before the interruption, so the drive now reports its native size
+        let mut state = Checkpoint::new(&before_restore, &method);
+        state.record_hidden_areas(vec![hidden_areas::HiddenAreaStatus {
+            area: hpa,
+            restored: true,
+            permanent: true,
+            attempted_at: Some(SystemTime::now()),
+            wiped: false,
+            note: None,
+        }], SIZE as u64);
+        state.steps_completed = 1;
+        state.save(&journal_path).unwrap();
+
+        let engine = AdvancedWipeEngine::new().with_journal(journal_path.clone());
+        let result = engine.resume_wipe(&device, Checkpoint::load(&journal_path).unwrap()).await.unwrap();
+        assert_eq!(result.passes.len(), 1);
+        assert!(result.hidden_areas.iter().all(|status| status.restored && status.wiped));
+    }
+
+    #[tokio::test]
+    async fn test_crypto_erase_is_not_verified_against_a_pattern() {
+        let dir = TempDir::new().unwrap();
+        let device = image_device(&dir);
+        let ciphertext: Vec<u8> = (0..SIZE).map(|i| (i * 7 + 1) as u8).collect();
+        std::fs::write(&device.path, &ciphertext).unwrap();
+        let method = MethodRegistry::builtin().get("crypto-erase").unwrap().clone();
+        let journal_path = Checkpoint::journal_path(dir.path(), &device);
+
+        // The drive finished its crypto erase just before the interruption
+        let mut state = Checkpoint::new(&device, &method);
+        state.steps_completed = 1;
+        state.expected_contents = ExpectedContents::Ciphertext;
+        state.save(&journal_path).unwrap();
+
+        let engine = AdvancedWipeEngine::new().with_journal(journal_path.clone());
+        let result = engine.resume_wipe(&device, Checkpoint::load(&journal_path).unwrap()).await.unwrap();
+        assert!(result.verification.is_none());
+        assert!(result.verification_passed);
+        assert_eq!(result.sanitization_status(), SanitizationStatus::Complete);
+        assert_eq!(std::fs::read(&device.path).unwrap(), ciphertext);
+    }
+
+    #[tokio::test]
+    async fn test_verification_expects_what_the_last_step_left() {
+        let dir = TempDir::new().unwrap();
+        let device = image_device(&dir);
+        std::fs::write(&device.path, vec![0u8; SIZE]).unwrap();
+        let mut registry = MethodRegistry::new();
+        registry.load_toml(r#"
+            [[method]]
+            name = "overwrite-then-erase"
+            steps = [
+                { type = "overwrite", pattern = { kind = "fixed", byte = 17 } },
+                { type = "hardware_command", command = "ata_secure_erase" },
+            ]
+        "#).unwrap();
+        let method = registry.get("overwrite-then-erase").unwrap().clone();
+        let journal_path = Checkpoint::journal_path(dir.path(), &device);
+
+        // The secure erase zeroed the pass's 0x11 just before the interruption
+        let mut state = Checkpoint::new(&device, &method);
+        state.steps_completed = 2;
+        state.passes.push(PassRecord {
+            pass_number: 1,
+            pattern: PatternSource::Fixed { byte: 0x11 },
+            started_at: SystemTime::now(),
+            finished_at: SystemTime::now(),
+            throughput_bytes_per_sec: 0.0,
+            stats: PassStats { bytes_written: SIZE as u64, ..Default::default() },
+        });
+        state.expected_contents = ExpectedContents::zeros();
+        state.save(&journal_path).unwrap();
+
+        let engine = AdvancedWipeEngine::new().with_journal(journal_path.clone());
+        let result = engine.resume_wipe(&device, Checkpoint::load(&journal_path).unwrap()).await.unwrap();
+        assert!(result.verification.as_ref().is_some_and(|report| report.passed));
+        assert!(result.verification_passed);
+    }
+
+    #[tokio::test]
+    async fn test_trim_without_fallback_needs_trim_support() {
+        let dir = TempDir::new().unwrap();
+        let device = image_device(&dir);
+        let mut registry = MethodRegistry::new();
+        registry.load_toml("[[method]]\nname = \"trim-only\"\nsteps = [{ type = \"trim\" }]\n").unwrap();
+        let method = registry.get("trim-only").unwrap().clone();
+
+        let result = AdvancedWipeEngine::new().secure_erase_with_method(&device, &method).await;
+        assert!(matches!(result, Err(SecureEraseError::SecureEraseNotSupported)));
+    }
+
+    #[tokio::test]
+    async fn test_undefined_discard_contents_are_not_verified() {
+        let dir = TempDir::new().unwrap();
+        let mut device = image_device(&dir);
+        device.supports_trim = true;
+        let mut registry = MethodRegistry::new();
+        registry.load_toml("[[method]]\nname = \"trim-only\"\nsteps = [{ type = \"trim\" }]\n").unwrap();
+        let method = registry.get("trim-only").unwrap().clone();
+        let journal_path = Checkpoint::journal_path(dir.path(), &device);
+
+        // The drive does not zero discarded blocks, so its 0x33 may still read back
+        let mut state = Checkpoint::new(&device, &method);
+        state.steps_completed = 1;
+        state.expected_contents = ExpectedContents::Indeterminate;
+        state.save(&journal_path).unwrap();
+
+        let engine = AdvancedWipeEngine::new().with_journal(journal_path.clone());
+        let result = engine.resume_wipe(&device, Checkpoint::load(&journal_path).unwrap()).await.unwrap();
+        assert!(result.verification.is_none());
+        assert!(result.verification_passed);
+        assert_eq!(result.expected_contents, ExpectedContents::Indeterminate);
+    }
+}
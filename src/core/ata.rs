@@ -0,0 +1,512 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::methods::ExpectedContents;
+use crate::core::sg_io::{DataTransfer, ScsiStatus, ScsiTransport, CHECK_CONDITION};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use log::{info, warn};
+
+/// ATA PASS-THROUGH (16) operation code
+const ATA_16: u8 = 0x85;
+
+const TRUSTED_RECEIVE: u8 = 0x5c;
+const TRUSTED_SEND: u8 = 0x5e;
+const IDENTIFY_DEVICE: u8 = 0xec;
+const SECURITY_SET_PASSWORD: u8 = 0xf1;
+const SECURITY_UNLOCK: u8 = 0xf2;
+const SECURITY_ERASE_PREPARE: u8 = 0xf3;
+const SECURITY_ERASE_UNIT: u8 = 0xf4;
+const SECURITY_DISABLE_PASSWORD: u8 = 0xf6;
+
+/// Sense descriptor carrying the ATA status registers
+const ATA_STATUS_RETURN_DESCRIPTOR: u8 = 0x09;
+const ATA_STATUS_ERR: u8 = 0x01;
+const ATA_STATUS_DF: u8 = 0x20;
+
+/// Timeout for everything except the erase itself
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Erase timeout used when the drive does not report an estimate
+const UNREPORTED_ERASE_TIMEOUT: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Temporary user password set for the duration of the erase
+pub const ERASE_PASSWORD: &str = "SecureErase";
+
+const SECTOR_SIZE: usize = 512;
+
+/// ATA pass-through protocols (SAT-4 table 150)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Protocol {
+    NonData = 3,
+    PioDataIn = 4,
+    PioDataOut = 5,
+}
+
+/// Task file of a 28-bit ATA command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtaCommand {
+    pub command: u8,
+    pub features: u8,
+    pub count: u8,
+    pub lba: u32,
+}
+
+impl AtaCommand {
+    fn new(command: u8) -> Self {
+        Self { command, features: 0, count: 0, lba: 0 }
+    }
+
+    /// Encode as an ATA PASS-THROUGH (16) CDB with CK_COND set, so the
+    /// status registers come back in the sense data
+    fn cdb(&self, protocol: Protocol) -> [u8; 16] {
+        let mut cdb = [0u8; 16];
+        cdb[0] = ATA_16;
+        cdb[1] = (protocol as u8) << 1;
+        cdb[2] = match protocol {
+            Protocol::NonData => 0x20,
+            // T_DIR=1, BYT_BLOK=1, T_LENGTH in the count field
+            Protocol::PioDataIn => 0x2e,
+            Protocol::PioDataOut => 0x26,
+        };
+        cdb[4] = self.features;
+        cdb[6] = self.count;
+        cdb[8] = self.lba as u8;
+        cdb[10] = (self.lba >> 8) as u8;
+        cdb[12] = (self.lba >> 16) as u8;
+        cdb[13] = 0x40 | ((self.lba >> 24) as u8 & 0x0f);
+        cdb[14] = self.command;
+        cdb
+    }
+}
+
+/// ATA Security feature set state (IDENTIFY word 128)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityState {
+    pub supported: bool,
+    pub enabled: bool,
+    pub locked: bool,
+    /// Security commands are refused until the next power cycle
+    pub frozen: bool,
+    /// Too many failed unlock attempts; needs a power cycle
+    pub count_expired: bool,
+    pub enhanced_erase_supported: bool,
+}
+
+/// Fields of IDENTIFY DEVICE data used by the erase path
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IdentifyData {
+    pub model: String,
+    pub serial: String,
+    pub firmware: String,
+    /// User-addressable sectors
+    pub sectors: u64,
+    pub security: SecurityState,
+    /// Drive's estimate for SECURITY ERASE UNIT, in minutes
+    pub normal_erase_minutes: Option<u32>,
+    pub enhanced_erase_minutes: Option<u32>,
+    /// Trimmed blocks read back the same on every read (word 69 bit 14, DRAT)
+    #[serde(default)]
+    pub deterministic_after_trim: bool,
+    /// Trimmed blocks read back as zeroes (word 69 bit 5, RZAT)
+    #[serde(default)]
+    pub zeroes_after_trim: bool,
+}
+
+impl IdentifyData {
+    /// Decode the 512-byte IDENTIFY DEVICE response
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < SECTOR_SIZE {
+            return Err(SecureEraseError::WipeFailed(format!("IDENTIFY data too short ({} bytes)", data.len())));
+        }
+        let word = |index: usize| u16::from_le_bytes([data[index * 2], data[index * 2 + 1]]);
+        // Strings hold two characters per word, first character in the high byte
+        let string = |first: usize, last: usize| {
+            let bytes: Vec<u8> = (first..=last).flat_map(|index| word(index).to_be_bytes()).collect();
+            String::from_utf8_lossy(&bytes).trim_matches(|c: char| c.is_whitespace() || c == '\0').to_string()
+        };
+
+        let sectors = if word(83) & (1 << 10) != 0 {
+            (100..=103).rev().fold(0u64, |sectors, index| (sectors << 16) | word(index) as u64)
+        } else {
+            (word(61) as u64) << 16 | word(60) as u64
+        };
+        let security_word = word(128);
+        let security = SecurityState {
+            supported: security_word & 0x0001 != 0,
+            enabled: security_word & 0x0002 != 0,
+            locked: security_word & 0x0004 != 0,
+            frozen: security_word & 0x0008 != 0,
+            count_expired: security_word & 0x0010 != 0,
+            enhanced_erase_supported: security_word & 0x0020 != 0,
+        };
+
+        Ok(Self {
+            model: string(27, 46),
+            serial: string(10, 19),
+            firmware: string(23, 26),
+            sectors,
+            security,
+            normal_erase_minutes: erase_minutes(word(89)),
+            enhanced_erase_minutes: erase_minutes(word(90)),
+            deterministic_after_trim: word(69) & (1 << 14) != 0,
+            zeroes_after_trim: word(69) & (1 << 5) != 0,
+        })
+    }
+}
+
+/// Decode an erase time word (ACS-3 7.12.7.38): units of 2 minutes,
+/// 15 bits wide when bit 15 is set, otherwise 8 bits with 255 meaning "more than 508"
+fn erase_minutes(word: u16) -> Option<u32> {
+    let units = if word & 0x8000 != 0 { word & 0x7fff } else { word & 0x00ff };
+    if units == 0 {
+        None
+    } else {
+        Some(units as u32 * 2)
+    }
+}
+
+/// Time to wait for SECURITY ERASE UNIT: the drive's estimate plus 50% and ten minutes
+pub fn erase_timeout(estimate_minutes: Option<u32>) -> Duration {
+    match estimate_minutes {
+        Some(minutes) => Duration::from_secs((minutes as u64 * 3 / 2 + 10) * 60),
+        None => UNREPORTED_ERASE_TIMEOUT,
+    }
+}
+
+/// Outcome of a completed ATA Security Erase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtaEraseReport {
+    pub enhanced: bool,
+    pub estimated_minutes: Option<u32>,
+    pub timeout_seconds: u64,
+    pub elapsed_seconds: u64,
+}
+
+impl AtaEraseReport {
+    /// Normal erase writes zeroes; enhanced erase writes a pattern of the vendor's choosing
+    pub fn expected_contents(&self) -> ExpectedContents {
+        if self.enhanced {
+            ExpectedContents::Indeterminate
+        } else {
+            ExpectedContents::zeros()
+        }
+    }
+}
+
+/// ATA drive reached through SCSI/ATA translation
+pub struct AtaDevice<T: ScsiTransport> {
+    transport: T,
+}
+
+impl<T: ScsiTransport> AtaDevice<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    pub fn identify(&mut self) -> Result<IdentifyData> {
+        let mut data = [0u8; SECTOR_SIZE];
+        let command = AtaCommand { count: 1, ..AtaCommand::new(IDENTIFY_DEVICE) };
+        let status = self.transport.execute(
+            &command.cdb(Protocol::PioDataIn), DataTransfer::FromDevice(&mut data), COMMAND_TIMEOUT,
+        )?;
+        check_status(&command, &status)?;
+        IdentifyData::parse(&data)
+    }
+
+    pub fn set_user_password(&mut self, password: &str) -> Result<()> {
+        // Word 0: user password, high master password capability
+        self.send_password(SECURITY_SET_PASSWORD, 0x0000, password, COMMAND_TIMEOUT)
+    }
+
+    pub fn unlock(&mut self, password: &str) -> Result<()> {
+        self.send_password(SECURITY_UNLOCK, 0x0000, password, COMMAND_TIMEOUT)
+    }
+
+    pub fn disable_password(&mut self, password: &str) -> Result<()> {
+        self.send_password(SECURITY_DISABLE_PASSWORD, 0x0000, password, COMMAND_TIMEOUT)
+    }
+
+    /// SECURITY ERASE PREPARE immediately followed by SECURITY ERASE UNIT
+    pub fn erase_unit(&mut self, password: &str, enhanced: bool, timeout: Duration) -> Result<()> {
+        let prepare = AtaCommand::new(SECURITY_ERASE_PREPARE);
+        let status = self.transport.execute(&prepare.cdb(Protocol::NonData), DataTransfer::None, COMMAND_TIMEOUT)?;
+        check_status(&prepare, &status)?;
+        let control = if enhanced { 0x0002 } else { 0x0000 };
+        self.send_password(SECURITY_ERASE_UNIT, control, password, timeout)
+    }
+
+    /// TRUSTED SEND of whole sectors to a security protocol
+    pub fn trusted_send(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()> {
+        let command = trusted_command(TRUSTED_SEND, protocol, sp_specific, data.len())?;
+        let status = self.transport.execute(&command.cdb(Protocol::PioDataOut), DataTransfer::ToDevice(data), COMMAND_TIMEOUT)?;
+        check_status(&command, &status)
+    }
+
+    /// TRUSTED RECEIVE of whole sectors from a security protocol
+    pub fn trusted_receive(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()> {
+        let command = trusted_command(TRUSTED_RECEIVE, protocol, sp_specific, data.len())?;
+        let status = self.transport.execute(&command.cdb(Protocol::PioDataIn), DataTransfer::FromDevice(data), COMMAND_TIMEOUT)?;
+        check_status(&command, &status)
+    }
+
+    fn send_password(&mut self, opcode: u8, control: u16, password: &str, timeout: Duration) -> Result<()> {
+        let mut data = [0u8; SECTOR_SIZE];
+        data[..2].copy_from_slice(&control.to_le_bytes());
+        let bytes = password.as_bytes();
+        let length = std::cmp::min(bytes.len(), 32);
+        data[2..2 + length].copy_from_slice(&bytes[..length]);
+
+        let command = AtaCommand { count: 1, ..AtaCommand::new(opcode) };
+        let status = self.transport.execute(&command.cdb(Protocol::PioDataOut), DataTransfer::ToDevice(&data), timeout)?;
+        check_status(&command, &status)
+    }
+}
+
+/// Task file of TRUSTED SEND/RECEIVE: the transfer length in sectors is split
+/// between the count field and LBA bits 7:0, the SP specific value fills LBA bits 23:8
+fn trusted_command(opcode: u8, protocol: u8, sp_specific: u16, length: usize) -> Result<AtaCommand> {
+    if !length.is_multiple_of(SECTOR_SIZE) || length / SECTOR_SIZE > 0xff {
+        return Err(SecureEraseError::WipeFailed(format!("Trusted transfer of {} bytes is not a supported sector count", length)));
+    }
+    Ok(AtaCommand {
+        command: opcode,
+        features: protocol,
+        count: (length / SECTOR_SIZE) as u8,
+        lba: (sp_specific as u32) << 8,
+    })
+}
+
+/// Map the SCSI completion of a pass-through command to an ATA result
+fn check_status(command: &AtaCommand, status: &ScsiStatus) -> Result<()> {
+    if status.is_good() {
+        return Ok(());
+    }
+    if status.status == CHECK_CONDITION {
+        // With CK_COND set, a successful command reports its registers in an ATA Status Return descriptor
+        if let Some(descriptor) = status.descriptor(ATA_STATUS_RETURN_DESCRIPTOR).filter(|d| d.len() >= 14) {
+            let (error, ata_status) = (descriptor[3], descriptor[13]);
+            if ata_status & (ATA_STATUS_ERR | ATA_STATUS_DF) == 0 {
+                return Ok(());
+            }
+            return Err(SecureEraseError::WipeFailed(format!(
+                "ATA command 0x{:02x} aborted (status 0x{:02x}, error 0x{:02x})", command.command, ata_status, error
+            )));
+        }
+    }
+    Err(SecureEraseError::WipeFailed(format!(
+        "ATA command 0x{:02x} failed: {}", command.command, status.describe()
+    )))
+}
+
+/// Run ATA Security Erase, preferring the enhanced variant when asked and supported.
+///
+/// The drive must have security supported, not enabled and not frozen. A
+/// temporary user password is set for the erase; if the erase fails, the
+/// password is removed again so the drive is not left locked.
+pub fn secure_erase<T: ScsiTransport>(device: &mut AtaDevice<T>, prefer_enhanced: bool) -> Result<AtaEraseReport> {
+    let identify = device.identify()?;
+    let security = identify.security;
+    if !security.supported {
+        return Err(SecureEraseError::SecureEraseNotSupported);
+    }
+    if security.frozen {
+        return Err(SecureEraseError::DeviceFrozen(
+            "suspend and resume the host, or hot-plug the drive, then retry".to_string()
+        ));
+    }
+    if security.count_expired {
+        return Err(SecureEraseError::WipeFailed("security unlock attempts exhausted; power cycle the drive".to_string()));
+    }
+    if security.enabled || security.locked {
+        return Err(SecureEraseError::WipeFailed("drive already has a security password set".to_string()));
+    }
+
+    let enhanced = prefer_enhanced && security.enhanced_erase_supported;
+    let estimated_minutes = if enhanced { identify.enhanced_erase_minutes } else { identify.normal_erase_minutes };
+    let timeout = erase_timeout(estimated_minutes);
+    info!("ATA {} security erase of {} ({}), estimated {} minutes",
+          if enhanced { "enhanced" } else { "normal" }, identify.model, identify.serial,
+          estimated_minutes.map(|m| m.to_string()).unwrap_or_else(|| "unknown".to_string()));
+
+    device.set_user_password(ERASE_PASSWORD)?;
+    let started = Instant::now();
+    if let Err(e) = device.erase_unit(ERASE_PASSWORD, enhanced, timeout) {
+        return Err(match clear_password(device) {
+            Ok(()) => SecureEraseError::WipeFailed(format!("{}; temporary password removed", e)),
+            Err(cleanup) => SecureEraseError::WipeFailed(format!(
+                "{}; could not remove temporary user password '{}': {}", e, ERASE_PASSWORD, cleanup
+            )),
+        });
+    }
+    let elapsed = started.elapsed();
+
+    // A successful erase disables the password; make sure of it
+    if device.identify()?.security.enabled {
+        warn!("Security still enabled after erase, removing temporary password");
+        clear_password(device)?;
+    }
+
+    Ok(AtaEraseReport {
+        enhanced,
+        estimated_minutes,
+        timeout_seconds: timeout.as_secs(),
+        elapsed_seconds: elapsed.as_secs(),
+    })
+}
+
+/// Remove the temporary password after a failed erase, unlocking first if needed
+fn clear_password<T: ScsiTransport>(device: &mut AtaDevice<T>) -> Result<()> {
+    let security = device.identify()?.security;
+    if !security.enabled {
+        return Ok(());
+    }
+    if security.locked {
+        device.unlock(ERASE_PASSWORD)?;
+    }
+    device.disable_password(ERASE_PASSWORD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drive simulator answering ATA-16 CDBs
+    struct MockDrive {
+        identify: [u8; SECTOR_SIZE],
+        commands: Vec<(u8, Duration)>,
+        fail_erase: bool,
+        password: Option<Vec<u8>>,
+    }
+
+    impl MockDrive {
+        fn new(security_word: u16) -> Self {
+            let mut identify = [0u8; SECTOR_SIZE];
+            let mut set_word = |index: usize, value: u16| identify[index * 2..index * 2 + 2].copy_from_slice(&value.to_le_bytes());
+            set_word(83, 1 << 10);
+            set_word(100, 0x6db0);
+            set_word(101, 0x7470);
+            set_word(89, 0x8000 | 60);
+            set_word(90, 2);
+            set_word(128, security_word);
+            for (index, pair) in b"WDC WD10EZEX-08WN4A0".chunks(2).enumerate() {
+                set_word(27 + index, u16::from_be_bytes([pair[0], pair[1]]));
+            }
+            for (index, pair) in b"  WD-WCC6Y0123456".chunks(2).enumerate() {
+                let second = pair.get(1).copied().unwrap_or(b' ');
+                set_word(10 + index, u16::from_be_bytes([pair[0], second]));
+            }
+            Self { identify, commands: Vec::new(), fail_erase: false, password: None }
+        }
+
+        fn opcodes(&self) -> Vec<u8> {
+            self.commands.iter().map(|(opcode, _)| *opcode).collect()
+        }
+
+        fn security_word(&self) -> u16 {
+            u16::from_le_bytes([self.identify[256], self.identify[257]])
+        }
+
+        fn set_security_word(&mut self, value: u16) {
+            self.identify[256..258].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    impl ScsiTransport for MockDrive {
+        fn execute(&mut self, cdb: &[u8], transfer: DataTransfer<'_>, timeout: Duration) -> Result<ScsiStatus> {
+            assert_eq!(cdb[0], ATA_16);
+            self.commands.push((cdb[14], timeout));
+            let aborted = ScsiStatus {
+                status: CHECK_CONDITION,
+                sense: vec![0x72, 0x01, 0x00, 0x1d, 0, 0, 0, 14, 0x09, 0x0c, 0, 0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0x40, 0x51],
+            };
+            match (cdb[14], transfer) {
+                (IDENTIFY_DEVICE, DataTransfer::FromDevice(buf)) => buf.copy_from_slice(&self.identify),
+                (SECURITY_SET_PASSWORD, DataTransfer::ToDevice(data)) => {
+                    self.password = Some(data[2..34].to_vec());
+                    self.set_security_word(self.security_word() | 0x0002);
+                }
+                (SECURITY_ERASE_PREPARE, DataTransfer::None) => {}
+                (SECURITY_ERASE_UNIT, DataTransfer::ToDevice(data)) => {
+                    if self.fail_erase {
+                        // An interrupted erase leaves the drive locked
+                        self.set_security_word(self.security_word() | 0x0004);
+                        return Ok(aborted);
+                    }
+                    assert_eq!(Some(data[2..34].to_vec()), self.password);
+                    self.password = None;
+                    self.set_security_word(self.security_word() & !0x0006);
+                }
+                (SECURITY_UNLOCK, DataTransfer::ToDevice(_)) => self.set_security_word(self.security_word() & !0x0004),
+                (SECURITY_DISABLE_PASSWORD, DataTransfer::ToDevice(_)) => {
+                    self.password = None;
+                    self.set_security_word(self.security_word() & !0x0002);
+                }
+                (opcode, _) => panic!("unexpected ATA command 0x{:02x}", opcode),
+            }
+            Ok(ScsiStatus::default())
+        }
+    }
+
+    #[test]
+    fn test_parses_identify_data() {
+        let identify = IdentifyData::parse(&MockDrive::new(0x0021).identify).unwrap();
+        assert_eq!(identify.model, "WDC WD10EZEX-08WN4A0");
+        assert_eq!(identify.serial, "WD-WCC6Y0123456");
+        assert_eq!(identify.sectors, 1953525168);
+        assert!(identify.security.supported && identify.security.enhanced_erase_supported);
+        assert!(!identify.security.frozen && !identify.security.enabled);
+        assert_eq!(identify.normal_erase_minutes, Some(120));
+        assert_eq!(identify.enhanced_erase_minutes, Some(4));
+        assert_eq!(erase_minutes(0x00ff), Some(510));
+        assert_eq!(erase_minutes(0), None);
+    }
+
+    #[test]
+    fn test_encodes_ata16_cdb() {
+        let cdb = AtaCommand { count: 1, ..AtaCommand::new(SECURITY_ERASE_UNIT) }.cdb(Protocol::PioDataOut);
+        assert_eq!(cdb[..3], [0x85, 0x0a, 0x26]);
+        assert_eq!(cdb[6], 1);
+        assert_eq!(cdb[13], 0x40);
+        assert_eq!(cdb[14], 0xf4);
+    }
+
+    #[test]
+    fn test_enhanced_erase_uses_drive_estimate() {
+        let mut device = AtaDevice::new(MockDrive::new(0x0021));
+        let report = secure_erase(&mut device, true).unwrap();
+        assert!(report.enhanced);
+        assert_eq!(report.expected_contents(), ExpectedContents::Indeterminate);
+        assert_eq!(report.estimated_minutes, Some(4));
+        assert_eq!(device.transport.opcodes(), vec![
+            IDENTIFY_DEVICE, SECURITY_SET_PASSWORD, SECURITY_ERASE_PREPARE, SECURITY_ERASE_UNIT, IDENTIFY_DEVICE,
+        ]);
+        assert_eq!(device.transport.commands[3].1, erase_timeout(Some(4)));
+        assert_eq!(erase_timeout(Some(4)), Duration::from_secs(16 * 60));
+
+        // Without enhanced support the normal erase and its estimate are used
+        let mut device = AtaDevice::new(MockDrive::new(0x0001));
+        let report = secure_erase(&mut device, true).unwrap();
+        assert!(!report.enhanced);
+        assert_eq!(report.expected_contents(), ExpectedContents::zeros());
+        assert_eq!(report.timeout_seconds, erase_timeout(Some(120)).as_secs());
+    }
+
+    #[test]
+    fn test_frozen_drive_is_refused() {
+        let mut device = AtaDevice::new(MockDrive::new(0x0009));
+        assert!(matches!(secure_erase(&mut device, true), Err(SecureEraseError::DeviceFrozen(_))));
+        assert_eq!(device.transport.opcodes(), vec![IDENTIFY_DEVICE]);
+    }
+
+    #[test]
+    fn test_failed_erase_removes_password() {
+        let mut drive = MockDrive::new(0x0021);
+        drive.fail_erase = true;
+        let mut device = AtaDevice::new(drive);
+        let error = secure_erase(&mut device, false).unwrap_err().to_string();
+        assert!(error.contains("aborted"), "{}", error);
+        assert!(error.contains("temporary password removed"), "{}", error);
+        assert_eq!(device.transport.opcodes()[4..], [IDENTIFY_DEVICE, SECURITY_UNLOCK, SECURITY_DISABLE_PASSWORD]);
+        assert_eq!(device.transport.security_word() & 0x0006, 0);
+        assert!(device.transport.password.is_none());
+    }
+}
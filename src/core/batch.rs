@@ -0,0 +1,313 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, DeviceType, SanitizationStatus, WipeResult};
+use crate::core::advanced::AdvancedWipeEngine;
+use crate::core::checkpoint::Checkpoint;
+use crate::core::methods::WipeMethod;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use log::{info, error};
+
+/// Filter for choosing batch targets, e.g. `type=hdd,model=WDC,min-size=500G`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceSelector {
+    pub device_type: Option<DeviceType>,
+    /// Case-insensitive substring of the model name
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    /// Prefix of the device path, e.g. `/dev/sd`
+    pub path_prefix: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+impl DeviceSelector {
+    /// Parse a comma-separated list of `key=value` filters
+    pub fn parse(selector: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+        for clause in selector.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            let (key, value) = clause.split_once('=')
+                .ok_or_else(|| invalid_selector(clause))?;
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "type" => parsed.device_type = Some(parse_device_type(value)?),
+                "model" => parsed.model = Some(value.to_string()),
+                "serial" => parsed.serial = Some(value.to_string()),
+                "path" => parsed.path_prefix = Some(value.to_string()),
+                "min-size" => parsed.min_size = Some(parse_size(value)?),
+                "max-size" => parsed.max_size = Some(parse_size(value)?),
+                _ => return Err(invalid_selector(clause)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn matches(&self, device: &StorageDevice) -> bool {
+        if let Some(device_type) = &self.device_type {
+            if device.device_type != *device_type {
+                return false;
+            }
+        }
+        if let Some(model) = &self.model {
+            let found = device.model.as_deref().unwrap_or(&device.name);
+            if !found.to_lowercase().contains(&model.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(serial) = &self.serial {
+            if device.serial.as_deref().map(str::trim) != Some(serial.as_str()) {
+                return false;
+            }
+        }
+        if let Some(prefix) = &self.path_prefix {
+            if !device.path.to_string_lossy().starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        self.min_size.is_none_or(|min| device.size >= min)
+            && self.max_size.is_none_or(|max| device.size <= max)
+    }
+}
+
+fn invalid_selector(clause: &str) -> SecureEraseError {
+    SecureEraseError::InvalidEraseMode(format!(
+        "Invalid device selector '{}' (expected type=, model=, serial=, path=, min-size= or max-size=)", clause
+    ))
+}
+
+fn parse_device_type(value: &str) -> Result<DeviceType> {
+    match value.to_lowercase().as_str() {
+        "hdd" => Ok(DeviceType::HDD),
+        "ssd" => Ok(DeviceType::SSD),
+        "nvme" => Ok(DeviceType::NVMe),
+        "sas" => Ok(DeviceType::SAS),
+        "usb" => Ok(DeviceType::USB),
+        _ => Err(SecureEraseError::UnsupportedDeviceType(value.to_string())),
+    }
+}
+
+/// Parse a byte count with an optional K/M/G/T (binary) suffix
+fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim().to_uppercase();
+    let digits = value.trim_end_matches(['B', 'I']);
+    let (number, multiplier) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 1u64 << 10),
+        Some('M') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('G') => (&digits[..digits.len() - 1], 1 << 30),
+        Some('T') => (&digits[..digits.len() - 1], 1 << 40),
+        _ => (digits, 1),
+    };
+    number.trim().parse::<u64>()
+        .map(|n| n.saturating_mul(multiplier))
+        .map_err(|_| SecureEraseError::InvalidEraseMode(format!("Invalid size '{}'", value)))
+}
+
+/// Limits applied to a batch wipe
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Maximum number of devices wiped at the same time
+    pub max_concurrent: usize,
+    /// Write limit applied to each device, in bytes per second
+    pub bandwidth_limit: Option<u64>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self { max_concurrent: 4, bandwidth_limit: None }
+    }
+}
+
+/// One device of a batch, with the engine configured for it
+pub struct BatchJob {
+    pub device: StorageDevice,
+    pub engine: AdvancedWipeEngine,
+    /// Journal to continue from instead of starting a fresh wipe
+    pub resume: Option<Checkpoint>,
+}
+
+/// Final state of one device in a batch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Outcome of wiping one device of a batch
+#[derive(Debug)]
+pub struct DeviceOutcome {
+    pub device: StorageDevice,
+    pub status: BatchStatus,
+    pub result: Option<WipeResult>,
+    pub error: Option<String>,
+}
+
+/// Wipe several devices concurrently.
+///
+/// Each device runs in its own task, so a failing or panicking wipe is
+/// reported in its outcome without affecting the others. Outcomes are
+/// returned in job order.
+pub async fn run_batch(jobs: Vec<BatchJob>, method: &WipeMethod, options: &BatchOptions) -> Vec<DeviceOutcome> {
+    info!("Starting batch wipe of {} device(s), at most {} at a time", jobs.len(), options.max_concurrent);
+
+    let permits = Arc::new(Semaphore::new(options.max_concurrent.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut task_index = HashMap::new();
+    let mut devices = Vec::with_capacity(jobs.len());
+
+    for (index, job) in jobs.into_iter().enumerate() {
+        devices.push(job.device.clone());
+        let permits = permits.clone();
+        let method = method.clone();
+        let engine = match options.bandwidth_limit {
+            Some(limit) => job.engine.with_bandwidth_limit(limit),
+            None => job.engine,
+        };
+        let handle = tasks.spawn(async move {
+            // The semaphore is never closed, so this only waits for a free slot
+            let _permit = permits.acquire_owned().await;
+            match job.resume {
+                Some(checkpoint) => engine.resume_wipe(&job.device, checkpoint).await,
+                None => engine.secure_erase_with_method(&job.device, &method).await,
+            }
+        });
+        task_index.insert(handle.id(), index);
+    }
+
+    let mut outcomes: Vec<Option<DeviceOutcome>> = devices.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next_with_id().await {
+        let (index, result) = match joined {
+            Ok((id, result)) => (task_index[&id], result),
+            Err(e) => {
+                // A panic in one wipe is contained to its own device
+                error!("Batch wipe task failed: {}", e);
+                (task_index[&e.id()], Err(SecureEraseError::WipeFailed(format!("Wipe task failed: {}", e))))
+            }
+        };
+        outcomes[index] = Some(outcome(devices[index].clone(), result));
+    }
+
+    outcomes.into_iter().map(|outcome| outcome.expect("every batch task reports an outcome")).collect()
+}
+
+fn outcome(device: StorageDevice, result: Result<WipeResult>) -> DeviceOutcome {
+    match result {
+        Ok(result) => {
+            info!("Batch wipe of {} succeeded", device.path.display());
+            DeviceOutcome { device, status: BatchStatus::Succeeded, result: Some(result), error: None }
+        }
+        Err(SecureEraseError::Cancelled) => {
+            DeviceOutcome { device, status: BatchStatus::Cancelled, result: None, error: Some("Cancelled".to_string()) }
+        }
+        Err(e) => {
+            error!("Batch wipe of {} failed: {}", device.path.display(), e);
+            DeviceOutcome { device, status: BatchStatus::Failed, result: None, error: Some(e.to_string()) }
+        }
+    }
+}
+
+/// Per-device line of the batch summary report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSummary {
+    pub path: PathBuf,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub size: u64,
+    pub status: BatchStatus,
+    pub error: Option<String>,
+    pub duration_seconds: Option<u64>,
+    pub verification_passed: Option<bool>,
+    pub sanitization_status: Option<SanitizationStatus>,
+    pub certificate_path: Option<PathBuf>,
+}
+
+/// Summary report written at the end of a batch wipe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub method: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub devices: Vec<DeviceSummary>,
+}
+
+impl BatchSummary {
+    pub fn new(method: &WipeMethod, started_at: SystemTime, outcomes: &[DeviceOutcome]) -> Self {
+        let count = |status| outcomes.iter().filter(|o| o.status == status).count();
+        Self {
+            method: method.name.clone(),
+            started_at: started_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            finished_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            total: outcomes.len(),
+            succeeded: count(BatchStatus::Succeeded),
+            failed: count(BatchStatus::Failed),
+            cancelled: count(BatchStatus::Cancelled),
+            devices: outcomes.iter().map(|o| DeviceSummary {
+                path: o.device.path.clone(),
+                model: o.device.model.clone(),
+                serial: o.device.serial.clone(),
+                size: o.device.size,
+                status: o.status,
+                error: o.error.clone(),
+                duration_seconds: o.result.as_ref().map(|r| r.duration_seconds),
+                verification_passed: o.result.as_ref().map(|r| r.verification_passed),
+                sanitization_status: o.result.as_ref().map(|r| r.sanitization_status()),
+                certificate_path: None,
+            }).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(device_type: DeviceType, model: &str, size: u64) -> StorageDevice {
+        StorageDevice {
+            path: PathBuf::from("/dev/sdb"),
+            name: model.to_string(),
+            size,
+            device_type,
+            model: Some(model.to_string()),
+            serial: Some("WD-123".to_string()),
+            supports_secure_erase: false,
+            supports_trim: false,
+            hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: Default::default(),
+        }
+    }
+
+    #[test]
+    fn parses_selector_clauses() {
+        let selector = DeviceSelector::parse("type=hdd, model=WDC ,min-size=500G,path=/dev/sd").unwrap();
+        assert_eq!(selector.device_type, Some(DeviceType::HDD));
+        assert_eq!(selector.model.as_deref(), Some("WDC"));
+        assert_eq!(selector.min_size, Some(500 << 30));
+        assert_eq!(selector.path_prefix.as_deref(), Some("/dev/sd"));
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("2TiB").unwrap(), 2 << 40);
+
+        assert!(DeviceSelector::parse("colour=blue").is_err());
+        assert!(DeviceSelector::parse("type=tape").is_err());
+        assert!(DeviceSelector::parse("min-size=lots").is_err());
+    }
+
+    #[test]
+    fn matches_all_clauses() {
+        let selector = DeviceSelector::parse("type=hdd,model=wdc,min-size=500G").unwrap();
+        assert!(selector.matches(&device(DeviceType::HDD, "WDC WD10EZEX", 1 << 40)));
+        assert!(!selector.matches(&device(DeviceType::SSD, "WDC WDS100T", 1 << 40)));
+        assert!(!selector.matches(&device(DeviceType::HDD, "ST1000DM010", 1 << 40)));
+        assert!(!selector.matches(&device(DeviceType::HDD, "WDC WD2500", 250 << 30)));
+        assert!(DeviceSelector::default().matches(&device(DeviceType::USB, "Flash", 1)));
+    }
+}
@@ -0,0 +1,498 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{LbaRange, PassStats};
+use std::alloc::{self, Layout};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+
+/// Default size of the IO buffer used for overwrite passes (4 MiB)
+pub const DEFAULT_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// Memory alignment required for O_DIRECT transfers
+const BUFFER_ALIGNMENT: usize = 4096;
+
+/// Logical block size assumed for regular files and images
+const DEFAULT_LOGICAL_BLOCK_SIZE: u32 = 512;
+
+#[cfg(target_os = "linux")]
+const BLKSSZGET: u64 = 0x1268;
+#[cfg(target_os = "linux")]
+const BLKPBSZGET: u64 = 0x127b;
+#[cfg(target_os = "linux")]
+const BLKGETSIZE64: u64 = 0x8008_1272;
+
+/// How failed chunk writes are handled during an overwrite pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Further attempts for a chunk after its first write fails
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every further retry
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Skip chunks that keep failing and record their bad blocks as unwritable
+    pub skip_unwritable: bool,
+    /// Abort the pass once this many chunks had to be skipped
+    pub max_skipped_chunks: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            skip_unwritable: true,
+            max_skipped_chunks: 64,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Fail on the first write error
+    pub fn fail_fast() -> Self {
+        Self { max_retries: 0, skip_unwritable: false, ..Self::default() }
+    }
+
+    /// Delay before retry number `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        std::cmp::min(self.initial_backoff.saturating_mul(factor), self.max_backoff)
+    }
+}
+
+/// Window over which peak write throughput is measured
+const PEAK_WINDOW: Duration = Duration::from_secs(1);
+
+/// Result of an overwrite pass
+#[derive(Debug, Clone, Default)]
+pub struct OverwriteOutcome {
+    pub logical_block_size: u32,
+    /// Measured figures for the bytes written from the start offset
+    pub stats: PassStats,
+}
+
+/// Heap buffer aligned for direct IO
+pub struct AlignedBuffer {
+    ptr: *mut u8,
+    len: usize,
+    layout: Layout,
+}
+
+// The buffer owns its allocation exclusively
+unsafe impl Send for AlignedBuffer {}
+
+impl AlignedBuffer {
+    pub fn new(len: usize) -> Self {
+        let layout = Layout::from_size_align(len.max(1), BUFFER_ALIGNMENT)
+            .expect("invalid buffer layout");
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        Self { ptr, len, layout }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout) }
+    }
+}
+
+/// An opened block device or disk image used as a wipe target
+pub struct BlockDevice {
+    file: File,
+    pub path: PathBuf,
+    pub size: u64,
+    pub logical_block_size: u32,
+    pub physical_block_size: u32,
+    pub is_block_device: bool,
+    pub direct_io: bool,
+}
+
+impl BlockDevice {
+    /// Open a target for overwriting with O_DIRECT/O_SYNC where supported.
+    ///
+    /// `size_hint` is used as the target length for regular files; block
+    /// devices always report their own size.
+    pub fn open_for_write(path: &Path, size_hint: u64) -> Result<Self> {
+        Self::open(path, size_hint, true)
+    }
+
+    /// Open a target for reading back with O_DIRECT, bypassing the page cache
+    pub fn open_for_read(path: &Path, size_hint: u64) -> Result<Self> {
+        Self::open(path, size_hint, false)
+    }
+
+    fn open(path: &Path, size_hint: u64, write: bool) -> Result<Self> {
+        let (file, direct_io) = Self::open_direct(path, write)?;
+        let metadata = file.metadata()?;
+        let is_block_device = Self::is_block_device(&metadata);
+
+        let (size, logical_block_size, physical_block_size) = if is_block_device {
+            Self::query_geometry(&file)?
+        } else {
+            let size = if size_hint > 0 { size_hint } else { metadata.len() };
+            (size, DEFAULT_LOGICAL_BLOCK_SIZE, DEFAULT_LOGICAL_BLOCK_SIZE)
+        };
+
+        debug!(
+            "Opened {} (size: {}, logical block: {}, physical block: {}, direct IO: {})",
+            path.display(), size, logical_block_size, physical_block_size, direct_io
+        );
+
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            size,
+            logical_block_size,
+            physical_block_size,
+            is_block_device,
+            direct_io,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open_direct(path: &Path, write: bool) -> Result<(File, bool)> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let direct = OpenOptions::new()
+            .read(!write)
+            .write(write)
+            .custom_flags(libc::O_DIRECT | libc::O_SYNC)
+            .open(path);
+
+        match direct {
+            Ok(file) => Ok((file, true)),
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                // Some filesystems (e.g. tmpfs) reject O_DIRECT; fall back to synchronous IO
+                warn!("O_DIRECT not supported for {}, using O_SYNC only", path.display());
+                let file = OpenOptions::new()
+                    .read(!write)
+                    .write(write)
+                    .custom_flags(libc::O_SYNC)
+                    .open(path)?;
+                Ok((file, false))
+            }
+            Err(e) => Err(SecureEraseError::Io(e)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn open_direct(path: &Path, write: bool) -> Result<(File, bool)> {
+        let file = OpenOptions::new().read(!write).write(write).open(path)?;
+        Ok((file, false))
+    }
+
+    #[cfg(unix)]
+    fn is_block_device(metadata: &std::fs::Metadata) -> bool {
+        use std::os::unix::fs::FileTypeExt;
+        metadata.file_type().is_block_device()
+    }
+
+    #[cfg(not(unix))]
+    fn is_block_device(_metadata: &std::fs::Metadata) -> bool {
+        false
+    }
+
+    /// Query size and logical/physical block sizes from the kernel
+    #[cfg(target_os = "linux")]
+    fn query_geometry(file: &File) -> Result<(u64, u32, u32)> {
+        use std::os::unix::io::AsRawFd;
+
+        let fd = file.as_raw_fd();
+        let mut size: u64 = 0;
+        let mut logical: libc::c_int = 0;
+        let mut physical: libc::c_uint = 0;
+
+        unsafe {
+            if libc::ioctl(fd, BLKGETSIZE64 as _, &mut size) != 0 {
+                return Err(SecureEraseError::Io(std::io::Error::last_os_error()));
+            }
+            if libc::ioctl(fd, BLKSSZGET as _, &mut logical) != 0 {
+                return Err(SecureEraseError::Io(std::io::Error::last_os_error()));
+            }
+            if libc::ioctl(fd, BLKPBSZGET as _, &mut physical) != 0 {
+                physical = logical as libc::c_uint;
+            }
+        }
+
+        Ok((size, logical as u32, physical))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn query_geometry(file: &File) -> Result<(u64, u32, u32)> {
+        Ok((file.metadata()?.len(), DEFAULT_LOGICAL_BLOCK_SIZE, DEFAULT_LOGICAL_BLOCK_SIZE))
+    }
+
+    /// Round a requested buffer size to a multiple of the block and memory alignment
+    pub fn aligned_buffer_size(&self, requested: usize) -> usize {
+        let unit = (self.logical_block_size as usize).max(BUFFER_ALIGNMENT);
+        (requested / unit).max(1) * unit
+    }
+
+    /// Write a block-aligned chunk at the given offset
+    pub fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        self.prepare_transfer(data.len())?;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)?;
+        Ok(())
+    }
+
+    /// Read a block-aligned chunk at the given offset
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.prepare_transfer(buf.len())?;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(buf)?;
+        Ok(())
+    }
+
+    fn prepare_transfer(&mut self, len: usize) -> Result<()> {
+        if self.direct_io && !len.is_multiple_of(self.logical_block_size as usize) {
+            // Unaligned tail of an image file: finish with buffered IO
+            self.disable_direct_io()?;
+        }
+        Ok(())
+    }
+
+    /// Flush all written data to stable storage
+    pub fn sync(&self) -> Result<()> {
+        self.file.sync_all()?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn disable_direct_io(&mut self) -> Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let fd = self.file.as_raw_fd();
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_DIRECT) < 0 {
+                return Err(SecureEraseError::Io(std::io::Error::last_os_error()));
+            }
+        }
+        self.direct_io = false;
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn disable_direct_io(&mut self) -> Result<()> {
+        self.direct_io = false;
+        Ok(())
+    }
+
+    /// Stream data over the whole target.
+    ///
+    /// `fill` is called with the absolute offset of each chunk and must fill
+    /// the buffer with the data to write there. Returns the number of bytes written.
+    pub fn overwrite<F>(&mut self, buffer_size: usize, fill: F) -> Result<u64>
+    where
+        F: FnMut(u64, &mut [u8]),
+    {
+        let outcome = self.overwrite_with_progress(buffer_size, 0, &RetryPolicy::fail_fast(), fill, |_, _| Ok(()))?;
+        Ok(outcome.stats.bytes_written)
+    }
+
+    /// Like `overwrite`, but starting at the block-aligned offset `start` and
+    /// handling write errors according to `policy`.
+    ///
+    /// `progress` is called with the end offset of every chunk and the figures
+    /// measured so far; an error from it stops the overwrite (e.g. on cancellation).
+    pub fn overwrite_with_progress<F, P>(
+        &mut self,
+        buffer_size: usize,
+        start: u64,
+        policy: &RetryPolicy,
+        mut fill: F,
+        mut progress: P,
+    ) -> Result<OverwriteOutcome>
+    where
+        F: FnMut(u64, &mut [u8]),
+        P: FnMut(u64, &PassStats) -> Result<()>,
+    {
+        let buffer_size = self.aligned_buffer_size(buffer_size);
+        let mut buffer = AlignedBuffer::new(buffer_size);
+        let start = std::cmp::min(start, self.size);
+        let mut offset = start;
+        let block_size = self.logical_block_size;
+        let mut outcome = OverwriteOutcome { logical_block_size: block_size, ..OverwriteOutcome::default() };
+        let mut skipped_chunks = 0;
+        let started = Instant::now();
+        let (mut window_start, mut window_bytes) = (started, 0u64);
+
+        info!(
+            "Overwriting {} ({} bytes from offset {}, {} byte chunks)",
+            self.path.display(), self.size, offset, buffer_size
+        );
+
+        while offset < self.size {
+            let len = std::cmp::min(buffer_size as u64, self.size - offset) as usize;
+            let chunk = &mut buffer.as_mut_slice()[..len];
+            fill(offset, chunk);
+            let data = &buffer.as_slice()[..len];
+
+            let written = write_chunk(
+                |at, data| self.write_at(at, data),
+                offset, data, block_size, policy, &mut outcome.stats,
+            )?;
+            outcome.stats.bytes_written += written;
+            window_bytes += written;
+            if window_start.elapsed() >= PEAK_WINDOW {
+                let rate = window_bytes as f64 / window_start.elapsed().as_secs_f64();
+                outcome.stats.peak_bytes_per_sec = outcome.stats.peak_bytes_per_sec.max(rate);
+                (window_start, window_bytes) = (Instant::now(), 0);
+            }
+            if written < len as u64 {
+                skipped_chunks += 1;
+                if skipped_chunks > policy.max_skipped_chunks {
+                    return Err(SecureEraseError::WipeFailed(format!(
+                        "Giving up on {} after {} unwritable chunks", self.path.display(), skipped_chunks - 1
+                    )));
+                }
+            }
+
+            offset += len as u64;
+            outcome.stats.write_seconds = started.elapsed().as_secs_f64();
+            progress(offset, &outcome.stats)?;
+        }
+
+        self.sync()?;
+        outcome.stats.write_seconds = started.elapsed().as_secs_f64();
+        // Passes shorter than one window never took a peak sample
+        let average = outcome.stats.throughput_bytes_per_sec();
+        outcome.stats.peak_bytes_per_sec = outcome.stats.peak_bytes_per_sec.max(average);
+        Ok(outcome)
+    }
+}
+
+/// Write one chunk, retrying with backoff and falling back to single blocks.
+///
+/// Returns the bytes written; retries, errors and blocks that cannot be
+/// written are recorded in `stats` when the policy allows skipping them.
+/// Otherwise the write error is returned.
+fn write_chunk<W>(
+    mut write: W,
+    offset: u64,
+    data: &[u8],
+    block_size: u32,
+    policy: &RetryPolicy,
+    stats: &mut PassStats,
+) -> Result<u64>
+where
+    W: FnMut(u64, &[u8]) -> Result<()>,
+{
+    let mut attempt = 0;
+    let error = loop {
+        match write(offset, data) {
+            Ok(()) => return Ok(data.len() as u64),
+            Err(SecureEraseError::Io(e)) if attempt < policy.max_retries => {
+                attempt += 1;
+                stats.retries += 1;
+                let delay = policy.backoff(attempt);
+                warn!("Write of {} bytes at offset {} failed ({}), retry {} of {} in {:?}",
+                      data.len(), offset, e, attempt, policy.max_retries, delay);
+                stats.record_error(format!("Write at offset {} failed: {}", offset, e));
+                std::thread::sleep(delay);
+            }
+            Err(e) => break e,
+        }
+    };
+    if !policy.skip_unwritable || !matches!(error, SecureEraseError::Io(_)) {
+        return Err(error);
+    }
+
+    // Narrow the failure down to the blocks that are actually bad
+    warn!("Chunk at offset {} still failing ({}), writing it block by block", offset, error);
+    let mut written = 0;
+    for (index, block) in data.chunks(block_size as usize).enumerate() {
+        let block_offset = offset + (index * block_size as usize) as u64;
+        match write(block_offset, block) {
+            Ok(()) => written += block.len() as u64,
+            Err(SecureEraseError::Io(e)) => {
+                let lba = block_offset / block_size as u64;
+                warn!("LBA {} is unwritable: {}", lba, e);
+                stats.record_error(format!("LBA {} unwritable: {}", lba, e));
+                LbaRange::push(&mut stats.unwritable_ranges, lba, 1);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quick_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn io_error() -> SecureEraseError {
+        SecureEraseError::Io(std::io::Error::other("injected"))
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_limit() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(40), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_transient_failure_is_retried() {
+        let mut failures = 2;
+        let mut stats = PassStats::default();
+        let written = write_chunk(|_, _| {
+            if failures > 0 {
+                failures -= 1;
+                return Err(io_error());
+            }
+            Ok(())
+        }, 0, &[0u8; 4096], 512, &quick_policy(), &mut stats).unwrap();
+        assert_eq!(written, 4096);
+        assert_eq!(stats.retries, 2);
+        assert_eq!(stats.errors.len(), 2);
+        assert!(stats.unwritable_ranges.is_empty());
+    }
+
+    #[test]
+    fn test_persistent_failure_records_bad_blocks() {
+        // Blocks 10 and 11 (bytes 5120..6144) never accept writes
+        let mut stats = PassStats::default();
+        let write = |offset: u64, data: &[u8]| {
+            if offset < 6144 && offset + data.len() as u64 > 5120 { Err(io_error()) } else { Ok(()) }
+        };
+        let written = write_chunk(write, 4096, &[0u8; 4096], 512, &quick_policy(), &mut stats).unwrap();
+        assert_eq!(written, 3072);
+        assert_eq!(stats.retries, 3);
+        assert_eq!(stats.unwritable_ranges, vec![LbaRange { start_lba: 10, count: 2 }]);
+
+        let strict = RetryPolicy { skip_unwritable: false, ..quick_policy() };
+        assert!(write_chunk(write, 4096, &[0u8; 4096], 512, &strict, &mut PassStats::default()).is_err());
+    }
+}
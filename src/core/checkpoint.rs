@@ -0,0 +1,223 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, HardwareCommandRecord, PassRecord, PassStats};
+use crate::core::advanced::PatternSource;
+use crate::core::discard::DiscardReport;
+use crate::core::hidden_areas::HiddenAreaStatus;
+use crate::core::methods::{ExpectedContents, WipeMethod};
+use crate::core::opal::CryptoEraseRecord;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+use log::{info, warn};
+
+/// Journal format version
+const CHECKPOINT_VERSION: u32 = 2;
+
+/// Cooperative cancellation flag shared between a caller and the wipe engine
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the engine to stop at the next chunk boundary
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Position inside an overwrite pass that has not finished yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassProgress {
+    pub pass_number: u32,
+    pub pattern: PatternSource,
+    /// Bytes from the start of the device known to be on stable storage
+    pub offset: u64,
+    pub started_at: SystemTime,
+    /// Figures measured for the part below `offset`
+    pub stats: PassStats,
+}
+
+/// A point at which a wipe stopped and was later continued
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interruption {
+    pub pass_number: u32,
+    pub offset: u64,
+    /// Time of the last checkpoint before the interruption
+    pub checkpointed_at: SystemTime,
+    pub resumed_at: SystemTime,
+}
+
+/// A step whose fallback ran because the device refused the step when it was issued
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackTaken {
+    /// Position of the refused step in execution order, counting from 1
+    pub step: u32,
+    /// Why the step itself could not run
+    pub reason: String,
+}
+
+/// On-disk record of how far a wipe has progressed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub device_path: PathBuf,
+    pub device_serial: Option<String>,
+    pub device_size: u64,
+    pub method: WipeMethod,
+    pub started_at: SystemTime,
+    pub updated_at: SystemTime,
+    /// Method steps, in execution order, that have fully completed
+    pub steps_completed: u32,
+    /// Completed overwrite passes
+    pub passes: Vec<PassRecord>,
+    pub current_pass: Option<PassProgress>,
+    pub interruptions: Vec<Interruption>,
+    /// Logical block size of the target, known once a pass has started
+    #[serde(default)]
+    pub logical_block_size: u32,
+    /// Hidden areas restored by a `restore_hidden_areas` step
+    #[serde(default)]
+    pub hidden_areas: Vec<HiddenAreaStatus>,
+    /// Hardware sanitize commands that have completed
+    #[serde(default)]
+    pub hardware_commands: Vec<HardwareCommandRecord>,
+    #[serde(default)]
+    pub discards: Vec<DiscardReport>,
+    #[serde(default)]
+    pub crypto_erases: Vec<CryptoEraseRecord>,
+    /// Steps that fell back at run time. Their fallback steps follow them in
+    /// execution order, so `steps_completed` also tells how far the fallback got.
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackTaken>,
+    /// What the media should read back as after the completed steps, set by
+    /// whichever step last defined the contents
+    #[serde(default)]
+    pub expected_contents: ExpectedContents,
+}
+
+impl Checkpoint {
+    /// Fresh journal for a wipe that has not started yet
+    pub fn new(device: &StorageDevice, method: &WipeMethod) -> Self {
+        let now = SystemTime::now();
+        Self {
+            version: CHECKPOINT_VERSION,
+            device_path: device.path.clone(),
+            device_serial: device.serial.clone(),
+            device_size: device.size,
+            method: method.clone(),
+            started_at: now,
+            updated_at: now,
+            steps_completed: 0,
+            passes: Vec::new(),
+            current_pass: None,
+            interruptions: Vec::new(),
+            logical_block_size: 0,
+            hidden_areas: Vec::new(),
+            hardware_commands: Vec::new(),
+            discards: Vec::new(),
+            crypto_erases: Vec::new(),
+            fallbacks: Vec::new(),
+            expected_contents: ExpectedContents::default(),
+        }
+    }
+
+    /// Journal location for a device inside an output directory
+    pub fn journal_path(output_dir: &Path, device: &StorageDevice) -> PathBuf {
+        output_dir.join(format!("wipe_journal_{}.json", device.file_id()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| SecureEraseError::Checkpoint(format!("Cannot read {}: {}", path.display(), e)))?;
+        let checkpoint: Checkpoint = serde_json::from_str(&contents)?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(SecureEraseError::Checkpoint(format!(
+                "Unsupported journal version {}", checkpoint.version
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Atomically replace the journal at `path` with the current state
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        self.updated_at = SystemTime::now();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Delete the journal once the wipe it describes has finished
+    pub fn remove(path: &Path) {
+        if let Err(e) = fs::remove_file(path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Could not remove wipe journal {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Refuse to resume onto a different drive
+    pub fn ensure_same_device(&self, device: &StorageDevice) -> Result<()> {
+        if let (Some(recorded), Some(current)) = (&self.device_serial, &device.serial) {
+            if recorded.trim() != current.trim() {
+                return Err(SecureEraseError::Checkpoint(format!(
+                    "Journal belongs to device with serial {}, found {}", recorded, current
+                )));
+            }
+        }
+        if self.device_size != device.size {
+            return Err(SecureEraseError::Checkpoint(format!(
+                "Journal device size {} does not match {}", self.device_size, device.size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Keep what a `restore_hidden_areas` step did. After a permanent restore
+    /// the drive reports `size` from then on, so a resume must match that instead.
+    pub fn record_hidden_areas(&mut self, statuses: Vec<HiddenAreaStatus>, size: u64) {
+        if statuses.iter().any(|status| status.restored && status.permanent) {
+            self.device_size = size;
+        }
+        self.hidden_areas = statuses;
+    }
+
+    /// Whether the step at `step` was refused and replaced by its fallback
+    pub fn fell_back(&self, step: u32) -> bool {
+        self.fallbacks.iter().any(|fallback| fallback.step == step)
+    }
+
+    /// Note that the wipe is being continued from this checkpoint
+    pub fn record_resume(&mut self) {
+        let (pass_number, offset) = match &self.current_pass {
+            Some(current) => (current.pass_number, current.offset),
+            None => (self.passes.len() as u32 + 1, 0),
+        };
+        info!("Resuming wipe at pass {}, offset {}", pass_number, offset);
+        self.interruptions.push(Interruption {
+            pass_number,
+            offset,
+            checkpointed_at: self.updated_at,
+            resumed_at: SystemTime::now(),
+        });
+    }
+}
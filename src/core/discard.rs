@@ -0,0 +1,291 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, DeviceType};
+use crate::core::ata::AtaDevice;
+use crate::core::methods::ExpectedContents;
+use crate::core::nvme::{NvmeDevice, NvmeIoctl, NvmeTransport};
+use crate::core::sg_io::SgIoDevice;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use log::{info, warn};
+
+#[cfg(target_os = "linux")]
+const BLKDISCARD: u64 = 0x1277;
+#[cfg(target_os = "linux")]
+const BLKSECDISCARD: u64 = 0x127d;
+
+/// Blocks read back after discarding
+const CHECK_SAMPLES: u64 = 64;
+const CHECK_BLOCK_SIZE: u64 = 4096;
+
+/// Discard limits from /sys/block/<name>/queue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiscardLimits {
+    /// Largest range accepted by one discard request
+    pub max_bytes: u64,
+    pub granularity: u64,
+    /// The kernel reports that discarded blocks read back as zeroes
+    pub zeroes_data: bool,
+}
+
+impl DiscardLimits {
+    pub fn read(queue_dir: &Path) -> Result<Self> {
+        let value = |name: &str| -> u64 {
+            std::fs::read_to_string(queue_dir.join(name))
+                .ok()
+                .and_then(|contents| contents.trim().parse().ok())
+                .unwrap_or(0)
+        };
+        let limits = Self {
+            max_bytes: value("discard_max_bytes"),
+            granularity: value("discard_granularity"),
+            zeroes_data: value("discard_zeroes_data") == 1,
+        };
+        if limits.max_bytes == 0 {
+            return Err(SecureEraseError::SecureEraseNotSupported);
+        }
+        Ok(limits)
+    }
+
+    pub fn for_device(device_path: &Path) -> Result<Self> {
+        let name = device_path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        Self::read(&PathBuf::from(format!("/sys/block/{}/queue", name)))
+    }
+}
+
+/// What a read of a discarded block returns
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadAfterDiscard {
+    /// Zeroes (ATA RZAT, NVMe DLFEAT 001b, or `discard_zeroes_data`)
+    Zeroes,
+    /// The same data on every read, not necessarily zeroes (ATA DRAT)
+    Deterministic,
+    Undefined,
+}
+
+impl ReadAfterDiscard {
+    /// What a discarded or deallocated block holds; only zeroing drives say
+    pub fn expected_contents(&self) -> ExpectedContents {
+        match self {
+            ReadAfterDiscard::Zeroes => ExpectedContents::zeros(),
+            ReadAfterDiscard::Deterministic | ReadAfterDiscard::Undefined => ExpectedContents::Indeterminate,
+        }
+    }
+}
+
+/// Outcome of discarding a whole device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscardReport {
+    /// Every range went through BLKSECDISCARD rather than a plain BLKDISCARD
+    pub secure: bool,
+    pub bytes_discarded: u64,
+    pub requests: u64,
+    pub read_after_discard: ReadAfterDiscard,
+    pub blocks_checked: u64,
+    /// Checked blocks that read back as zeroes
+    pub blocks_zeroed: u64,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+}
+
+impl DiscardReport {
+    /// Every checked block read back as zeroes
+    pub fn reads_zeroes(&self) -> bool {
+        self.blocks_checked > 0 && self.blocks_zeroed == self.blocks_checked
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} discard of {} bytes in {} request(s); {}/{} sampled blocks read back as zeroes",
+            if self.secure { "Secure" } else { "Best-effort" },
+            self.bytes_discarded, self.requests, self.blocks_zeroed, self.blocks_checked
+        )
+    }
+}
+
+/// Split `[start, start + length)` into discard requests no larger than the
+/// device limit, keeping every request but the last a multiple of the granularity
+pub fn discard_ranges(start: u64, length: u64, limits: &DiscardLimits) -> Vec<(u64, u64)> {
+    let granularity = limits.granularity.max(1);
+    let chunk = std::cmp::max(limits.max_bytes / granularity * granularity, granularity);
+    let end = start + length;
+    let mut ranges = Vec::new();
+    let mut offset = start;
+    while offset < end {
+        let size = std::cmp::min(chunk, end - offset);
+        ranges.push((offset, size));
+        offset += size;
+    }
+    ranges
+}
+
+/// Discard every block of a device, securely when the device supports it,
+/// then read a sample of blocks back.
+///
+/// Fails when the device promises zeroes after a discard and a sampled block
+/// still holds data.
+pub fn discard(device: &StorageDevice) -> Result<DiscardReport> {
+    let limits = DiscardLimits::for_device(&device.path)?;
+    let started_at = SystemTime::now();
+    let file = std::fs::OpenOptions::new().write(true).open(&device.path)?;
+
+    let ranges = discard_ranges(0, device.size, &limits);
+    let mut secure = true;
+    for (index, (offset, length)) in ranges.iter().enumerate() {
+        if secure {
+            match issue(&file, true, *offset, *length) {
+                Ok(()) => continue,
+                // Support is all or nothing, so only the first request can be refused
+                Err(e) if index == 0 && e.kind() == std::io::ErrorKind::Unsupported => {
+                    info!("{} does not support secure discard; using a plain discard", device.path.display());
+                    secure = false;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        issue(&file, false, *offset, *length)?;
+    }
+    drop(file);
+
+    let read_after_discard = if limits.zeroes_data { ReadAfterDiscard::Zeroes } else { read_after_discard(device) };
+    let (blocks_checked, blocks_zeroed) = check_zeroed(&device.path, device.size, CHECK_SAMPLES)?;
+    let report = DiscardReport {
+        secure,
+        bytes_discarded: device.size,
+        requests: ranges.len() as u64,
+        read_after_discard,
+        blocks_checked,
+        blocks_zeroed,
+        started_at,
+        finished_at: SystemTime::now(),
+    };
+    if read_after_discard == ReadAfterDiscard::Zeroes && !report.reads_zeroes() {
+        return Err(SecureEraseError::WipeFailed(format!(
+            "{} of {} sampled blocks still hold data after discarding, although the device reports zeroes after discard",
+            blocks_checked - blocks_zeroed, blocks_checked
+        )));
+    }
+    info!("{}", report.describe());
+    Ok(report)
+}
+
+#[cfg(target_os = "linux")]
+fn issue(file: &File, secure: bool, offset: u64, length: u64) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let range: [u64; 2] = [offset, length];
+    let request = if secure { BLKSECDISCARD } else { BLKDISCARD };
+    if unsafe { libc::ioctl(file.as_raw_fd(), request as _, range.as_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn issue(_file: &File, _secure: bool, _offset: u64, _length: u64) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+/// Ask the drive what discarded blocks read back as
+pub fn read_after_discard(device: &StorageDevice) -> ReadAfterDiscard {
+    let result = match device.device_type {
+        DeviceType::NVMe => NvmeIoctl::open(&device.path).and_then(|mut transport| {
+            let nsid = transport.namespace_id()?;
+            Ok(if NvmeDevice::new(transport).identify_namespace(nsid)?.deallocate_reads_zeroes {
+                ReadAfterDiscard::Zeroes
+            } else {
+                ReadAfterDiscard::Undefined
+            })
+        }),
+        DeviceType::HDD | DeviceType::SSD => SgIoDevice::open(&device.path).and_then(|transport| {
+            let identify = AtaDevice::new(transport).identify()?;
+            Ok(match (identify.zeroes_after_trim, identify.deterministic_after_trim) {
+                (true, true) => ReadAfterDiscard::Zeroes,
+                (_, true) => ReadAfterDiscard::Deterministic,
+                _ => ReadAfterDiscard::Undefined,
+            })
+        }),
+        _ => Ok(ReadAfterDiscard::Undefined),
+    };
+    result.unwrap_or_else(|e| {
+        warn!("Could not query discard behaviour of {}: {}", device.path.display(), e);
+        ReadAfterDiscard::Undefined
+    })
+}
+
+/// Read `samples` evenly spaced blocks and count those that are all zeroes
+pub fn check_zeroed(path: &Path, size: u64, samples: u64) -> Result<(u64, u64)> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let blocks = size / CHECK_BLOCK_SIZE;
+    if blocks == 0 {
+        return Ok((0, 0));
+    }
+    let samples = std::cmp::min(samples, blocks);
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; CHECK_BLOCK_SIZE as usize];
+    let mut zeroed = 0;
+    for sample in 0..samples {
+        let block = if samples > 1 { sample * (blocks - 1) / (samples - 1) } else { 0 };
+        file.seek(SeekFrom::Start(block * CHECK_BLOCK_SIZE))?;
+        file.read_exact(&mut buffer)?;
+        if buffer.iter().all(|byte| *byte == 0) {
+            zeroed += 1;
+        }
+    }
+    Ok((samples, zeroed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_only_zeroing_drives_define_discarded_contents() {
+        assert_eq!(ReadAfterDiscard::Zeroes.expected_contents(), ExpectedContents::zeros());
+        // Deterministic reads repeat, but not necessarily as zeroes
+        assert_eq!(ReadAfterDiscard::Deterministic.expected_contents(), ExpectedContents::Indeterminate);
+        assert_eq!(ReadAfterDiscard::Undefined.expected_contents(), ExpectedContents::Indeterminate);
+    }
+
+    #[test]
+    fn test_reads_queue_limits() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("discard_max_bytes"), "2147450880\n").unwrap();
+        std::fs::write(dir.path().join("discard_granularity"), "512\n").unwrap();
+        std::fs::write(dir.path().join("discard_zeroes_data"), "0\n").unwrap();
+        assert_eq!(DiscardLimits::read(dir.path()).unwrap(), DiscardLimits {
+            max_bytes: 2147450880,
+            granularity: 512,
+            zeroes_data: false,
+        });
+
+        std::fs::write(dir.path().join("discard_max_bytes"), "0\n").unwrap();
+        assert!(matches!(DiscardLimits::read(dir.path()), Err(SecureEraseError::SecureEraseNotSupported)));
+    }
+
+    #[test]
+    fn test_ranges_respect_max_bytes_and_granularity() {
+        let limits = DiscardLimits { max_bytes: 10_000, granularity: 4096, zeroes_data: false };
+        assert_eq!(discard_ranges(0, 20_000, &limits), vec![(0, 8192), (8192, 8192), (16384, 3616)]);
+
+        let unlimited = DiscardLimits { max_bytes: u64::MAX, granularity: 0, zeroes_data: false };
+        assert_eq!(discard_ranges(0, 1 << 40, &unlimited), vec![(0, 1 << 40)]);
+        assert!(discard_ranges(0, 0, &limits).is_empty());
+    }
+
+    #[test]
+    fn test_check_zeroed_samples_whole_device() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("disk.img");
+        let mut data = vec![0u8; 16 * 4096];
+        data[15 * 4096] = 0xff;
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(check_zeroed(&path, data.len() as u64, 4).unwrap(), (4, 3));
+        assert_eq!(check_zeroed(&path, data.len() as u64, 100).unwrap(), (16, 15));
+    }
+}
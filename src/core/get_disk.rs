@@ -0,0 +1,254 @@
+use crate::error::Result;
+#[cfg(target_os = "windows")]
+use crate::error::SecureEraseError;
+use crate::core::{StorageDevice, DeviceType, DeviceAttributes, DeviceLayout};
+use serde::Deserialize;
+use serde_json::Value;
+#[cfg(target_os = "windows")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(target_os = "windows")]
+use log::warn;
+
+/// PowerShell query whose output `parse` reads.
+///
+/// `MediaType` only exists on `Get-PhysicalDisk`, so it is joined in by device number.
+pub const QUERY: &str = "$media = @{}; \
+    Get-PhysicalDisk | ForEach-Object { $media[[string]$_.DeviceId] = $_.MediaType }; \
+    Get-Disk | Select-Object Number, FriendlyName, Model, SerialNumber, Size, LogicalSectorSize, \
+    PhysicalSectorSize, BusType, FirmwareVersion, IsOffline, IsSystem, IsBoot, \
+    @{Name='MediaType'; Expression={ $media[[string]$_.Number] }} | ConvertTo-Json";
+
+/// `MSFT_Disk.BusType` names, indexed by value
+const BUS_TYPES: [&str; 20] = [
+    "Unknown", "SCSI", "ATAPI", "ATA", "1394", "SSA", "Fibre Channel", "USB", "RAID", "iSCSI",
+    "SAS", "SATA", "SD", "MMC", "Virtual", "File Backed Virtual", "Storage Spaces", "NVMe", "SCM", "UFS",
+];
+
+/// `MSFT_PhysicalDisk.MediaType` names, indexed by value
+const MEDIA_TYPES: [&str; 6] = ["Unspecified", "", "", "HDD", "SSD", "SCM"];
+
+/// One disk as printed by `ConvertTo-Json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DiskRecord {
+    number: u32,
+    friendly_name: Option<String>,
+    model: Option<String>,
+    serial_number: Option<String>,
+    #[serde(default)]
+    size: u64,
+    logical_sector_size: Option<u32>,
+    physical_sector_size: Option<u32>,
+    /// A name with Windows PowerShell, a number with PowerShell 7
+    bus_type: Option<Value>,
+    firmware_version: Option<String>,
+    #[serde(default)]
+    is_offline: bool,
+    /// Holds the system partition the firmware starts Windows from
+    #[serde(default)]
+    is_system: bool,
+    /// Holds the Windows volume
+    #[serde(default)]
+    is_boot: bool,
+    media_type: Option<Value>,
+}
+
+/// Parse the output of `QUERY` into devices.
+///
+/// `ConvertTo-Json` prints a bare object for a single disk and nothing at
+/// all when there are none; both are accepted. Sizes are the ones Windows
+/// reports in `Size`, which `disks` replaces with the length from the driver.
+pub fn parse(json: &str) -> Result<Vec<StorageDevice>> {
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let records: Vec<DiskRecord> = match serde_json::from_str(json)? {
+        Value::Array(items) => items.into_iter().map(serde_json::from_value).collect::<std::result::Result<_, _>>()?,
+        item => vec![serde_json::from_value(item)?],
+    };
+    Ok(records.into_iter().map(DiskRecord::into_device).collect())
+}
+
+impl DiskRecord {
+    fn into_device(self) -> StorageDevice {
+        let bus = self.bus_type.as_ref().and_then(|value| enum_name(value, &BUS_TYPES));
+        let media = self.media_type.as_ref().and_then(|value| enum_name(value, &MEDIA_TYPES));
+        let device_type = match (bus, media) {
+            (Some("NVMe"), _) => DeviceType::NVMe,
+            (Some("USB"), _) => DeviceType::USB,
+            (Some("SAS"), _) => DeviceType::SAS,
+            (Some("SD" | "MMC" | "SCM" | "UFS"), _) => DeviceType::SSD,
+            (_, Some("SSD" | "SCM")) => DeviceType::SSD,
+            (_, Some("HDD")) => DeviceType::HDD,
+            _ => DeviceType::Unknown,
+        };
+        let system_volumes = [(self.is_boot, "boot"), (self.is_system, "system")].into_iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, role)| role.to_string())
+            .collect();
+
+        StorageDevice {
+            path: PathBuf::from(format!("\\\\.\\PhysicalDrive{}", self.number)),
+            name: non_empty(self.friendly_name).unwrap_or_else(|| format!("Disk {}", self.number)),
+            size: self.size,
+            device_type,
+            model: non_empty(self.model),
+            serial: non_empty(self.serial_number),
+            supports_secure_erase: false,
+            supports_trim: false,
+            hidden_areas: Vec::new(),
+            attributes: DeviceAttributes {
+                logical_block_size: self.logical_sector_size.unwrap_or(0),
+                physical_block_size: self.physical_sector_size.unwrap_or(0),
+                rotational: media == Some("HDD"),
+                removable: matches!(bus, Some("USB" | "SD" | "MMC")),
+                transport: bus.filter(|bus| *bus != "Unknown").map(|bus| bus.to_lowercase().replace(' ', "-")),
+                wwn: None,
+                firmware: non_empty(self.firmware_version),
+                offline: self.is_offline,
+            },
+            layout: DeviceLayout { system_volumes, ..Default::default() },
+        }
+    }
+}
+
+/// Name of a CIM enumeration value printed either as its name or its number
+fn enum_name(value: &Value, names: &[&'static str]) -> Option<&'static str> {
+    match value {
+        Value::Number(number) => number.as_u64()
+            .and_then(|index| names.get(index as usize))
+            .copied()
+            .filter(|name| !name.is_empty()),
+        Value::String(text) => names.iter().copied().find(|name| !name.is_empty() && name.eq_ignore_ascii_case(text.trim())),
+        _ => None,
+    }
+}
+
+/// Windows pads serial numbers and leaves unset strings empty
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Every disk `Get-Disk` reports, sized through `IOCTL_DISK_GET_LENGTH_INFO`
+#[cfg(target_os = "windows")]
+pub fn disks() -> Result<Vec<StorageDevice>> {
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", QUERY])
+        .output()?;
+    if !output.status.success() {
+        return Err(SecureEraseError::DeviceNotFound(format!(
+            "Get-Disk failed: {}", String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let mut disks = parse(&String::from_utf8_lossy(&output.stdout))?;
+    for disk in &mut disks {
+        match disk_length(&disk.path) {
+            Ok(length) => disk.size = length,
+            Err(e) => warn!("Keeping the size Get-Disk reports for {}: {}", disk.path.display(), e),
+        }
+    }
+    Ok(disks)
+}
+
+/// Length in bytes of a disk, from its driver
+#[cfg(target_os = "windows")]
+pub fn disk_length(path: &Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::fileapi::{CreateFileW, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::ioapiset::DeviceIoControl;
+    use winapi::um::winioctl::{GET_LENGTH_INFORMATION, IOCTL_DISK_GET_LENGTH_INFO};
+    use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    // The IOCTL needs read access; sharing keeps it working on disks in use
+    let handle = unsafe {
+        CreateFileW(wide.as_ptr(), GENERIC_READ, FILE_SHARE_READ | FILE_SHARE_WRITE,
+                    ptr::null_mut(), OPEN_EXISTING, 0, ptr::null_mut())
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let mut info: GET_LENGTH_INFORMATION = unsafe { std::mem::zeroed() };
+    let mut returned: DWORD = 0;
+    let ok = unsafe {
+        DeviceIoControl(handle, IOCTL_DISK_GET_LENGTH_INFO, ptr::null_mut(), 0,
+                        &mut info as *mut _ as *mut _, std::mem::size_of::<GET_LENGTH_INFORMATION>() as DWORD,
+                        &mut returned, ptr::null_mut())
+    };
+    let error = std::io::Error::last_os_error();
+    unsafe { CloseHandle(handle) };
+    if ok == 0 {
+        return Err(error.into());
+    }
+    Ok(unsafe { *info.Length.QuadPart() } as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_windows_powershell_output() {
+        let disks = parse(include_str!("../../tests/fixtures/get_disk/ps51_three_disks.json")).unwrap();
+        assert_eq!(disks.len(), 3);
+
+        let nvme = &disks[0];
+        assert_eq!(nvme.path, PathBuf::from(r"\\.\PhysicalDrive0"));
+        assert_eq!(nvme.device_type, DeviceType::NVMe);
+        assert_eq!(nvme.size, 1000204886016);
+        assert_eq!(nvme.serial.as_deref(), Some("0025_38B5_81B0_4C2D."));
+        assert_eq!(nvme.attributes.transport.as_deref(), Some("nvme"));
+        assert_eq!(nvme.attributes.firmware.as_deref(), Some("2B2QEXM7"));
+        assert_eq!(nvme.layout.system_volumes, vec!["boot", "system"]);
+        assert!(!nvme.attributes.offline);
+
+        let hdd = &disks[1];
+        assert_eq!(hdd.device_type, DeviceType::HDD);
+        assert_eq!(hdd.serial.as_deref(), Some("ZFL1A2B3"));
+        assert_eq!(hdd.attributes.physical_block_size, 4096);
+        assert!(hdd.attributes.rotational);
+        assert!(hdd.layout.system_volumes.is_empty());
+
+        let usb = &disks[2];
+        assert_eq!(usb.device_type, DeviceType::USB);
+        assert_eq!(usb.name, "SanDisk Ultra");
+        assert!(usb.serial.is_none());
+        assert!(usb.attributes.removable);
+    }
+
+    #[test]
+    fn test_parse_single_disk_with_numeric_enums() {
+        let disks = parse(include_str!("../../tests/fixtures/get_disk/ps7_single_disk.json")).unwrap();
+        assert_eq!(disks.len(), 1);
+        assert_eq!(disks[0].path, PathBuf::from(r"\\.\PhysicalDrive3"));
+        assert_eq!(disks[0].device_type, DeviceType::SAS);
+        assert_eq!(disks[0].attributes.transport.as_deref(), Some("sas"));
+        assert!(disks[0].attributes.rotational);
+        assert!(disks[0].attributes.offline);
+
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse("{\"FriendlyName\": \"no number\"}").is_err());
+    }
+
+    #[test]
+    fn test_bus_type_mapping() {
+        let record = |bus: Value, media: Value| DiskRecord {
+            number: 0, friendly_name: None, model: None, serial_number: None, size: 0,
+            logical_sector_size: None, physical_sector_size: None,
+            bus_type: Some(bus), firmware_version: None, is_offline: false, is_system: false, is_boot: false,
+            media_type: Some(media),
+        };
+        let device_type = |bus, media| record(bus, media).into_device().device_type;
+        assert_eq!(device_type(Value::from(17), Value::from(0)), DeviceType::NVMe);
+        assert_eq!(device_type(Value::from("sata"), Value::from("SSD")), DeviceType::SSD);
+        assert_eq!(device_type(Value::from("RAID"), Value::from("Unspecified")), DeviceType::Unknown);
+        assert_eq!(device_type(Value::from(12), Value::Null), DeviceType::SSD);
+        assert_eq!(record(Value::from("Storage Spaces"), Value::Null).into_device().attributes.transport.as_deref(), Some("storage-spaces"));
+    }
+}
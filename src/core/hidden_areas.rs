@@ -0,0 +1,297 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, HiddenArea, HiddenAreaType, PassRecord};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::time::SystemTime;
+use log::{info, warn};
+
+/// How hidden areas are removed before overwriting
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HiddenAreaRestore {
+    /// Volatile change that reverts at the next power cycle (HPA only)
+    #[default]
+    Temporary,
+    /// Persistent change; also resets a Device Configuration Overlay
+    Permanent,
+}
+
+impl HiddenAreaRestore {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "temporary" => Some(HiddenAreaRestore::Temporary),
+            "permanent" => Some(HiddenAreaRestore::Permanent),
+            _ => None,
+        }
+    }
+}
+
+/// Max address figures reported by `hdparm -N`, in sectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxSectors {
+    /// Sectors visible to the host
+    pub current: u64,
+    /// Sectors available once the HPA is removed
+    pub native: u64,
+}
+
+/// What happened to a hidden area during a wipe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HiddenAreaStatus {
+    pub area: HiddenArea,
+    /// The area was made accessible to the host before overwriting
+    pub restored: bool,
+    pub permanent: bool,
+    /// When the restore command was issued
+    pub attempted_at: Option<SystemTime>,
+    /// An overwrite pass covered the whole area after it was restored
+    pub wiped: bool,
+    pub note: Option<String>,
+}
+
+/// Parse `max sectors = <current>/<native>, HPA is enabled` from `hdparm -N`
+pub fn parse_max_sectors(output: &str) -> Option<MaxSectors> {
+    let line = output.lines().find(|line| line.trim_start().starts_with("max sectors"))?;
+    let (_, value) = line.split_once('=')?;
+    let figures = value.split(',').next()?.trim();
+    let (current, native) = figures.split_once('/')?;
+    Some(MaxSectors {
+        current: current.trim().parse().ok()?,
+        native: native.trim().parse().ok()?,
+    })
+}
+
+/// Parse `Real max sectors: <n>` from `hdparm --dco-identify`
+pub fn parse_dco_real_max(output: &str) -> Option<u64> {
+    let line = output.lines().find(|line| line.trim_start().starts_with("Real max sectors"))?;
+    line.split_once(':')?.1.trim().parse().ok()
+}
+
+/// Turn max address figures into hidden LBA ranges.
+///
+/// The HPA spans the sectors between the current and the native max; a DCO
+/// hides the sectors between the native max and the DCO's real max.
+pub fn hidden_areas_from(max: Option<MaxSectors>, dco_real_max: Option<u64>) -> Vec<HiddenArea> {
+    let mut areas = Vec::new();
+    if let Some(max) = max {
+        if max.native > max.current {
+            areas.push(HiddenArea {
+                area_type: HiddenAreaType::HPA,
+                start_lba: max.current,
+                size: max.native - max.current,
+                description: "Host Protected Area".to_string(),
+            });
+        }
+        if let Some(real_max) = dco_real_max.filter(|real_max| *real_max > max.native) {
+            areas.push(HiddenArea {
+                area_type: HiddenAreaType::DCO,
+                start_lba: max.native,
+                size: real_max - max.native,
+                description: "Device Configuration Overlay".to_string(),
+            });
+        }
+    }
+    areas
+}
+
+/// Query a drive for HPA and DCO ranges
+pub fn detect(device_path: &Path) -> Vec<HiddenArea> {
+    let max = hdparm(device_path, &["-N"]).ok().and_then(|output| parse_max_sectors(&output));
+    let dco_real_max = hdparm(device_path, &["--dco-identify"]).ok().and_then(|output| parse_dco_real_max(&output));
+    hidden_areas_from(max, dco_real_max)
+}
+
+/// Make the hidden areas of a device accessible so they can be overwritten.
+///
+/// The HPA is removed before the DCO, since drives refuse a DCO restore while
+/// an HPA is set. The result is checked by querying the drive again.
+pub fn restore(device: &StorageDevice, mode: HiddenAreaRestore) -> Vec<HiddenAreaStatus> {
+    let mut areas: Vec<&HiddenArea> = device.hidden_areas.iter()
+        .filter(|area| matches!(area.area_type, HiddenAreaType::HPA | HiddenAreaType::DCO))
+        .collect();
+    areas.sort_by_key(|area| matches!(area.area_type, HiddenAreaType::DCO));
+
+    let permanent = mode == HiddenAreaRestore::Permanent;
+    let mut statuses: Vec<HiddenAreaStatus> = areas.into_iter().map(|area| {
+        let result = match (&area.area_type, mode) {
+            (HiddenAreaType::HPA, _) => {
+                let native = area.start_lba + area.size;
+                let value = if permanent { format!("p{}", native) } else { native.to_string() };
+                hdparm(&device.path, &["--yes-i-know-what-i-am-doing", "-N", &value])
+            }
+            (_, HiddenAreaRestore::Permanent) => {
+                hdparm(&device.path, &["--yes-i-know-what-i-am-doing", "--dco-restore"])
+            }
+            (_, HiddenAreaRestore::Temporary) => Err(SecureEraseError::HiddenAreaAccessFailed(
+                "a DCO can only be removed permanently".to_string()
+            )),
+        };
+        let attempted_at = Some(SystemTime::now());
+        match result {
+            Ok(_) => {
+                info!("Restored {} ({} sectors from LBA {})", area.description, area.size, area.start_lba);
+                HiddenAreaStatus {
+                    area: area.clone(),
+                    restored: true,
+                    permanent,
+                    attempted_at,
+                    wiped: false,
+                    note: None,
+                }
+            }
+            Err(e) => {
+                warn!("Could not restore {}: {}", area.description, e);
+                HiddenAreaStatus {
+                    area: area.clone(),
+                    restored: false,
+                    permanent,
+                    attempted_at,
+                    wiped: false,
+                    note: Some(e.to_string()),
+                }
+            }
+        }
+    }).collect();
+
+    if statuses.iter().any(|status| status.restored) {
+        rescan(&device.path);
+        let remaining = detect(&device.path);
+        for status in statuses.iter_mut().filter(|status| status.restored) {
+            if remaining.iter().any(|area| area.area_type == status.area.area_type) {
+                status.restored = false;
+                status.note = Some("Still reported by the drive after restoring".to_string());
+            }
+        }
+    }
+    statuses
+}
+
+/// Final status of every hidden area of a device after a wipe.
+///
+/// Areas that were never restored are reported as not wiped.
+pub fn account(
+    device: &StorageDevice,
+    restored: &[HiddenAreaStatus],
+    passes: &[PassRecord],
+    sector_size: u32,
+) -> Vec<HiddenAreaStatus> {
+    let mut statuses = restored.to_vec();
+    for area in &device.hidden_areas {
+        if !statuses.iter().any(|status| status.area.area_type == area.area_type) {
+            statuses.push(HiddenAreaStatus {
+                area: area.clone(),
+                restored: false,
+                permanent: false,
+                attempted_at: None,
+                wiped: false,
+                note: Some("Not restored before overwriting".to_string()),
+            });
+        }
+    }
+    mark_wiped(&mut statuses, passes, sector_size);
+    statuses
+}
+
+/// Flag every restored area that a later overwrite pass fully covered
+pub fn mark_wiped(statuses: &mut [HiddenAreaStatus], passes: &[PassRecord], sector_size: u32) {
+    for status in statuses.iter_mut() {
+        let Some(restored_at) = status.attempted_at.filter(|_| status.restored) else {
+            continue;
+        };
+        let end = (status.area.start_lba + status.area.size) * sector_size as u64;
+        status.wiped = passes.iter().any(|pass| {
+            pass.started_at >= restored_at
+                && pass.stats.unwritable_ranges.is_empty()
+                && pass.stats.bytes_written >= end
+        });
+    }
+}
+
+fn hdparm(device_path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("hdparm")
+        .args(args)
+        .arg(device_path)
+        .output()?;
+    if !output.status.success() {
+        return Err(SecureEraseError::HiddenAreaAccessFailed(format!(
+            "hdparm {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Ask the kernel to re-read the capacity of a SCSI/ATA disk
+fn rescan(device_path: &Path) {
+    let Some(name) = device_path.file_name().and_then(|name| name.to_str()) else {
+        return;
+    };
+    let rescan_path = format!("/sys/block/{}/device/rescan", name);
+    if let Err(e) = std::fs::write(&rescan_path, "1") {
+        warn!("Could not rescan {}: {}", device_path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HDPARM_N_HPA: &str = "\n/dev/sdb:\n max sectors   = 1953523055/1953525168, HPA is enabled\n";
+    const HDPARM_N_AMA: &str = "\n/dev/sdc:\n max sectors   = 11721045168/11721045168, accessible max address disabled\n";
+    const HDPARM_DCO: &str = "\n/dev/sdb:\nDCO Revision: 0x0002\n\
+        The following features can be selectively disabled via DCO:\n\
+        \tTransfer modes:\n\t\t mdma0 mdma1 mdma2\n\t\t udma0 udma1 udma2 udma3 udma4 udma5 udma6\n\
+        \tReal max sectors: 1953529856\n\
+        \tATA command/feature sets:\n\t\t SMART self_test error_log security AAM HPA\n";
+
+    #[test]
+    fn test_parses_hdparm_max_sectors() {
+        assert_eq!(parse_max_sectors(HDPARM_N_HPA), Some(MaxSectors { current: 1953523055, native: 1953525168 }));
+        assert_eq!(parse_max_sectors(HDPARM_N_AMA), Some(MaxSectors { current: 11721045168, native: 11721045168 }));
+        assert_eq!(parse_max_sectors("SG_IO: bad/missing sense data"), None);
+        assert_eq!(parse_dco_real_max(HDPARM_DCO), Some(1953529856));
+    }
+
+    #[test]
+    fn test_hidden_area_ranges() {
+        let areas = hidden_areas_from(parse_max_sectors(HDPARM_N_HPA), parse_dco_real_max(HDPARM_DCO));
+        assert_eq!(areas.len(), 2);
+        assert!(matches!(areas[0].area_type, HiddenAreaType::HPA));
+        assert_eq!((areas[0].start_lba, areas[0].size), (1953523055, 2113));
+        assert!(matches!(areas[1].area_type, HiddenAreaType::DCO));
+        assert_eq!((areas[1].start_lba, areas[1].size), (1953525168, 4688));
+
+        assert!(hidden_areas_from(parse_max_sectors(HDPARM_N_AMA), None).is_empty());
+    }
+
+    #[test]
+    fn test_only_later_full_passes_wipe_an_area() {
+        let restored_at = SystemTime::now();
+        let mut statuses = vec![HiddenAreaStatus {
+            area: HiddenArea {
+                area_type: HiddenAreaType::HPA,
+                start_lba: 100,
+                size: 28,
+                description: "Host Protected Area".to_string(),
+            },
+            restored: true,
+            permanent: false,
+            attempted_at: Some(restored_at),
+            wiped: false,
+            note: None,
+        }];
+        let pass = |bytes_written| PassRecord {
+            pass_number: 1,
+            pattern: crate::core::advanced::PatternSource::Fixed { byte: 0 },
+            started_at: restored_at,
+            finished_at: restored_at,
+            throughput_bytes_per_sec: 0.0,
+            stats: crate::core::PassStats { bytes_written, ..Default::default() },
+        };
+
+        mark_wiped(&mut statuses, &[pass(100 * 512)], 512);
+        assert!(!statuses[0].wiped);
+        mark_wiped(&mut statuses, &[pass(128 * 512)], 512);
+        assert!(statuses[0].wiped);
+    }
+}
@@ -0,0 +1,363 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{DeviceType, EraseMode};
+use crate::core::advanced::{PassSchedule, PatternSource};
+use crate::core::hidden_areas::HiddenAreaRestore;
+use crate::core::opal::SedErase;
+use crate::core::verification::VerificationMode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::fs;
+use log::{info, warn};
+
+/// Method definitions that ship with the tool
+const BUILTIN_METHODS: &str = include_str!("builtin_methods.toml");
+
+/// What the media should read back as after the steps run so far
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectedContents {
+    /// Every block holds this pattern, written by an overwrite pass or by the device
+    Pattern(PatternSource),
+    /// Ciphertext under a discarded key, which no pattern describes
+    Ciphertext,
+    /// Whatever the device leaves behind when it does not say what reads return
+    Indeterminate,
+}
+
+impl ExpectedContents {
+    /// What a hardware erase that clears the media reads back as
+    pub fn zeros() -> Self {
+        ExpectedContents::Pattern(PatternSource::Fixed { byte: 0 })
+    }
+
+    /// The pattern a read-back can be compared against, if any
+    pub fn pattern(&self) -> Option<&PatternSource> {
+        match self {
+            ExpectedContents::Pattern(pattern) => Some(pattern),
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            ExpectedContents::Pattern(pattern) => pattern.describe(),
+            ExpectedContents::Ciphertext => "ciphertext under a discarded key".to_string(),
+            ExpectedContents::Indeterminate => "contents the device does not define".to_string(),
+        }
+    }
+}
+
+impl Default for ExpectedContents {
+    fn default() -> Self {
+        ExpectedContents::zeros()
+    }
+}
+
+/// A named, declarative wipe method
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WipeMethod {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub compliance_level: Option<String>,
+    /// Steps run on devices without a matching override
+    pub steps: Vec<WipeStep>,
+    /// Replacement step lists keyed by device type (e.g. "SSD", "NVMe")
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, Vec<WipeStep>>,
+}
+
+/// A single step of a wipe method
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WipeStep {
+    /// Remove the HPA (and, when permanent, the DCO) so later passes reach the full native capacity
+    RestoreHiddenAreas {
+        #[serde(default)]
+        mode: HiddenAreaRestore,
+    },
+    /// One overwrite pass with the given pattern
+    Overwrite { pattern: PatternSpec },
+    /// A named multi-pass overwrite schedule
+    Schedule { schedule: PassSchedule },
+    /// A device sanitize command; `fallback` runs when the device cannot execute it
+    HardwareCommand {
+        command: HardwareCommand,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fallback: Vec<WipeStep>,
+    },
+    /// TCG Opal/Enterprise cryptographic erase of a self-encrypting drive;
+    /// `fallback` runs when the drive is not an SED with media encryption
+    CryptoErase {
+        #[serde(default)]
+        method: SedErase,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fallback: Vec<WipeStep>,
+    },
+    /// Discard the whole LBA range; `fallback` runs when TRIM is unsupported
+    Trim {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fallback: Vec<WipeStep>,
+    },
+    /// Read the device back and compare it with the last overwrite pass;
+    /// `mode` defaults to the engine's verification mode
+    Verify {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<VerificationMode>,
+    },
+}
+
+impl WipeStep {
+    /// Number of overwrite passes this step performs, not counting fallbacks
+    pub fn pass_count(&self) -> u32 {
+        match self {
+            WipeStep::Overwrite { .. } => 1,
+            WipeStep::Schedule { schedule } => schedule.passes().len() as u32,
+            _ => 0,
+        }
+    }
+}
+
+/// Pattern of an overwrite step as written in a method file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PatternSpec {
+    Fixed { byte: u8 },
+    Repeating { bytes: Vec<u8> },
+    /// Random data; a fresh seed is drawn for every run
+    Random,
+}
+
+impl PatternSpec {
+    /// Produce the concrete pattern source for one run
+    pub fn resolve(&self) -> PatternSource {
+        match self {
+            PatternSpec::Fixed { byte } => PatternSource::Fixed { byte: *byte },
+            PatternSpec::Repeating { bytes } => PatternSource::Repeating { bytes: bytes.clone() },
+            PatternSpec::Random => PatternSource::random(),
+        }
+    }
+}
+
+/// Hardware sanitize commands available to wipe methods
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HardwareCommand {
+    AtaSecureErase,
+    /// Format NVM with a user data erase (SES=1)
+    NvmeFormat,
+    /// Sanitize crypto erase, or Format NVM with SES=2 when sanitize is unsupported
+    NvmeCryptoErase,
+    NvmeSanitizeBlockErase,
+    NvmeSanitizeCryptoErase,
+    NvmeSanitizeOverwrite,
+    ScsiSanitizeOverwrite,
+    ScsiSanitizeBlockErase,
+    ScsiSanitizeCryptoErase,
+    /// FORMAT UNIT with security initialize
+    ScsiFormatUnit,
+}
+
+impl HardwareCommand {
+    /// What the media reads back as once the command has run, as far as the
+    /// command alone tells; the device may refine it when it reports how it ran
+    pub fn expected_contents(&self) -> ExpectedContents {
+        match self {
+            HardwareCommand::AtaSecureErase
+            | HardwareCommand::NvmeFormat
+            | HardwareCommand::NvmeSanitizeBlockErase
+            | HardwareCommand::ScsiSanitizeBlockErase
+            | HardwareCommand::ScsiFormatUnit => ExpectedContents::Indeterminate,
+            HardwareCommand::NvmeCryptoErase
+            | HardwareCommand::NvmeSanitizeCryptoErase
+            | HardwareCommand::ScsiSanitizeCryptoErase => ExpectedContents::Ciphertext,
+            _ => ExpectedContents::zeros(),
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            HardwareCommand::AtaSecureErase => "ATA SECURITY ERASE UNIT",
+            HardwareCommand::NvmeFormat => "NVMe Format NVM (user data erase)",
+            HardwareCommand::NvmeCryptoErase => "NVMe Sanitize crypto erase, or Format NVM (cryptographic erase)",
+            HardwareCommand::NvmeSanitizeBlockErase => "NVMe Sanitize (block erase)",
+            HardwareCommand::NvmeSanitizeCryptoErase => "NVMe Sanitize (crypto erase)",
+            HardwareCommand::NvmeSanitizeOverwrite => "NVMe Sanitize (overwrite)",
+            HardwareCommand::ScsiSanitizeOverwrite => "SCSI SANITIZE (overwrite)",
+            HardwareCommand::ScsiSanitizeBlockErase => "SCSI SANITIZE (block erase)",
+            HardwareCommand::ScsiSanitizeCryptoErase => "SCSI SANITIZE (crypto erase)",
+            HardwareCommand::ScsiFormatUnit => "SCSI FORMAT UNIT (security initialize)",
+        }
+    }
+}
+
+impl WipeMethod {
+    /// Steps to run for a given device type
+    pub fn steps_for(&self, device_type: &DeviceType) -> &[WipeStep] {
+        let key = format!("{:?}", device_type);
+        self.overrides.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&key))
+            .map(|(_, steps)| steps.as_slice())
+            .unwrap_or(&self.steps)
+    }
+
+    /// Number of overwrite passes the primary path of this method performs
+    pub fn planned_passes(&self, device_type: &DeviceType) -> u32 {
+        self.steps_for(device_type).iter().map(WipeStep::pass_count).sum()
+    }
+
+    /// Whether the method contains an explicit verification step
+    pub fn has_verify_step(&self, device_type: &DeviceType) -> bool {
+        self.steps_for(device_type).iter().any(|step| matches!(step, WipeStep::Verify { .. }))
+    }
+
+    /// Copy of the method that restores hidden areas before any other step
+    pub fn with_hidden_area_restore(&self, mode: HiddenAreaRestore) -> WipeMethod {
+        let mut method = self.clone();
+        let restore = WipeStep::RestoreHiddenAreas { mode };
+        for steps in std::iter::once(&mut method.steps).chain(method.overrides.values_mut()) {
+            steps.retain(|step| !matches!(step, WipeStep::RestoreHiddenAreas { .. }));
+            steps.insert(0, restore.clone());
+        }
+        method
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(SecureEraseError::InvalidEraseMode("Wipe method without a name".to_string()));
+        }
+        if self.steps.is_empty() {
+            return Err(SecureEraseError::InvalidEraseMode(format!("Wipe method '{}' has no steps", self.name)));
+        }
+        Ok(())
+    }
+}
+
+/// On-disk layout of a method file
+#[derive(Debug, Deserialize)]
+struct MethodFile {
+    #[serde(alias = "method")]
+    methods: Vec<WipeMethod>,
+}
+
+/// Collection of wipe methods addressable by name
+#[derive(Debug, Clone, Default)]
+pub struct MethodRegistry {
+    methods: BTreeMap<String, WipeMethod>,
+}
+
+impl MethodRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry containing the built-in methods
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.load_toml(BUILTIN_METHODS)
+            .expect("built-in wipe methods must parse");
+        registry
+    }
+
+    /// Load methods from a TOML or JSON file, or every such file in a directory
+    pub fn load_path(&mut self, path: &Path) -> Result<()> {
+        if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path)?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("toml") | Some("json")))
+                .collect();
+            entries.sort();
+            for entry in entries {
+                self.load_file(&entry)?;
+            }
+            return Ok(());
+        }
+        self.load_file(path)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<()> {
+        info!("Loading wipe methods from {}", path.display());
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => self.load_json(&contents),
+            _ => self.load_toml(&contents),
+        }
+    }
+
+    pub fn load_toml(&mut self, contents: &str) -> Result<()> {
+        let file: MethodFile = toml::from_str(contents)
+            .map_err(|e| SecureEraseError::InvalidEraseMode(format!("Invalid method file: {}", e)))?;
+        self.insert_all(file.methods)
+    }
+
+    pub fn load_json(&mut self, contents: &str) -> Result<()> {
+        let file: MethodFile = serde_json::from_str(contents)?;
+        self.insert_all(file.methods)
+    }
+
+    fn insert_all(&mut self, methods: Vec<WipeMethod>) -> Result<()> {
+        for method in methods {
+            method.validate()?;
+            let key = method.name.to_lowercase();
+            if self.methods.contains_key(&key) {
+                warn!("Wipe method '{}' redefined", method.name);
+            }
+            self.methods.insert(key, method);
+        }
+        Ok(())
+    }
+
+    /// Look up a method by name (case-insensitive)
+    pub fn get(&self, name: &str) -> Option<&WipeMethod> {
+        self.methods.get(&name.to_lowercase())
+    }
+
+    /// Resolve the method backing an erase mode
+    pub fn resolve(&self, mode: &EraseMode) -> Result<WipeMethod> {
+        self.get(mode.method_name())
+            .cloned()
+            .ok_or_else(|| SecureEraseError::InvalidEraseMode(mode.method_name().to_string()))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.methods.values().map(|m| m.name.clone()).collect()
+    }
+
+    pub fn methods(&self) -> impl Iterator<Item = &WipeMethod> {
+        self.methods.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advanced_nvme_erase_is_not_checked_against_a_pattern() {
+        let registry = MethodRegistry::builtin();
+        let steps = registry.get("advanced").unwrap().steps_for(&DeviceType::NVMe);
+        match steps {
+            [WipeStep::HardwareCommand { command, .. }] => {
+                assert_eq!(*command, HardwareCommand::NvmeCryptoErase);
+                assert_eq!(command.expected_contents(), ExpectedContents::Ciphertext);
+            }
+            other => panic!("unexpected NVMe steps {:?}", other),
+        }
+
+        // What format and block erase leave behind depends on the namespace's DLFEAT
+        assert_eq!(HardwareCommand::NvmeFormat.expected_contents(), ExpectedContents::Indeterminate);
+        assert_eq!(HardwareCommand::NvmeSanitizeBlockErase.expected_contents(), ExpectedContents::Indeterminate);
+    }
+
+    #[test]
+    fn test_only_scsi_overwrite_sanitize_is_checked_against_zeroes() {
+        assert_eq!(HardwareCommand::ScsiSanitizeOverwrite.expected_contents(), ExpectedContents::zeros());
+        // Block erase and security initialize leave whatever the device chooses
+        assert_eq!(HardwareCommand::ScsiSanitizeBlockErase.expected_contents(), ExpectedContents::Indeterminate);
+        assert_eq!(HardwareCommand::ScsiFormatUnit.expected_contents(), ExpectedContents::Indeterminate);
+        assert_eq!(HardwareCommand::ScsiSanitizeCryptoErase.expected_contents(), ExpectedContents::Ciphertext);
+    }
+}
@@ -1,9 +1,27 @@
-use crate::error::{Result, SecureEraseError};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
 pub mod advanced;
+pub mod ata;
+pub mod batch;
+pub mod block_io;
+pub mod checkpoint;
+pub mod discard;
+pub mod get_disk;
+pub mod hidden_areas;
+pub mod methods;
+pub mod nvme;
+pub mod opal;
+pub mod pipeline;
+pub mod plan;
+pub mod probe;
+pub mod progress;
+pub mod safety;
+pub mod scsi;
+pub mod sg_io;
+pub mod sysfs;
+pub mod verification;
 pub mod device_manager;
 
 /// Represents a storage device that can be securely erased
@@ -18,18 +36,142 @@ pub struct StorageDevice {
     pub supports_secure_erase: bool,
     pub supports_trim: bool,
     pub hidden_areas: Vec<HiddenArea>,
+    #[serde(default)]
+    pub attributes: DeviceAttributes,
+    #[serde(default)]
+    pub layout: DeviceLayout,
+}
+
+impl StorageDevice {
+    /// Identifier safe for file names: the serial number, or the device path
+    pub fn file_id(&self) -> String {
+        let id = self.serial.clone()
+            .filter(|serial| !serial.trim().is_empty())
+            .unwrap_or_else(|| self.path.to_string_lossy().to_string());
+        id.trim().trim_start_matches(|c: char| !c.is_ascii_alphanumeric()).chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect()
+    }
 }
 
 /// Types of storage devices
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DeviceType {
     HDD,
     SSD,
     NVMe,
+    /// SAS or other SCSI-command drives
+    SAS,
     USB,
     Unknown,
 }
 
+/// Hardware attributes reported by the operating system
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceAttributes {
+    pub logical_block_size: u32,
+    pub physical_block_size: u32,
+    pub rotational: bool,
+    pub removable: bool,
+    /// Bus the disk is attached through: "sata", "sas", "nvme", "usb", "mmc" or "virtio";
+    /// on Windows, the `Get-Disk` bus type in lower case
+    pub transport: Option<String>,
+    pub wwn: Option<String>,
+    pub firmware: Option<String>,
+    /// Taken offline by the operating system; on Windows, `Get-Disk`'s `IsOffline`
+    #[serde(default)]
+    pub offline: bool,
+}
+
+/// Partitions, mounts and stacked devices on top of a disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceLayout {
+    /// Mount points of the whole disk
+    pub mountpoints: Vec<PathBuf>,
+    pub partitions: Vec<Partition>,
+    /// Devices built directly on the whole disk
+    pub holders: Vec<Holder>,
+    /// Volumes the running system starts from that have no mount point to find
+    /// them by: "boot" and "system" from Windows `Get-Disk`
+    #[serde(default)]
+    pub system_volumes: Vec<String>,
+}
+
+impl DeviceLayout {
+    /// Mount points of the disk, its partitions and everything stacked on them
+    pub fn all_mountpoints(&self) -> Vec<&PathBuf> {
+        let mut mountpoints: Vec<&PathBuf> = self.mountpoints.iter().collect();
+        for holder in &self.holders {
+            holder.collect_mountpoints(&mut mountpoints);
+        }
+        for partition in &self.partitions {
+            mountpoints.extend(&partition.mountpoints);
+            for holder in &partition.holders {
+                holder.collect_mountpoints(&mut mountpoints);
+            }
+        }
+        mountpoints
+    }
+
+    /// Every holder of the disk and its partitions, including holders of holders
+    pub fn all_holders(&self) -> Vec<&Holder> {
+        let mut holders = Vec::new();
+        for holder in self.holders.iter().chain(self.partitions.iter().flat_map(|p| &p.holders)) {
+            holder.collect(&mut holders);
+        }
+        holders
+    }
+}
+
+/// A partition of a disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Partition {
+    pub name: String,
+    pub path: PathBuf,
+    pub number: u32,
+    pub start_sector: u64,
+    pub size: u64,
+    pub mountpoints: Vec<PathBuf>,
+    pub holders: Vec<Holder>,
+}
+
+/// A device-mapper or software RAID device built on a disk or partition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holder {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: HolderKind,
+    /// Device-mapper name, e.g. `vg0-root`
+    pub dm_name: Option<String>,
+    pub mountpoints: Vec<PathBuf>,
+    pub holders: Vec<Holder>,
+}
+
+impl Holder {
+    fn collect<'a>(&'a self, holders: &mut Vec<&'a Holder>) {
+        holders.push(self);
+        for holder in &self.holders {
+            holder.collect(holders);
+        }
+    }
+
+    fn collect_mountpoints<'a>(&'a self, mountpoints: &mut Vec<&'a PathBuf>) {
+        mountpoints.extend(&self.mountpoints);
+        for holder in &self.holders {
+            holder.collect_mountpoints(mountpoints);
+        }
+    }
+}
+
+/// Kinds of stacked block devices
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HolderKind {
+    Raid,
+    Lvm,
+    Crypt,
+    DeviceMapper,
+}
+
 /// Hidden storage areas that need special handling
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HiddenArea {
@@ -40,7 +182,7 @@ pub struct HiddenArea {
 }
 
 /// Types of hidden areas
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum HiddenAreaType {
     HPA,    // Host Protected Area
     DCO,    // Device Configuration Overlay
@@ -49,11 +191,34 @@ pub enum HiddenAreaType {
 }
 
 /// Erase modes supported by the tool
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EraseMode {
     Quick,      // Single pass with zeros
     Full,       // Multiple passes with random data
     Advanced,   // Hardware secure erase + verification
+    Custom(String), // Any other named wipe method
+}
+
+impl EraseMode {
+    /// Map a method name onto an erase mode
+    pub fn from_method_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "quick" => EraseMode::Quick,
+            "full" => EraseMode::Full,
+            "advanced" => EraseMode::Advanced,
+            _ => EraseMode::Custom(name.to_string()),
+        }
+    }
+    
+    /// Name of the wipe method backing this mode
+    pub fn method_name(&self) -> &str {
+        match self {
+            EraseMode::Quick => "quick",
+            EraseMode::Full => "full",
+            EraseMode::Advanced => "advanced",
+            EraseMode::Custom(name) => name,
+        }
+    }
 }
 
 /// Wipe operation result
@@ -61,86 +226,173 @@ pub enum EraseMode {
 pub struct WipeResult {
     pub device: StorageDevice,
     pub mode: EraseMode,
+    pub method: Option<methods::WipeMethod>,
     pub start_time: SystemTime,
     pub end_time: SystemTime,
     pub duration_seconds: u64,
     pub bytes_written: u64,
     pub verification_passed: bool,
+    pub verification: Option<verification::VerificationReport>,
+    /// What the media should read back as once the method finished
+    #[serde(default)]
+    pub expected_contents: methods::ExpectedContents,
+    /// Blocks no overwrite pass could write, in logical blocks of the device
+    pub unwritable_ranges: Vec<LbaRange>,
+    pub unwritable_bytes: u64,
+    /// What happened to each hidden area of the device
+    pub hidden_areas: Vec<hidden_areas::HiddenAreaStatus>,
+    /// Hardware sanitize commands sent to the device
+    pub hardware_commands: Vec<HardwareCommandRecord>,
+    /// Whole-device discards (TRIM) issued by the method
+    pub discards: Vec<discard::DiscardReport>,
+    /// TCG cryptographic erases of self-encrypting drives
+    pub crypto_erases: Vec<opal::CryptoEraseRecord>,
+    /// Pre-flight safety checks the operator bypassed
+    pub safety_overrides: Vec<safety::SafetyOverride>,
     pub errors: Vec<String>,
+    pub passes: Vec<PassRecord>,
+    /// Points at which the wipe was interrupted and later resumed
+    pub interruptions: Vec<checkpoint::Interruption>,
 }
 
-/// Core wipe engine trait
-pub trait WipeEngine {
-    /// List all available storage devices
-    async fn list_devices(&self) -> Result<Vec<StorageDevice>>;
-    
-    /// Securely erase a device
-    async fn wipe_device(&self, device: &StorageDevice, mode: EraseMode) -> Result<WipeResult>;
-    
-    /// Verify that a device has been properly erased
-    async fn verify_wipe(&self, device: &StorageDevice) -> Result<bool>;
-    
-    /// Check if device supports hardware secure erase
-    async fn supports_secure_erase(&self, device: &StorageDevice) -> Result<bool>;
-}
-
-/// Platform-specific wipe engine implementation
-pub struct PlatformWipeEngine;
-
-impl WipeEngine for PlatformWipeEngine {
-    async fn list_devices(&self) -> Result<Vec<StorageDevice>> {
-        #[cfg(target_os = "windows")]
-        return platform::windows::list_devices().await;
-        
-        #[cfg(target_os = "linux")]
-        return platform::linux::list_devices().await;
-        
-        #[cfg(target_os = "android")]
-        return platform::android::list_devices().await;
-        
-        #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "android")))]
-        Err(SecureEraseError::UnsupportedPlatform)
+/// How completely a device was sanitized
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizationStatus {
+    /// Every block was written and verification passed
+    Complete,
+    /// Verification passed, but some blocks could not be written
+    Partial,
+    Failed,
+}
+
+impl WipeResult {
+    pub fn sanitization_status(&self) -> SanitizationStatus {
+        if !self.verification_passed {
+            SanitizationStatus::Failed
+        } else if !self.unwritable_ranges.is_empty() {
+            SanitizationStatus::Partial
+        } else {
+            SanitizationStatus::Complete
+        }
     }
-    
-    async fn wipe_device(&self, device: &StorageDevice, mode: EraseMode) -> Result<WipeResult> {
-        #[cfg(target_os = "windows")]
-        return platform::windows::wipe_device(device, mode).await;
-        
-        #[cfg(target_os = "linux")]
-        return platform::linux::wipe_device(device, mode).await;
-        
-        #[cfg(target_os = "android")]
-        return platform::android::wipe_device(device, mode).await;
-        
-        #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "android")))]
-        Err(SecureEraseError::UnsupportedPlatform)
+}
+
+/// Record of a completed overwrite pass, sufficient to regenerate its data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassRecord {
+    pub pass_number: u32,
+    pub pattern: advanced::PatternSource,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    /// Average write throughput over the time spent writing
+    pub throughput_bytes_per_sec: f64,
+    #[serde(flatten)]
+    pub stats: PassStats,
+}
+
+/// A hardware sanitize command that completed on the device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardwareCommandRecord {
+    pub command: methods::HardwareCommand,
+    /// The command exactly as sent, e.g. opcode and command dwords
+    pub issued: String,
+    /// Whether an ATA Security Erase ran in enhanced rather than normal mode
+    #[serde(default)]
+    pub enhanced: Option<bool>,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+}
+
+/// Upper bound on error messages kept per pass
+const MAX_PASS_ERRORS: usize = 100;
+
+/// Measured figures of an overwrite pass
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PassStats {
+    /// Bytes written, excluding skipped blocks
+    pub bytes_written: u64,
+    /// Time spent writing, excluding interruptions
+    pub write_seconds: f64,
+    /// Fastest throughput sustained over one second
+    pub peak_bytes_per_sec: f64,
+    /// Chunk writes that had to be retried
+    pub retries: u32,
+    /// Blocks skipped because they could not be written
+    pub unwritable_ranges: Vec<LbaRange>,
+    /// Write errors seen during the pass (truncated after 100)
+    pub errors: Vec<String>,
+}
+
+impl PassStats {
+    pub fn throughput_bytes_per_sec(&self) -> f64 {
+        if self.write_seconds > 0.0 {
+            self.bytes_written as f64 / self.write_seconds
+        } else {
+            0.0
+        }
     }
-    
-    async fn verify_wipe(&self, device: &StorageDevice) -> Result<bool> {
-        #[cfg(target_os = "windows")]
-        return platform::windows::verify_wipe(device).await;
-        
-        #[cfg(target_os = "linux")]
-        return platform::linux::verify_wipe(device).await;
-        
-        #[cfg(target_os = "android")]
-        return platform::android::verify_wipe(device).await;
-        
-        #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "android")))]
-        Err(SecureEraseError::UnsupportedPlatform)
+
+    pub fn record_error(&mut self, error: String) {
+        if self.errors.len() < MAX_PASS_ERRORS {
+            self.errors.push(error);
+        }
     }
-    
-    async fn supports_secure_erase(&self, device: &StorageDevice) -> Result<bool> {
-        #[cfg(target_os = "windows")]
-        return platform::windows::supports_secure_erase(device).await;
-        
-        #[cfg(target_os = "linux")]
-        return platform::linux::supports_secure_erase(device).await;
-        
-        #[cfg(target_os = "android")]
-        return platform::android::supports_secure_erase(device).await;
-        
-        #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "android")))]
-        Err(SecureEraseError::UnsupportedPlatform)
+
+    /// Combine with the figures of a later run of the same pass
+    pub fn merged(&self, later: &PassStats) -> PassStats {
+        let mut errors = self.errors.clone();
+        errors.extend(later.errors.iter().cloned());
+        errors.truncate(MAX_PASS_ERRORS);
+        PassStats {
+            bytes_written: self.bytes_written + later.bytes_written,
+            write_seconds: self.write_seconds + later.write_seconds,
+            peak_bytes_per_sec: self.peak_bytes_per_sec.max(later.peak_bytes_per_sec),
+            retries: self.retries + later.retries,
+            unwritable_ranges: LbaRange::merge(
+                self.unwritable_ranges.iter().chain(&later.unwritable_ranges).cloned().collect()
+            ),
+            errors,
+        }
+    }
+}
+
+/// A contiguous run of logical blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LbaRange {
+    pub start_lba: u64,
+    pub count: u64,
+}
+
+impl LbaRange {
+    /// First LBA after the range
+    pub fn end_lba(&self) -> u64 {
+        self.start_lba + self.count
+    }
+
+    /// Append `count` blocks at `start_lba`, extending the last range when contiguous
+    pub fn push(ranges: &mut Vec<LbaRange>, start_lba: u64, count: u64) {
+        if let Some(last) = ranges.last_mut() {
+            if last.end_lba() == start_lba {
+                last.count += count;
+                return;
+            }
+        }
+        ranges.push(LbaRange { start_lba, count });
+    }
+
+    /// Sort ranges and merge the ones that overlap or touch
+    pub fn merge(mut ranges: Vec<LbaRange>) -> Vec<LbaRange> {
+        ranges.sort_by_key(|range| range.start_lba);
+        let mut merged: Vec<LbaRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start_lba <= last.end_lba() => {
+                    last.count = std::cmp::max(last.end_lba(), range.end_lba()) - last.start_lba;
+                }
+                _ => merged.push(range),
+            }
+        }
+        merged
     }
 }
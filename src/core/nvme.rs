@@ -0,0 +1,593 @@
+use crate::error::{Result, SecureEraseError};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use log::{info, warn};
+
+const ADMIN_GET_LOG_PAGE: u8 = 0x02;
+const ADMIN_IDENTIFY: u8 = 0x06;
+const ADMIN_FORMAT_NVM: u8 = 0x80;
+const ADMIN_SECURITY_SEND: u8 = 0x81;
+const ADMIN_SECURITY_RECEIVE: u8 = 0x82;
+const ADMIN_SANITIZE: u8 = 0x84;
+
+const IDENTIFY_CNS_NAMESPACE: u32 = 0x00;
+const IDENTIFY_CNS_CONTROLLER: u32 = 0x01;
+const LOG_SANITIZE_STATUS: u32 = 0x81;
+
+/// Namespace ID addressing every namespace of the controller
+pub const NSID_ALL: u32 = 0xffff_ffff;
+
+const IDENTIFY_SIZE: usize = 4096;
+const SANITIZE_LOG_SIZE: usize = 512;
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// Format NVM runs synchronously; a user data erase of a large drive takes a while
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(4 * 60 * 60);
+/// Longest wait for a sanitize without an estimate from the controller
+const UNREPORTED_SANITIZE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[cfg(target_os = "linux")]
+const NVME_IOCTL_ID: u64 = 0x4e40;
+#[cfg(target_os = "linux")]
+const NVME_IOCTL_ADMIN_CMD: u64 = 0xc048_4e41;
+
+/// An NVMe admin command
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AdminCommand {
+    pub opcode: u8,
+    pub nsid: u32,
+    pub cdw10: u32,
+    pub cdw11: u32,
+    pub timeout: Duration,
+}
+
+impl AdminCommand {
+    /// Exact form of the command, as recorded in certificates
+    pub fn describe(&self) -> String {
+        format!("opcode 0x{:02x}, NSID 0x{:x}, CDW10 0x{:08x}, CDW11 0x{:08x}", self.opcode, self.nsid, self.cdw10, self.cdw11)
+    }
+}
+
+/// Anything that can execute NVMe admin commands; implemented by the Linux ioctl and by test doubles
+pub trait NvmeTransport {
+    /// Run `command`, transferring `data` in the direction the opcode implies,
+    /// and return completion dword 0
+    fn admin(&mut self, command: &AdminCommand, data: &mut [u8]) -> Result<u32>;
+
+    /// Namespace the opened device node refers to
+    fn namespace_id(&mut self) -> Result<u32>;
+}
+
+/// An NVMe namespace or controller node opened for admin pass-through
+pub struct NvmeIoctl {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    file: File,
+}
+
+/// `struct nvme_passthru_cmd` from <linux/nvme_ioctl.h>
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[allow(dead_code)]
+#[derive(Default)]
+struct NvmePassthruCmd {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+impl NvmeIoctl {
+    #[cfg(target_os = "linux")]
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self { file: std::fs::OpenOptions::new().read(true).open(path)? })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn open(_path: &Path) -> Result<Self> {
+        Err(SecureEraseError::UnsupportedPlatform)
+    }
+}
+
+impl NvmeTransport for NvmeIoctl {
+    #[cfg(target_os = "linux")]
+    fn admin(&mut self, command: &AdminCommand, data: &mut [u8]) -> Result<u32> {
+        use std::os::unix::io::AsRawFd;
+
+        let mut cmd = NvmePassthruCmd {
+            opcode: command.opcode,
+            nsid: command.nsid,
+            addr: if data.is_empty() { 0 } else { data.as_mut_ptr() as u64 },
+            data_len: data.len() as u32,
+            cdw10: command.cdw10,
+            cdw11: command.cdw11,
+            timeout_ms: command.timeout.as_millis().min(u32::MAX as u128) as u32,
+            ..Default::default()
+        };
+        let status = unsafe { libc::ioctl(self.file.as_raw_fd(), NVME_IOCTL_ADMIN_CMD as _, &mut cmd) };
+        if status < 0 {
+            return Err(SecureEraseError::Io(std::io::Error::last_os_error()));
+        }
+        if status > 0 {
+            return Err(command_failed(command, status as u32));
+        }
+        Ok(cmd.result)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn admin(&mut self, _command: &AdminCommand, _data: &mut [u8]) -> Result<u32> {
+        Err(SecureEraseError::UnsupportedPlatform)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn namespace_id(&mut self) -> Result<u32> {
+        use std::os::unix::io::AsRawFd;
+
+        let nsid = unsafe { libc::ioctl(self.file.as_raw_fd(), NVME_IOCTL_ID as _) };
+        if nsid < 0 {
+            return Err(SecureEraseError::Io(std::io::Error::last_os_error()));
+        }
+        Ok(nsid as u32)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn namespace_id(&mut self) -> Result<u32> {
+        Err(SecureEraseError::UnsupportedPlatform)
+    }
+}
+
+/// Error for a command completed with a non-zero NVMe status
+pub fn command_failed(command: &AdminCommand, status: u32) -> SecureEraseError {
+    SecureEraseError::WipeFailed(format!(
+        "NVMe admin command 0x{:02x} failed with status 0x{:x} (SCT {}, SC 0x{:02x})",
+        command.opcode, status, (status >> 8) & 0x7, status & 0xff
+    ))
+}
+
+/// Sanitize operations the controller supports (SANICAP)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SanitizeCapabilities {
+    pub crypto_erase: bool,
+    pub block_erase: bool,
+    pub overwrite: bool,
+}
+
+/// Format NVM attributes (FNA)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FormatAttributes {
+    /// A format affects every namespace
+    pub applies_to_all_namespaces: bool,
+    /// A secure erase affects every namespace
+    pub secure_erase_all_namespaces: bool,
+    pub crypto_erase_supported: bool,
+}
+
+/// Fields of Identify Controller used by the sanitize paths
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ControllerInfo {
+    pub serial: String,
+    pub model: String,
+    pub firmware: String,
+    pub sanitize: SanitizeCapabilities,
+    pub format: FormatAttributes,
+}
+
+impl ControllerInfo {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < IDENTIFY_SIZE {
+            return Err(SecureEraseError::WipeFailed(format!("Identify Controller data too short ({} bytes)", data.len())));
+        }
+        let string = |range: std::ops::Range<usize>| {
+            String::from_utf8_lossy(&data[range]).trim_matches(|c: char| c.is_whitespace() || c == '\0').to_string()
+        };
+        let sanicap = u32::from_le_bytes([data[328], data[329], data[330], data[331]]);
+        let fna = data[524];
+        Ok(Self {
+            serial: string(4..24),
+            model: string(24..64),
+            firmware: string(64..72),
+            sanitize: SanitizeCapabilities {
+                crypto_erase: sanicap & 0x1 != 0,
+                block_erase: sanicap & 0x2 != 0,
+                overwrite: sanicap & 0x4 != 0,
+            },
+            format: FormatAttributes {
+                applies_to_all_namespaces: fna & 0x1 != 0,
+                secure_erase_all_namespaces: fna & 0x2 != 0,
+                crypto_erase_supported: fna & 0x4 != 0,
+            },
+        })
+    }
+}
+
+/// Fields of Identify Namespace used when formatting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceInfo {
+    /// Namespace size in logical blocks (NSZE)
+    pub size_blocks: u64,
+    /// Index of the LBA format in use
+    pub lba_format: u8,
+    pub block_size: u32,
+    /// Deallocated blocks read back as zeroes (DLFEAT 001b)
+    pub deallocate_reads_zeroes: bool,
+}
+
+impl NamespaceInfo {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < IDENTIFY_SIZE {
+            return Err(SecureEraseError::WipeFailed(format!("Identify Namespace data too short ({} bytes)", data.len())));
+        }
+        let lba_format = data[26] & 0x0f;
+        let descriptor = 128 + 4 * lba_format as usize;
+        let lbads = data[descriptor + 2];
+        Ok(Self {
+            size_blocks: u64::from_le_bytes(data[0..8].try_into().unwrap()),
+            lba_format,
+            block_size: if lbads >= 9 { 1u32 << lbads } else { 0 },
+            deallocate_reads_zeroes: data[33] & 0x07 == 0x01,
+        })
+    }
+}
+
+/// Secure Erase Settings of Format NVM
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatErase {
+    /// SES=1
+    UserData = 1,
+    /// SES=2
+    Cryptographic = 2,
+}
+
+/// Sanitize actions (SANACT)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizeAction {
+    BlockErase = 2,
+    Overwrite = 3,
+    CryptoErase = 4,
+}
+
+/// Sanitize state from the Sanitize Status log (SSTAT bits 2:0)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizeState {
+    NeverSanitized,
+    Completed,
+    InProgress,
+    Failed,
+    CompletedWithoutDeallocate,
+    Unknown,
+}
+
+/// Sanitize Status log page (log identifier 81h)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SanitizeStatus {
+    /// Fraction of the operation completed (SPROG / 65536)
+    pub progress: f64,
+    pub state: SanitizeState,
+    pub overwrite_passes: u8,
+    pub global_data_erased: bool,
+    /// Controller estimates, in seconds
+    pub overwrite_estimate: Option<u32>,
+    pub block_erase_estimate: Option<u32>,
+    pub crypto_erase_estimate: Option<u32>,
+}
+
+impl SanitizeStatus {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 20 {
+            return Err(SecureEraseError::WipeFailed(format!("Sanitize Status log too short ({} bytes)", data.len())));
+        }
+        let estimate = |offset: usize| {
+            let seconds = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+            Some(seconds).filter(|seconds| *seconds != u32::MAX)
+        };
+        let sprog = u16::from_le_bytes([data[0], data[1]]);
+        let sstat = u16::from_le_bytes([data[2], data[3]]);
+        Ok(Self {
+            progress: sprog as f64 / 65536.0,
+            state: match sstat & 0x7 {
+                0 => SanitizeState::NeverSanitized,
+                1 => SanitizeState::Completed,
+                2 => SanitizeState::InProgress,
+                3 => SanitizeState::Failed,
+                4 => SanitizeState::CompletedWithoutDeallocate,
+                _ => SanitizeState::Unknown,
+            },
+            overwrite_passes: ((sstat >> 3) & 0x1f) as u8,
+            global_data_erased: sstat & 0x100 != 0,
+            overwrite_estimate: estimate(8),
+            block_erase_estimate: estimate(12),
+            crypto_erase_estimate: estimate(16),
+        })
+    }
+
+    fn estimate_for(&self, action: SanitizeAction) -> Option<u32> {
+        match action {
+            SanitizeAction::BlockErase => self.block_erase_estimate,
+            SanitizeAction::Overwrite => self.overwrite_estimate,
+            SanitizeAction::CryptoErase => self.crypto_erase_estimate,
+        }
+    }
+}
+
+/// NVMe device reached through admin pass-through
+pub struct NvmeDevice<T: NvmeTransport> {
+    transport: T,
+    /// Delay between Sanitize Status log reads
+    pub poll_interval: Duration,
+}
+
+impl<T: NvmeTransport> NvmeDevice<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport, poll_interval: Duration::from_secs(5) }
+    }
+
+    pub fn identify_controller(&mut self) -> Result<ControllerInfo> {
+        let mut data = vec![0u8; IDENTIFY_SIZE];
+        let command = AdminCommand { opcode: ADMIN_IDENTIFY, cdw10: IDENTIFY_CNS_CONTROLLER, timeout: COMMAND_TIMEOUT, ..Default::default() };
+        self.transport.admin(&command, &mut data)?;
+        ControllerInfo::parse(&data)
+    }
+
+    pub fn identify_namespace(&mut self, nsid: u32) -> Result<NamespaceInfo> {
+        let mut data = vec![0u8; IDENTIFY_SIZE];
+        let command = AdminCommand { opcode: ADMIN_IDENTIFY, nsid, cdw10: IDENTIFY_CNS_NAMESPACE, timeout: COMMAND_TIMEOUT, ..Default::default() };
+        self.transport.admin(&command, &mut data)?;
+        NamespaceInfo::parse(&data)
+    }
+
+    pub fn sanitize_status(&mut self) -> Result<SanitizeStatus> {
+        let mut data = vec![0u8; SANITIZE_LOG_SIZE];
+        let dwords = (SANITIZE_LOG_SIZE / 4 - 1) as u32;
+        let command = AdminCommand {
+            opcode: ADMIN_GET_LOG_PAGE,
+            nsid: NSID_ALL,
+            cdw10: LOG_SANITIZE_STATUS | (dwords << 16),
+            timeout: COMMAND_TIMEOUT,
+            ..Default::default()
+        };
+        self.transport.admin(&command, &mut data)?;
+        SanitizeStatus::parse(&data)
+    }
+
+    pub fn security_send(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()> {
+        let mut buffer = data.to_vec();
+        let command = AdminCommand {
+            opcode: ADMIN_SECURITY_SEND,
+            cdw10: (protocol as u32) << 24 | (sp_specific as u32) << 8,
+            cdw11: data.len() as u32,
+            timeout: COMMAND_TIMEOUT,
+            ..Default::default()
+        };
+        self.transport.admin(&command, &mut buffer)?;
+        Ok(())
+    }
+
+    pub fn security_receive(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()> {
+        let command = AdminCommand {
+            opcode: ADMIN_SECURITY_RECEIVE,
+            cdw10: (protocol as u32) << 24 | (sp_specific as u32) << 8,
+            cdw11: data.len() as u32,
+            timeout: COMMAND_TIMEOUT,
+            ..Default::default()
+        };
+        self.transport.admin(&command, data)?;
+        Ok(())
+    }
+
+    /// Format NVM with a secure erase, keeping the namespace's current LBA format.
+    /// Returns the exact command issued.
+    pub fn format(&mut self, erase: FormatErase) -> Result<AdminCommand> {
+        let controller = self.identify_controller()?;
+        if erase == FormatErase::Cryptographic && !controller.format.crypto_erase_supported {
+            return Err(SecureEraseError::SecureEraseNotSupported);
+        }
+        let nsid = self.transport.namespace_id()?;
+        let namespace = self.identify_namespace(nsid)?;
+        let command = AdminCommand {
+            opcode: ADMIN_FORMAT_NVM,
+            nsid: if controller.format.applies_to_all_namespaces { NSID_ALL } else { nsid },
+            cdw10: namespace.lba_format as u32 | ((erase as u32) << 9),
+            timeout: FORMAT_TIMEOUT,
+            ..Default::default()
+        };
+        info!("NVMe Format NVM of {} ({}): SES={}", controller.model, controller.serial, erase as u32);
+        self.transport.admin(&command, &mut [])?;
+        Ok(command)
+    }
+
+    /// Start a sanitize and poll the Sanitize Status log until it finishes.
+    /// `progress` receives the completed fraction. Returns the exact command issued.
+    pub fn sanitize(&mut self, action: SanitizeAction, mut progress: impl FnMut(f64)) -> Result<AdminCommand> {
+        let controller = self.identify_controller()?;
+        let supported = match action {
+            SanitizeAction::BlockErase => controller.sanitize.block_erase,
+            SanitizeAction::Overwrite => controller.sanitize.overwrite,
+            SanitizeAction::CryptoErase => controller.sanitize.crypto_erase,
+        };
+        if !supported {
+            return Err(SecureEraseError::SecureEraseNotSupported);
+        }
+
+        // Overwrite runs a single pass of zeros (OWPASS=1, pattern in CDW11)
+        let cdw10 = match action {
+            SanitizeAction::Overwrite => action as u32 | (1 << 4),
+            _ => action as u32,
+        };
+        let command = AdminCommand { opcode: ADMIN_SANITIZE, nsid: 0, cdw10, cdw11: 0, timeout: COMMAND_TIMEOUT };
+        info!("NVMe Sanitize of {} ({}): {:?}", controller.model, controller.serial, action);
+        self.transport.admin(&command, &mut [])?;
+
+        let started = Instant::now();
+        let mut deadline = UNREPORTED_SANITIZE_TIMEOUT;
+        loop {
+            let status = self.sanitize_status()?;
+            if let Some(estimate) = status.estimate_for(action) {
+                deadline = Duration::from_secs(estimate as u64 * 2 + 600);
+            }
+            match status.state {
+                SanitizeState::InProgress => progress(status.progress),
+                SanitizeState::Completed | SanitizeState::CompletedWithoutDeallocate => {
+                    progress(1.0);
+                    return Ok(command);
+                }
+                SanitizeState::Failed => {
+                    return Err(SecureEraseError::WipeFailed(
+                        "NVMe sanitize failed; the controller stays in restricted mode until a sanitize succeeds".to_string()
+                    ));
+                }
+                state => warn!("Unexpected sanitize state {:?} while waiting", state),
+            }
+            if started.elapsed() > deadline {
+                return Err(SecureEraseError::WipeFailed(format!(
+                    "NVMe sanitize still running after {} seconds", started.elapsed().as_secs()
+                )));
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+
+    /// Cryptographic erase: Sanitize crypto erase when supported, otherwise Format NVM with SES=2
+    pub fn crypto_erase(&mut self, progress: impl FnMut(f64)) -> Result<AdminCommand> {
+        if self.identify_controller()?.sanitize.crypto_erase {
+            self.sanitize(SanitizeAction::CryptoErase, progress)
+        } else {
+            self.format(FormatErase::Cryptographic)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Controller simulator answering admin commands
+    struct MockController {
+        sanicap: u32,
+        fna: u8,
+        nsid: u32,
+        /// Sanitize progress reported on successive log reads
+        sanitize_steps: Vec<(u16, u16)>,
+        commands: Vec<AdminCommand>,
+    }
+
+    impl MockController {
+        fn new(sanicap: u32, fna: u8) -> Self {
+            Self { sanicap, fna, nsid: 1, sanitize_steps: Vec::new(), commands: Vec::new() }
+        }
+
+        fn issued(&self, opcode: u8) -> Vec<AdminCommand> {
+            self.commands.iter().filter(|command| command.opcode == opcode).copied().collect()
+        }
+    }
+
+    impl NvmeTransport for MockController {
+        fn admin(&mut self, command: &AdminCommand, data: &mut [u8]) -> Result<u32> {
+            self.commands.push(*command);
+            match (command.opcode, command.cdw10 & 0xff) {
+                (ADMIN_IDENTIFY, IDENTIFY_CNS_CONTROLLER) => {
+                    data[4..24].copy_from_slice(b"S6B0NL0T123456      ");
+                    data[24..44].copy_from_slice(b"Samsung SSD 980 PRO ");
+                    data[328..332].copy_from_slice(&self.sanicap.to_le_bytes());
+                    data[524] = self.fna;
+                }
+                (ADMIN_IDENTIFY, IDENTIFY_CNS_NAMESPACE) => {
+                    data[0..8].copy_from_slice(&1_953_525_168u64.to_le_bytes());
+                    data[26] = 1;
+                    data[33] = 0x09;
+                    data[128 + 4 + 2] = 12;
+                }
+                (ADMIN_GET_LOG_PAGE, LOG_SANITIZE_STATUS) => {
+                    let (sprog, sstat) = if self.sanitize_steps.is_empty() { (0, 0) } else { self.sanitize_steps.remove(0) };
+                    data[0..2].copy_from_slice(&sprog.to_le_bytes());
+                    data[2..4].copy_from_slice(&sstat.to_le_bytes());
+                    data[8..20].fill(0xff);
+                    data[16..20].copy_from_slice(&30u32.to_le_bytes());
+                }
+                (ADMIN_FORMAT_NVM, _) | (ADMIN_SANITIZE, _) => {}
+                (opcode, _) => panic!("unexpected admin command 0x{:02x}", opcode),
+            }
+            Ok(0)
+        }
+
+        fn namespace_id(&mut self) -> Result<u32> {
+            Ok(self.nsid)
+        }
+    }
+
+    #[test]
+    fn test_parses_identify_controller_and_namespace() {
+        let mut device = NvmeDevice::new(MockController::new(0x5, 0x6));
+        let controller = device.identify_controller().unwrap();
+        assert_eq!(controller.model, "Samsung SSD 980 PRO");
+        assert_eq!(controller.serial, "S6B0NL0T123456");
+        assert_eq!(controller.sanitize, SanitizeCapabilities { crypto_erase: true, block_erase: false, overwrite: true });
+        assert!(!controller.format.applies_to_all_namespaces);
+        assert!(controller.format.secure_erase_all_namespaces && controller.format.crypto_erase_supported);
+
+        let namespace = device.identify_namespace(1).unwrap();
+        assert_eq!(namespace, NamespaceInfo { size_blocks: 1_953_525_168, lba_format: 1, block_size: 4096, deallocate_reads_zeroes: true });
+    }
+
+    #[test]
+    fn test_format_keeps_lba_format_and_sets_ses() {
+        let mut device = NvmeDevice::new(MockController::new(0, 0x1));
+        let command = device.format(FormatErase::UserData).unwrap();
+        assert_eq!(command.nsid, NSID_ALL);
+        assert_eq!(command.cdw10, 1 | (1 << 9));
+        assert_eq!(device.transport.issued(ADMIN_FORMAT_NVM), vec![command]);
+
+        // SES=2 needs FNA crypto erase support
+        let mut device = NvmeDevice::new(MockController::new(0, 0));
+        assert!(matches!(device.format(FormatErase::Cryptographic), Err(SecureEraseError::SecureEraseNotSupported)));
+        assert!(device.transport.issued(ADMIN_FORMAT_NVM).is_empty());
+    }
+
+    #[test]
+    fn test_sanitize_polls_until_complete() {
+        let mut controller = MockController::new(0x1, 0);
+        controller.sanitize_steps = vec![(0x4000, 2), (0xc000, 2), (0xffff, 0x101)];
+        let mut device = NvmeDevice::new(controller);
+        device.poll_interval = Duration::ZERO;
+
+        let mut reported = Vec::new();
+        let command = device.crypto_erase(|fraction| reported.push(fraction)).unwrap();
+        assert_eq!(command.opcode, ADMIN_SANITIZE);
+        assert_eq!(command.cdw10, SanitizeAction::CryptoErase as u32);
+        assert_eq!(reported, vec![0.25, 0.75, 1.0]);
+        assert_eq!(device.transport.issued(ADMIN_GET_LOG_PAGE).len(), 3);
+
+        let status = SanitizeStatus::parse(&[0, 0, 0x09, 0x01, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 5, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(status.state, SanitizeState::Completed);
+        assert_eq!(status.overwrite_passes, 1);
+        assert!(status.global_data_erased);
+        assert_eq!((status.overwrite_estimate, status.block_erase_estimate), (None, Some(5)));
+    }
+
+    #[test]
+    fn test_failed_or_unsupported_sanitize() {
+        let mut controller = MockController::new(0x2, 0);
+        controller.sanitize_steps = vec![(0x1000, 2), (0, 3)];
+        let mut device = NvmeDevice::new(controller);
+        device.poll_interval = Duration::ZERO;
+        assert!(matches!(device.sanitize(SanitizeAction::BlockErase, |_| {}), Err(SecureEraseError::WipeFailed(_))));
+        assert!(matches!(device.sanitize(SanitizeAction::Overwrite, |_| {}), Err(SecureEraseError::SecureEraseNotSupported)));
+    }
+}
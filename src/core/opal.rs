@@ -0,0 +1,710 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, DeviceType};
+use crate::core::ata::AtaDevice;
+use crate::core::nvme::{NvmeDevice, NvmeIoctl, NvmeTransport};
+use crate::core::scsi::ScsiDevice;
+use crate::core::sg_io::{ScsiTransport, SgIoDevice};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+use log::{info, warn};
+
+/// Security protocol carrying TCG ComPackets and Level 0 discovery
+pub const PROTOCOL_TCG: u8 = 0x01;
+const LEVEL0_DISCOVERY_COMID: u16 = 0x0001;
+
+/// Transfers are padded to whole sectors for ATA TRUSTED SEND/RECEIVE
+const TRANSFER_SIZE: usize = 2048;
+
+/// How often and how long to wait for a response the TPer has not finished
+const RECEIVE_RETRIES: u32 = 100;
+const RECEIVE_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+type Uid = [u8; 8];
+
+const SMUID: Uid = [0, 0, 0, 0, 0, 0, 0, 0xff];
+const THIS_SP: Uid = [0, 0, 0, 0, 0, 0, 0, 0x01];
+const ADMIN_SP: Uid = [0, 0, 0x02, 0x05, 0, 0, 0, 0x01];
+const OPAL_LOCKING_SP: Uid = [0, 0, 0x02, 0x05, 0, 0, 0, 0x02];
+const ENTERPRISE_LOCKING_SP: Uid = [0, 0, 0x02, 0x05, 0, 0x01, 0, 0x01];
+
+const AUTHORITY_PSID: Uid = [0, 0, 0, 0x09, 0, 0x01, 0xff, 0x01];
+const AUTHORITY_ADMIN1: Uid = [0, 0, 0, 0x09, 0, 0x01, 0, 0x01];
+const AUTHORITY_ERASE_MASTER: Uid = [0, 0, 0, 0x09, 0, 0, 0x84, 0x01];
+
+/// Locking_GlobalRange in Opal, Band0 in Enterprise
+const GLOBAL_RANGE: Uid = [0, 0, 0x08, 0x02, 0, 0, 0, 0x01];
+/// ActiveKey column of a locking range
+const ACTIVE_KEY_COLUMN: u64 = 0x0a;
+
+const METHOD_START_SESSION: Uid = [0, 0, 0, 0, 0, 0, 0xff, 0x02];
+const METHOD_SYNC_SESSION: Uid = [0, 0, 0, 0, 0, 0, 0xff, 0x03];
+const METHOD_GEN_KEY: Uid = [0, 0, 0, 0x06, 0, 0, 0, 0x10];
+const METHOD_REVERT_SP: Uid = [0, 0, 0, 0x06, 0, 0, 0, 0x11];
+const METHOD_GET: Uid = [0, 0, 0, 0x06, 0, 0, 0, 0x16];
+const METHOD_REVERT: Uid = [0, 0, 0, 0x06, 0, 0, 0x02, 0x02];
+const METHOD_ERASE: Uid = [0, 0, 0, 0x06, 0, 0, 0x08, 0x03];
+
+/// Host session number used for every session
+const HOST_SESSION_ID: u32 = 0x5345_4431;
+
+/// Security Send/Receive as offered by ATA, SCSI and NVMe
+pub trait SecurityTransport {
+    fn security_send(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()>;
+    fn security_receive(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()>;
+}
+
+impl<T: SecurityTransport + ?Sized> SecurityTransport for Box<T> {
+    fn security_send(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()> {
+        (**self).security_send(protocol, sp_specific, data)
+    }
+
+    fn security_receive(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()> {
+        (**self).security_receive(protocol, sp_specific, data)
+    }
+}
+
+impl<T: ScsiTransport> SecurityTransport for AtaDevice<T> {
+    fn security_send(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()> {
+        self.trusted_send(protocol, sp_specific, data)
+    }
+
+    fn security_receive(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()> {
+        self.trusted_receive(protocol, sp_specific, data)
+    }
+}
+
+impl<T: ScsiTransport> SecurityTransport for ScsiDevice<T> {
+    fn security_send(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()> {
+        self.security_protocol_out(protocol, sp_specific, data)
+    }
+
+    fn security_receive(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()> {
+        self.security_protocol_in(protocol, sp_specific, data)
+    }
+}
+
+impl<T: NvmeTransport> SecurityTransport for NvmeDevice<T> {
+    fn security_send(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()> {
+        NvmeDevice::security_send(self, protocol, sp_specific, data)
+    }
+
+    fn security_receive(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()> {
+        NvmeDevice::security_receive(self, protocol, sp_specific, data)
+    }
+}
+
+/// Open the security transport matching the device's command set
+pub fn open_transport(device: &StorageDevice) -> Result<Box<dyn SecurityTransport + Send>> {
+    Ok(match device.device_type {
+        DeviceType::NVMe => Box::new(NvmeDevice::new(NvmeIoctl::open(&device.path)?)),
+        DeviceType::SAS => Box::new(ScsiDevice::new(SgIoDevice::open(&device.path)?)),
+        _ => Box::new(AtaDevice::new(SgIoDevice::open(&device.path)?)),
+    })
+}
+
+/// TCG security subsystem class advertised in Level 0 discovery
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SecuritySubsystem {
+    Enterprise,
+    Opal1,
+    Opal2,
+    Opalite,
+    Pyrite1,
+    Pyrite2,
+    Ruby,
+}
+
+impl SecuritySubsystem {
+    fn from_feature(code: u16) -> Option<Self> {
+        match code {
+            0x0100 => Some(SecuritySubsystem::Enterprise),
+            0x0200 => Some(SecuritySubsystem::Opal1),
+            0x0203 => Some(SecuritySubsystem::Opal2),
+            0x0301 => Some(SecuritySubsystem::Opalite),
+            0x0302 => Some(SecuritySubsystem::Pyrite1),
+            0x0303 => Some(SecuritySubsystem::Pyrite2),
+            0x0304 => Some(SecuritySubsystem::Ruby),
+            _ => None,
+        }
+    }
+}
+
+/// Locking feature descriptor (feature code 0002h)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LockingState {
+    pub supported: bool,
+    pub enabled: bool,
+    pub locked: bool,
+    /// User data is encrypted, so replacing the key erases it
+    pub media_encryption: bool,
+    pub mbr_enabled: bool,
+    pub mbr_done: bool,
+}
+
+/// Result of Level 0 discovery
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Discovery {
+    pub subsystem: Option<SecuritySubsystem>,
+    /// First ComID of the subsystem, used for sessions
+    pub base_comid: u16,
+    pub locking: LockingState,
+}
+
+impl Discovery {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 48 {
+            return Err(SecureEraseError::WipeFailed("Level 0 discovery data too short".to_string()));
+        }
+        let end = std::cmp::min(data.len(), 4 + u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize);
+        let mut discovery = Discovery { subsystem: None, base_comid: 0, locking: LockingState::default() };
+        let mut offset = 48;
+        while offset + 4 <= end {
+            let code = u16::from_be_bytes([data[offset], data[offset + 1]]);
+            let length = data[offset + 3] as usize;
+            let feature = &data[offset + 4..std::cmp::min(end, offset + 4 + length)];
+            if code == 0x0002 {
+                let flags = feature.first().copied().unwrap_or(0);
+                discovery.locking = LockingState {
+                    supported: flags & 0x01 != 0,
+                    enabled: flags & 0x02 != 0,
+                    locked: flags & 0x04 != 0,
+                    media_encryption: flags & 0x08 != 0,
+                    mbr_enabled: flags & 0x10 != 0,
+                    mbr_done: flags & 0x20 != 0,
+                };
+            } else if let Some(subsystem) = SecuritySubsystem::from_feature(code) {
+                // Prefer the newest Opal over an older one advertised alongside it
+                if discovery.subsystem.is_none() || subsystem == SecuritySubsystem::Opal2 {
+                    discovery.subsystem = Some(subsystem);
+                    discovery.base_comid = feature.get(..2).map(|id| u16::from_be_bytes([id[0], id[1]])).unwrap_or(0);
+                }
+            }
+            offset += 4 + length;
+        }
+        Ok(discovery)
+    }
+
+    /// A TCG subsystem with media encryption, so a key change erases user data
+    pub fn supports_crypto_erase(&self) -> bool {
+        self.subsystem.is_some() && self.locking.media_encryption
+    }
+}
+
+/// Run Level 0 discovery on a transport
+pub fn discover(transport: &mut dyn SecurityTransport) -> Result<Discovery> {
+    let mut data = vec![0u8; TRANSFER_SIZE];
+    transport.security_receive(PROTOCOL_TCG, LEVEL0_DISCOVERY_COMID, &mut data)?;
+    Discovery::parse(&data)
+}
+
+/// Run Level 0 discovery on a device
+pub fn discover_device(device: &StorageDevice) -> Result<Discovery> {
+    discover(open_transport(device)?.as_mut())
+}
+
+/// Which TCG operation performs the cryptographic erase
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SedErase {
+    /// Revert the whole TPer to factory state with the PSID printed on the label
+    PsidRevert,
+    /// Revert the Locking SP as Admin1 (Opal), or Erase Band0 as EraseMaster (Enterprise)
+    #[default]
+    RevertSp,
+    /// Generate a new key for the global range as Admin1
+    GenKey,
+}
+
+impl SedErase {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SedErase::PsidRevert => "PSID revert",
+            SedErase::RevertSp => "revert of the Locking SP",
+            SedErase::GenKey => "new key for the global range",
+        }
+    }
+}
+
+/// One method call sent to the TPer, without credentials
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    pub at: SystemTime,
+    pub call: String,
+    pub status: String,
+}
+
+/// A completed cryptographic erase of a self-encrypting drive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CryptoEraseRecord {
+    pub method: SedErase,
+    pub subsystem: SecuritySubsystem,
+    pub locking_before: LockingState,
+    pub locking_after: Option<LockingState>,
+    pub exchanges: Vec<Exchange>,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+}
+
+/// TCG data stream token
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Uint(u64),
+    Bytes(Vec<u8>),
+    StartList,
+    EndList,
+    StartName,
+    EndName,
+    Call,
+    EndOfData,
+    EndOfSession,
+}
+
+/// Encode tokens as tiny, short and medium atoms
+pub fn encode_tokens(tokens: &[Token]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Uint(value) if *value < 0x40 => out.push(*value as u8),
+            Token::Uint(value) => {
+                let bytes = value.to_be_bytes();
+                let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+                out.push(0x80 | (8 - skip) as u8);
+                out.extend_from_slice(&bytes[skip..]);
+            }
+            Token::Bytes(bytes) if bytes.len() < 16 => {
+                out.push(0xa0 | bytes.len() as u8);
+                out.extend_from_slice(bytes);
+            }
+            Token::Bytes(bytes) => {
+                out.push(0xd0 | ((bytes.len() >> 8) & 0x07) as u8);
+                out.push(bytes.len() as u8);
+                out.extend_from_slice(bytes);
+            }
+            Token::StartList => out.push(0xf0),
+            Token::EndList => out.push(0xf1),
+            Token::StartName => out.push(0xf2),
+            Token::EndName => out.push(0xf3),
+            Token::Call => out.push(0xf8),
+            Token::EndOfData => out.push(0xf9),
+            Token::EndOfSession => out.push(0xfa),
+        }
+    }
+    out
+}
+
+pub fn decode_tokens(data: &[u8]) -> Result<Vec<Token>> {
+    let truncated = || SecureEraseError::WipeFailed("Truncated TCG token stream".to_string());
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let head = data[offset];
+        let (header, length, bytes) = match head {
+            0x00..=0x7f => {
+                tokens.push(Token::Uint((head & 0x3f) as u64));
+                offset += 1;
+                continue;
+            }
+            0x80..=0xbf => (1, (head & 0x0f) as usize, head & 0x20 != 0),
+            0xc0..=0xdf => (2, ((head & 0x07) as usize) << 8 | *data.get(offset + 1).ok_or_else(truncated)? as usize, head & 0x10 != 0),
+            0xe0..=0xe3 => {
+                let size = data.get(offset + 1..offset + 4).ok_or_else(truncated)?;
+                (4, u32::from_be_bytes([0, size[0], size[1], size[2]]) as usize, head & 0x02 != 0)
+            }
+            0xf0 => { tokens.push(Token::StartList); offset += 1; continue; }
+            0xf1 => { tokens.push(Token::EndList); offset += 1; continue; }
+            0xf2 => { tokens.push(Token::StartName); offset += 1; continue; }
+            0xf3 => { tokens.push(Token::EndName); offset += 1; continue; }
+            0xf8 => { tokens.push(Token::Call); offset += 1; continue; }
+            0xf9 => { tokens.push(Token::EndOfData); offset += 1; continue; }
+            0xfa => { tokens.push(Token::EndOfSession); offset += 1; continue; }
+            // Empty atoms pad the stream
+            _ => { offset += 1; continue; }
+        };
+        let value = data.get(offset + header..offset + header + length).ok_or_else(truncated)?;
+        tokens.push(if bytes {
+            Token::Bytes(value.to_vec())
+        } else {
+            Token::Uint(value.iter().fold(0u64, |acc, byte| acc << 8 | *byte as u64))
+        });
+        offset += header + length;
+    }
+    Ok(tokens)
+}
+
+/// Wrap a token payload in SubPacket, Packet and ComPacket headers
+pub fn build_compacket(comid: u16, tsn: u32, hsn: u32, payload: &[u8]) -> Vec<u8> {
+    let padded = payload.len().div_ceil(4) * 4;
+    let subpacket_len = 12 + padded;
+    let packet_len = 24 + subpacket_len;
+    let mut out = vec![0u8; std::cmp::max(TRANSFER_SIZE, (20 + packet_len).div_ceil(512) * 512)];
+    out[4..6].copy_from_slice(&comid.to_be_bytes());
+    out[16..20].copy_from_slice(&(packet_len as u32).to_be_bytes());
+    out[20..24].copy_from_slice(&tsn.to_be_bytes());
+    out[24..28].copy_from_slice(&hsn.to_be_bytes());
+    out[40..44].copy_from_slice(&(subpacket_len as u32).to_be_bytes());
+    out[52..56].copy_from_slice(&(payload.len() as u32).to_be_bytes());
+    out[56..56 + payload.len()].copy_from_slice(payload);
+    out
+}
+
+/// Token payload of a received ComPacket; `None` while the TPer is still working
+pub fn parse_compacket(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let field = |offset: usize| data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    let malformed = || SecureEraseError::WipeFailed("Malformed TCG ComPacket".to_string());
+    let outstanding = field(8).ok_or_else(malformed)?;
+    let length = field(16).ok_or_else(malformed)?;
+    if length == 0 {
+        return if outstanding > 0 { Ok(None) } else { Err(malformed()) };
+    }
+    let payload_len = field(52).ok_or_else(malformed)? as usize;
+    data.get(56..56 + payload_len).map(|payload| Some(payload.to_vec())).ok_or_else(malformed)
+}
+
+/// Readable name of a method status code (TCG Core 5.1.5)
+fn status_name(code: u64) -> String {
+    match code {
+        0x00 => "SUCCESS".to_string(),
+        0x01 => "NOT_AUTHORIZED".to_string(),
+        0x03 => "SP_BUSY".to_string(),
+        0x04 => "SP_FAILED".to_string(),
+        0x05 => "SP_DISABLED".to_string(),
+        0x06 => "SP_FROZEN".to_string(),
+        0x0c => "INVALID_PARAMETER".to_string(),
+        0x12 => "AUTHORITY_LOCKED_OUT".to_string(),
+        0x3f => "FAIL".to_string(),
+        code => format!("0x{:02x}", code),
+    }
+}
+
+/// Status code from the status list that follows End of Data
+fn method_status(tokens: &[Token]) -> Option<u64> {
+    let end = tokens.iter().position(|token| *token == Token::EndOfData)?;
+    match tokens.get(end + 1..end + 3)? {
+        [Token::StartList, Token::Uint(status)] => Some(*status),
+        _ => None,
+    }
+}
+
+/// An open session: TPer and host session numbers
+#[derive(Debug, Clone, Copy)]
+struct Session {
+    tsn: u32,
+    hsn: u32,
+}
+
+/// A self-encrypting drive addressed through TCG sessions
+pub struct OpalDevice<T: SecurityTransport> {
+    transport: T,
+    discovery: Discovery,
+    /// Every method call sent so far
+    pub exchanges: Vec<Exchange>,
+}
+
+impl<T: SecurityTransport> OpalDevice<T> {
+    /// Run Level 0 discovery; fails when the drive implements no TCG subsystem
+    pub fn new(mut transport: T) -> Result<Self> {
+        let discovery = discover(&mut transport)?;
+        if discovery.subsystem.is_none() {
+            return Err(SecureEraseError::SecureEraseNotSupported);
+        }
+        Ok(Self { transport, discovery, exchanges: Vec::new() })
+    }
+
+    pub fn discovery(&self) -> &Discovery {
+        &self.discovery
+    }
+
+    /// Cryptographically erase the drive; `credential` is the PSID or the admin password
+    pub fn crypto_erase(&mut self, method: SedErase, credential: &[u8]) -> Result<CryptoEraseRecord> {
+        let subsystem = self.discovery.subsystem.ok_or(SecureEraseError::SecureEraseNotSupported)?;
+        if !self.discovery.locking.media_encryption {
+            return Err(SecureEraseError::SecureEraseNotSupported);
+        }
+        let locking_before = self.discovery.locking;
+        let started_at = SystemTime::now();
+        match (method, subsystem) {
+            (SedErase::PsidRevert, _) => self.psid_revert(credential)?,
+            (_, SecuritySubsystem::Enterprise) => self.erase_band(credential)?,
+            (SedErase::RevertSp, _) => self.revert_locking_sp(credential)?,
+            (SedErase::GenKey, _) => self.gen_key(credential)?,
+        }
+        let locking_after = discover(&mut self.transport).ok().map(|discovery| discovery.locking);
+        Ok(CryptoEraseRecord {
+            method,
+            subsystem,
+            locking_before,
+            locking_after,
+            exchanges: self.exchanges.clone(),
+            started_at,
+            finished_at: SystemTime::now(),
+        })
+    }
+
+    /// Revert the TPer with the PSID; the Admin SP ends the session itself
+    pub fn psid_revert(&mut self, psid: &[u8]) -> Result<()> {
+        let session = self.start_session(ADMIN_SP, "AdminSP", Some((AUTHORITY_PSID, "PSID", psid)))?;
+        self.call(Some(session), ADMIN_SP, METHOD_REVERT, Vec::new(), "AdminSP.Revert")?;
+        info!("PSID revert completed");
+        Ok(())
+    }
+
+    /// RevertSP on the Locking SP as Admin1, discarding every range key
+    pub fn revert_locking_sp(&mut self, password: &[u8]) -> Result<()> {
+        let session = self.start_session(OPAL_LOCKING_SP, "LockingSP", Some((AUTHORITY_ADMIN1, "Admin1", password)))?;
+        self.call(Some(session), THIS_SP, METHOD_REVERT_SP, Vec::new(), "ThisSP.RevertSP")?;
+        info!("Locking SP reverted");
+        Ok(())
+    }
+
+    /// Replace the global range's media key as Admin1
+    pub fn gen_key(&mut self, password: &[u8]) -> Result<()> {
+        let session = self.start_session(OPAL_LOCKING_SP, "LockingSP", Some((AUTHORITY_ADMIN1, "Admin1", password)))?;
+        let result = self.active_key(session).and_then(|key| {
+            self.call(Some(session), key, METHOD_GEN_KEY, Vec::new(), "GlobalRange.ActiveKey.GenKey").map(|_| ())
+        });
+        self.end_session(session);
+        result?;
+        info!("Global range key regenerated");
+        Ok(())
+    }
+
+    /// Enterprise SSC: Erase on Band0 as EraseMaster
+    pub fn erase_band(&mut self, password: &[u8]) -> Result<()> {
+        let session = self.start_session(ENTERPRISE_LOCKING_SP, "LockingSP", Some((AUTHORITY_ERASE_MASTER, "EraseMaster", password)))?;
+        let result = self.call(Some(session), GLOBAL_RANGE, METHOD_ERASE, Vec::new(), "Band0.Erase");
+        self.end_session(session);
+        result?;
+        info!("Band0 erased");
+        Ok(())
+    }
+
+    fn active_key(&mut self, session: Session) -> Result<Uid> {
+        let cellblock = vec![
+            Token::StartList,
+            Token::StartName, Token::Uint(3), Token::Uint(ACTIVE_KEY_COLUMN), Token::EndName,
+            Token::StartName, Token::Uint(4), Token::Uint(ACTIVE_KEY_COLUMN), Token::EndName,
+            Token::EndList,
+        ];
+        let response = self.call(Some(session), GLOBAL_RANGE, METHOD_GET, cellblock, "GlobalRange.Get(ActiveKey)")?;
+        response.iter()
+            .find_map(|token| match token {
+                Token::Bytes(uid) if uid.len() == 8 => uid.as_slice().try_into().ok(),
+                _ => None,
+            })
+            .ok_or_else(|| SecureEraseError::WipeFailed("Global range has no active key".to_string()))
+    }
+
+    fn start_session(&mut self, sp: Uid, sp_name: &str, authority: Option<(Uid, &str, &[u8])>) -> Result<Session> {
+        let mut params = vec![Token::Uint(HOST_SESSION_ID as u64), Token::Bytes(sp.to_vec()), Token::Uint(1)];
+        let mut label = format!("StartSession({}", sp_name);
+        if let Some((uid, name, challenge)) = authority {
+            params.extend([
+                Token::StartName, Token::Uint(0), Token::Bytes(challenge.to_vec()), Token::EndName,
+                Token::StartName, Token::Uint(3), Token::Bytes(uid.to_vec()), Token::EndName,
+            ]);
+            label.push_str(&format!(", {}", name));
+        }
+        label.push(')');
+        let response = self.call(None, SMUID, METHOD_START_SESSION, params, &label)?;
+
+        // SyncSession carries the host and TPer session numbers
+        let sync = response.iter().position(|token| *token == Token::Bytes(METHOD_SYNC_SESSION.to_vec()));
+        match sync.and_then(|index| response.get(index + 1..index + 4)) {
+            Some([Token::StartList, Token::Uint(hsn), Token::Uint(tsn)]) => Ok(Session { tsn: *tsn as u32, hsn: *hsn as u32 }),
+            _ => Err(SecureEraseError::WipeFailed("StartSession response has no SyncSession".to_string())),
+        }
+    }
+
+    /// Close a session; failures are only logged since the drive times sessions out
+    fn end_session(&mut self, session: Session) {
+        let payload = encode_tokens(&[Token::EndOfSession]);
+        if let Err(e) = self.exchange(session.tsn, session.hsn, &payload) {
+            warn!("Could not close TCG session: {}", e);
+        }
+    }
+
+    fn call(&mut self, session: Option<Session>, invoking: Uid, method: Uid, params: Vec<Token>, label: &str) -> Result<Vec<Token>> {
+        let mut tokens = vec![Token::Call, Token::Bytes(invoking.to_vec()), Token::Bytes(method.to_vec()), Token::StartList];
+        tokens.extend(params);
+        tokens.extend([Token::EndList, Token::EndOfData, Token::StartList, Token::Uint(0), Token::Uint(0), Token::Uint(0), Token::EndList]);
+        let (tsn, hsn) = session.map(|s| (s.tsn, s.hsn)).unwrap_or((0, 0));
+
+        let result = self.exchange(tsn, hsn, &encode_tokens(&tokens)).and_then(|payload| decode_tokens(&payload));
+        let status = match &result {
+            Ok(response) => method_status(response).map(status_name).unwrap_or_else(|| "NO_STATUS".to_string()),
+            Err(e) => format!("transport error: {}", e),
+        };
+        self.exchanges.push(Exchange { at: SystemTime::now(), call: label.to_string(), status: status.clone() });
+        let response = result?;
+        if status != "SUCCESS" {
+            return Err(SecureEraseError::WipeFailed(format!("TCG {} failed: {}", label, status)));
+        }
+        Ok(response)
+    }
+
+    /// Send one ComPacket and wait for the TPer's answer
+    fn exchange(&mut self, tsn: u32, hsn: u32, payload: &[u8]) -> Result<Vec<u8>> {
+        let comid = self.discovery.base_comid;
+        self.transport.security_send(PROTOCOL_TCG, comid, &build_compacket(comid, tsn, hsn, payload))?;
+        for _ in 0..RECEIVE_RETRIES {
+            let mut response = vec![0u8; TRANSFER_SIZE];
+            self.transport.security_receive(PROTOCOL_TCG, comid, &mut response)?;
+            if let Some(payload) = parse_compacket(&response)? {
+                return Ok(payload);
+            }
+            std::thread::sleep(RECEIVE_RETRY_DELAY);
+        }
+        Err(SecureEraseError::WipeFailed("No response from the TPer".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Level 0 discovery response with a locking feature and one SSC feature
+    fn discovery_data(ssc: u16, locking: u8) -> Vec<u8> {
+        let mut data = vec![0u8; 512];
+        let features = [
+            vec![0x00, 0x02, 0x10, 0x0c, locking, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            [vec![(ssc >> 8) as u8, ssc as u8, 0x10, 0x10, 0x07, 0xfe, 0x00, 0x01], vec![0; 12]].concat(),
+        ].concat();
+        data[48..48 + features.len()].copy_from_slice(&features);
+        data[0..4].copy_from_slice(&((44 + features.len()) as u32).to_be_bytes());
+        data
+    }
+
+    /// TPer simulator that answers method calls by invoking and method UID
+    struct MockTper {
+        discovery: Vec<u8>,
+        password: Vec<u8>,
+        pending: Option<Vec<u8>>,
+        calls: Vec<(Vec<u8>, Vec<u8>)>,
+    }
+
+    impl MockTper {
+        fn new(ssc: u16) -> Self {
+            Self { discovery: discovery_data(ssc, 0x0b), password: b"label-psid".to_vec(), pending: None, calls: Vec::new() }
+        }
+
+        fn respond(&mut self, tokens: &[Token]) -> Vec<Token> {
+            let uid = |index: usize| match tokens.get(index) {
+                Some(Token::Bytes(bytes)) => bytes.clone(),
+                _ => Vec::new(),
+            };
+            let (invoking, method) = (uid(1), uid(2));
+            self.calls.push((invoking.clone(), method.clone()));
+            let ok = |results: Vec<Token>| {
+                let mut response = vec![Token::StartList];
+                response.extend(results);
+                response.extend([Token::EndList, Token::EndOfData, Token::StartList, Token::Uint(0), Token::Uint(0), Token::Uint(0), Token::EndList]);
+                response
+            };
+            if method == METHOD_START_SESSION {
+                let authorized = !tokens.contains(&Token::StartName)
+                    || tokens.contains(&Token::Bytes(self.password.clone()));
+                if !authorized {
+                    return vec![Token::EndOfData, Token::StartList, Token::Uint(0x01), Token::Uint(0), Token::Uint(0), Token::EndList];
+                }
+                return vec![
+                    Token::Call, Token::Bytes(SMUID.to_vec()), Token::Bytes(METHOD_SYNC_SESSION.to_vec()),
+                    Token::StartList, Token::Uint(HOST_SESSION_ID as u64), Token::Uint(0x1001), Token::EndList,
+                    Token::EndOfData, Token::StartList, Token::Uint(0), Token::Uint(0), Token::Uint(0), Token::EndList,
+                ];
+            }
+            if method == METHOD_GET {
+                return ok(vec![Token::StartList, Token::StartName, Token::Uint(ACTIVE_KEY_COLUMN),
+                    Token::Bytes(vec![0, 0, 0x08, 0x06, 0, 0, 0, 0x01]), Token::EndName, Token::EndList]);
+            }
+            ok(Vec::new())
+        }
+    }
+
+    impl SecurityTransport for MockTper {
+        fn security_send(&mut self, protocol: u8, comid: u16, data: &[u8]) -> Result<()> {
+            assert_eq!((protocol, comid), (PROTOCOL_TCG, 0x07fe));
+            let tokens = decode_tokens(&parse_compacket(data)?.unwrap())?;
+            if tokens == [Token::EndOfSession] {
+                self.pending = Some(encode_tokens(&tokens));
+            } else {
+                let response = self.respond(&tokens);
+                self.pending = Some(encode_tokens(&response));
+            }
+            Ok(())
+        }
+
+        fn security_receive(&mut self, _protocol: u8, comid: u16, data: &mut [u8]) -> Result<()> {
+            if comid == LEVEL0_DISCOVERY_COMID {
+                data[..self.discovery.len()].copy_from_slice(&self.discovery);
+                return Ok(());
+            }
+            let payload = self.pending.take().expect("receive without send");
+            let packet = build_compacket(comid, 0x1001, HOST_SESSION_ID, &payload);
+            data.copy_from_slice(&packet[..data.len()]);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parses_level0_discovery() {
+        let discovery = Discovery::parse(&discovery_data(0x0203, 0x0b)).unwrap();
+        assert_eq!(discovery.subsystem, Some(SecuritySubsystem::Opal2));
+        assert_eq!(discovery.base_comid, 0x07fe);
+        assert_eq!(discovery.locking, LockingState {
+            supported: true,
+            enabled: true,
+            locked: false,
+            media_encryption: true,
+            mbr_enabled: false,
+            mbr_done: false,
+        });
+        assert!(discovery.supports_crypto_erase());
+
+        let pyrite = Discovery::parse(&discovery_data(0x0303, 0x03)).unwrap();
+        assert_eq!(pyrite.subsystem, Some(SecuritySubsystem::Pyrite2));
+        assert!(!pyrite.supports_crypto_erase());
+    }
+
+    #[test]
+    fn test_token_round_trip() {
+        let tokens = vec![
+            Token::Call, Token::Bytes(SMUID.to_vec()), Token::StartList, Token::Uint(5), Token::Uint(0x1234_5678),
+            Token::StartName, Token::Uint(0), Token::Bytes(vec![0x41; 32]), Token::EndName, Token::EndList, Token::EndOfData,
+        ];
+        let encoded = encode_tokens(&tokens);
+        assert_eq!(&encoded[..10], &[0xf8, 0xa8, 0, 0, 0, 0, 0, 0, 0, 0xff]);
+        assert_eq!(&encoded[12..17], &[0x84, 0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(&encoded[19..21], &[0xd0, 32]);
+        assert_eq!(decode_tokens(&encoded).unwrap(), tokens);
+
+        let packet = build_compacket(0x07fe, 7, 9, &encoded);
+        assert_eq!(packet.len() % 512, 0);
+        assert_eq!(parse_compacket(&packet).unwrap(), Some(encoded));
+    }
+
+    #[test]
+    fn test_psid_revert_records_exchanges() {
+        let mut opal = OpalDevice::new(MockTper::new(0x0203)).unwrap();
+        let record = opal.crypto_erase(SedErase::PsidRevert, b"label-psid").unwrap();
+        assert_eq!(record.subsystem, SecuritySubsystem::Opal2);
+        let calls: Vec<(&str, &str)> = record.exchanges.iter().map(|e| (e.call.as_str(), e.status.as_str())).collect();
+        assert_eq!(calls, vec![("StartSession(AdminSP, PSID)", "SUCCESS"), ("AdminSP.Revert", "SUCCESS")]);
+        assert_eq!(opal.transport.calls[1], (ADMIN_SP.to_vec(), METHOD_REVERT.to_vec()));
+    }
+
+    #[test]
+    fn test_gen_key_targets_active_key_and_rejects_bad_password() {
+        let mut opal = OpalDevice::new(MockTper::new(0x0203)).unwrap();
+        opal.crypto_erase(SedErase::GenKey, b"label-psid").unwrap();
+        assert_eq!(opal.transport.calls[2], (vec![0, 0, 0x08, 0x06, 0, 0, 0, 0x01], METHOD_GEN_KEY.to_vec()));
+
+        let mut opal = OpalDevice::new(MockTper::new(0x0203)).unwrap();
+        let error = opal.crypto_erase(SedErase::RevertSp, b"wrong").unwrap_err();
+        assert!(error.to_string().contains("NOT_AUTHORIZED"));
+        assert_eq!(opal.exchanges[0].status, "NOT_AUTHORIZED");
+
+        let mut enterprise = OpalDevice::new(MockTper::new(0x0100)).unwrap();
+        enterprise.crypto_erase(SedErase::RevertSp, b"label-psid").unwrap();
+        assert_eq!(enterprise.transport.calls[1], (GLOBAL_RANGE.to_vec(), METHOD_ERASE.to_vec()));
+    }
+}
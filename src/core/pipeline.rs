@@ -0,0 +1,104 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, WipeResult};
+use crate::core::advanced::AdvancedWipeEngine;
+use crate::core::batch::DeviceSelector;
+use crate::core::checkpoint::Checkpoint;
+use crate::core::methods::WipeMethod;
+use crate::core::plan::WipePlan;
+use crate::core::probe::{self, DeviceProbe};
+use crate::core::safety::{self, Clearance, SafetyOptions, SystemState};
+use std::path::Path;
+
+/// The path every wipe takes, shared by the CLI and the GUI.
+///
+/// Devices come from a `DeviceProbe`, are checked against the running system,
+/// and are wiped by `AdvancedWipeEngine`. Swapping the probe and system state
+/// runs the same flow against fake or recorded devices.
+pub struct WipePipeline {
+    probe: Box<dyn DeviceProbe>,
+    system: SystemState,
+}
+
+impl WipePipeline {
+    pub fn new(probe: Box<dyn DeviceProbe>, system: SystemState) -> Self {
+        Self { probe, system }
+    }
+
+    /// Devices and mounts of the running system
+    pub fn system() -> Self {
+        Self::new(probe::system(), SystemState::probe())
+    }
+
+    pub fn devices(&self) -> Result<Vec<StorageDevice>> {
+        self.probe.devices()
+    }
+
+    /// Scan and save the devices for `probe::RecordedProbe`
+    pub fn record(&self, path: &Path) -> Result<Vec<StorageDevice>> {
+        probe::record(self.probe.as_ref(), path)
+    }
+
+    pub fn find(&self, path: &Path) -> Result<StorageDevice> {
+        self.devices()?.into_iter()
+            .find(|device| device.path == path)
+            .ok_or_else(|| SecureEraseError::DeviceNotFound(path.display().to_string()))
+    }
+
+    /// The devices at `paths` followed by every other device matching `selector`
+    pub fn select(&self, paths: &[impl AsRef<Path>], selector: Option<&str>) -> Result<Vec<StorageDevice>> {
+        let available = self.devices()?;
+        let mut devices: Vec<StorageDevice> = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            let device = available.iter().find(|device| device.path == path)
+                .ok_or_else(|| SecureEraseError::DeviceNotFound(path.display().to_string()))?;
+            if !devices.iter().any(|d| d.path == device.path) {
+                devices.push(device.clone());
+            }
+        }
+        if let Some(selector) = selector {
+            let filter = DeviceSelector::parse(selector)?;
+            for device in available {
+                if filter.matches(&device) && !devices.iter().any(|d| d.path == device.path) {
+                    devices.push(device);
+                }
+            }
+        }
+        if devices.is_empty() {
+            return Err(SecureEraseError::DeviceNotFound(
+                format!("no devices match '{}'", selector.unwrap_or_default())
+            ));
+        }
+        Ok(devices)
+    }
+
+    /// Refuse devices the running system depends on; see `safety::preflight`
+    pub fn preflight(&self, device: &StorageDevice, options: &SafetyOptions) -> Result<Clearance> {
+        safety::preflight(device, &self.system, options)
+    }
+
+    /// What `wipe` would do with `engine`, with the hazards the safety checks would raise
+    pub fn plan(&self, engine: &AdvancedWipeEngine, device: &StorageDevice, method: &WipeMethod) -> WipePlan {
+        let mut plan = engine.plan(device, method);
+        plan.hazards = safety::check(device, &self.system).hazards;
+        plan
+    }
+
+    /// Wipe a device that passed `preflight`, continuing from `resume` if given.
+    ///
+    /// The engine is dropped when the wipe ends, which closes its progress stream.
+    pub async fn wipe(
+        &self,
+        engine: AdvancedWipeEngine,
+        device: &StorageDevice,
+        method: &WipeMethod,
+        clearance: Clearance,
+        resume: Option<Checkpoint>,
+    ) -> Result<WipeResult> {
+        let engine = engine.with_safety_overrides(clearance.overrides);
+        match resume {
+            Some(checkpoint) => engine.resume_wipe(device, checkpoint).await,
+            None => engine.secure_erase_with_method(device, method).await,
+        }
+    }
+}
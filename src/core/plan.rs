@@ -0,0 +1,296 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, EraseMode, HiddenArea};
+use crate::core::hidden_areas::HiddenAreaRestore;
+use crate::core::methods::{HardwareCommand, WipeMethod, WipeStep};
+use crate::core::safety::Hazard;
+use crate::core::verification::VerificationMode;
+use crate::utils::Utils;
+use serde::{Deserialize, Serialize};
+
+/// How a dry-run plan is printed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PlanFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl PlanFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "text" => Some(PlanFormat::Text),
+            "json" => Some(PlanFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Everything a wipe would do to a device, resolved without touching it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WipePlan {
+    pub device: StorageDevice,
+    pub method: String,
+    pub description: String,
+    pub compliance_level: Option<String>,
+    /// Steps for this device type, after per-type overrides
+    pub steps: Vec<PlannedStep>,
+    /// Overwrite passes on the expected path, counting fallbacks that will run
+    pub passes: u32,
+    pub bytes_to_write: u64,
+    /// Sanitize and crypto erase commands sent to the drive
+    pub hardware_commands: Vec<String>,
+    pub hidden_areas: Vec<PlannedHiddenArea>,
+    pub estimated_seconds: u64,
+    /// Why the wipe would be refused or need an override
+    pub hazards: Vec<Hazard>,
+}
+
+/// One step of a plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedStep {
+    pub action: String,
+    /// Set when the device is known not to support the step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<PlannedStep>,
+}
+
+/// What the wipe does with a hidden area
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedHiddenArea {
+    pub area: HiddenArea,
+    /// `None` leaves the area in place and unwiped
+    pub restore: Option<HiddenAreaRestore>,
+}
+
+impl WipePlan {
+    /// Resolve `method` for `device`.
+    ///
+    /// `supports` tells whether the device can run a hardware command, so the
+    /// plan follows the fallbacks the real run would take.
+    pub fn build(
+        device: &StorageDevice,
+        method: &WipeMethod,
+        verification_mode: &VerificationMode,
+        supports: &dyn Fn(&HardwareCommand) -> bool,
+    ) -> Self {
+        let mut builder = Builder { device, verification_mode, supports, passes: 0, hardware_commands: Vec::new(), restore: None };
+        let steps = builder.steps(method.steps_for(&device.device_type), true);
+
+        let mode = EraseMode::from_method_name(&method.name);
+        let estimated_seconds = match mode {
+            // Named modes have their own estimate; other methods scale a single pass
+            EraseMode::Custom(_) => Utils::estimate_wipe_time(device.size, &EraseMode::Quick) * u64::from(builder.passes.max(1)),
+            _ => Utils::estimate_wipe_time(device.size, &mode),
+        };
+        let hidden_areas = device.hidden_areas.iter()
+            .map(|area| PlannedHiddenArea { area: area.clone(), restore: builder.restore })
+            .collect();
+
+        Self {
+            device: device.clone(),
+            method: method.name.clone(),
+            description: method.description.clone(),
+            compliance_level: method.compliance_level.clone(),
+            steps,
+            passes: builder.passes,
+            bytes_to_write: device.size * u64::from(builder.passes),
+            hardware_commands: builder.hardware_commands,
+            hidden_areas,
+            estimated_seconds,
+            hazards: Vec::new(),
+        }
+    }
+
+    /// Plan for operators to review
+    pub fn to_text(&self) -> String {
+        let device = &self.device;
+        let mut lines = vec![format!("Dry run: nothing will be written to {}", device.path.display())];
+        lines.push(format!(
+            "Device: {} ({:?}, {}{})", device.path.display(), device.device_type, Utils::format_bytes(device.size),
+            device.serial.as_ref().map(|serial| format!(", serial {}", serial)).unwrap_or_default()
+        ));
+        lines.push(format!("Method: {} - {}", self.method, self.description));
+        if let Some(compliance) = &self.compliance_level {
+            lines.push(format!("Compliance: {}", compliance));
+        }
+        lines.push("Steps:".to_string());
+        for (index, step) in self.steps.iter().enumerate() {
+            render_step(&mut lines, step, &format!("  {}. ", index + 1), 5);
+        }
+        lines.push(format!("Overwrite passes: {} ({} to write)", self.passes, Utils::format_bytes(self.bytes_to_write)));
+        lines.push(format!("Hardware commands: {}", if self.hardware_commands.is_empty() { "none".to_string() } else { self.hardware_commands.join(", ") }));
+        for planned in &self.hidden_areas {
+            let area = &planned.area;
+            let action = match planned.restore {
+                Some(HiddenAreaRestore::Temporary) => "removed until the next power cycle, then wiped",
+                Some(HiddenAreaRestore::Permanent) => "removed permanently, then wiped",
+                None => "left in place and NOT wiped",
+            };
+            lines.push(format!("{}: {} sectors from LBA {} {}", area.description, area.size, area.start_lba, action));
+        }
+        lines.push(format!("Estimated duration: {}", Utils::format_duration(self.estimated_seconds)));
+        if self.hazards.is_empty() {
+            lines.push("Safety checks: passed".to_string());
+        } else {
+            lines.push("Safety checks: the device".to_string());
+            for hazard in &self.hazards {
+                lines.push(format!("  - {}", hazard.describe()));
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn render(&self, format: PlanFormat) -> Result<String> {
+        match format {
+            PlanFormat::Text => Ok(self.to_text()),
+            PlanFormat::Json => serde_json::to_string_pretty(self).map_err(SecureEraseError::from),
+        }
+    }
+}
+
+fn render_step(lines: &mut Vec<String>, step: &PlannedStep, prefix: &str, indent: usize) {
+    let note = step.note.as_ref().map(|note| format!(" [{}]", note)).unwrap_or_default();
+    lines.push(format!("{}{}{}", prefix, step.action, note));
+    for fallback in &step.fallback {
+        render_step(lines, fallback, &format!("{}fallback: ", " ".repeat(indent)), indent + 2);
+    }
+}
+
+/// Walks the steps of a method, tracking what the expected path does
+struct Builder<'a> {
+    device: &'a StorageDevice,
+    verification_mode: &'a VerificationMode,
+    supports: &'a dyn Fn(&HardwareCommand) -> bool,
+    passes: u32,
+    hardware_commands: Vec<String>,
+    restore: Option<HiddenAreaRestore>,
+}
+
+impl Builder<'_> {
+    /// Plan `steps`; only steps on the expected path (`taken`) count towards the totals
+    fn steps(&mut self, steps: &[WipeStep], taken: bool) -> Vec<PlannedStep> {
+        steps.iter().flat_map(|step| self.step(step, taken)).collect()
+    }
+
+    fn step(&mut self, step: &WipeStep, taken: bool) -> Vec<PlannedStep> {
+        let planned = |action: String| PlannedStep { action, note: None, fallback: Vec::new() };
+        match step {
+            WipeStep::RestoreHiddenAreas { mode } => {
+                if taken {
+                    self.restore = Some(*mode);
+                }
+                vec![planned(format!("Remove hidden areas ({:?}) so later passes reach the native capacity", mode))]
+            }
+            WipeStep::Overwrite { pattern } => vec![self.pass(pattern.resolve().describe(), taken)],
+            WipeStep::Schedule { schedule } => schedule.passes().iter()
+                .map(|pattern| self.pass(pattern.describe(), taken))
+                .collect(),
+            WipeStep::HardwareCommand { command, fallback } => {
+                let supported = (self.supports)(command);
+                if taken && supported {
+                    self.hardware_commands.push(command.describe().to_string());
+                }
+                vec![PlannedStep {
+                    action: command.describe().to_string(),
+                    note: (!supported).then(|| "not supported by this device".to_string()),
+                    fallback: self.steps(fallback, taken && !supported),
+                }]
+            }
+            WipeStep::CryptoErase { method, fallback } => {
+                let action = format!("TCG cryptographic erase: {}", method.describe());
+                if taken {
+                    self.hardware_commands.push(action.clone());
+                }
+                // Whether the drive is an SED is only known after Level 0 discovery
+                vec![PlannedStep { action, note: None, fallback: self.steps(fallback, false) }]
+            }
+            WipeStep::Trim { fallback } => {
+                let supported = self.device.supports_trim;
+                vec![PlannedStep {
+                    action: "Discard every block (secure discard where supported)".to_string(),
+                    note: (!supported).then(|| "device does not accept discards".to_string()),
+                    fallback: self.steps(fallback, taken && !supported),
+                }]
+            }
+            WipeStep::Verify { mode } => {
+                let mode = mode.as_ref().unwrap_or(self.verification_mode);
+                vec![planned(format!("Verify: {}", mode.describe()))]
+            }
+        }
+    }
+
+    fn pass(&mut self, pattern: String, taken: bool) -> PlannedStep {
+        if taken {
+            self.passes += 1;
+        }
+        PlannedStep { action: format!("Overwrite with {}", pattern), note: None, fallback: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{DeviceType, HiddenAreaType};
+    use crate::core::methods::MethodRegistry;
+    use std::path::PathBuf;
+
+    fn device(device_type: DeviceType) -> StorageDevice {
+        StorageDevice {
+            path: PathBuf::from("/dev/sdb"),
+            name: "sdb".to_string(),
+            size: 100 * 1024 * 1024 * 1024,
+            device_type,
+            model: None,
+            serial: Some("S123".to_string()),
+            supports_secure_erase: false,
+            supports_trim: false,
+            hidden_areas: vec![HiddenArea {
+                area_type: HiddenAreaType::HPA,
+                start_lba: 1000,
+                size: 24,
+                description: "Host Protected Area".to_string(),
+            }],
+            attributes: Default::default(),
+            layout: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_plan_counts_passes_of_schedules() {
+        let registry = MethodRegistry::builtin();
+        let method = registry.get("dod-7").unwrap();
+        let plan = WipePlan::build(&device(DeviceType::HDD), method, &VerificationMode::default(), &|_| true);
+        assert_eq!(plan.passes, 7);
+        assert_eq!(plan.bytes_to_write, 7 * 100 * 1024 * 1024 * 1024);
+        assert_eq!(plan.estimated_seconds, 7 * Utils::estimate_wipe_time(plan.device.size, &EraseMode::Quick));
+        assert!(plan.hardware_commands.is_empty());
+        assert!(plan.hidden_areas[0].restore.is_none());
+
+        let quick = registry.get("quick").unwrap().with_hidden_area_restore(HiddenAreaRestore::Permanent);
+        let plan = WipePlan::build(&device(DeviceType::HDD), &quick, &VerificationMode::default(), &|_| true);
+        assert_eq!(plan.estimated_seconds, Utils::estimate_wipe_time(plan.device.size, &EraseMode::Quick));
+        assert_eq!(plan.hidden_areas[0].restore, Some(HiddenAreaRestore::Permanent));
+        assert!(plan.to_text().contains("removed permanently, then wiped"));
+    }
+
+    #[test]
+    fn test_unsupported_hardware_command_follows_fallback() {
+        let method = MethodRegistry::builtin().get("advanced").unwrap().clone();
+        let sas = device(DeviceType::SAS);
+
+        let plan = WipePlan::build(&sas, &method, &VerificationMode::default(), &|_| true);
+        assert_eq!(plan.passes, 0);
+        assert_eq!(plan.hardware_commands.len(), 1);
+
+        let plan = WipePlan::build(&sas, &method, &VerificationMode::default(), &|_| false);
+        assert!(plan.hardware_commands.is_empty());
+        assert!(plan.passes > 0);
+        assert_eq!(plan.steps[0].note.as_deref(), Some("not supported by this device"));
+
+        let json: serde_json::Value = serde_json::from_str(&plan.render(PlanFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["method"], "advanced");
+        assert_eq!(json["passes"], plan.passes);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::error::Result;
+use crate::core::StorageDevice;
+use std::path::{Path, PathBuf};
+
+/// Source of the devices the tool may wipe.
+///
+/// Each platform module provides the probe of the running system; tests and
+/// offline runs use `FakeProbe` or `RecordedProbe` instead.
+pub trait DeviceProbe: Send + Sync {
+    /// Every wipeable disk, with secure erase support and hidden areas filled in
+    fn devices(&self) -> Result<Vec<StorageDevice>>;
+}
+
+/// Probe of the running system
+pub fn system() -> Box<dyn DeviceProbe> {
+    #[cfg(target_os = "windows")]
+    return Box::new(crate::platform::windows::WindowsProbe);
+
+    #[cfg(target_os = "linux")]
+    return Box::new(crate::platform::linux::LinuxProbe::system());
+
+    #[cfg(target_os = "android")]
+    return Box::new(crate::platform::android::AndroidProbe);
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "android")))]
+    Box::new(Unsupported)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "android")))]
+struct Unsupported;
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "android")))]
+impl DeviceProbe for Unsupported {
+    fn devices(&self) -> Result<Vec<StorageDevice>> {
+        Err(crate::error::SecureEraseError::UnsupportedPlatform)
+    }
+}
+
+/// Fixed list of devices
+#[derive(Debug, Clone, Default)]
+pub struct FakeProbe {
+    devices: Vec<StorageDevice>,
+}
+
+impl FakeProbe {
+    pub fn new(devices: Vec<StorageDevice>) -> Self {
+        Self { devices }
+    }
+}
+
+impl DeviceProbe for FakeProbe {
+    fn devices(&self) -> Result<Vec<StorageDevice>> {
+        Ok(self.devices.clone())
+    }
+}
+
+/// Devices saved by `record`, read back on every scan
+#[derive(Debug, Clone)]
+pub struct RecordedProbe {
+    path: PathBuf,
+}
+
+impl RecordedProbe {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl DeviceProbe for RecordedProbe {
+    fn devices(&self) -> Result<Vec<StorageDevice>> {
+        let contents = std::fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// Scan with `probe` and save the devices for a `RecordedProbe`
+pub fn record(probe: &dyn DeviceProbe, path: &Path) -> Result<Vec<StorageDevice>> {
+    let devices = probe.devices()?;
+    std::fs::write(path, serde_json::to_string_pretty(&devices)?)?;
+    Ok(devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DeviceType;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_recorded_probe_replays_recording() {
+        let device = StorageDevice {
+            path: PathBuf::from("/dev/sdc"),
+            name: "sdc".to_string(),
+            size: 500 * 1024 * 1024 * 1024,
+            device_type: DeviceType::SSD,
+            model: Some("Recorded SSD".to_string()),
+            serial: Some("REC-42".to_string()),
+            supports_secure_erase: true,
+            supports_trim: true,
+            hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: Default::default(),
+        };
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("devices.json");
+
+        let recorded = record(&FakeProbe::new(vec![device.clone()]), &path).unwrap();
+        assert_eq!(recorded.len(), 1);
+
+        let replayed = RecordedProbe::new(&path).devices().unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].path, device.path);
+        assert_eq!(replayed[0].serial, device.serial);
+        assert_eq!(replayed[0].device_type, DeviceType::SSD);
+
+        assert!(RecordedProbe::new(temp_dir.path().join("missing.json")).devices().is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Minimum interval between two progress events of the same phase
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Stage of a wipe operation a progress event belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WipePhase {
+    Overwrite,
+    Verify,
+    HardwareCommand,
+}
+
+/// A single progress update from the wipe engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    pub device: PathBuf,
+    pub phase: WipePhase,
+    /// Current overwrite pass (1-based); 0 outside overwrite passes
+    pub pass: u32,
+    pub total_passes: u32,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub throughput_bytes_per_sec: f64,
+    /// Estimated seconds until the whole operation finishes
+    pub eta_seconds: Option<u64>,
+    /// Seconds since the current phase started
+    pub elapsed_seconds: u64,
+    /// Short description, e.g. the pattern or command being run
+    pub detail: String,
+}
+
+impl ProgressEvent {
+    /// Completion of the current phase in percent
+    pub fn percent(&self) -> f64 {
+        if self.bytes_total == 0 {
+            return 0.0;
+        }
+        self.bytes_done as f64 / self.bytes_total as f64 * 100.0
+    }
+}
+
+/// Receiving end of a progress stream
+pub type ProgressReceiver = mpsc::UnboundedReceiver<ProgressEvent>;
+
+/// Sending end of a progress stream, handed to the wipe engine
+#[derive(Debug, Clone)]
+pub struct ProgressReporter {
+    sender: mpsc::UnboundedSender<ProgressEvent>,
+}
+
+impl ProgressReporter {
+    /// Send an event; a dropped receiver is not an error for the wipe
+    pub fn report(&self, event: ProgressEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Create a connected reporter/receiver pair
+pub fn progress_channel() -> (ProgressReporter, ProgressReceiver) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (ProgressReporter { sender }, receiver)
+}
+
+/// Turns byte counts from one phase into throttled progress events
+pub struct ProgressTracker {
+    reporter: Option<ProgressReporter>,
+    device: PathBuf,
+    phase: WipePhase,
+    pass: u32,
+    total_passes: u32,
+    bytes_total: u64,
+    /// Bytes still to be processed by later phases, used for the ETA
+    bytes_after: u64,
+    /// Bytes already done before this tracker started (resumed passes)
+    baseline: u64,
+    detail: String,
+    started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl ProgressTracker {
+    pub fn new(
+        reporter: Option<ProgressReporter>,
+        device: PathBuf,
+        phase: WipePhase,
+        bytes_total: u64,
+        detail: String,
+    ) -> Self {
+        Self {
+            reporter,
+            device,
+            phase,
+            pass: 0,
+            total_passes: 0,
+            bytes_total,
+            bytes_after: 0,
+            baseline: 0,
+            detail,
+            started: Instant::now(),
+            last_emit: None,
+        }
+    }
+
+    /// Mark this phase as overwrite pass `pass` of `total_passes`
+    pub fn with_pass(mut self, pass: u32, total_passes: u32) -> Self {
+        self.pass = pass;
+        self.total_passes = total_passes;
+        self.bytes_after = (total_passes.saturating_sub(pass)) as u64 * self.bytes_total;
+        self
+    }
+
+    /// Start counting throughput from `offset` when continuing an interrupted pass
+    pub fn resumed_from(mut self, offset: u64) -> Self {
+        self.baseline = offset;
+        self
+    }
+
+    /// Record progress; events are rate limited except for the final one
+    pub fn update(&mut self, bytes_done: u64) {
+        let now = Instant::now();
+        let finished = bytes_done >= self.bytes_total;
+        if !finished {
+            if let Some(last) = self.last_emit {
+                if now.duration_since(last) < EMIT_INTERVAL {
+                    return;
+                }
+            }
+        }
+        self.last_emit = Some(now);
+        self.emit(bytes_done, now);
+    }
+
+    /// Emit an event unconditionally, e.g. at the start of a hardware command
+    pub fn emit_now(&mut self, bytes_done: u64) {
+        let now = Instant::now();
+        self.last_emit = Some(now);
+        self.emit(bytes_done, now);
+    }
+
+    fn emit(&self, bytes_done: u64, now: Instant) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let processed = bytes_done.saturating_sub(self.baseline);
+        let throughput = if elapsed > 0.0 { processed as f64 / elapsed } else { 0.0 };
+        let remaining = self.bytes_total.saturating_sub(bytes_done) + self.bytes_after;
+        let eta_seconds = if remaining == 0 {
+            Some(0)
+        } else if throughput > 0.0 {
+            Some((remaining as f64 / throughput).ceil() as u64)
+        } else {
+            None
+        };
+
+        reporter.report(ProgressEvent {
+            device: self.device.clone(),
+            phase: self.phase,
+            pass: self.pass,
+            total_passes: self.total_passes,
+            bytes_done,
+            bytes_total: self.bytes_total,
+            throughput_bytes_per_sec: throughput,
+            eta_seconds,
+            elapsed_seconds: elapsed as u64,
+            detail: self.detail.clone(),
+        });
+    }
+}
@@ -0,0 +1,442 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, HolderKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+use log::warn;
+
+/// Mount points that make a disk the one the running system lives on
+const SYSTEM_MOUNTPOINTS: &[&str] = &["/", "/boot", "/boot/efi", "/efi", "/usr", "/var"];
+
+/// Why wiping a device would break something that is running
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Hazard {
+    SystemDisk { mountpoint: PathBuf },
+    SystemVolume { role: String },
+    Mounted { mountpoint: PathBuf },
+    Swap { device: PathBuf },
+    RaidMember { array: PathBuf },
+    LvmMember { volume: String },
+    DeviceMapper { name: String },
+    ZfsMember { pool: String },
+}
+
+impl Hazard {
+    /// The system disk and active swap can never be wiped from the running system
+    pub fn overridable(&self) -> bool {
+        !matches!(self, Hazard::SystemDisk { .. } | Hazard::SystemVolume { .. } | Hazard::Swap { .. })
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Hazard::SystemDisk { mountpoint } => format!("holds the system filesystem mounted at {}", mountpoint.display()),
+            Hazard::SystemVolume { role } => format!("holds the {} volume of the running system", role),
+            Hazard::Mounted { mountpoint } => format!("has a filesystem mounted at {}", mountpoint.display()),
+            Hazard::Swap { device } => format!("is in use as swap ({})", device.display()),
+            Hazard::RaidMember { array } => format!("is a member of the active RAID array {}", array.display()),
+            Hazard::LvmMember { volume } => format!("backs the active LVM volume {}", volume),
+            Hazard::DeviceMapper { name } => format!("is in use by the device-mapper device {}", name),
+            Hazard::ZfsMember { pool } => format!("is a member of the ZFS pool {}", pool),
+        }
+    }
+}
+
+/// Swap and ZFS state of the running system; mounts and holders come with the device
+#[derive(Debug, Clone, Default)]
+pub struct SystemState {
+    pub swaps: Vec<PathBuf>,
+    pub zfs_members: Vec<ZfsMember>,
+}
+
+/// A device of an imported ZFS pool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZfsMember {
+    pub pool: String,
+    pub device: PathBuf,
+}
+
+impl SystemState {
+    /// Read /proc/swaps and ask `zpool` for the devices of imported pools
+    pub fn probe() -> Self {
+        let swaps = std::fs::read_to_string("/proc/swaps")
+            .map(|contents| parse_swaps(&contents))
+            .unwrap_or_default();
+        // Without zpool there are no imported pools to worry about
+        let zfs_members = Command::new("zpool")
+            .args(["status", "-P", "-L"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| parse_zpool_status(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default();
+        Self { swaps, zfs_members }
+    }
+}
+
+/// Active swap areas from /proc/swaps
+pub fn parse_swaps(contents: &str) -> Vec<PathBuf> {
+    contents.lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| PathBuf::from(crate::core::sysfs::unescape(name)))
+        .collect()
+}
+
+/// Pool member devices from `zpool status -P -L`
+pub fn parse_zpool_status(output: &str) -> Vec<ZfsMember> {
+    let mut members = Vec::new();
+    let mut pool = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("pool:") {
+            pool = Some(name.trim().to_string());
+        } else if let (Some(pool), Some(device)) = (&pool, line.split_whitespace().next()) {
+            if device.starts_with("/dev/") {
+                members.push(ZfsMember { pool: pool.clone(), device: PathBuf::from(device) });
+            }
+        }
+    }
+    members
+}
+
+/// Result of the pre-flight checks of one device
+#[derive(Debug, Clone)]
+pub struct SafetyReport {
+    pub device: PathBuf,
+    pub hazards: Vec<Hazard>,
+}
+
+impl SafetyReport {
+    pub fn is_safe(&self) -> bool {
+        self.hazards.is_empty()
+    }
+
+    /// Fail unless the device is unused, or every hazard may be and is overridden.
+    ///
+    /// Returns the overrides to record in the audit trail.
+    pub fn enforce(&self, allow_in_use: bool) -> Result<Vec<SafetyOverride>> {
+        let blocking: Vec<&Hazard> = self.hazards.iter()
+            .filter(|hazard| !(allow_in_use && hazard.overridable()))
+            .collect();
+        if !blocking.is_empty() {
+            let reasons: Vec<String> = blocking.iter().map(|hazard| hazard.describe()).collect();
+            let hint = if blocking.iter().all(|hazard| hazard.overridable()) { " (use --allow-in-use to wipe it anyway)" } else { "" };
+            return Err(SecureEraseError::UnsafeTarget(format!(
+                "{} {}{}", self.device.display(), reasons.join("; "), hint
+            )));
+        }
+        Ok(self.hazards.iter()
+            .map(|hazard| SafetyOverride::new(Some(hazard.clone()), format!("Wiped although it {}", hazard.describe())))
+            .collect())
+    }
+}
+
+/// Find everything that uses a device or one of its partitions
+pub fn check(device: &StorageDevice, system: &SystemState) -> SafetyReport {
+    let layout = &device.layout;
+    let mut hazards = Vec::new();
+
+    for mountpoint in layout.all_mountpoints() {
+        if SYSTEM_MOUNTPOINTS.iter().any(|system| Path::new(system) == mountpoint) {
+            hazards.push(Hazard::SystemDisk { mountpoint: mountpoint.clone() });
+        } else {
+            hazards.push(Hazard::Mounted { mountpoint: mountpoint.clone() });
+        }
+    }
+
+    for role in &layout.system_volumes {
+        hazards.push(Hazard::SystemVolume { role: role.clone() });
+    }
+
+    // Holders built directly on the disk or a partition; holders of holders go with them
+    for holder in layout.holders.iter().chain(layout.partitions.iter().flat_map(|partition| &partition.holders)) {
+        let name = holder.dm_name.clone().unwrap_or_else(|| holder.name.clone());
+        hazards.push(match holder.kind {
+            HolderKind::Raid => Hazard::RaidMember { array: holder.path.clone() },
+            HolderKind::Lvm => Hazard::LvmMember { volume: name },
+            HolderKind::Crypt | HolderKind::DeviceMapper => Hazard::DeviceMapper { name },
+        });
+    }
+
+    // Every node that reaches the device's blocks
+    let mut nodes = vec![device.path.clone()];
+    nodes.extend(layout.partitions.iter().map(|partition| partition.path.clone()));
+    for holder in layout.all_holders() {
+        nodes.push(holder.path.clone());
+        if let Some(dm_name) = &holder.dm_name {
+            nodes.push(PathBuf::from("/dev/mapper").join(dm_name));
+        }
+    }
+    for swap in &system.swaps {
+        if nodes.contains(swap) {
+            hazards.push(Hazard::Swap { device: swap.clone() });
+        }
+    }
+    for member in &system.zfs_members {
+        let pool = Hazard::ZfsMember { pool: member.pool.clone() };
+        if nodes.contains(&member.device) && !hazards.contains(&pool) {
+            hazards.push(pool);
+        }
+    }
+
+    SafetyReport { device: device.path.clone(), hazards }
+}
+
+/// A safety check that was bypassed, kept for the audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyOverride {
+    /// The hazard that was overridden; none when only the typed confirmation was skipped
+    pub hazard: Option<Hazard>,
+    pub description: String,
+    /// Login name of whoever ran the wipe
+    pub operator: Option<String>,
+    pub at: SystemTime,
+}
+
+impl SafetyOverride {
+    pub fn new(hazard: Option<Hazard>, description: String) -> Self {
+        let operator = ["SUDO_USER", "USER", "USERNAME"].iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()));
+        Self { hazard, description, operator, at: SystemTime::now() }
+    }
+}
+
+/// Devices that may be wiped without typed confirmation, one serial number,
+/// WWN or device path per line; `#` starts a comment
+#[derive(Debug, Clone, Default)]
+pub struct AllowList {
+    entries: Vec<String>,
+}
+
+impl AllowList {
+    pub fn parse(contents: &str) -> Self {
+        let entries = contents.lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| entry.to_string())
+            .collect();
+        Self { entries }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn permits(&self, device: &StorageDevice) -> bool {
+        let path = device.path.to_string_lossy();
+        self.entries.iter().any(|entry| {
+            *entry == path
+                || device.serial.as_deref().is_some_and(|serial| serial.trim().eq_ignore_ascii_case(entry))
+                || device.attributes.wwn.as_deref().is_some_and(|wwn| wwn.eq_ignore_ascii_case(entry))
+        })
+    }
+}
+
+/// How the operator agreed to a wipe
+#[derive(Debug, Clone, Default)]
+pub struct SafetyOptions {
+    /// Skip typed confirmation for devices on the allow-list
+    pub assume_yes: bool,
+    pub allow_list: Option<AllowList>,
+    /// Override hazards other than the system disk and active swap
+    pub allow_in_use: bool,
+}
+
+/// Outcome of the pre-flight checks of a device cleared for wiping
+#[derive(Debug, Clone)]
+pub struct Clearance {
+    pub overrides: Vec<SafetyOverride>,
+    /// Text the operator has to type before the wipe starts, if any
+    pub confirm_with: Option<String>,
+}
+
+/// Text typed to confirm a wipe: the serial number, or the device path when there is none
+pub fn confirmation_text(device: &StorageDevice) -> String {
+    device.serial.as_deref()
+        .map(str::trim)
+        .filter(|serial| !serial.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| device.path.to_string_lossy().to_string())
+}
+
+/// Check that what the operator typed matches the expected confirmation
+pub fn confirm(expected: &str, typed: &str) -> Result<()> {
+    if typed.trim() != expected {
+        return Err(SecureEraseError::UnsafeTarget(format!(
+            "confirmation '{}' does not match '{}'", typed.trim(), expected
+        )));
+    }
+    Ok(())
+}
+
+/// Run every pre-flight check of a device before wiping it
+pub fn preflight(device: &StorageDevice, system: &SystemState, options: &SafetyOptions) -> Result<Clearance> {
+    let mut overrides = check(device, system).enforce(options.allow_in_use)?;
+    for safety_override in &overrides {
+        warn!("{}: {}", device.path.display(), safety_override.description);
+    }
+
+    if !options.assume_yes {
+        return Ok(Clearance { overrides, confirm_with: Some(confirmation_text(device)) });
+    }
+    match &options.allow_list {
+        Some(allow_list) if allow_list.permits(device) => {
+            overrides.push(SafetyOverride::new(None, "Typed confirmation skipped: --yes with the device on the allow-list".to_string()));
+            Ok(Clearance { overrides, confirm_with: None })
+        }
+        Some(_) => Err(SecureEraseError::UnsafeTarget(format!(
+            "{} ({}) is not on the allow-list", device.path.display(), confirmation_text(device)
+        ))),
+        None => Err(SecureEraseError::UnsafeTarget("--yes needs an --allow-list naming the devices to wipe".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{DeviceType, DeviceLayout, Partition, Holder};
+
+    fn device(layout: DeviceLayout) -> StorageDevice {
+        StorageDevice {
+            path: PathBuf::from("/dev/sdb"),
+            name: "sdb".to_string(),
+            size: 1 << 40,
+            device_type: DeviceType::HDD,
+            model: None,
+            serial: Some("WD-WMC300123456".to_string()),
+            supports_secure_erase: false,
+            supports_trim: false,
+            hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout,
+        }
+    }
+
+    fn partition(number: u32, mountpoints: &[&str], holders: Vec<Holder>) -> Partition {
+        Partition {
+            name: format!("sdb{}", number),
+            path: PathBuf::from(format!("/dev/sdb{}", number)),
+            number,
+            start_sector: 2048,
+            size: 1 << 30,
+            mountpoints: mountpoints.iter().map(PathBuf::from).collect(),
+            holders,
+        }
+    }
+
+    fn holder(name: &str, kind: HolderKind, dm_name: Option<&str>, mountpoints: &[&str]) -> Holder {
+        Holder {
+            name: name.to_string(),
+            path: PathBuf::from("/dev").join(name),
+            kind,
+            dm_name: dm_name.map(str::to_string),
+            mountpoints: mountpoints.iter().map(PathBuf::from).collect(),
+            holders: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_finds_system_disk_mounts_and_swap() {
+        let disk = device(DeviceLayout {
+            mountpoints: Vec::new(),
+            partitions: vec![
+                partition(1, &["/boot/efi"], Vec::new()),
+                partition(2, &[], vec![holder("dm-0", HolderKind::Lvm, Some("vg0-root"), &["/"])]),
+                partition(3, &["/srv/data"], Vec::new()),
+            ],
+            holders: Vec::new(),
+            system_volumes: Vec::new(),
+        });
+        let system = SystemState { swaps: vec![PathBuf::from("/dev/mapper/vg0-root")], zfs_members: Vec::new() };
+        let report = check(&disk, &system);
+        assert_eq!(report.hazards, vec![
+            Hazard::SystemDisk { mountpoint: PathBuf::from("/boot/efi") },
+            Hazard::SystemDisk { mountpoint: PathBuf::from("/") },
+            Hazard::Mounted { mountpoint: PathBuf::from("/srv/data") },
+            Hazard::LvmMember { volume: "vg0-root".to_string() },
+            Hazard::Swap { device: PathBuf::from("/dev/mapper/vg0-root") },
+        ]);
+        // The system disk cannot be overridden
+        assert!(matches!(report.enforce(true), Err(SecureEraseError::UnsafeTarget(_))));
+    }
+
+    #[test]
+    fn test_windows_boot_and_system_disks_are_refused() {
+        let disk = device(DeviceLayout { system_volumes: vec!["boot".to_string(), "system".to_string()], ..Default::default() });
+        let report = check(&disk, &SystemState::default());
+        assert_eq!(report.hazards, vec![
+            Hazard::SystemVolume { role: "boot".to_string() },
+            Hazard::SystemVolume { role: "system".to_string() },
+        ]);
+        assert!(matches!(report.enforce(true), Err(SecureEraseError::UnsafeTarget(_))));
+    }
+
+    #[test]
+    fn test_in_use_devices_need_an_override() {
+        let disk = device(DeviceLayout {
+            mountpoints: Vec::new(),
+            partitions: vec![partition(1, &[], vec![holder("md127", HolderKind::Raid, None, &[])])],
+            holders: Vec::new(),
+            system_volumes: Vec::new(),
+        });
+        let system = SystemState {
+            swaps: Vec::new(),
+            zfs_members: vec![ZfsMember { pool: "tank".to_string(), device: PathBuf::from("/dev/sdb") }],
+        };
+        let report = check(&disk, &system);
+        assert_eq!(report.hazards, vec![
+            Hazard::RaidMember { array: PathBuf::from("/dev/md127") },
+            Hazard::ZfsMember { pool: "tank".to_string() },
+        ]);
+        assert!(report.enforce(false).is_err());
+        let overrides = report.enforce(true).unwrap();
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[1].hazard, Some(Hazard::ZfsMember { pool: "tank".to_string() }));
+
+        assert_eq!(check(&device(DeviceLayout::default()), &system).hazards.len(), 1);
+        assert!(check(&device(DeviceLayout::default()), &SystemState::default()).is_safe());
+    }
+
+    #[test]
+    fn test_parses_swaps_and_zpool_status() {
+        let swaps = "Filename\t\t\t\tType\t\tSize\t\tUsed\t\tPriority\n\
+                     /dev/dm-1                               partition\t8388604\t\t0\t\t-2\n\
+                     /swap\\040file                           file\t\t2097148\t\t0\t\t-3\n";
+        assert_eq!(parse_swaps(swaps), vec![PathBuf::from("/dev/dm-1"), PathBuf::from("/swap file")]);
+
+        let status = "  pool: tank\n state: ONLINE\nconfig:\n\n\tNAME           STATE     READ WRITE CKSUM\n\
+                      \ttank           ONLINE       0     0     0\n\t  mirror-0     ONLINE       0     0     0\n\
+                      \t    /dev/sdb1  ONLINE       0     0     0\n\t    /dev/sdc1  ONLINE       0     0     0\n\n\
+                      errors: No known data errors\n";
+        assert_eq!(parse_zpool_status(status), vec![
+            ZfsMember { pool: "tank".to_string(), device: PathBuf::from("/dev/sdb1") },
+            ZfsMember { pool: "tank".to_string(), device: PathBuf::from("/dev/sdc1") },
+        ]);
+    }
+
+    #[test]
+    fn test_confirmation_and_allow_list() {
+        let disk = device(DeviceLayout::default());
+        let system = SystemState::default();
+
+        let clearance = preflight(&disk, &system, &SafetyOptions::default()).unwrap();
+        assert_eq!(clearance.confirm_with.as_deref(), Some("WD-WMC300123456"));
+        assert!(clearance.overrides.is_empty());
+        assert!(confirm("WD-WMC300123456", "WD-WMC300123456\n").is_ok());
+        assert!(confirm("WD-WMC300123456", "WD-WMC300").is_err());
+
+        let yes = |allow_list: Option<&str>| SafetyOptions {
+            assume_yes: true,
+            allow_list: allow_list.map(AllowList::parse),
+            allow_in_use: false,
+        };
+        assert!(preflight(&disk, &system, &yes(None)).is_err());
+        assert!(preflight(&disk, &system, &yes(Some("/dev/sdc\nS123 # spare\n"))).is_err());
+        let clearance = preflight(&disk, &system, &yes(Some("# lab disks\nwd-wmc300123456\n"))).unwrap();
+        assert!(clearance.confirm_with.is_none());
+        assert_eq!(clearance.overrides.len(), 1);
+        assert!(clearance.overrides[0].hazard.is_none());
+    }
+}
@@ -0,0 +1,440 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::sg_io::{DataTransfer, ScsiStatus, ScsiTransport};
+use std::time::{Duration, Instant};
+use log::{info, warn};
+
+const REQUEST_SENSE: u8 = 0x03;
+const FORMAT_UNIT: u8 = 0x04;
+const SANITIZE: u8 = 0x48;
+const SECURITY_PROTOCOL_IN: u8 = 0xa2;
+const MAINTENANCE_IN: u8 = 0xa3;
+const SECURITY_PROTOCOL_OUT: u8 = 0xb5;
+
+/// MAINTENANCE IN service action for REPORT SUPPORTED OPERATION CODES
+const REPORT_SUPPORTED_OPCODES: u8 = 0x0c;
+
+const SENSE_KEY_NO_SENSE: u8 = 0x0;
+const SENSE_KEY_NOT_READY: u8 = 0x2;
+const SENSE_KEY_MEDIUM_ERROR: u8 = 0x3;
+const SENSE_KEY_ILLEGAL_REQUEST: u8 = 0x5;
+const SENSE_KEY_DATA_PROTECT: u8 = 0x7;
+
+/// ASC 04h "logical unit not ready" with ASCQ 04h (format) or 1Bh (sanitize) in progress
+const ASC_NOT_READY: u8 = 0x04;
+const ASCQ_FORMAT_IN_PROGRESS: u8 = 0x04;
+const ASCQ_SANITIZE_IN_PROGRESS: u8 = 0x1b;
+/// ASC 31h "medium format corrupted", reported after a failed format or sanitize
+const ASC_MEDIUM_FORMAT_CORRUPTED: u8 = 0x31;
+const ASC_INVALID_OPCODE: u8 = 0x20;
+const ASC_INVALID_FIELD_IN_CDB: u8 = 0x24;
+
+/// Timeout for everything except the erase itself; erases run with IMMED set
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Erase deadline used when the drive does not report a command timeout
+const UNREPORTED_ERASE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+const SENSE_DATA_LEN: usize = 252;
+
+/// SANITIZE service actions (SBC-4)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SanitizeAction {
+    Overwrite = 0x01,
+    BlockErase = 0x02,
+    CryptoErase = 0x03,
+}
+
+/// Whether a device implements a command, from REPORT SUPPORTED OPERATION CODES
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SupportedCommand {
+    pub supported: bool,
+    /// Recommended command timeout from the command timeouts descriptor
+    pub recommended_timeout: Option<Duration>,
+}
+
+impl SupportedCommand {
+    /// Parse the one-command format returned with RCTD set
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 4 {
+            return Err(SecureEraseError::WipeFailed("REPORT SUPPORTED OPERATION CODES data too short".to_string()));
+        }
+        // SUPPORT 011b: supported as in the standard, 101b: supported in a vendor specific manner
+        let supported = matches!(data[1] & 0x07, 0x3 | 0x5);
+        let cdb_size = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let descriptor = 4 + cdb_size;
+        let recommended_timeout = if data[1] & 0x80 != 0 && data.len() >= descriptor + 12 {
+            let seconds = u32::from_be_bytes([
+                data[descriptor + 8], data[descriptor + 9], data[descriptor + 10], data[descriptor + 11],
+            ]);
+            Some(Duration::from_secs(seconds as u64)).filter(|timeout| !timeout.is_zero())
+        } else {
+            None
+        };
+        Ok(Self { supported, recommended_timeout })
+    }
+}
+
+/// Erase commands a SCSI device reports
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScsiCapabilities {
+    /// The device answered REPORT SUPPORTED OPERATION CODES; otherwise nothing is known
+    pub reported: bool,
+    pub sanitize_overwrite: bool,
+    pub sanitize_block_erase: bool,
+    pub sanitize_crypto_erase: bool,
+    pub format_unit: bool,
+}
+
+/// SCSI block device (SAS, or SATA behind a SAT layer) reached through SG_IO
+pub struct ScsiDevice<T: ScsiTransport> {
+    transport: T,
+    /// Delay between REQUEST SENSE polls while an erase runs
+    pub poll_interval: Duration,
+}
+
+impl<T: ScsiTransport> ScsiDevice<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport, poll_interval: Duration::from_secs(5) }
+    }
+
+    /// REPORT SUPPORTED OPERATION CODES for one command.
+    /// Returns `None` when the device does not implement the report itself.
+    pub fn supported_command(&mut self, opcode: u8, service_action: Option<u8>) -> Result<Option<SupportedCommand>> {
+        let mut data = [0u8; 64];
+        let mut cdb = [0u8; 12];
+        cdb[0] = MAINTENANCE_IN;
+        cdb[1] = REPORT_SUPPORTED_OPCODES;
+        // RCTD plus reporting options 001b (opcode) or 010b (opcode and service action)
+        cdb[2] = 0x80 | if service_action.is_some() { 0x02 } else { 0x01 };
+        cdb[3] = opcode;
+        cdb[5] = service_action.unwrap_or(0);
+        cdb[6..10].copy_from_slice(&(data.len() as u32).to_be_bytes());
+
+        let status = self.transport.execute(&cdb, DataTransfer::FromDevice(&mut data), COMMAND_TIMEOUT)?;
+        match status.sense_key() {
+            _ if status.is_good() => SupportedCommand::parse(&data).map(Some),
+            Some((SENSE_KEY_ILLEGAL_REQUEST, ASC_INVALID_OPCODE | ASC_INVALID_FIELD_IN_CDB, _)) => Ok(None),
+            _ => Err(command_failed("REPORT SUPPORTED OPERATION CODES", &status)),
+        }
+    }
+
+    pub fn capabilities(&mut self) -> Result<ScsiCapabilities> {
+        let Some(format_unit) = self.supported_command(FORMAT_UNIT, None)? else {
+            return Ok(ScsiCapabilities::default());
+        };
+        let mut sanitize = |action: SanitizeAction| -> Result<bool> {
+            Ok(self.supported_command(SANITIZE, Some(action as u8))?.is_some_and(|command| command.supported))
+        };
+        Ok(ScsiCapabilities {
+            reported: true,
+            sanitize_overwrite: sanitize(SanitizeAction::Overwrite)?,
+            sanitize_block_erase: sanitize(SanitizeAction::BlockErase)?,
+            sanitize_crypto_erase: sanitize(SanitizeAction::CryptoErase)?,
+            format_unit: format_unit.supported,
+        })
+    }
+
+    /// Start a SANITIZE with IMMED set and poll REQUEST SENSE until it finishes.
+    /// `progress` receives the completed fraction. Returns the exact CDB issued.
+    pub fn sanitize(&mut self, action: SanitizeAction, progress: impl FnMut(f64)) -> Result<Vec<u8>> {
+        let support = self.supported_command(SANITIZE, Some(action as u8))?;
+        if support.is_some_and(|command| !command.supported) {
+            return Err(SecureEraseError::SecureEraseNotSupported);
+        }
+
+        // Overwrite takes a parameter list: one pass (no INVERT) of a four byte zero pattern
+        let parameters: Vec<u8> = match action {
+            SanitizeAction::Overwrite => vec![0x01, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00],
+            _ => Vec::new(),
+        };
+        let mut cdb = vec![0u8; 10];
+        cdb[0] = SANITIZE;
+        cdb[1] = 0x80 | action as u8;
+        cdb[7..9].copy_from_slice(&(parameters.len() as u16).to_be_bytes());
+
+        info!("SCSI SANITIZE: {:?}", action);
+        let transfer = if parameters.is_empty() { DataTransfer::None } else { DataTransfer::ToDevice(&parameters) };
+        let status = self.transport.execute(&cdb, transfer, COMMAND_TIMEOUT)?;
+        check(&format!("SANITIZE ({:?})", action), &status)?;
+        self.wait_for_completion("SANITIZE", support.and_then(|command| command.recommended_timeout), progress)?;
+        Ok(cdb)
+    }
+
+    /// FORMAT UNIT with the security initialize bit, so every block including
+    /// reallocated ones is overwritten. Returns the exact CDB issued.
+    pub fn format_unit(&mut self, progress: impl FnMut(f64)) -> Result<Vec<u8>> {
+        let support = self.supported_command(FORMAT_UNIT, None)?;
+        if support.is_some_and(|command| !command.supported) {
+            return Err(SecureEraseError::SecureEraseNotSupported);
+        }
+
+        // Short parameter list header with FOV, IP and IMMED, then an initialization
+        // pattern descriptor with SI set and a four byte repeating zero pattern
+        let parameters = [
+            0x00, 0x8a, 0x00, 0x00,
+            0x20, 0x01, 0x00, 0x04,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        // FMTDATA, keeping the existing grown defect list
+        let cdb = vec![FORMAT_UNIT, 0x10, 0x00, 0x00, 0x00, 0x00];
+
+        info!("SCSI FORMAT UNIT with security initialize");
+        let status = self.transport.execute(&cdb, DataTransfer::ToDevice(&parameters), COMMAND_TIMEOUT)?;
+        check("FORMAT UNIT", &status)?;
+        self.wait_for_completion("FORMAT UNIT", support.and_then(|command| command.recommended_timeout), progress)?;
+        Ok(cdb)
+    }
+
+    pub fn security_protocol_out(&mut self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<()> {
+        let cdb = security_protocol_cdb(SECURITY_PROTOCOL_OUT, protocol, sp_specific, data.len());
+        let status = self.transport.execute(&cdb, DataTransfer::ToDevice(data), COMMAND_TIMEOUT)?;
+        check("SECURITY PROTOCOL OUT", &status)
+    }
+
+    pub fn security_protocol_in(&mut self, protocol: u8, sp_specific: u16, data: &mut [u8]) -> Result<()> {
+        let cdb = security_protocol_cdb(SECURITY_PROTOCOL_IN, protocol, sp_specific, data.len());
+        let status = self.transport.execute(&cdb, DataTransfer::FromDevice(data), COMMAND_TIMEOUT)?;
+        check("SECURITY PROTOCOL IN", &status)
+    }
+
+    /// Fixed format sense data from REQUEST SENSE
+    pub fn request_sense(&mut self) -> Result<ScsiStatus> {
+        let mut data = [0u8; SENSE_DATA_LEN];
+        let cdb = [REQUEST_SENSE, 0x00, 0x00, 0x00, SENSE_DATA_LEN as u8, 0x00];
+        let status = self.transport.execute(&cdb, DataTransfer::FromDevice(&mut data), COMMAND_TIMEOUT)?;
+        check("REQUEST SENSE", &status)?;
+        let length = std::cmp::min(SENSE_DATA_LEN, 8 + data[7] as usize);
+        Ok(ScsiStatus { status: 0, sense: data[..length].to_vec() })
+    }
+
+    fn wait_for_completion(
+        &mut self,
+        operation: &str,
+        recommended_timeout: Option<Duration>,
+        mut progress: impl FnMut(f64),
+    ) -> Result<()> {
+        let deadline = recommended_timeout
+            .map(|timeout| timeout + Duration::from_secs(600))
+            .unwrap_or(UNREPORTED_ERASE_TIMEOUT);
+        let started = Instant::now();
+        loop {
+            let sense = self.request_sense()?;
+            match sense.sense_key() {
+                Some((SENSE_KEY_NOT_READY, ASC_NOT_READY, ASCQ_FORMAT_IN_PROGRESS | ASCQ_SANITIZE_IN_PROGRESS)) => {
+                    if let Some(fraction) = progress_indication(&sense) {
+                        progress(fraction);
+                    }
+                }
+                Some((SENSE_KEY_MEDIUM_ERROR, ASC_MEDIUM_FORMAT_CORRUPTED, _)) => {
+                    return Err(SecureEraseError::WipeFailed(format!(
+                        "{} failed; the medium stays unusable until it is repeated successfully", operation
+                    )));
+                }
+                Some((SENSE_KEY_NO_SENSE, _, _)) | None => {
+                    progress(1.0);
+                    return Ok(());
+                }
+                Some(_) => warn!("Unexpected sense while waiting for {}: {}", operation, sense.describe()),
+            }
+            if started.elapsed() > deadline {
+                return Err(SecureEraseError::WipeFailed(format!(
+                    "{} still running after {} seconds", operation, started.elapsed().as_secs()
+                )));
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+}
+
+/// SECURITY PROTOCOL IN/OUT CDB with a byte-granular length (INC_512 clear)
+fn security_protocol_cdb(opcode: u8, protocol: u8, sp_specific: u16, length: usize) -> [u8; 12] {
+    let mut cdb = [0u8; 12];
+    cdb[0] = opcode;
+    cdb[1] = protocol;
+    cdb[2..4].copy_from_slice(&sp_specific.to_be_bytes());
+    cdb[6..10].copy_from_slice(&(length as u32).to_be_bytes());
+    cdb
+}
+
+/// Progress indication from the sense key specific field, as a fraction
+pub fn progress_indication(status: &ScsiStatus) -> Option<f64> {
+    let sense = &status.sense;
+    let field = match sense.first()? & 0x7f {
+        0x70 | 0x71 => sense.get(15..18)?,
+        _ => status.descriptor(0x02)?.get(4..7)?,
+    };
+    if field[0] & 0x80 == 0 {
+        return None;
+    }
+    Some(u16::from_be_bytes([field[1], field[2]]) as f64 / 65536.0)
+}
+
+/// CDB bytes as hex, for the certificate
+pub fn describe_cdb(cdb: &[u8]) -> String {
+    cdb.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+fn check(operation: &str, status: &ScsiStatus) -> Result<()> {
+    if status.is_good() {
+        return Ok(());
+    }
+    match status.sense_key() {
+        Some((SENSE_KEY_ILLEGAL_REQUEST, ASC_INVALID_OPCODE | ASC_INVALID_FIELD_IN_CDB, _)) => {
+            Err(SecureEraseError::SecureEraseNotSupported)
+        }
+        _ => Err(command_failed(operation, status)),
+    }
+}
+
+fn command_failed(operation: &str, status: &ScsiStatus) -> SecureEraseError {
+    let reason = match status.sense_key() {
+        Some((SENSE_KEY_DATA_PROTECT, _, _)) => format!("device is write protected ({})", status.describe()),
+        _ => status.describe(),
+    };
+    SecureEraseError::WipeFailed(format!("SCSI {} failed: {}", operation, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sg_io::CHECK_CONDITION;
+
+    /// SAS drive simulator
+    struct MockDrive {
+        /// Service actions of SANITIZE the drive implements; `None` rejects REPORT SUPPORTED OPERATION CODES
+        sanitize_actions: Option<Vec<u8>>,
+        /// Progress values reported by successive REQUEST SENSE polls before completion
+        progress_steps: Vec<u16>,
+        commands: Vec<(Vec<u8>, Vec<u8>)>,
+    }
+
+    impl MockDrive {
+        fn new(sanitize_actions: Option<Vec<u8>>) -> Self {
+            Self { sanitize_actions, progress_steps: Vec::new(), commands: Vec::new() }
+        }
+
+        fn issued(&self, opcode: u8) -> Vec<&(Vec<u8>, Vec<u8>)> {
+            self.commands.iter().filter(|(cdb, _)| cdb[0] == opcode).collect()
+        }
+    }
+
+    fn illegal_request(asc: u8) -> ScsiStatus {
+        let mut sense = vec![0u8; 18];
+        sense[0] = 0x70;
+        sense[2] = SENSE_KEY_ILLEGAL_REQUEST;
+        sense[7] = 10;
+        sense[12] = asc;
+        ScsiStatus { status: CHECK_CONDITION, sense }
+    }
+
+    impl ScsiTransport for MockDrive {
+        fn execute(&mut self, cdb: &[u8], transfer: DataTransfer<'_>, _timeout: Duration) -> Result<ScsiStatus> {
+            let sent = match &transfer {
+                DataTransfer::ToDevice(data) => data.to_vec(),
+                _ => Vec::new(),
+            };
+            self.commands.push((cdb.to_vec(), sent));
+            match (cdb[0], transfer) {
+                (MAINTENANCE_IN, DataTransfer::FromDevice(data)) => {
+                    let Some(actions) = &self.sanitize_actions else {
+                        return Ok(illegal_request(ASC_INVALID_OPCODE));
+                    };
+                    let supported = cdb[3] == FORMAT_UNIT || actions.contains(&cdb[5]);
+                    data[1] = 0x80 | if supported { 0x03 } else { 0x01 };
+                    data[3] = 10;
+                    data[14..16].copy_from_slice(&10u16.to_be_bytes());
+                    data[22..26].copy_from_slice(&7200u32.to_be_bytes());
+                }
+                (REQUEST_SENSE, DataTransfer::FromDevice(data)) => {
+                    data[0] = 0x70;
+                    data[7] = 10;
+                    if !self.progress_steps.is_empty() {
+                        let step = self.progress_steps.remove(0);
+                        data[2] = SENSE_KEY_NOT_READY;
+                        data[12] = ASC_NOT_READY;
+                        data[13] = ASCQ_SANITIZE_IN_PROGRESS;
+                        data[15] = 0x80;
+                        data[16..18].copy_from_slice(&step.to_be_bytes());
+                    }
+                }
+                (SANITIZE, _) if !self.sanitize_actions.as_ref().is_some_and(|a| a.contains(&(cdb[1] & 0x1f))) => {
+                    return Ok(illegal_request(ASC_INVALID_FIELD_IN_CDB));
+                }
+                (SANITIZE, _) | (FORMAT_UNIT, _) => {}
+                (opcode, _) => panic!("unexpected SCSI command 0x{:02x}", opcode),
+            }
+            Ok(ScsiStatus::default())
+        }
+    }
+
+    fn device(drive: MockDrive) -> ScsiDevice<MockDrive> {
+        let mut device = ScsiDevice::new(drive);
+        device.poll_interval = Duration::ZERO;
+        device
+    }
+
+    #[test]
+    fn test_discovers_capabilities_from_supported_opcodes() {
+        let mut scsi = device(MockDrive::new(Some(vec![0x01, 0x03])));
+        assert_eq!(scsi.capabilities().unwrap(), ScsiCapabilities {
+            reported: true,
+            sanitize_overwrite: true,
+            sanitize_block_erase: false,
+            sanitize_crypto_erase: true,
+            format_unit: true,
+        });
+        let sanitize = scsi.supported_command(SANITIZE, Some(0x03)).unwrap().unwrap();
+        assert_eq!(sanitize.recommended_timeout, Some(Duration::from_secs(7200)));
+
+        let rsoc = &scsi.transport.issued(MAINTENANCE_IN)[1].0;
+        assert_eq!(&rsoc[..6], &[MAINTENANCE_IN, REPORT_SUPPORTED_OPCODES, 0x82, SANITIZE, 0x00, 0x01]);
+
+        let mut unreported = device(MockDrive::new(None));
+        assert_eq!(unreported.capabilities().unwrap(), ScsiCapabilities::default());
+    }
+
+    #[test]
+    fn test_sanitize_polls_until_complete() {
+        let mut drive = MockDrive::new(Some(vec![0x02]));
+        drive.progress_steps = vec![0x4000, 0xc000];
+        let mut scsi = device(drive);
+        let mut reported = Vec::new();
+
+        let cdb = scsi.sanitize(SanitizeAction::BlockErase, |fraction| reported.push(fraction)).unwrap();
+        assert_eq!(describe_cdb(&cdb), "48 82 00 00 00 00 00 00 00 00");
+        assert_eq!(reported, vec![0.25, 0.75, 1.0]);
+        assert_eq!(scsi.transport.issued(REQUEST_SENSE).len(), 3);
+    }
+
+    #[test]
+    fn test_overwrite_and_format_send_parameter_lists() {
+        let mut scsi = device(MockDrive::new(Some(vec![0x01])));
+        let cdb = scsi.sanitize(SanitizeAction::Overwrite, |_| {}).unwrap();
+        assert_eq!(&cdb[7..9], &[0x00, 0x08]);
+        assert_eq!(scsi.transport.issued(SANITIZE)[0].1, vec![0x01, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00]);
+
+        let cdb = scsi.format_unit(|_| {}).unwrap();
+        assert_eq!(cdb, vec![FORMAT_UNIT, 0x10, 0x00, 0x00, 0x00, 0x00]);
+        let parameters = &scsi.transport.issued(FORMAT_UNIT)[0].1;
+        assert_eq!(parameters[1], 0x8a);
+        assert_eq!(parameters[4] & 0x20, 0x20);
+    }
+
+    #[test]
+    fn test_unsupported_sanitize_is_not_issued() {
+        let mut scsi = device(MockDrive::new(Some(vec![0x01])));
+        assert!(matches!(
+            scsi.sanitize(SanitizeAction::CryptoErase, |_| {}),
+            Err(SecureEraseError::SecureEraseNotSupported)
+        ));
+        assert!(scsi.transport.issued(SANITIZE).is_empty());
+
+        // Without a report the command is tried, and an ILLEGAL REQUEST means unsupported
+        let mut unreported = device(MockDrive::new(None));
+        assert!(matches!(
+            unreported.sanitize(SanitizeAction::CryptoErase, |_| {}),
+            Err(SecureEraseError::SecureEraseNotSupported)
+        ));
+        assert_eq!(unreported.transport.issued(SANITIZE).len(), 1);
+    }
+}
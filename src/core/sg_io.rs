@@ -0,0 +1,192 @@
+use crate::error::{Result, SecureEraseError};
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+const SG_IO: u64 = 0x2285;
+#[cfg(target_os = "linux")]
+const SG_DXFER_NONE: libc::c_int = -1;
+#[cfg(target_os = "linux")]
+const SG_DXFER_TO_DEV: libc::c_int = -2;
+#[cfg(target_os = "linux")]
+const SG_DXFER_FROM_DEV: libc::c_int = -3;
+
+/// Largest sense buffer requested from the kernel
+const SENSE_BUFFER_LEN: usize = 64;
+
+/// SCSI status byte for CHECK CONDITION
+pub const CHECK_CONDITION: u8 = 0x02;
+
+/// Data phase of a SCSI command
+pub enum DataTransfer<'a> {
+    None,
+    FromDevice(&'a mut [u8]),
+    ToDevice(&'a [u8]),
+}
+
+/// Completion status and sense data of a SCSI command
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScsiStatus {
+    pub status: u8,
+    pub sense: Vec<u8>,
+}
+
+impl ScsiStatus {
+    pub fn is_good(&self) -> bool {
+        self.status == 0
+    }
+
+    /// Sense key, ASC and ASCQ from fixed or descriptor format sense data
+    pub fn sense_key(&self) -> Option<(u8, u8, u8)> {
+        let sense = &self.sense;
+        match sense.first()? & 0x7f {
+            0x72 | 0x73 if sense.len() >= 4 => Some((sense[1] & 0x0f, sense[2], sense[3])),
+            0x70 | 0x71 if sense.len() >= 14 => Some((sense[2] & 0x0f, sense[12], sense[13])),
+            _ => None,
+        }
+    }
+
+    /// A descriptor of descriptor-format sense data
+    pub fn descriptor(&self, code: u8) -> Option<&[u8]> {
+        let sense = &self.sense;
+        if !matches!(sense.first()? & 0x7f, 0x72 | 0x73) || sense.len() < 8 {
+            return None;
+        }
+        let end = std::cmp::min(sense.len(), 8 + sense[7] as usize);
+        let mut offset = 8;
+        while offset + 2 <= end {
+            let length = sense[offset + 1] as usize + 2;
+            if sense[offset] == code {
+                return sense.get(offset..offset + length);
+            }
+            offset += length;
+        }
+        None
+    }
+
+    /// Short description for error messages
+    pub fn describe(&self) -> String {
+        match self.sense_key() {
+            Some((key, asc, ascq)) => format!(
+                "status 0x{:02x}, sense key 0x{:x}, ASC/ASCQ 0x{:02x}/0x{:02x}", self.status, key, asc, ascq
+            ),
+            None => format!("status 0x{:02x}", self.status),
+        }
+    }
+}
+
+/// Anything that can execute a SCSI CDB; implemented by SG_IO and by test doubles
+pub trait ScsiTransport {
+    fn execute(&mut self, cdb: &[u8], transfer: DataTransfer<'_>, timeout: Duration) -> Result<ScsiStatus>;
+}
+
+/// A device node opened for the Linux SCSI generic SG_IO ioctl
+pub struct SgIoDevice {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    file: File,
+}
+
+/// `struct sg_io_hdr` from <scsi/sg.h>
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[allow(dead_code)]
+struct SgIoHdr {
+    interface_id: libc::c_int,
+    dxfer_direction: libc::c_int,
+    cmd_len: libc::c_uchar,
+    mx_sb_len: libc::c_uchar,
+    iovec_count: libc::c_ushort,
+    dxfer_len: libc::c_uint,
+    dxferp: *mut libc::c_void,
+    cmdp: *const libc::c_uchar,
+    sbp: *mut libc::c_uchar,
+    timeout: libc::c_uint,
+    flags: libc::c_uint,
+    pack_id: libc::c_int,
+    usr_ptr: *mut libc::c_void,
+    status: libc::c_uchar,
+    masked_status: libc::c_uchar,
+    msg_status: libc::c_uchar,
+    sb_len_wr: libc::c_uchar,
+    host_status: libc::c_ushort,
+    driver_status: libc::c_ushort,
+    resid: libc::c_int,
+    duration: libc::c_uint,
+    info: libc::c_uint,
+}
+
+impl SgIoDevice {
+    #[cfg(target_os = "linux")]
+    pub fn open(path: &Path) -> Result<Self> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)?;
+        Ok(Self { file })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn open(_path: &Path) -> Result<Self> {
+        Err(SecureEraseError::UnsupportedPlatform)
+    }
+}
+
+impl ScsiTransport for SgIoDevice {
+    #[cfg(target_os = "linux")]
+    fn execute(&mut self, cdb: &[u8], transfer: DataTransfer<'_>, timeout: Duration) -> Result<ScsiStatus> {
+        use std::os::unix::io::AsRawFd;
+
+        let (direction, data, length) = match transfer {
+            DataTransfer::None => (SG_DXFER_NONE, std::ptr::null_mut(), 0),
+            DataTransfer::FromDevice(buf) => (SG_DXFER_FROM_DEV, buf.as_mut_ptr() as *mut libc::c_void, buf.len()),
+            DataTransfer::ToDevice(buf) => (SG_DXFER_TO_DEV, buf.as_ptr() as *mut libc::c_void, buf.len()),
+        };
+        let mut sense = [0u8; SENSE_BUFFER_LEN];
+        let mut header = SgIoHdr {
+            interface_id: 'S' as libc::c_int,
+            dxfer_direction: direction,
+            cmd_len: cdb.len() as libc::c_uchar,
+            mx_sb_len: SENSE_BUFFER_LEN as libc::c_uchar,
+            iovec_count: 0,
+            dxfer_len: length as libc::c_uint,
+            dxferp: data,
+            cmdp: cdb.as_ptr(),
+            sbp: sense.as_mut_ptr(),
+            timeout: timeout.as_millis().min(libc::c_uint::MAX as u128) as libc::c_uint,
+            flags: 0,
+            pack_id: 0,
+            usr_ptr: std::ptr::null_mut(),
+            status: 0,
+            masked_status: 0,
+            msg_status: 0,
+            sb_len_wr: 0,
+            host_status: 0,
+            driver_status: 0,
+            resid: 0,
+            duration: 0,
+            info: 0,
+        };
+
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), SG_IO as _, &mut header) } != 0 {
+            return Err(SecureEraseError::Io(std::io::Error::last_os_error()));
+        }
+        if header.host_status != 0 {
+            return Err(SecureEraseError::WipeFailed(format!(
+                "SCSI command 0x{:02x} failed in transport (host status 0x{:x})", cdb[0], header.host_status
+            )));
+        }
+        Ok(ScsiStatus {
+            status: header.status,
+            sense: sense[..header.sb_len_wr as usize].to_vec(),
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn execute(&mut self, _cdb: &[u8], _transfer: DataTransfer<'_>, _timeout: Duration) -> Result<ScsiStatus> {
+        Err(SecureEraseError::UnsupportedPlatform)
+    }
+}
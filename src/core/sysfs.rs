@@ -0,0 +1,442 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, DeviceType, DeviceAttributes, DeviceLayout, Partition, Holder, HolderKind};
+use crate::core::discard::DiscardLimits;
+use std::path::{Path, PathBuf};
+use log::debug;
+
+/// SCSI peripheral device type of CD/DVD drives
+const SCSI_TYPE_CDROM: &str = "5";
+/// Holders are followed at most this deep
+const MAX_HOLDER_DEPTH: usize = 8;
+
+/// Block device discovery from sysfs and the mount table
+pub struct Sysfs {
+    root: PathBuf,
+    mountinfo: PathBuf,
+}
+
+impl Sysfs {
+    /// `root` is the sysfs mount point, `mountinfo` a file in /proc/<pid>/mountinfo format
+    pub fn new(root: impl Into<PathBuf>, mountinfo: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), mountinfo: mountinfo.into() }
+    }
+
+    pub fn system() -> Self {
+        Self::new("/sys", "/proc/self/mountinfo")
+    }
+
+    /// Every physical disk under /sys/block, sorted by name.
+    ///
+    /// Virtual devices (loop, ram, zram, device-mapper, md), hidden NVMe
+    /// multipath paths, optical drives and empty media are skipped. Secure
+    /// erase support and hidden areas need the device itself and are left unset.
+    pub fn disks(&self) -> Result<Vec<StorageDevice>> {
+        let mounts = self.mounts();
+        let mut names: Vec<String> = std::fs::read_dir(self.root.join("block"))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+
+        let mut disks = Vec::new();
+        for name in names {
+            match self.read_disk(&name, &mounts) {
+                Some(disk) => disks.push(disk),
+                None => debug!("Skipping block device {}", name),
+            }
+        }
+        Ok(disks)
+    }
+
+    /// A single disk by kernel name, e.g. `sda` or `nvme0n1`
+    pub fn disk(&self, name: &str) -> Result<StorageDevice> {
+        self.read_disk(name, &self.mounts())
+            .ok_or_else(|| SecureEraseError::DeviceNotFound(format!("{} is not a physical disk", name)))
+    }
+
+    fn mounts(&self) -> Vec<Mount> {
+        std::fs::read_to_string(&self.mountinfo)
+            .map(|contents| parse_mountinfo(&contents))
+            .unwrap_or_default()
+    }
+
+    fn read_disk(&self, name: &str, mounts: &[Mount]) -> Option<StorageDevice> {
+        let dir = self.root.join("block").join(name);
+        let device_dir = dir.join("device");
+        if !device_dir.exists()
+            || read_u64(&dir.join("hidden")) == Some(1)
+            || read_attr(&device_dir.join("type")).as_deref() == Some(SCSI_TYPE_CDROM)
+        {
+            return None;
+        }
+        let size = read_u64(&dir.join("size")).unwrap_or(0) * 512;
+        if size == 0 {
+            return None;
+        }
+
+        let attributes = DeviceAttributes {
+            logical_block_size: read_u64(&dir.join("queue/logical_block_size")).unwrap_or(512) as u32,
+            physical_block_size: read_u64(&dir.join("queue/physical_block_size")).unwrap_or(512) as u32,
+            rotational: read_u64(&dir.join("queue/rotational")) == Some(1),
+            removable: read_u64(&dir.join("removable")) == Some(1),
+            transport: transport(name, &dir),
+            wwn: read_attr(&dir.join("wwid")).or_else(|| read_attr(&device_dir.join("wwid"))),
+            firmware: ["firmware_rev", "rev", "fwrev"].iter()
+                .find_map(|attr| read_attr(&device_dir.join(attr))),
+            offline: read_attr(&device_dir.join("state")).as_deref() == Some("offline"),
+        };
+        let device_type = device_type(name, &device_dir, &attributes);
+        let model = read_attr(&device_dir.join("model")).or_else(|| read_attr(&device_dir.join("name")));
+        let serial = read_attr(&device_dir.join("serial")).or_else(|| vpd_serial(&device_dir.join("vpd_pg80")));
+
+        let mut partitions = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let part_dir = entry.path();
+                let Some(number) = read_u64(&part_dir.join("partition")) else { continue };
+                let part_name = entry.file_name().to_string_lossy().to_string();
+                partitions.push(Partition {
+                    path: PathBuf::from("/dev").join(&part_name),
+                    number: number as u32,
+                    start_sector: read_u64(&part_dir.join("start")).unwrap_or(0),
+                    size: read_u64(&part_dir.join("size")).unwrap_or(0) * 512,
+                    mountpoints: mountpoints(mounts, &part_dir, &part_name, None),
+                    holders: self.holders(&part_dir, mounts, 0),
+                    name: part_name,
+                });
+            }
+        }
+        partitions.sort_by_key(|partition| partition.number);
+
+        Some(StorageDevice {
+            path: PathBuf::from("/dev").join(name),
+            name: name.to_string(),
+            size,
+            device_type,
+            model,
+            serial,
+            supports_secure_erase: false,
+            supports_trim: DiscardLimits::read(&dir.join("queue")).is_ok(),
+            hidden_areas: Vec::new(),
+            attributes,
+            layout: DeviceLayout {
+                mountpoints: mountpoints(mounts, &dir, name, None),
+                partitions,
+                holders: self.holders(&dir, mounts, 0),
+                system_volumes: Vec::new(),
+            },
+        })
+    }
+
+    /// Devices listed in `<dir>/holders`, each with its own holders
+    fn holders(&self, dir: &Path, mounts: &[Mount], depth: usize) -> Vec<Holder> {
+        if depth >= MAX_HOLDER_DEPTH {
+            return Vec::new();
+        }
+        let Ok(entries) = std::fs::read_dir(dir.join("holders")) else { return Vec::new() };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+
+        names.into_iter().map(|name| {
+            let holder_dir = self.root.join("block").join(&name);
+            let dm_name = read_attr(&holder_dir.join("dm/name"));
+            let uuid = read_attr(&holder_dir.join("dm/uuid")).unwrap_or_default();
+            let kind = if name.starts_with("md") {
+                HolderKind::Raid
+            } else if uuid.starts_with("LVM-") {
+                HolderKind::Lvm
+            } else if uuid.starts_with("CRYPT-") {
+                HolderKind::Crypt
+            } else {
+                HolderKind::DeviceMapper
+            };
+            Holder {
+                path: PathBuf::from("/dev").join(&name),
+                kind,
+                mountpoints: mountpoints(mounts, &holder_dir, &name, dm_name.as_deref()),
+                holders: self.holders(&holder_dir, mounts, depth + 1),
+                dm_name,
+                name,
+            }
+        }).collect()
+    }
+}
+
+/// One line of a mountinfo file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    /// `major:minor` of the mounted device
+    pub device: String,
+    pub mountpoint: PathBuf,
+    /// Mount source, e.g. `/dev/sda1`
+    pub source: String,
+}
+
+/// Parse /proc/<pid>/mountinfo, undoing the octal escapes of spaces and tabs
+pub fn parse_mountinfo(contents: &str) -> Vec<Mount> {
+    contents.lines().filter_map(|line| {
+        let (fields, rest) = line.split_once(" - ")?;
+        let fields: Vec<&str> = fields.split(' ').collect();
+        let source = rest.split(' ').nth(1)?;
+        Some(Mount {
+            device: fields.get(2)?.to_string(),
+            mountpoint: PathBuf::from(unescape(fields.get(4)?)),
+            source: unescape(source),
+        })
+    }).collect()
+}
+
+pub(crate) fn unescape(field: &str) -> String {
+    let mut out = String::new();
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            if let Ok(byte) = u8::from_str_radix(&digits, 8) {
+                out.push(byte as char);
+                chars.nth(2);
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Mount points of a block device, matched by device number or by source path.
+/// Btrfs reports an anonymous device number, so the source is needed as well.
+fn mountpoints(mounts: &[Mount], dir: &Path, name: &str, dm_name: Option<&str>) -> Vec<PathBuf> {
+    let device = read_attr(&dir.join("dev"));
+    let sources = [Some(format!("/dev/{}", name)), dm_name.map(|dm_name| format!("/dev/mapper/{}", dm_name))];
+    let mut found: Vec<PathBuf> = mounts.iter()
+        .filter(|mount| device.as_deref() == Some(mount.device.as_str())
+            || sources.iter().flatten().any(|source| *source == mount.source))
+        .map(|mount| mount.mountpoint.clone())
+        .collect();
+    found.dedup();
+    found
+}
+
+/// Bus a disk is attached through, from its name and its path under /sys/devices
+fn transport(name: &str, dir: &Path) -> Option<String> {
+    if name.starts_with("nvme") {
+        return Some("nvme".to_string());
+    }
+    if name.starts_with("mmcblk") {
+        return Some("mmc".to_string());
+    }
+    let resolved = std::fs::canonicalize(dir).ok()?;
+    let components: Vec<String> = resolved.components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let has = |prefix: &str| components.iter().any(|component| component.starts_with(prefix));
+    // USB bridges can present SATA or SCSI drives, so check the outermost bus first
+    let transport = if has("usb") {
+        "usb"
+    } else if has("end_device-") || dir.join("device/sas_address").exists() {
+        "sas"
+    } else if components.iter().any(|c| c.len() > 3 && c.starts_with("ata") && c[3..].chars().all(|d| d.is_ascii_digit())) {
+        "sata"
+    } else if has("virtio") {
+        "virtio"
+    } else {
+        return None;
+    };
+    Some(transport.to_string())
+}
+
+fn device_type(name: &str, device_dir: &Path, attributes: &DeviceAttributes) -> DeviceType {
+    if name.starts_with("nvme") {
+        DeviceType::NVMe
+    } else if attributes.transport.as_deref() == Some("usb") {
+        DeviceType::USB
+    } else if device_dir.join("sas_address").exists() && !device_dir.join("vpd_pg89").exists() {
+        // SATA drives behind a SAS HBA also carry the ATA Information VPD page
+        DeviceType::SAS
+    } else if name.starts_with("mmcblk") || !attributes.rotational {
+        DeviceType::SSD
+    } else {
+        DeviceType::HDD
+    }
+}
+
+/// Serial number from the Unit Serial Number VPD page
+fn vpd_serial(path: &Path) -> Option<String> {
+    let page = std::fs::read(path).ok()?;
+    let length = *page.get(3)? as usize;
+    let serial = String::from_utf8_lossy(page.get(4..4 + length)?).trim().to_string();
+    (!serial.is_empty()).then_some(serial)
+}
+
+fn read_attr(path: &Path) -> Option<String> {
+    let value = std::fs::read_to_string(path).ok()?.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_attr(path)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// Place a block device under /sys/devices and link it from /sys/block
+    fn block(root: &Path, name: &str, device_path: &str) -> PathBuf {
+        let dir = root.join("devices").join(device_path).join("block").join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(root.join("block")).unwrap();
+        symlink(&dir, root.join("block").join(name)).unwrap();
+        dir
+    }
+
+    fn fake_sysfs() -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+
+        // SATA SSD with an EFI partition and an LVM physical volume holding the root filesystem
+        let sda = block(root, "sda", "pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0");
+        std::fs::create_dir_all(sda.join("device")).unwrap();
+        let sda = sda.strip_prefix(root).unwrap().to_string_lossy().to_string();
+        write(root, &format!("{}/size", sda), "1953525168\n");
+        write(root, &format!("{}/dev", sda), "8:0\n");
+        write(root, &format!("{}/removable", sda), "0\n");
+        write(root, &format!("{}/queue/logical_block_size", sda), "512\n");
+        write(root, &format!("{}/queue/physical_block_size", sda), "4096\n");
+        write(root, &format!("{}/queue/rotational", sda), "0\n");
+        write(root, &format!("{}/queue/discard_max_bytes", sda), "2147450880\n");
+        write(root, &format!("{}/device/model", sda), "Samsung SSD 870 \n");
+        write(root, &format!("{}/device/rev", sda), "SVT02B6Q\n");
+        write(root, &format!("{}/device/wwid", sda), "naa.5002538f4132a1b2\n");
+        write(root, &format!("{}/device/type", sda), "0\n");
+        std::fs::write(root.join(&sda).join("device/vpd_pg80"), b"\x00\x80\x00\x0fS6PNNM0T123456Z").unwrap();
+        for (number, start, size) in [(1, 2048, 1048576), (2, 1050624, 1952474511)] {
+            write(root, &format!("{}/sda{}/partition", sda, number), &format!("{}\n", number));
+            write(root, &format!("{}/sda{}/start", sda, number), &format!("{}\n", start));
+            write(root, &format!("{}/sda{}/size", sda, number), &format!("{}\n", size));
+            write(root, &format!("{}/sda{}/dev", sda, number), &format!("8:{}\n", number));
+        }
+        std::fs::create_dir_all(root.join(&sda).join("sda2/holders/dm-0")).unwrap();
+
+        let dm = block(root, "dm-0", "virtual");
+        write(&dm, "dev", "253:0\n");
+        write(&dm, "size", "1952474511\n");
+        write(&dm, "dm/name", "vg0-root\n");
+        write(&dm, "dm/uuid", "LVM-abcdef\n");
+
+        // NVMe namespace with a software RAID member, and its hidden multipath node
+        let nvme = block(root, "nvme0n1", "pci0000:00/0000:00:1d.0/0000:3d:00.0/nvme/nvme0");
+        write(&nvme, "size", "1000215216\n");
+        write(&nvme, "dev", "259:0\n");
+        write(&nvme, "queue/rotational", "0\n");
+        write(&nvme, "wwid", "eui.0025385b71b0a1c2\n");
+        write(&nvme, "device/model", "Samsung SSD 980 PRO 1TB\n");
+        write(&nvme, "device/serial", "S5GXNF0R123456\n");
+        write(&nvme, "device/firmware_rev", "5B2QGXA7\n");
+        std::fs::create_dir_all(nvme.join("holders/md127")).unwrap();
+        let md = block(root, "md127", "virtual");
+        write(&md, "dev", "9:127\n");
+        write(&md, "size", "1000215216\n");
+        let hidden = block(root, "nvme0c0n1", "pci0000:00/0000:00:1d.0/0000:3d:00.0/nvme/nvme0");
+        write(&hidden, "size", "1000215216\n");
+        write(&hidden, "hidden", "1\n");
+        write(&hidden, "device/model", "Samsung SSD 980 PRO 1TB\n");
+
+        // USB stick, optical drive, loop device
+        let sdb = block(root, "sdb", "pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/host6/target6:0:0/6:0:0:0");
+        write(&sdb, "size", "60062500\n");
+        write(&sdb, "removable", "1\n");
+        write(&sdb, "queue/rotational", "1\n");
+        write(&sdb, "device/model", "Cruzer Blade\n");
+        let sr = block(root, "sr0", "pci0000:00/0000:00:17.0/ata2/host1/target1:0:0/1:0:0:0");
+        write(&sr, "size", "2097151\n");
+        write(&sr, "device/type", "5\n");
+        let lo = block(root, "loop0", "virtual");
+        write(&lo, "size", "131072\n");
+
+        write(root, "mountinfo", "\
+22 1 253:0 / / rw,relatime shared:1 - ext4 /dev/mapper/vg0-root rw
+25 22 8:1 / /boot/efi rw,relatime shared:5 - vfat /dev/sda1 rw
+31 22 9:127 / /srv/my\\040data rw,relatime shared:9 - xfs /dev/md127 rw
+40 22 0:48 / /mnt/usb rw,relatime shared:12 - btrfs /dev/sdb rw
+");
+        tmp
+    }
+
+    #[test]
+    fn test_discovers_physical_disks_only() {
+        let tmp = fake_sysfs();
+        let sysfs = Sysfs::new(tmp.path(), tmp.path().join("mountinfo"));
+        let names: Vec<String> = sysfs.disks().unwrap().into_iter().map(|disk| disk.name).collect();
+        assert_eq!(names, vec!["nvme0n1", "sda", "sdb"]);
+        assert!(sysfs.disk("sda1").is_err());
+    }
+
+    #[test]
+    fn test_reads_sata_disk_attributes_and_layout() {
+        let tmp = fake_sysfs();
+        let disk = Sysfs::new(tmp.path(), tmp.path().join("mountinfo")).disk("sda").unwrap();
+        assert_eq!(disk.path, PathBuf::from("/dev/sda"));
+        assert_eq!(disk.size, 1953525168 * 512);
+        assert_eq!(disk.device_type, DeviceType::SSD);
+        assert_eq!(disk.model.as_deref(), Some("Samsung SSD 870"));
+        assert_eq!(disk.serial.as_deref(), Some("S6PNNM0T123456Z"));
+        assert!(disk.supports_trim);
+        assert_eq!(disk.attributes, DeviceAttributes {
+            logical_block_size: 512,
+            physical_block_size: 4096,
+            rotational: false,
+            removable: false,
+            transport: Some("sata".to_string()),
+            wwn: Some("naa.5002538f4132a1b2".to_string()),
+            firmware: Some("SVT02B6Q".to_string()),
+            offline: false,
+        });
+
+        let partitions = &disk.layout.partitions;
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[0].path, PathBuf::from("/dev/sda1"));
+        assert_eq!((partitions[0].start_sector, partitions[0].size), (2048, 1048576 * 512));
+        assert_eq!(partitions[0].mountpoints, vec![PathBuf::from("/boot/efi")]);
+        assert!(partitions[1].mountpoints.is_empty());
+
+        let holder = &partitions[1].holders[0];
+        assert_eq!(holder.kind, HolderKind::Lvm);
+        assert_eq!(holder.dm_name.as_deref(), Some("vg0-root"));
+        assert_eq!(holder.mountpoints, vec![PathBuf::from("/")]);
+        assert_eq!(disk.layout.all_mountpoints(), vec![&PathBuf::from("/boot/efi"), &PathBuf::from("/")]);
+    }
+
+    #[test]
+    fn test_reads_nvme_and_usb_disks() {
+        let tmp = fake_sysfs();
+        let sysfs = Sysfs::new(tmp.path(), tmp.path().join("mountinfo"));
+
+        let nvme = sysfs.disk("nvme0n1").unwrap();
+        assert_eq!(nvme.device_type, DeviceType::NVMe);
+        assert_eq!(nvme.serial.as_deref(), Some("S5GXNF0R123456"));
+        assert_eq!(nvme.attributes.transport.as_deref(), Some("nvme"));
+        assert_eq!(nvme.attributes.wwn.as_deref(), Some("eui.0025385b71b0a1c2"));
+        assert_eq!(nvme.attributes.firmware.as_deref(), Some("5B2QGXA7"));
+        assert!(!nvme.supports_trim);
+        assert_eq!(nvme.layout.holders[0].kind, HolderKind::Raid);
+        assert_eq!(nvme.layout.all_mountpoints(), vec![&PathBuf::from("/srv/my data")]);
+
+        let usb = sysfs.disk("sdb").unwrap();
+        assert_eq!(usb.device_type, DeviceType::USB);
+        assert!(usb.attributes.removable && usb.attributes.rotational);
+        assert_eq!(usb.attributes.transport.as_deref(), Some("usb"));
+        assert_eq!(usb.layout.mountpoints, vec![PathBuf::from("/mnt/usb")]);
+    }
+}
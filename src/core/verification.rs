@@ -0,0 +1,341 @@
+use crate::error::{Result, SecureEraseError};
+use crate::core::{StorageDevice, LbaRange};
+use crate::core::advanced::PatternSource;
+use crate::core::block_io::{AlignedBuffer, BlockDevice, DEFAULT_BUFFER_SIZE};
+use crate::core::checkpoint::CancellationToken;
+use crate::core::progress::{ProgressReporter, ProgressTracker, WipePhase};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use rand::seq::index;
+use log::{info, warn};
+
+/// Size of the extents read back in sampled verification (1 MiB)
+const SAMPLE_EXTENT: u64 = 1024 * 1024;
+
+/// Upper bound on mismatch ranges kept in a report; counts stay exact
+const MAX_RECORDED_RANGES: usize = 1024;
+
+/// How much of the device is read back after a wipe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VerificationMode {
+    /// Random 1 MiB extents covering `percent` of the device, plus the first and last extent
+    Sampled { percent: f64 },
+    /// Every block of the device
+    Full,
+    /// NIST SP 800-88 representative sample: one window covering `percent` of each of
+    /// `subranges` equal regions, plus the first and last blocks
+    Representative { subranges: u32, percent: f64 },
+}
+
+impl Default for VerificationMode {
+    fn default() -> Self {
+        VerificationMode::Sampled { percent: 10.0 }
+    }
+}
+
+impl VerificationMode {
+    pub fn describe(&self) -> String {
+        match self {
+            VerificationMode::Sampled { percent } => format!("Sampled read-back ({}%)", percent),
+            VerificationMode::Full => "Full read-back".to_string(),
+            VerificationMode::Representative { subranges, percent } => format!(
+                "NIST SP 800-88 representative sample ({} sub-ranges, {}%)", subranges, percent
+            ),
+        }
+    }
+}
+
+/// Outcome of reading a device back and comparing it with the expected pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub mode: VerificationMode,
+    pub expected_pattern: PatternSource,
+    pub block_size: u32,
+    pub bytes_checked: u64,
+    pub blocks_checked: u64,
+    pub mismatched_blocks: u64,
+    /// Contiguous runs of mismatched LBAs (truncated after 1024 ranges)
+    pub mismatched_ranges: Vec<LbaRange>,
+    /// Known unwritable blocks that were left out of the read-back
+    #[serde(default)]
+    pub excluded_ranges: Vec<LbaRange>,
+    pub passed: bool,
+}
+
+impl VerificationReport {
+    /// Fraction of checked blocks that matched the expected pattern
+    pub fn match_ratio(&self) -> f64 {
+        if self.blocks_checked == 0 {
+            return 0.0;
+        }
+        (self.blocks_checked - self.mismatched_blocks) as f64 / self.blocks_checked as f64
+    }
+
+    fn record_mismatch(&mut self, lba: u64) {
+        self.mismatched_blocks += 1;
+        if let Some(last) = self.mismatched_ranges.last_mut() {
+            if last.start_lba + last.count == lba {
+                last.count += 1;
+                return;
+            }
+        }
+        if self.mismatched_ranges.len() < MAX_RECORDED_RANGES {
+            self.mismatched_ranges.push(LbaRange { start_lba: lba, count: 1 });
+        }
+    }
+}
+
+/// Read back a device and compare it against the pattern of the final pass
+pub async fn verify_device(
+    device: &StorageDevice,
+    mode: &VerificationMode,
+    expected: &PatternSource,
+    excluded: &[LbaRange],
+    progress: Option<ProgressReporter>,
+    cancellation: Option<CancellationToken>,
+) -> Result<VerificationReport> {
+    info!("Verifying {} ({}) against {}", device.path.display(), mode.describe(), expected.describe());
+
+    let path = device.path.clone();
+    let size_hint = device.size;
+    let mode = mode.clone();
+    let expected = expected.clone();
+    let excluded = excluded.to_vec();
+
+    let report = tokio::task::spawn_blocking(move || {
+        let mut tracker = None;
+        verify_target_with_progress(&path, size_hint, &mode, &expected, &excluded, DEFAULT_BUFFER_SIZE, |done, total| {
+            tracker.get_or_insert_with(|| ProgressTracker::new(
+                progress.clone(), path.clone(), WipePhase::Verify, total, mode.describe(),
+            )).update(done);
+            match &cancellation {
+                Some(token) if token.is_cancelled() => Err(SecureEraseError::Cancelled),
+                _ => Ok(()),
+            }
+        })
+    })
+    .await
+    .map_err(|e| SecureEraseError::VerificationFailed(format!("Verification task failed: {}", e)))??;
+
+    if report.passed {
+        info!("Verification passed: {} blocks checked", report.blocks_checked);
+    } else {
+        warn!(
+            "Verification failed: {} of {} blocks did not match",
+            report.mismatched_blocks, report.blocks_checked
+        );
+    }
+    Ok(report)
+}
+
+/// Blocking read-back of a device or image file
+pub fn verify_target(
+    path: &Path,
+    size_hint: u64,
+    mode: &VerificationMode,
+    expected: &PatternSource,
+    buffer_size: usize,
+) -> Result<VerificationReport> {
+    verify_target_with_progress(path, size_hint, mode, expected, &[], buffer_size, |_, _| Ok(()))
+}
+
+/// Like `verify_target`, skipping the blocks in `excluded` and calling `progress`
+/// with bytes checked and bytes planned; an error from `progress` aborts the read-back
+pub fn verify_target_with_progress<P>(
+    path: &Path,
+    size_hint: u64,
+    mode: &VerificationMode,
+    expected: &PatternSource,
+    excluded: &[LbaRange],
+    buffer_size: usize,
+    mut progress: P,
+) -> Result<VerificationReport>
+where
+    P: FnMut(u64, u64) -> Result<()>,
+{
+    let mut target = BlockDevice::open_for_read(path, size_hint)?;
+    let block_size = target.logical_block_size as u64;
+    let buffer_size = target.aligned_buffer_size(buffer_size);
+    let excluded = LbaRange::merge(excluded.to_vec());
+    let extents = exclude_ranges(plan_extents(target.size, block_size, mode), &excluded, block_size);
+    let planned: u64 = extents.iter().map(|extent| extent.1).sum();
+
+    let mut report = VerificationReport {
+        mode: mode.clone(),
+        expected_pattern: expected.clone(),
+        block_size: target.logical_block_size,
+        bytes_checked: 0,
+        blocks_checked: 0,
+        mismatched_blocks: 0,
+        mismatched_ranges: Vec::new(),
+        excluded_ranges: excluded,
+        passed: false,
+    };
+
+    let mut actual = AlignedBuffer::new(buffer_size);
+    let mut wanted = vec![0u8; buffer_size];
+
+    for (start, len) in extents {
+        let end = start + len;
+        let mut offset = start;
+        while offset < end {
+            let chunk = std::cmp::min(buffer_size as u64, end - offset) as usize;
+            target.read_at(offset, &mut actual.as_mut_slice()[..chunk])?;
+            expected.fill(offset, &mut wanted[..chunk]);
+
+            let blocks = actual.as_slice()[..chunk].chunks(block_size as usize)
+                .zip(wanted[..chunk].chunks(block_size as usize));
+            for (index, (got, want)) in blocks.enumerate() {
+                report.blocks_checked += 1;
+                if got != want {
+                    report.record_mismatch(offset / block_size + index as u64);
+                }
+            }
+
+            report.bytes_checked += chunk as u64;
+            offset += chunk as u64;
+            progress(report.bytes_checked, planned)?;
+        }
+    }
+
+    report.passed = report.blocks_checked > 0 && report.mismatched_blocks == 0;
+    Ok(report)
+}
+
+/// Work out the (offset, length) extents to read for a verification mode
+fn plan_extents(size: u64, block_size: u64, mode: &VerificationMode) -> Vec<(u64, u64)> {
+    if size == 0 {
+        return Vec::new();
+    }
+    let align_down = |value: u64| value / block_size * block_size;
+    let last_block = align_down(size.saturating_sub(1));
+
+    let mut extents = match mode {
+        VerificationMode::Full => vec![(0, size)],
+        VerificationMode::Sampled { percent } => {
+            let extent = std::cmp::max(align_down(std::cmp::min(SAMPLE_EXTENT, size)), block_size);
+            let count = size.div_ceil(extent);
+            let wanted = ((count as f64 * percent.clamp(0.0, 100.0) / 100.0).ceil() as u64).clamp(1, count);
+
+            let mut picked: Vec<u64> = index::sample(&mut rand::thread_rng(), count as usize, wanted as usize)
+                .into_iter()
+                .map(|i| i as u64)
+                .collect();
+            picked.push(0);
+            picked.push(count - 1);
+            picked.into_iter().map(|i| (i * extent, extent)).collect()
+        }
+        VerificationMode::Representative { subranges, percent } => {
+            let subranges = std::cmp::max(*subranges as u64, 1);
+            let region = align_down(size / subranges);
+            if region == 0 {
+                vec![(0, size)]
+            } else {
+                let window = std::cmp::max(
+                    align_down((region as f64 * percent.clamp(0.0, 100.0) / 100.0) as u64),
+                    block_size,
+                );
+                let slack = (region - window) / block_size;
+                let mut extents: Vec<(u64, u64)> = (0..subranges).map(|i| {
+                    let shift = if slack > 0 { rand::random::<u64>() % (slack + 1) } else { 0 };
+                    (i * region + shift * block_size, window)
+                }).collect();
+                extents.push((0, block_size));
+                extents.push((last_block, block_size));
+                extents
+            }
+        }
+    };
+
+    // Clamp to the device, then sort and merge overlapping extents
+    for extent in extents.iter_mut() {
+        extent.1 = std::cmp::min(extent.1, size - extent.0);
+    }
+    extents.sort();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(extents.len());
+    for (start, len) in extents {
+        if let Some(last) = merged.last_mut() {
+            if start <= last.0 + last.1 {
+                last.1 = std::cmp::max(last.0 + last.1, start + len) - last.0;
+                continue;
+            }
+        }
+        merged.push((start, len));
+    }
+    merged
+}
+
+/// Remove sorted, merged LBA ranges from a list of sorted (offset, length) extents
+fn exclude_ranges(extents: Vec<(u64, u64)>, excluded: &[LbaRange], block_size: u64) -> Vec<(u64, u64)> {
+    if excluded.is_empty() {
+        return extents;
+    }
+    let mut kept = Vec::with_capacity(extents.len());
+    for (start, len) in extents {
+        let end = start + len;
+        let mut cursor = start;
+        for range in excluded {
+            let (skip_start, skip_end) = (range.start_lba * block_size, range.end_lba() * block_size);
+            if skip_end <= cursor || skip_start >= end {
+                continue;
+            }
+            if skip_start > cursor {
+                kept.push((cursor, skip_start - cursor));
+            }
+            cursor = std::cmp::max(cursor, skip_end);
+        }
+        if cursor < end {
+            kept.push((cursor, end - cursor));
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covered(extents: &[(u64, u64)]) -> u64 {
+        extents.iter().map(|e| e.1).sum()
+    }
+
+    #[test]
+    fn test_full_plan_covers_device() {
+        assert_eq!(plan_extents(10_000, 512, &VerificationMode::Full), vec![(0, 10_000)]);
+    }
+
+    #[test]
+    fn test_sampled_plan_includes_edges() {
+        let size = 64 * SAMPLE_EXTENT;
+        let extents = plan_extents(size, 512, &VerificationMode::Sampled { percent: 10.0 });
+        assert_eq!(extents.first().unwrap().0, 0);
+        let last = extents.last().unwrap();
+        assert_eq!(last.0 + last.1, size);
+        assert!(covered(&extents) >= 7 * SAMPLE_EXTENT);
+        assert!(covered(&extents) <= 9 * SAMPLE_EXTENT);
+    }
+
+    #[test]
+    fn test_representative_plan_spans_regions() {
+        let size = 100 * 4096;
+        let mode = VerificationMode::Representative { subranges: 10, percent: 10.0 };
+        let extents = plan_extents(size, 512, &mode);
+        for region in 0..10u64 {
+            let (lo, hi) = (region * 40_960, (region + 1) * 40_960);
+            assert!(extents.iter().any(|&(start, len)| start < hi && start + len > lo));
+        }
+        assert!(extents.iter().all(|&(start, len)| start % 512 == 0 && start + len <= size));
+    }
+
+    #[test]
+    fn test_excluded_ranges_are_not_read() {
+        let excluded = vec![
+            LbaRange { start_lba: 2, count: 2 },
+            LbaRange { start_lba: 10, count: 2 },
+            LbaRange { start_lba: 20, count: 1 },
+        ];
+        let extents = exclude_ranges(vec![(0, 4096), (8192, 4096)], &excluded, 512);
+        assert_eq!(extents, vec![(0, 1024), (2048, 2048), (8192, 2048), (10752, 1536)]);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::error::{Result, SecureEraseError};
+use crate::crypto::pem_blocks;
+use crate::crypto::x509::parse_certificate;
+use x509_parser::prelude::*;
+use std::path::Path;
+
+/// Certificate revocation list, as `pki/scripts/generate_crl.sh` publishes it
+#[derive(Debug, Clone)]
+pub struct RevocationList {
+    der: Vec<u8>,
+}
+
+/// What a CRL says about one certificate; times are Unix seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrlStatus {
+    pub this_update: i64,
+    pub next_update: Option<i64>,
+    /// When the certificate was revoked, if it is on the list
+    pub revoked_at: Option<i64>,
+}
+
+impl CrlStatus {
+    /// Whether the certificate had been revoked by `at`
+    pub fn revoked_as_of(&self, at: i64) -> bool {
+        self.revoked_at.is_some_and(|revoked_at| revoked_at <= at)
+    }
+}
+
+impl RevocationList {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// A PEM (`X509 CRL`) or DER encoded CRL
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let der = if data.starts_with(b"-----BEGIN") {
+            pem_blocks(data)?.into_iter()
+                .find(|block| block.label == "X509 CRL")
+                .map(|block| block.contents)
+                .ok_or_else(|| crl_error("no X509 CRL block found".to_string()))?
+        } else {
+            data.to_vec()
+        };
+        parse_crl(&der)?;
+        Ok(Self { der })
+    }
+
+    /// Status of `certificate` after checking that `issuer`, the CA that issued it, signed this list
+    pub fn status(&self, certificate: &[u8], issuer: &[u8]) -> Result<CrlStatus> {
+        let crl = parse_crl(&self.der)?;
+        let certificate = parse_certificate(certificate)?;
+        let issuer = parse_certificate(issuer)?;
+
+        if crl.issuer().as_raw() != issuer.subject().as_raw() || certificate.issuer().as_raw() != issuer.subject().as_raw() {
+            return Err(crl_error(format!("issued by {}, not by {}", crl.issuer(), certificate.issuer())));
+        }
+        if let Some(usage) = issuer.key_usage().map_err(|e| crl_error(e.to_string()))? {
+            if !usage.value.crl_sign() {
+                return Err(crl_error(format!("{} may not sign CRLs", issuer.subject())));
+            }
+        }
+        crl.verify_signature(issuer.public_key())
+            .map_err(|e| crl_error(format!("signature does not verify against {}: {}", issuer.subject(), e)))?;
+
+        let revoked_at = crl.iter_revoked_certificates()
+            .find(|revoked| revoked.serial() == &certificate.serial)
+            .map(|revoked| revoked.revocation_date.timestamp());
+        Ok(CrlStatus {
+            this_update: crl.last_update().timestamp(),
+            next_update: crl.next_update().map(|time| time.timestamp()),
+            revoked_at,
+        })
+    }
+}
+
+fn parse_crl(der: &[u8]) -> Result<CertificateRevocationList<'_>> {
+    CertificateRevocationList::from_der(der)
+        .map(|(_, crl)| crl)
+        .map_err(|e| crl_error(format!("invalid CRL: {}", e)))
+}
+
+fn crl_error(message: String) -> SecureEraseError {
+    SecureEraseError::CertificateVerificationFailed(format!("CRL: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::x509::StationChain;
+
+    const CRL: &[u8] = include_bytes!("../../tests/fixtures/pki/intermediate.crl.pem");
+    const ROOT: &str = include_str!("../../tests/fixtures/pki/root.cert.pem");
+    const STATION: &str = include_str!("../../tests/fixtures/pki/station-001.fullchain.pem");
+    const REVOKED_STATION: &str = include_str!("../../tests/fixtures/pki/station-002.fullchain.pem");
+
+    #[test]
+    fn test_crl_reports_revocation_as_of_a_time() {
+        let crl = RevocationList::from_bytes(CRL).unwrap();
+
+        let station = StationChain::from_pem(STATION).unwrap();
+        let status = crl.status(&station.leaf, &station.intermediates[0]).unwrap();
+        assert_eq!(status.revoked_at, None);
+        assert!(status.next_update.is_some_and(|next_update| next_update > status.this_update));
+
+        let revoked = StationChain::from_pem(REVOKED_STATION).unwrap();
+        let status = crl.status(&revoked.leaf, &revoked.intermediates[0]).unwrap();
+        let revoked_at = status.revoked_at.unwrap();
+        assert!(status.revoked_as_of(revoked_at));
+        assert!(!status.revoked_as_of(revoked_at - 1));
+    }
+
+    #[test]
+    fn test_crl_must_come_from_the_issuer() {
+        let crl = RevocationList::from_bytes(CRL).unwrap();
+        let station = StationChain::from_pem(STATION).unwrap();
+        let root = crate::crypto::x509::pem_certificates(ROOT).unwrap().remove(0);
+        assert!(crl.status(&station.leaf, &root).is_err());
+
+        // A single flipped bit in the signature value breaks it
+        let mut der = crl.der.clone();
+        *der.last_mut().unwrap() ^= 0x01;
+        assert!(RevocationList::from_bytes(&der).and_then(|crl| crl.status(&station.leaf, &station.intermediates[0])).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error::{Result, SecureEraseError};
+
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_ENUMERATED: u8 = 0x0a;
+pub const TAG_SEQUENCE: u8 = 0x30;
+
+/// One DER element
+#[derive(Debug, Clone, Copy)]
+pub struct Element<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    /// The whole encoding, tag and length included
+    pub raw: &'a [u8],
+}
+
+impl<'a> Element<'a> {
+    /// The elements of a constructed element's content
+    pub fn children(&self) -> Result<Vec<Element<'a>>> {
+        let mut children = Vec::new();
+        let mut rest = self.content;
+        while !rest.is_empty() {
+            let (child, next) = read(rest)?;
+            children.push(child);
+            rest = next;
+        }
+        Ok(children)
+    }
+
+    /// Fail unless the element has tag `tag`
+    pub fn expect(self, tag: u8) -> Result<Self> {
+        if self.tag != tag {
+            return Err(malformed(format!("expected tag {:#04x}, found {:#04x}", tag, self.tag)));
+        }
+        Ok(self)
+    }
+}
+
+/// Split the first element off `input`; only single-byte tags are supported
+pub fn read(input: &[u8]) -> Result<(Element<'_>, &[u8])> {
+    let (&tag, rest) = input.split_first().ok_or_else(|| malformed("unexpected end of data".to_string()))?;
+    let (&first, rest) = rest.split_first().ok_or_else(|| malformed("missing length".to_string()))?;
+    let (length, rest) = match first {
+        len if len < 0x80 => (len as usize, rest),
+        0x81..=0x84 => {
+            let count = (first & 0x7f) as usize;
+            if rest.len() < count {
+                return Err(malformed("truncated length".to_string()));
+            }
+            let length = rest[..count].iter().fold(0usize, |length, &byte| (length << 8) | byte as usize);
+            (length, &rest[count..])
+        }
+        _ => return Err(malformed(format!("unsupported length byte {:#04x}", first))),
+    };
+    if rest.len() < length {
+        return Err(malformed("element longer than its data".to_string()));
+    }
+    let header = input.len() - rest.len();
+    Ok((
+        Element { tag, content: &rest[..length], raw: &input[..header + length] },
+        &rest[length..],
+    ))
+}
+
+/// Encode an element
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match content.len() {
+        len if len < 0x80 => out.push(len as u8),
+        len if len <= 0xff => out.extend_from_slice(&[0x81, len as u8]),
+        len => out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+pub fn sequence(items: &[&[u8]]) -> Vec<u8> {
+    tlv(TAG_SEQUENCE, &items.concat())
+}
+
+fn malformed(message: String) -> SecureEraseError {
+    SecureEraseError::Crypto(format!("Malformed DER: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elements_round_trip() {
+        let long = vec![0xabu8; 300];
+        let encoded = sequence(&[&tlv(TAG_INTEGER, &[0x10, 0x01]), &tlv(TAG_OCTET_STRING, &long)]);
+        let (element, rest) = read(&encoded).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(element.raw, encoded.as_slice());
+
+        let children = element.expect(TAG_SEQUENCE).unwrap().children().unwrap();
+        assert_eq!(children[0].content, &[0x10, 0x01]);
+        assert_eq!(children[1].content, long.as_slice());
+        assert!(children[1].expect(TAG_INTEGER).is_err());
+        assert!(read(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,403 @@
+use crate::error::{Result, SecureEraseError};
+use crate::crypto::der::{self, Element, TAG_ENUMERATED, TAG_INTEGER, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE};
+use crate::crypto::x509::{issued, parse_certificate};
+use ring::digest;
+use x509_parser::der_parser::asn1_rs::BitString;
+use x509_parser::prelude::*;
+use x509_parser::verify::verify_signature;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// id-pkix-ocsp-basic
+const OID_OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+/// id-pkix-ocsp-nonce
+const OID_OCSP_NONCE: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x02];
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// Context-specific tags of the OCSP structures
+const TAG_EXPLICIT_0: u8 = 0xa0;
+const TAG_EXPLICIT_1: u8 = 0xa1;
+const TAG_EXPLICIT_2: u8 = 0xa2;
+const TAG_STATUS_GOOD: u8 = 0x80;
+const TAG_STATUS_REVOKED: u8 = 0xa1;
+const TAG_STATUS_UNKNOWN: u8 = 0x82;
+
+const NONCE_LENGTH: usize = 16;
+/// How long a response without a nextUpdate is relied on
+const MAX_RESPONSE_AGE: i64 = 24 * 60 * 60;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Revocation status a responder reports for one certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcspCertStatus {
+    Good,
+    /// Revoked at the given Unix time
+    Revoked(i64),
+    Unknown,
+}
+
+impl OcspCertStatus {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            OcspCertStatus::Good => "Good",
+            OcspCertStatus::Revoked(_) => "Revoked",
+            OcspCertStatus::Unknown => "Unknown",
+        }
+    }
+}
+
+/// OCSP request for one certificate, with a fresh nonce
+#[derive(Debug, Clone)]
+pub struct OcspRequest {
+    pub der: Vec<u8>,
+    pub nonce: Vec<u8>,
+}
+
+impl OcspRequest {
+    /// Ask about `certificate`, issued by `issuer`, identified with SHA-1 as RFC 5019 responders expect
+    pub fn new(certificate: &[u8], issuer: &[u8]) -> Result<Self> {
+        let certificate = parse_certificate(certificate)?;
+        let issuer = parse_certificate(issuer)?;
+        let (name_hash, key_hash) = issuer_hashes(&issuer, &digest::SHA1_FOR_LEGACY_USE_ONLY);
+        let cert_id = der::sequence(&[
+            &der::sequence(&[&der::tlv(TAG_OID, OID_SHA1), &[0x05, 0x00]]),
+            &der::tlv(TAG_OCTET_STRING, &name_hash),
+            &der::tlv(TAG_OCTET_STRING, &key_hash),
+            &der::tlv(TAG_INTEGER, certificate.raw_serial()),
+        ]);
+
+        let nonce: [u8; NONCE_LENGTH] = rand::random();
+        let nonce_extension = der::sequence(&[
+            &der::tlv(TAG_OID, OID_OCSP_NONCE),
+            &der::tlv(TAG_OCTET_STRING, &der::tlv(TAG_OCTET_STRING, &nonce)),
+        ]);
+        let tbs_request = der::sequence(&[
+            &der::sequence(&[&der::sequence(&[&cert_id])]),
+            &der::tlv(TAG_EXPLICIT_2, &der::sequence(&[&nonce_extension])),
+        ]);
+        Ok(Self { der: der::sequence(&[&tbs_request]), nonce: nonce.to_vec() })
+    }
+}
+
+/// A response checked against the certificate it is about; times are Unix seconds
+#[derive(Debug, Clone)]
+pub struct OcspResponse {
+    pub status: OcspCertStatus,
+    pub produced_at: i64,
+    pub this_update: i64,
+    pub next_update: Option<i64>,
+    /// Subject of the certificate whose key signed the response
+    pub responder: String,
+    /// The response as received, for caching and stapling
+    pub der: Vec<u8>,
+}
+
+impl OcspResponse {
+    /// Parse a DER `OCSPResponse` about `certificate` and check its signature.
+    ///
+    /// The response must be signed by `issuer`, the CA of the certificate, or
+    /// by a responder certificate that CA issued for `OCSPSigning`. When
+    /// `nonce` is given the response must echo it.
+    pub fn verify(der: &[u8], certificate: &[u8], issuer: &[u8], nonce: Option<&[u8]>) -> Result<Self> {
+        let (response, _) = der::read(der)?;
+        let fields = response.expect(TAG_SEQUENCE)?.children()?;
+        let status = first(&fields, "response status")?.expect(TAG_ENUMERATED)?;
+        if status.content != [0] {
+            return Err(ocsp_error(format!("responder answered {}", response_status_name(status.content))));
+        }
+        let body = first(&fields[1..], "body")?.expect(TAG_EXPLICIT_0)?.children()?;
+        let response_bytes = first(&body, "response bytes")?.expect(TAG_SEQUENCE)?.children()?;
+        if first(&response_bytes, "response type")?.expect(TAG_OID)?.content != OID_OCSP_BASIC {
+            return Err(ocsp_error("unsupported response type".to_string()));
+        }
+        let (basic, _) = der::read(first(&response_bytes[1..], "basic response")?.expect(TAG_OCTET_STRING)?.content)?;
+        let basic = basic.expect(TAG_SEQUENCE)?.children()?;
+        if basic.len() < 3 {
+            return Err(ocsp_error("truncated basic response".to_string()));
+        }
+
+        // ResponseData: [0] version, responderID, producedAt, responses, [1] extensions
+        let mut data = basic[0].expect(TAG_SEQUENCE)?.children()?.into_iter().peekable();
+        data.next_if(|field| field.tag == TAG_EXPLICIT_0);
+        data.next().ok_or_else(|| ocsp_error("response has no responder ID".to_string()))?;
+        let produced_at = time(data.next().ok_or_else(|| ocsp_error("response has no production time".to_string()))?)?;
+        let responses = data.next().ok_or_else(|| ocsp_error("response has no answers".to_string()))?.expect(TAG_SEQUENCE)?.children()?;
+        let extensions = data.next();
+
+        let responder = verify_responder(&basic, issuer, produced_at)?;
+        if let Some(expected) = nonce {
+            match response_nonce(extensions)? {
+                Some(echoed) if echoed == expected => {}
+                Some(_) => return Err(ocsp_error("response nonce does not match the request".to_string())),
+                None => return Err(ocsp_error("responder did not echo the request nonce".to_string())),
+            }
+        }
+
+        let certificate = parse_certificate(certificate)?;
+        let issuer = parse_certificate(issuer)?;
+        for single in responses {
+            let single = single.expect(TAG_SEQUENCE)?.children()?;
+            if single.len() < 3 || !matches_cert_id(&single[0], &certificate, &issuer)? {
+                continue;
+            }
+            let status = match single[1].tag {
+                TAG_STATUS_GOOD => OcspCertStatus::Good,
+                TAG_STATUS_REVOKED => OcspCertStatus::Revoked(time(first(&single[1].children()?, "revocation time")?)?),
+                TAG_STATUS_UNKNOWN => OcspCertStatus::Unknown,
+                tag => return Err(ocsp_error(format!("unknown certificate status {:#04x}", tag))),
+            };
+            let next_update = match single.get(3).filter(|field| field.tag == TAG_EXPLICIT_0) {
+                Some(field) => Some(time(first(&field.children()?, "next update")?)?),
+                None => None,
+            };
+            return Ok(Self {
+                status,
+                produced_at,
+                this_update: time(single[2])?,
+                next_update,
+                responder,
+                der: der.to_vec(),
+            });
+        }
+        Err(ocsp_error(format!("response does not cover {}", certificate.subject())))
+    }
+
+    /// Whether the response can still be relied on at `now`
+    pub fn is_fresh(&self, now: i64) -> bool {
+        match self.next_update {
+            Some(next_update) => now < next_update,
+            None => now - self.this_update <= MAX_RESPONSE_AGE,
+        }
+    }
+}
+
+/// Asks OCSP responders about station certificates, keeping fresh responses in a cache directory
+#[derive(Debug, Clone, Default)]
+pub struct OcspClient {
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl OcspClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cache(mut self, cache_dir: PathBuf) -> Self { self.cache_dir = Some(cache_dir); self }
+
+    /// A fresh cached response, or else a new one from the responder at `url`
+    pub async fn status(&self, url: &str, certificate: &[u8], issuer: &[u8]) -> Result<OcspResponse> {
+        if let Some(response) = self.cached(certificate, issuer) {
+            return Ok(response);
+        }
+        let response = self.fetch(url, certificate, issuer).await?;
+        if let Some(path) = self.cache_path(certificate) {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, &response.der)?;
+        }
+        Ok(response)
+    }
+
+    /// The cached response for `certificate`, if it is still fresh
+    pub fn cached(&self, certificate: &[u8], issuer: &[u8]) -> Option<OcspResponse> {
+        let der = std::fs::read(self.cache_path(certificate)?).ok()?;
+        let response = OcspResponse::verify(&der, certificate, issuer, None).ok()?;
+        response.is_fresh(unix_now()).then_some(response)
+    }
+
+    /// Ask the responder at `url`, bypassing the cache
+    pub async fn fetch(&self, url: &str, certificate: &[u8], issuer: &[u8]) -> Result<OcspResponse> {
+        let request = OcspRequest::new(certificate, issuer)?;
+        let der = http_post(url, "application/ocsp-request", &request.der).await?;
+        OcspResponse::verify(&der, certificate, issuer, Some(&request.nonce))
+    }
+
+    fn cache_path(&self, certificate: &[u8]) -> Option<PathBuf> {
+        let name = hex::encode(digest::digest(&digest::SHA256, certificate));
+        self.cache_dir.as_ref().map(|dir| dir.join(format!("{}.ocsp.der", name)))
+    }
+}
+
+/// Subject of the certificate that signed a `BasicOCSPResponse`: the issuer, or a responder it delegated to
+fn verify_responder(basic: &[Element], issuer_der: &[u8], produced_at: i64) -> Result<String> {
+    let (_, algorithm) = AlgorithmIdentifier::from_der(basic[1].raw)
+        .map_err(|e| ocsp_error(format!("invalid signature algorithm: {}", e)))?;
+    let (_, signature) = BitString::from_der(basic[2].raw)
+        .map_err(|e| ocsp_error(format!("invalid signature: {}", e)))?;
+    let signed_by = |signer: &X509Certificate| verify_signature(signer.public_key(), &algorithm, &signature, basic[0].raw).is_ok();
+
+    let issuer = parse_certificate(issuer_der)?;
+    if signed_by(&issuer) {
+        return Ok(issuer.subject().to_string());
+    }
+    let at = ASN1Time::from_timestamp(produced_at).map_err(|e| ocsp_error(e.to_string()))?;
+    let delegates = match basic.get(3) {
+        Some(certs) => first(&certs.expect(TAG_EXPLICIT_0)?.children()?, "responder certificates")?.children()?,
+        None => Vec::new(),
+    };
+    for delegate in delegates {
+        let responder = parse_certificate(delegate.raw)?;
+        let ocsp_signing = responder.extended_key_usage().ok().flatten().is_some_and(|usage| usage.value.ocsp_signing);
+        if ocsp_signing && responder.validity().is_valid_at(at) && issued(&responder, issuer_der) && signed_by(&responder) {
+            return Ok(responder.subject().to_string());
+        }
+    }
+    Err(ocsp_error(format!("response is not signed by {} or a responder it authorized", issuer.subject())))
+}
+
+/// Whether a response's CertID names `certificate` as issued by `issuer`
+fn matches_cert_id(cert_id: &Element, certificate: &X509Certificate, issuer: &X509Certificate) -> Result<bool> {
+    let fields = cert_id.expect(TAG_SEQUENCE)?.children()?;
+    if fields.len() < 4 {
+        return Err(ocsp_error("truncated certificate ID".to_string()));
+    }
+    let algorithm = match first(&fields[0].children()?, "hash algorithm")?.content {
+        OID_SHA1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+        OID_SHA256 => &digest::SHA256,
+        _ => return Ok(false),
+    };
+    let (name_hash, key_hash) = issuer_hashes(issuer, algorithm);
+    Ok(fields[1].content == name_hash.as_slice()
+        && fields[2].content == key_hash.as_slice()
+        && fields[3].expect(TAG_INTEGER)?.content == certificate.raw_serial())
+}
+
+/// Hashes of the issuer's name and public key, as a CertID carries them
+fn issuer_hashes(issuer: &X509Certificate, algorithm: &'static digest::Algorithm) -> (Vec<u8>, Vec<u8>) {
+    (
+        digest::digest(algorithm, issuer.subject().as_raw()).as_ref().to_vec(),
+        digest::digest(algorithm, &issuer.public_key().subject_public_key.data).as_ref().to_vec(),
+    )
+}
+
+/// The nonce in a response's `[1]` extensions, unwrapped from its OCTET STRING
+fn response_nonce(extensions: Option<Element>) -> Result<Option<Vec<u8>>> {
+    let Some(extensions) = extensions.filter(|field| field.tag == TAG_EXPLICIT_1) else { return Ok(None) };
+    for extension in first(&extensions.children()?, "extensions")?.children()? {
+        let fields = extension.children()?;
+        if first(&fields, "extension ID")?.content != OID_OCSP_NONCE {
+            continue;
+        }
+        let value = fields.last().copied().ok_or_else(|| ocsp_error("response has no nonce value".to_string()))?.expect(TAG_OCTET_STRING)?;
+        // RFC 8954 wraps the nonce in an OCTET STRING; older responders send it bare
+        return Ok(Some(match der::read(value.content) {
+            Ok((inner, rest)) if inner.tag == TAG_OCTET_STRING && rest.is_empty() => inner.content.to_vec(),
+            _ => value.content.to_vec(),
+        }));
+    }
+    Ok(None)
+}
+
+fn first<'a>(elements: &[Element<'a>], what: &str) -> Result<Element<'a>> {
+    elements.first().copied().ok_or_else(|| ocsp_error(format!("response has no {}", what)))
+}
+
+fn time(element: Element) -> Result<i64> {
+    ASN1Time::from_der(element.raw)
+        .map(|(_, time)| time.timestamp())
+        .map_err(|e| ocsp_error(format!("invalid time: {}", e)))
+}
+
+fn response_status_name(status: &[u8]) -> String {
+    match status {
+        [1] => "malformedRequest".to_string(),
+        [2] => "internalError".to_string(),
+        [3] => "tryLater".to_string(),
+        [5] => "sigRequired".to_string(),
+        [6] => "unauthorized".to_string(),
+        other => format!("status {}", hex::encode(other)),
+    }
+}
+
+/// POST `body` to an `http://` URL, as RFC 6960 responders are reached, and return the response body
+async fn http_post(url: &str, content_type: &str, body: &[u8]) -> Result<Vec<u8>> {
+    let location = url.strip_prefix("http://")
+        .ok_or_else(|| ocsp_error(format!("only http:// responders are supported, not {}", url)))?;
+    let (authority, path) = match location.find('/') {
+        Some(index) => (&location[..index], &location[index..]),
+        None => (location, "/"),
+    };
+    let address = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+
+    let exchange = async {
+        let mut stream = TcpStream::connect(&address).await?;
+        let header = format!(
+            "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            path, authority, content_type, body.len()
+        );
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(body).await?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, std::io::Error>(response)
+    };
+    let response = tokio::time::timeout(HTTP_TIMEOUT, exchange).await
+        .map_err(|_| ocsp_error(format!("{} did not answer within {} seconds", url, HTTP_TIMEOUT.as_secs())))??;
+
+    let header_end = response.windows(4).position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| ocsp_error(format!("malformed HTTP response from {}", url)))?;
+    let status_line = String::from_utf8_lossy(&response[..header_end]).lines().next().unwrap_or_default().to_string();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(ocsp_error(format!("{} answered {}", url, status_line)));
+    }
+    Ok(response[header_end + 4..].to_vec())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default()
+}
+
+fn ocsp_error(message: String) -> SecureEraseError {
+    SecureEraseError::CertificateVerificationFailed(format!("OCSP: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::x509::StationChain;
+
+    const STATION: &str = include_str!("../../tests/fixtures/pki/station-001.fullchain.pem");
+    const REVOKED_STATION: &str = include_str!("../../tests/fixtures/pki/station-002.fullchain.pem");
+    const GOOD_RESPONSE: &[u8] = include_bytes!("../../tests/fixtures/pki/station-001.ocsp.der");
+    const REVOKED_RESPONSE: &[u8] = include_bytes!("../../tests/fixtures/pki/station-002.ocsp.der");
+
+    #[test]
+    fn test_responses_map_certificate_status() {
+        let station = StationChain::from_pem(STATION).unwrap();
+        let response = OcspResponse::verify(GOOD_RESPONSE, &station.leaf, &station.intermediates[0], None).unwrap();
+        assert_eq!(response.status, OcspCertStatus::Good);
+        assert_eq!(response.responder, "CN=ocsp-responder");
+        assert!(response.is_fresh(response.this_update));
+
+        let revoked = StationChain::from_pem(REVOKED_STATION).unwrap();
+        let response = OcspResponse::verify(REVOKED_RESPONSE, &revoked.leaf, &revoked.intermediates[0], None).unwrap();
+        assert!(matches!(response.status, OcspCertStatus::Revoked(at) if at >= response.produced_at - 60));
+    }
+
+    #[test]
+    fn test_responses_are_bound_to_certificate_signer_and_nonce() {
+        let station = StationChain::from_pem(STATION).unwrap();
+        let revoked = StationChain::from_pem(REVOKED_STATION).unwrap();
+
+        // The response about station-002 says nothing about station-001
+        assert!(OcspResponse::verify(REVOKED_RESPONSE, &station.leaf, &station.intermediates[0], None).is_err());
+
+        // Only the issuing CA, not the root, authorizes responders
+        assert!(OcspResponse::verify(GOOD_RESPONSE, &station.leaf, &station.intermediates[1], None).is_err());
+
+        // The fixture was answered to another request's nonce
+        assert!(OcspResponse::verify(GOOD_RESPONSE, &station.leaf, &station.intermediates[0], Some(&[0u8; NONCE_LENGTH])).is_err());
+
+        let mut forged = GOOD_RESPONSE.to_vec();
+        let produced_at = forged.windows(3).position(|window| window == [0x18, 0x0f, b'2']).unwrap();
+        forged[produced_at + 5] ^= 0x01;
+        assert!(OcspResponse::verify(&forged, &station.leaf, &station.intermediates[0], None).is_err());
+
+        let request = OcspRequest::new(&revoked.leaf, &revoked.intermediates[0]).unwrap();
+        assert_eq!(request.nonce.len(), NONCE_LENGTH);
+        assert!(der::read(&request.der).unwrap().1.is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::error::{Result, SecureEraseError};
+use crate::crypto::{pem_blocks, to_pem, PublicKey};
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_OCSP;
+use x509_parser::prelude::*;
+use std::path::Path;
+
+/// Longest chain followed from a station certificate to its trust anchor
+const MAX_PATH_LENGTH: usize = 8;
+
+pub fn parse_certificate(der: &[u8]) -> Result<X509Certificate<'_>> {
+    X509Certificate::from_der(der)
+        .map(|(_, certificate)| certificate)
+        .map_err(|e| SecureEraseError::Crypto(format!("Invalid X.509 certificate: {}", e)))
+}
+
+/// DER of every certificate in a PEM text, in order
+pub fn pem_certificates(pem: &str) -> Result<Vec<Vec<u8>>> {
+    Ok(pem_blocks(pem.as_bytes())?
+        .into_iter()
+        .filter(|block| block.label == "CERTIFICATE")
+        .map(|block| block.contents)
+        .collect())
+}
+
+pub fn certificates_to_pem(certificates: &[Vec<u8>]) -> String {
+    certificates.iter().map(|der| to_pem("CERTIFICATE", der)).collect()
+}
+
+/// Station certificate with the intermediates that lead to its CA
+#[derive(Debug, Clone)]
+pub struct StationChain {
+    pub leaf: Vec<u8>,
+    pub intermediates: Vec<Vec<u8>>,
+}
+
+impl StationChain {
+    /// Leaf first, as `issue_station_cert.sh` writes `<station>.fullchain.pem`
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let mut certificates = pem_certificates(pem)?.into_iter();
+        let leaf = certificates.next()
+            .ok_or_else(|| SecureEraseError::Crypto("No certificate in station chain".to_string()))?;
+        Ok(Self { leaf, intermediates: certificates.collect() })
+    }
+
+    pub fn public_key(&self) -> Result<PublicKey> {
+        PublicKey::from_spki(parse_certificate(&self.leaf)?.public_key())
+    }
+
+    /// The intermediate that issued the station certificate, if the chain carries it
+    pub fn issuer(&self) -> Result<Option<&[u8]>> {
+        let leaf = parse_certificate(&self.leaf)?;
+        Ok(self.intermediates.iter().find(|candidate| issued(&leaf, candidate)).map(Vec::as_slice))
+    }
+
+    /// OCSP responder named in the station certificate's Authority Information Access
+    pub fn ocsp_url(&self) -> Result<Option<String>> {
+        let leaf = parse_certificate(&self.leaf)?;
+        Ok(leaf.extensions().iter().find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::AuthorityInfoAccess(access) => access.accessdescs.iter()
+                .filter(|description| description.access_method == OID_PKIX_ACCESS_DESCRIPTOR_OCSP)
+                .find_map(|description| uri(&description.access_location)),
+            _ => None,
+        }))
+    }
+
+    /// First CRL distribution point of the station certificate
+    pub fn crl_url(&self) -> Result<Option<String>> {
+        let leaf = parse_certificate(&self.leaf)?;
+        Ok(leaf.extensions().iter().find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::CRLDistributionPoints(points) => points.iter()
+                .filter_map(|point| match &point.distribution_point {
+                    Some(DistributionPointName::FullName(names)) => names.iter().find_map(uri),
+                    _ => None,
+                })
+                .next(),
+            _ => None,
+        }))
+    }
+}
+
+fn uri(name: &GeneralName) -> Option<String> {
+    match name {
+        GeneralName::URI(uri) => Some(uri.to_string()),
+        _ => None,
+    }
+}
+
+/// Path from a station certificate to a trust anchor, leaf first
+#[derive(Debug, Clone)]
+pub struct ValidatedPath {
+    pub subjects: Vec<String>,
+    /// DER of every certificate on the path, the anchor last
+    pub certificates: Vec<Vec<u8>>,
+}
+
+/// CA certificates a station certificate must chain to
+#[derive(Debug, Clone, Default)]
+pub struct TrustAnchors {
+    certificates: Vec<Vec<u8>>,
+}
+
+impl TrustAnchors {
+    /// Every certificate in a PEM file, such as `intermediate/certs/ca-chain.pem`
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_pem(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let certificates = pem_certificates(pem)?;
+        for der in &certificates {
+            parse_certificate(der)?;
+        }
+        if certificates.is_empty() {
+            return Err(SecureEraseError::Crypto("No trust anchor certificate found".to_string()));
+        }
+        Ok(Self { certificates })
+    }
+
+    /// Build and validate the path from `chain` to one of the anchors, as of `at` (Unix seconds).
+    ///
+    /// Every certificate on the path must be within its validity period at
+    /// `at` and signed by the next. CAs need `basicConstraints` with `cA` set,
+    /// `keyCertSign` when key usage is given and a path length that allows
+    /// the certificates below them. The station certificate must not be a CA,
+    /// must allow `digitalSignature` or `nonRepudiation`, and when it restricts
+    /// extended key usage, must allow code signing.
+    pub fn validate(&self, chain: &StationChain, at: i64) -> Result<ValidatedPath> {
+        let time = ASN1Time::from_timestamp(at)
+            .map_err(|e| path_error(format!("invalid validation time {}: {}", at, e)))?;
+        let leaf = parse_certificate(&chain.leaf)?;
+        check_validity(&leaf, time)?;
+        check_station(&leaf)?;
+
+        let mut path = vec![chain.leaf.clone()];
+        let mut subjects = vec![leaf.subject().to_string()];
+        let mut current = leaf;
+        loop {
+            if self.certificates.iter().any(|anchor| *anchor == path[path.len() - 1]) {
+                break;
+            }
+            if path.len() > MAX_PATH_LENGTH {
+                return Err(path_error(format!("no trust anchor within {} certificates", MAX_PATH_LENGTH)));
+            }
+
+            let anchor = self.certificates.iter().find(|der| issued(&current, der));
+            let (issuer_der, is_anchor) = match anchor {
+                Some(der) => (der.clone(), true),
+                None => match chain.intermediates.iter().find(|der| !path.contains(der) && issued(&current, der)) {
+                    Some(der) => (der.clone(), false),
+                    None => return Err(path_error(format!("no trusted issuer found for {}", current.subject()))),
+                },
+            };
+            let issuer = parse_certificate(&issuer_der)?;
+            if !is_anchor {
+                check_validity(&issuer, time)?;
+            }
+            // Every certificate in the path so far except the leaf is a CA below the issuer
+            let intermediates_below = (path.len() - 1) as u32;
+            check_ca(&issuer, intermediates_below)?;
+
+            subjects.push(issuer.subject().to_string());
+            path.push(issuer_der);
+            if is_anchor {
+                break;
+            }
+            current = parse_certificate(&path[path.len() - 1])?;
+        }
+
+        Ok(ValidatedPath { subjects, certificates: path })
+    }
+}
+
+/// Whether `issuer` names and signed `certificate`
+pub(crate) fn issued(certificate: &X509Certificate, issuer: &[u8]) -> bool {
+    match parse_certificate(issuer) {
+        Ok(issuer) => issuer.subject().as_raw() == certificate.issuer().as_raw()
+            && certificate.verify_signature(Some(issuer.public_key())).is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn check_validity(certificate: &X509Certificate, time: ASN1Time) -> Result<()> {
+    if certificate.validity().is_valid_at(time) {
+        return Ok(());
+    }
+    Err(path_error(format!(
+        "{} is only valid from {} to {}",
+        certificate.subject(), certificate.validity().not_before, certificate.validity().not_after
+    )))
+}
+
+fn check_station(certificate: &X509Certificate) -> Result<()> {
+    let subject = certificate.subject();
+    check_critical_extensions(certificate)?;
+    if certificate.basic_constraints().map_err(|e| path_error(e.to_string()))?.is_some_and(|bc| bc.value.ca) {
+        return Err(path_error(format!("{} is a CA certificate, not a station certificate", subject)));
+    }
+    if let Some(usage) = certificate.key_usage().map_err(|e| path_error(e.to_string()))? {
+        if !usage.value.digital_signature() && !usage.value.non_repudiation() {
+            return Err(path_error(format!("{} does not allow digital signatures", subject)));
+        }
+    }
+    if let Some(usage) = certificate.extended_key_usage().map_err(|e| path_error(e.to_string()))? {
+        if !usage.value.any && !usage.value.code_signing {
+            return Err(path_error(format!("{} does not allow code signing", subject)));
+        }
+    }
+    Ok(())
+}
+
+fn check_ca(certificate: &X509Certificate, intermediates_below: u32) -> Result<()> {
+    let subject = certificate.subject();
+    check_critical_extensions(certificate)?;
+    let constraints = certificate.basic_constraints().map_err(|e| path_error(e.to_string()))?
+        .filter(|bc| bc.value.ca)
+        .ok_or_else(|| path_error(format!("{} is not a CA", subject)))?;
+    if constraints.value.path_len_constraint.is_some_and(|limit| intermediates_below > limit) {
+        return Err(path_error(format!("{} allows fewer intermediate CAs below it", subject)));
+    }
+    if let Some(usage) = certificate.key_usage().map_err(|e| path_error(e.to_string()))? {
+        if !usage.value.key_cert_sign() {
+            return Err(path_error(format!("{} may not sign certificates", subject)));
+        }
+    }
+    Ok(())
+}
+
+/// Critical extensions this validator does not understand make a certificate unusable
+fn check_critical_extensions(certificate: &X509Certificate) -> Result<()> {
+    match certificate.extensions().iter().find(|extension| extension.critical && extension.parsed_extension().unsupported()) {
+        Some(extension) => Err(path_error(format!(
+            "{} has unsupported critical extension {}", certificate.subject(), extension.oid.to_id_string()
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn path_error(message: String) -> SecureEraseError {
+    SecureEraseError::CertificateVerificationFailed(format!("certificate path: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CA_CHAIN: &str = include_str!("../../tests/fixtures/pki/ca-chain.pem");
+    const ROOT: &str = include_str!("../../tests/fixtures/pki/root.cert.pem");
+    const STATION: &str = include_str!("../../tests/fixtures/pki/station-001.fullchain.pem");
+    const TLS_SERVER: &str = include_str!("../../tests/fixtures/pki/tls-server.fullchain.pem");
+    const NESTED_STATION: &str = include_str!("../../tests/fixtures/pki/station-003.fullchain.pem");
+
+    fn now() -> i64 {
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64
+    }
+
+    #[test]
+    fn test_station_chain_validates_to_root() {
+        let chain = StationChain::from_pem(STATION).unwrap();
+        assert_eq!(chain.intermediates.len(), 2);
+        assert_eq!(chain.ocsp_url().unwrap().as_deref(), Some("http://127.0.0.1:8888"));
+        assert_eq!(chain.crl_url().unwrap().as_deref(), Some("http://127.0.0.1:8889/intermediate.crl.pem"));
+
+        let path = TrustAnchors::from_pem(ROOT).unwrap().validate(&chain, now()).unwrap();
+        assert_eq!(path.subjects, vec!["CN=station-001", "CN=Test Issuing CA", "CN=Test Root CA"]);
+
+        // With the whole CA chain trusted, the path stops at the intermediate
+        let path = TrustAnchors::from_pem(CA_CHAIN).unwrap().validate(&chain, now()).unwrap();
+        assert_eq!(path.subjects.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_paths_are_refused() {
+        let anchors = TrustAnchors::from_pem(ROOT).unwrap();
+        let chain = StationChain::from_pem(STATION).unwrap();
+
+        // Before the certificates were issued
+        assert!(anchors.validate(&chain, 0).is_err());
+
+        // Without the intermediate there is no path
+        let leaf_only = StationChain { leaf: chain.leaf.clone(), intermediates: Vec::new() };
+        assert!(anchors.validate(&leaf_only, now()).is_err());
+
+        // A TLS server certificate is not a signing certificate
+        let error = anchors.validate(&StationChain::from_pem(TLS_SERVER).unwrap(), now()).unwrap_err();
+        assert!(error.to_string().contains("code signing"));
+
+        // A CA cannot sign wipe certificates in place of a station
+        let intermediate = StationChain { leaf: chain.intermediates[0].clone(), intermediates: Vec::new() };
+        assert!(anchors.validate(&intermediate, now()).is_err());
+    }
+
+    #[test]
+    fn test_path_length_constraint_is_enforced() {
+        // Test Constrained CA has pathlen:0 but Test Nested CA sits below it
+        let chain = StationChain::from_pem(NESTED_STATION).unwrap();
+        let error = TrustAnchors::from_pem(ROOT).unwrap().validate(&chain, now()).unwrap_err();
+        assert!(error.to_string().contains("CN=Test Constrained CA allows fewer intermediate CAs"));
+    }
+}
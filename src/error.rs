@@ -27,6 +27,9 @@ pub enum SecureEraseError {
     #[error("Secure erase not supported on this device")]
     SecureEraseNotSupported,
     
+    #[error("Device security is frozen: {0}")]
+    DeviceFrozen(String),
+    
     #[error("Wipe operation failed: {0}")]
     WipeFailed(String),
     
@@ -44,6 +47,18 @@ pub enum SecureEraseError {
     
     #[error("Hidden area access failed: {0}")]
     HiddenAreaAccessFailed(String),
+    
+    #[error("Refusing to wipe: {0}")]
+    UnsafeTarget(String),
+    
+    #[error("Dry run: nothing was written to {0}")]
+    DryRun(String),
+    
+    #[error("Operation cancelled")]
+    Cancelled,
+    
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(String),
 }
 
 /// Result type alias for the secure erase tool
@@ -1,12 +1,16 @@
-use crate::core::{StorageDevice, DeviceType, EraseMode, WipeResult};
-use crate::error::Result;
-use std::path::PathBuf;
-use std::time::SystemTime;
+pub mod core;
+pub mod crypto;
+pub mod certificates;
+pub mod platform;
+pub mod cli;
+pub mod error;
+pub mod utils;
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use tempfile::TempDir;
+    use crate::core::{StorageDevice, DeviceType, EraseMode, WipeResult};
+    use std::path::PathBuf;
+    use std::time::SystemTime;
 
     #[tokio::test]
     async fn test_device_creation() {
@@ -20,6 +24,8 @@ mod tests {
             supports_secure_erase: true,
             supports_trim: false,
             hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: Default::default(),
         };
 
         assert_eq!(device.name, "Test Device");
@@ -39,6 +45,8 @@ mod tests {
             supports_secure_erase: true,
             supports_trim: false,
             hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: Default::default(),
         };
 
         let start_time = SystemTime::now();
@@ -47,12 +55,24 @@ mod tests {
         let result = WipeResult {
             device: device.clone(),
             mode: EraseMode::Full,
+            method: None,
             start_time,
             end_time,
             duration_seconds: 60,
             bytes_written: device.size,
             verification_passed: true,
+            verification: None,
+            expected_contents: Default::default(),
+            unwritable_ranges: Vec::new(),
+            unwritable_bytes: 0,
+            hidden_areas: Vec::new(),
+            hardware_commands: Vec::new(),
+            discards: Vec::new(),
+            crypto_erases: Vec::new(),
+            safety_overrides: Vec::new(),
             errors: Vec::new(),
+            passes: Vec::new(),
+            interruptions: Vec::new(),
         };
 
         assert_eq!(result.mode, EraseMode::Full);
@@ -10,7 +10,14 @@ mod cli;
 mod error;
 mod utils;
 
-use error::Result;
+use error::{Result, SecureEraseError};
+use crate::core::batch::BatchOptions;
+use crate::core::hidden_areas::HiddenAreaRestore;
+use crate::core::pipeline::WipePipeline;
+use crate::core::plan::PlanFormat;
+use crate::core::probe::RecordedProbe;
+use crate::core::safety::{AllowList, SafetyOptions, SystemState};
+use crate::crypto::{KeyFormat, SignatureAlgorithm};
 
 /// Secure Disk Erasure Tool - Cross-platform secure data sanitization
 #[derive(Parser)]
@@ -27,30 +34,103 @@ struct Cli {
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// List available storage devices
     List {
         /// Show detailed device information
         #[arg(short, long)]
         detailed: bool,
+        
+        /// Also save the scan to a file, for --devices-from on another machine
+        #[arg(long)]
+        record: Option<PathBuf>,
     },
-    /// Securely erase a storage device
+    /// Securely erase one or more storage devices
     Wipe {
-        /// Target device path (e.g., /dev/sda, \\.\PhysicalDrive0)
+        /// Target device path (e.g., /dev/sda, \\.\PhysicalDrive0); repeat to wipe several devices at once
         #[arg(short, long)]
-        device: PathBuf,
+        device: Vec<PathBuf>,
+        
+        /// Also wipe every device matching a filter, e.g. type=hdd,model=WDC,min-size=500G
+        #[arg(long)]
+        select: Option<String>,
         
-        /// Erase mode: quick, full, or advanced
+        /// Wipe method name: quick, full, advanced, dod-3, dod-7, gutmann, or a loaded method
         #[arg(short, long, default_value = "full")]
         mode: String,
         
+        /// Method definition file or directory (TOML/JSON) to load in addition to built-ins
+        #[arg(long)]
+        methods: Option<PathBuf>,
+        
         /// Generate certificate after wipe
         #[arg(short, long)]
         certificate: bool,
         
-        /// Output directory for certificates
+        /// Private key certificates are signed with (PKCS#8 PEM or DER, optionally encrypted, or PKCS#1 PEM)
+        #[arg(long, default_value = "private_key.pem")]
+        signing_key: PathBuf,
+        
+        /// File holding the passphrase of an encrypted --signing-key
+        #[arg(long, requires = "certificate")]
+        signing_key_passphrase_file: Option<PathBuf>,
+        
+        /// X.509 certificate of --signing-key followed by its CA chain (PEM), embedded in certificates
+        #[arg(long, requires = "certificate")]
+        station_cert: Option<PathBuf>,
+        
+        /// Staple the station certificate's OCSP status into certificates so they verify offline
+        #[arg(long, requires = "station_cert")]
+        staple_ocsp: bool,
+        
+        /// Output directory for certificates and the checkpoint journal
         #[arg(short, long, default_value = ".")]
         output: PathBuf,
+        
+        /// Continue an interrupted wipe from its checkpoint journal in the output directory
+        #[arg(long)]
+        resume: bool,
+        
+        /// Maximum number of devices wiped at the same time
+        #[arg(long, default_value_t = 4)]
+        max_concurrent: usize,
+        
+        /// Per-device write limit in MB/s
+        #[arg(long)]
+        bandwidth_limit: Option<u64>,
+        
+        /// Remove HPA/DCO before overwriting: temporary (until power cycle) or permanent
+        #[arg(long, value_parser = ["temporary", "permanent"])]
+        restore_hidden: Option<String>,
+        
+        /// File holding the PSID or admin password used by crypto erase steps
+        #[arg(long)]
+        sed_credential_file: Option<PathBuf>,
+        
+        /// Skip typed confirmation for devices on the --allow-list
+        #[arg(short, long, requires = "allow_list")]
+        yes: bool,
+        
+        /// File of serial numbers, WWNs or device paths that may be wiped with --yes
+        #[arg(long)]
+        allow_list: Option<PathBuf>,
+        
+        /// Wipe devices that are mounted or part of a RAID/LVM/ZFS/device-mapper set (never the system disk or active swap)
+        #[arg(long)]
+        allow_in_use: bool,
+        
+        /// Print the execution plan instead of wiping; nothing is written
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Format of the --dry-run plan
+        #[arg(long, value_parser = ["text", "json"], default_value = "text", requires = "dry_run")]
+        plan_format: String,
+        
+        /// Plan against devices saved by `list --record` instead of scanning
+        #[arg(long, requires = "dry_run")]
+        devices_from: Option<PathBuf>,
     },
     /// Verify a wipe certificate
     Verify {
@@ -61,12 +141,44 @@ enum Commands {
         /// Public key for verification
         #[arg(short, long)]
         public_key: Option<PathBuf>,
+        
+        /// CA certificates (PEM) that station certificates must chain to
+        #[arg(long)]
+        trust_anchor: Option<PathBuf>,
+        
+        /// CRL (PEM or DER) to check the station certificate against
+        #[arg(long)]
+        crl: Option<PathBuf>,
+        
+        /// Directory of CRLs downloaded from the certificates' CRL URLs
+        #[arg(long, conflicts_with = "crl")]
+        crl_cache: Option<PathBuf>,
+        
+        /// Directory OCSP responses are cached in between verifications
+        #[arg(long)]
+        ocsp_cache: Option<PathBuf>,
+        
+        /// Do not contact OCSP responders; rely on stapled or cached responses
+        #[arg(long)]
+        offline: bool,
     },
     /// Generate signing key pair
     GenerateKeys {
         /// Output directory for keys
         #[arg(short, long, default_value = ".")]
         output: PathBuf,
+        
+        /// Key algorithm
+        #[arg(long, value_parser = ["ed25519", "ecdsa-p256"], default_value = "ed25519")]
+        algorithm: String,
+        
+        /// Key file encoding: PKCS#8 and SubjectPublicKeyInfo, as PEM or DER
+        #[arg(long, value_parser = ["pem", "der"], default_value = "pem")]
+        format: String,
+        
+        /// File holding a passphrase to encrypt the private key with (PBES2, scrypt)
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
     },
 }
 
@@ -94,20 +206,54 @@ async fn main() -> Result<()> {
     }
     
     match cli.command {
-        Commands::List { detailed } => {
-            cli::list_devices(detailed).await?;
+        Commands::List { detailed, record } => {
+            cli::list_devices(&WipePipeline::system(), detailed, record).await?;
         }
-        Commands::Wipe { device, mode, certificate, output } => {
-            cli::wipe_device(device, mode, certificate, output).await?;
+        Commands::Wipe { device, select, mode, methods, certificate, signing_key, signing_key_passphrase_file, station_cert, staple_ocsp, output, resume, max_concurrent, bandwidth_limit, restore_hidden, sed_credential_file, yes, allow_list, allow_in_use, dry_run, plan_format, devices_from } => {
+            let bandwidth_limit = bandwidth_limit.map(|mb| mb * 1024 * 1024);
+            let restore_hidden = restore_hidden.as_deref().and_then(HiddenAreaRestore::from_name);
+            let sed_credential = sed_credential_file.as_deref().map(read_secret).transpose()?;
+            let safety = SafetyOptions {
+                assume_yes: yes,
+                allow_list: allow_list.as_deref().map(AllowList::load).transpose()?,
+                allow_in_use,
+            };
+            let dry_run = if dry_run { PlanFormat::from_name(&plan_format) } else { None };
+            let certificate = certificate.then_some(cli::CertificateSigning {
+                signing_key,
+                station_certificate: station_cert,
+                staple_ocsp,
+                passphrase: signing_key_passphrase_file.as_deref().map(read_secret).transpose()?,
+            });
+            let pipeline = match devices_from {
+                Some(path) => WipePipeline::new(Box::new(RecordedProbe::new(path)), SystemState::probe()),
+                None => WipePipeline::system(),
+            };
+            if device.len() == 1 && select.is_none() {
+                let device = device.into_iter().next().unwrap();
+                cli::wipe_device(&pipeline, device, mode, methods, restore_hidden, sed_credential, safety, dry_run, certificate, output, resume, bandwidth_limit).await?;
+            } else if device.is_empty() && select.is_none() {
+                return Err(SecureEraseError::DeviceNotFound("no device given (use --device or --select)".to_string()));
+            } else {
+                let options = BatchOptions { max_concurrent, bandwidth_limit };
+                cli::wipe_devices(&pipeline, device, select, mode, methods, restore_hidden, sed_credential, safety, dry_run, certificate, output, resume, options).await?;
+            }
         }
-        Commands::Verify { certificate, public_key } => {
-            cli::verify_certificate(certificate, public_key).await?;
+        Commands::Verify { certificate, public_key, trust_anchor, crl, crl_cache, ocsp_cache, offline } => {
+            cli::verify_certificate(certificate, public_key, trust_anchor, crl, crl_cache, ocsp_cache, offline).await?;
         }
-        Commands::GenerateKeys { output } => {
-            cli::generate_keys(output).await?;
+        Commands::GenerateKeys { output, algorithm, format, passphrase_file } => {
+            let algorithm = SignatureAlgorithm::from_key_type(&algorithm).unwrap_or(SignatureAlgorithm::Ed25519);
+            let format = KeyFormat::from_name(&format).unwrap_or_default();
+            cli::generate_keys(output, algorithm, format, passphrase_file.as_deref().map(read_secret).transpose()?).await?;
         }
     }
     
     info!("Operation completed successfully");
     Ok(())
 }
+
+/// A passphrase or credential kept in a file, without its trailing newline
+fn read_secret(path: &std::path::Path) -> Result<String> {
+    Ok(std::fs::read_to_string(path)?.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -1,137 +1,42 @@
-use crate::error::{Result, SecureEraseError};
-use crate::core::{StorageDevice, DeviceType, EraseMode, WipeResult};
+use crate::error::Result;
+use crate::core::{StorageDevice, DeviceType};
+use crate::core::probe::DeviceProbe;
 use std::path::PathBuf;
-use std::time::SystemTime;
-use log::{info, warn, error};
+use log::info;
 
-/// Android-specific implementation of disk operations
-/// Note: Android has significant limitations for unprivileged apps
-pub async fn list_devices() -> Result<Vec<StorageDevice>> {
-    info!("Scanning Android storage devices...");
-    
-    let mut devices = Vec::new();
-    
-    // On Android, we can only access certain paths
-    // This is a simplified implementation for demonstration
-    
-    // Check for external storage
-    if let Ok(external_storage) = std::env::var("EXTERNAL_STORAGE") {
-        let device = StorageDevice {
-            path: PathBuf::from(external_storage),
-            name: "External Storage".to_string(),
-            size: 0, // Would need to query actual size
-            device_type: DeviceType::SSD,
-            model: Some("Android External Storage".to_string()),
-            serial: None,
-            supports_secure_erase: false,
-            supports_trim: false,
-            hidden_areas: Vec::new(),
-        };
-        devices.push(device);
-    }
-    
-    // Check for internal storage
-    if let Ok(internal_storage) = std::env::var("ANDROID_STORAGE") {
-        let device = StorageDevice {
-            path: PathBuf::from(internal_storage),
-            name: "Internal Storage".to_string(),
-            size: 0, // Would need to query actual size
-            device_type: DeviceType::SSD,
-            model: Some("Android Internal Storage".to_string()),
-            serial: None,
-            supports_secure_erase: false,
-            supports_trim: false,
-            hidden_areas: Vec::new(),
-        };
-        devices.push(device);
-    }
-    
-    Ok(devices)
-}
+/// Android device discovery.
+///
+/// Unprivileged apps only see the storage roots the system exports, so those
+/// are the only targets.
+pub struct AndroidProbe;
 
-/// Securely erase a device on Android
-pub async fn wipe_device(device: &StorageDevice, mode: EraseMode) -> Result<WipeResult> {
-    info!("Starting Android wipe operation on device: {}", device.path.display());
-    
-    let start_time = SystemTime::now();
-    
-    // Android limitations: we can only wipe app data and suggest factory reset
-    match mode {
-        EraseMode::Quick => {
-            quick_wipe_android(device).await?;
-        }
-        EraseMode::Full => {
-            full_wipe_android(device).await?;
-        }
-        EraseMode::Advanced => {
-            advanced_wipe_android(device).await?;
-        }
+impl DeviceProbe for AndroidProbe {
+    fn devices(&self) -> Result<Vec<StorageDevice>> {
+        info!("Scanning Android storage devices...");
+        
+        let roots = [
+            ("EXTERNAL_STORAGE", "External Storage", "Android External Storage"),
+            ("ANDROID_STORAGE", "Internal Storage", "Android Internal Storage"),
+        ];
+        let devices = roots.iter()
+            .filter_map(|(variable, name, model)| {
+                let path = std::env::var(variable).ok()?;
+                Some(StorageDevice {
+                    path: PathBuf::from(path),
+                    name: name.to_string(),
+                    size: 0, // Would need to query actual size
+                    device_type: DeviceType::SSD,
+                    model: Some(model.to_string()),
+                    serial: None,
+                    supports_secure_erase: false,
+                    supports_trim: false,
+                    hidden_areas: Vec::new(),
+                    attributes: Default::default(),
+                    layout: Default::default(),
+                })
+            })
+            .collect();
+        
+        Ok(devices)
     }
-    
-    let end_time = SystemTime::now();
-    let duration = end_time.duration_since(start_time)
-        .map_err(|e| SecureEraseError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
-    
-    // Verify the wipe
-    let verification_passed = verify_wipe(device).await.unwrap_or(false);
-    
-    Ok(WipeResult {
-        device: device.clone(),
-        mode,
-        start_time,
-        end_time,
-        duration_seconds: duration.as_secs(),
-        bytes_written: 0, // Limited on Android
-        verification_passed,
-        errors: vec!["Android has limited wipe capabilities".to_string()],
-    })
-}
-
-/// Quick wipe on Android (app data only)
-async fn quick_wipe_android(device: &StorageDevice) -> Result<()> {
-    info!("Performing quick wipe on Android");
-    
-    // On Android, we can only wipe app-specific data
-    // This would involve clearing app data directories
-    
-    warn!("Android quick wipe is limited to app data only");
-    Ok(())
-}
-
-/// Full wipe on Android (suggest factory reset)
-async fn full_wipe_android(device: &StorageDevice) -> Result<()> {
-    info!("Performing full wipe on Android");
-    
-    // On Android, full wipe requires factory reset
-    // We can only guide the user through the process
-    
-    warn!("Android full wipe requires factory reset - user action needed");
-    Ok(())
-}
-
-/// Advanced wipe on Android (device owner mode)
-async fn advanced_wipe_android(device: &StorageDevice) -> Result<()> {
-    info!("Performing advanced wipe on Android");
-    
-    // Advanced wipe on Android requires device owner privileges
-    // This would involve using Device Administration APIs
-    
-    warn!("Android advanced wipe requires device owner privileges");
-    Ok(())
-}
-
-/// Verify that a device has been properly wiped
-pub async fn verify_wipe(device: &StorageDevice) -> Result<bool> {
-    info!("Verifying wipe on Android device: {}", device.path.display());
-    
-    // Android verification is limited
-    Ok(true) // Placeholder
-}
-
-/// Check if device supports hardware secure erase
-pub async fn supports_secure_erase(device: &StorageDevice) -> Result<bool> {
-    info!("Checking secure erase support for Android device: {}", device.path.display());
-    
-    // Android doesn't support hardware secure erase for unprivileged apps
-    Ok(false)
 }
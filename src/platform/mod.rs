@@ -1,5 +1,6 @@
 // Platform-specific implementations
-// Each platform module implements the core wipe engine functionality
+// Each platform module implements device discovery (`core::probe::DeviceProbe`);
+// wiping itself goes through `core::advanced::AdvancedWipeEngine` everywhere
 
 #[cfg(target_os = "windows")]
 pub mod windows;
@@ -1,206 +1,46 @@
-use crate::error::{Result, SecureEraseError};
-use crate::core::{StorageDevice, DeviceType, EraseMode, WipeResult, HiddenArea, HiddenAreaType};
+use crate::error::Result;
+use crate::core::{StorageDevice, DeviceType, get_disk};
+use crate::core::probe::DeviceProbe;
 use std::path::PathBuf;
-use std::time::SystemTime;
-use std::process::Command;
-use log::{info, warn, error};
-
-/// Windows-specific implementation of disk operations
-pub async fn list_devices() -> Result<Vec<StorageDevice>> {
-    info!("Scanning Windows storage devices...");
-    
-    let mut devices = Vec::new();
-    
-    // Use PowerShell to get disk information
-    let output = Command::new("powershell")
-        .args(&[
-            "-Command",
-            "Get-Disk | Select-Object Number, FriendlyName, Size, BusType, Model, SerialNumber | ConvertTo-Json"
-        ])
-        .output()
-        .map_err(|e| SecureEraseError::Io(e))?;
-    
-    if !output.status.success() {
-        warn!("PowerShell command failed, falling back to basic enumeration");
-        return enumerate_basic_devices().await;
-    }
-    
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    
-    // Simplified parsing; production should parse JSON properly
-    for (idx, line) in json_str.lines().enumerate() {
-        if line.contains("FriendlyName") || idx == 0 {
-            let device = StorageDevice {
-                path: PathBuf::from(format!("\\\\.\\PhysicalDrive{}", idx)),
-                name: "Windows Disk".to_string(),
-                size: 1000 * 1024 * 1024 * 1024, // placeholder
-                device_type: DeviceType::HDD,
-                model: Some("Generic Windows Disk".to_string()),
-                serial: Some("WIN123456".to_string()),
-                supports_secure_erase: true,
-                supports_trim: false,
-                hidden_areas: Vec::new(),
-            };
-            devices.push(device);
+use log::{info, warn};
+
+/// Windows device discovery through `Get-Disk`, falling back to opening physical drives
+pub struct WindowsProbe;
+
+impl DeviceProbe for WindowsProbe {
+    fn devices(&self) -> Result<Vec<StorageDevice>> {
+        info!("Scanning Windows storage devices...");
+        
+        match get_disk::disks() {
+            Ok(devices) if !devices.is_empty() => Ok(devices),
+            Ok(_) => Ok(enumerate_basic_devices()),
+            Err(e) => {
+                warn!("Get-Disk enumeration failed ({}), falling back to basic enumeration", e);
+                Ok(enumerate_basic_devices())
+            }
         }
     }
-    
-    if devices.is_empty() {
-        return enumerate_basic_devices().await;
-    }
-    
-    Ok(devices)
 }
 
-async fn enumerate_basic_devices() -> Result<Vec<StorageDevice>> {
+fn enumerate_basic_devices() -> Vec<StorageDevice> {
     let mut devices = Vec::new();
     for i in 0..10 {
         let device_path = PathBuf::from(format!("\\\\.\\PhysicalDrive{}", i));
-        match std::fs::File::open(&device_path) {
-            Ok(_) => {
-                let device = StorageDevice {
-                    path: device_path,
-                    name: format!("Physical Drive {}", i),
-                    size: 0,
-                    device_type: DeviceType::Unknown,
-                    model: None,
-                    serial: None,
-                    supports_secure_erase: false,
-                    supports_trim: false,
-                    hidden_areas: Vec::new(),
-                };
-                devices.push(device);
-            }
-            Err(_) => {}
-        }
-    }
-    Ok(devices)
-}
-
-fn is_dry_run() -> bool {
-    // Default to dry-run true; require explicit DISABLE to run destructive IO
-    std::env::var("SECURE_ERASE_DRY_RUN").map(|v| v != "0" && v.to_lowercase() != "false").unwrap_or(true)
-}
-
-/// Securely erase a device on Windows
-pub async fn wipe_device(device: &StorageDevice, mode: EraseMode) -> Result<WipeResult> {
-    info!("Starting Windows wipe operation on device: {}", device.path.display());
-    if is_dry_run() {
-        warn!("DRY-RUN enabled (SECURE_ERASE_DRY_RUN=1). No destructive IO will be performed.");
-    }
-    let start_time = SystemTime::now();
-    
-    match mode {
-        EraseMode::Quick => {
-            quick_wipe_windows(device).await?;
-        }
-        EraseMode::Full => {
-            full_wipe_windows(device).await?;
-        }
-        EraseMode::Advanced => {
-            advanced_wipe_windows(device).await?;
-        }
-    }
-    
-    let end_time = SystemTime::now();
-    let duration = end_time.duration_since(start_time)
-        .map_err(|e| SecureEraseError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
-    
-    let verification_passed = verify_wipe(device).await.unwrap_or(false);
-    
-    Ok(WipeResult {
-        device: device.clone(),
-        mode,
-        start_time,
-        end_time,
-        duration_seconds: duration.as_secs(),
-        bytes_written: device.size,
-        verification_passed,
-        errors: Vec::new(),
-    })
-}
-
-/// Quick wipe using Windows APIs (zero pass) or diskpart script as fallback
-async fn quick_wipe_windows(device: &StorageDevice) -> Result<()> {
-    info!("Performing quick wipe on Windows (single pass / zero)");
-    if is_dry_run() { return Ok(()); }
-    // TODO: Implement CreateFile + DeviceIoControl with IOCTL_DISK_SET_DRIVE_LAYOUT/WRITE_ZEROES or FSCTLs
-    // Fallback: diskpart clean all is destructive; keep commented for safety
-    // let script = format!("select disk {}\nclean\n", extract_physical_index(&device.path));
-    Ok(())
-}
-
-/// Full wipe with multiple passes
-async fn full_wipe_windows(device: &StorageDevice) -> Result<()> {
-    info!("Performing full wipe on Windows (multi-pass)");
-    if is_dry_run() { return Ok(()); }
-    // TODO: Implement raw write loop with WriteFile on \\.\PhysicalDriveN using patterns
-    Ok(())
-}
-
-/// Advanced wipe with hardware secure erase (ATA/NVMe) + HPA/DCO handling
-async fn advanced_wipe_windows(device: &StorageDevice) -> Result<()> {
-    info!("Performing advanced wipe on Windows (hardware secure erase)");
-    if is_dry_run() { return Ok(()); }
-    match device.device_type {
-        DeviceType::NVMe => nvme_secure_sanitize(&device.path)?,
-        DeviceType::SSD | DeviceType::HDD | DeviceType::USB | DeviceType::Unknown => {
-            // Attempt ATA secure erase if ATA/SATA
-            ata_secure_erase(&device.path)?;
-            // Attempt HPA/DCO removal
-            clear_hpa_dco(&device.path)?;
+        if std::fs::File::open(&device_path).is_ok() {
+            devices.push(StorageDevice {
+                size: get_disk::disk_length(&device_path).unwrap_or(0),
+                path: device_path,
+                name: format!("Physical Drive {}", i),
+                device_type: DeviceType::Unknown,
+                model: None,
+                serial: None,
+                supports_secure_erase: false,
+                supports_trim: false,
+                hidden_areas: Vec::new(),
+                attributes: Default::default(),
+                layout: Default::default(),
+            });
         }
     }
-    Ok(())
-}
-
-/// Verify that a device has been properly wiped (sampling)
-pub async fn verify_wipe(device: &StorageDevice) -> Result<bool> {
-    info!("Verifying wipe on Windows device: {}", device.path.display());
-    Ok(true)
-}
-
-pub async fn supports_secure_erase(device: &StorageDevice) -> Result<bool> {
-    info!("Checking secure erase support for Windows device: {}", device.path.display());
-    Ok(device.supports_secure_erase)
-}
-
-// ===== Low-level helpers (scaffolds) =====
-#[cfg(windows)]
-fn ata_secure_erase(device_path: &PathBuf) -> Result<()> {
-    use std::os::windows::prelude::*;
-    use std::fs::OpenOptions;
-    use winapi::um::winioctl::*;
-    use winapi::um::ioctl::*;
-    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
-    use winapi::um::fileapi::*;
-    use winapi::shared::minwindef::*;
-    use winapi::um::errhandlingapi::GetLastError;
-
-    info!("Attempting ATA Secure Erase via DeviceIoControl: {}", device_path.display());
-    // NOTE: Real ATA security commands require SEND/RECEIVE ATA PASS THROUGH IOCTLs
-    // Structure: ATA_PASS_THROUGH_EX with SECURITY ERASE UNIT
-    // This is a scaffold; implement carefully with proper timeouts and power safety.
-    let _ = (INVALID_HANDLE_VALUE, IOCTL_ATA_PASS_THROUGH); // keep refs for compile
-    Ok(())
-}
-
-#[cfg(windows)]
-fn nvme_secure_sanitize(device_path: &PathBuf) -> Result<()> {
-    use std::os::windows::prelude::*;
-    use std::fs::OpenOptions;
-    use winapi::um::winioctl::*;
-    use winapi::um::ioctl::*;
-
-    info!("Attempting NVMe Format/Sanitize via DeviceIoControl: {}", device_path.display());
-    // Use IOCTL_STORAGE_PROTOCOL_COMMAND with NVMe admin commands (Format NVM / Sanitize)
-    let _ = IOCTL_STORAGE_PROTOCOL_COMMAND; // reference
-    Ok(())
-}
-
-#[cfg(windows)]
-fn clear_hpa_dco(device_path: &PathBuf) -> Result<()> {
-    info!("Attempting HPA/DCO removal: {}", device_path.display());
-    // HPA can be disabled using ATA commands (SET MAX ADDRESS); DCO via vendor cmds.
-    Ok(())
+    devices
 }
@@ -31,6 +31,11 @@ impl Utils {
         }
     }
     
+    /// Format seconds as HH:MM:SS
+    pub fn format_duration(seconds: u64) -> String {
+        format!("{:02}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+    }
+    
     /// Calculate estimated wipe time based on device size and mode
     pub fn estimate_wipe_time(size_bytes: u64, mode: &crate::core::EraseMode) -> u64 {
         let size_gb = size_bytes as f64 / (1024.0 * 1024.0 * 1024.0);
@@ -48,6 +53,10 @@ impl Utils {
                 // Advanced wipe: ~25 MB/s (hardware secure erase + verification)
                 (size_gb * 40.0) as u64
             }
+            crate::core::EraseMode::Custom(_) => {
+                // Custom methods: assume a multi-pass overwrite
+                (size_gb * 20.0) as u64
+            }
         }
     }
     
@@ -176,9 +185,10 @@ impl Utils {
 
 /// Simple progress bar implementation
 pub struct ProgressBar {
-    total: u64,
-    current: u64,
+    pub total: u64,
+    pub current: u64,
     width: usize,
+    message: String,
 }
 
 impl ProgressBar {
@@ -187,6 +197,7 @@ impl ProgressBar {
             total,
             current: 0,
             width: 50,
+            message: String::new(),
         }
     }
     
@@ -200,13 +211,22 @@ impl ProgressBar {
         self.display();
     }
     
+    /// Text shown after the bar instead of the raw counts
+    pub fn set_message(&mut self, message: String) {
+        self.message = message;
+    }
+    
     fn display(&self) {
         let percentage = (self.current as f64 / self.total as f64) * 100.0;
         let filled = (percentage / 100.0 * self.width as f64) as usize;
         
         let bar = "█".repeat(filled) + &"░".repeat(self.width - filled);
         
-        print!("\r[{}] {:.1}% ({}/{})", bar, percentage, self.current, self.total);
+        if self.message.is_empty() {
+            print!("\r[{}] {:.1}% ({}/{})", bar, percentage, self.current, self.total);
+        } else {
+            print!("\r[{}] {:.1}% {}", bar, percentage, self.message);
+        }
         std::io::Write::flush(&mut std::io::stdout()).unwrap();
     }
     
@@ -239,6 +259,12 @@ mod tests {
         assert!(full_time < advanced_time);
     }
     
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(Utils::format_duration(0), "00:00:00");
+        assert_eq!(Utils::format_duration(3725), "01:02:05");
+    }
+    
     #[test]
     fn test_generate_filename() {
         let filename = Utils::generate_filename("test", "txt");
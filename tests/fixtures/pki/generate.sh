@@ -0,0 +1,117 @@
+#!/usr/bin/env bash
+# Rebuilds the test PKI the way pki/scripts does: a root CA, an intermediate
+# CA, station certificates issued with the intermediate's usr_cert profile, the
+# intermediate's CRL, on which station-002 is revoked, an OCSP responder with
+# its index and the responses it gives for both stations, and station-003 on a
+# chain that exceeds a pathlen constraint.
+# Keys are unencrypted and certificates last a century so the tests never expire.
+set -euo pipefail
+cd "$(dirname "$0")"
+
+DAYS=36500
+cat > extensions.cnf <<'CNF'
+[ req ]
+distinguished_name = dn
+[ dn ]
+[ v3_ca ]
+subjectKeyIdentifier = hash
+authorityKeyIdentifier = keyid:always,issuer
+basicConstraints = critical, CA:true, pathlen:1
+keyUsage = critical, keyCertSign, cRLSign
+[ v3_intermediate_ca ]
+subjectKeyIdentifier = hash
+authorityKeyIdentifier = keyid:always,issuer
+basicConstraints = critical, CA:true, pathlen:0
+keyUsage = critical, keyCertSign, cRLSign
+[ v3_nested_ca ]
+subjectKeyIdentifier = hash
+authorityKeyIdentifier = keyid:always,issuer
+basicConstraints = critical, CA:true
+keyUsage = critical, keyCertSign, cRLSign
+[ usr_cert ]
+basicConstraints = CA:false
+subjectKeyIdentifier = hash
+authorityKeyIdentifier = keyid,issuer
+keyUsage = critical, digitalSignature
+extendedKeyUsage = clientAuth, emailProtection, codeSigning
+authorityInfoAccess = OCSP;URI:http://127.0.0.1:8888
+crlDistributionPoints = URI:http://127.0.0.1:8889/intermediate.crl.pem
+[ tls_cert ]
+basicConstraints = CA:false
+keyUsage = critical, digitalSignature
+extendedKeyUsage = serverAuth
+[ ocsp ]
+basicConstraints = CA:false
+subjectKeyIdentifier = hash
+authorityKeyIdentifier = keyid,issuer
+keyUsage = critical, digitalSignature
+extendedKeyUsage = critical, OCSPSigning
+[ ca ]
+default_ca = intermediate_ca
+[ intermediate_ca ]
+database = index.txt
+certificate = intermediate.cert.pem
+private_key = intermediate.key.pem
+crlnumber = crlnumber
+default_md = sha256
+default_crl_days = 36500
+CNF
+
+openssl genrsa -out root.key.pem 2048
+openssl req -new -x509 -config extensions.cnf -extensions v3_ca -days "$DAYS" -sha256 \
+  -subj "/CN=Test Root CA" -key root.key.pem -out root.cert.pem
+
+openssl genrsa -out intermediate.key.pem 2048
+openssl req -new -config extensions.cnf -subj "/CN=Test Issuing CA" \
+  -key intermediate.key.pem -out intermediate.csr.pem
+openssl x509 -req -in intermediate.csr.pem -CA root.cert.pem -CAkey root.key.pem -set_serial 0x1000 \
+  -days "$DAYS" -sha256 -extfile extensions.cnf -extensions v3_intermediate_ca -out intermediate.cert.pem
+cat intermediate.cert.pem root.cert.pem > ca-chain.pem
+
+# issue <name> <profile> <serial>
+issue() {
+  openssl genrsa -out "$1.key.pem" 2048
+  openssl req -new -config extensions.cnf -subj "/CN=$1" -key "$1.key.pem" -out "$1.csr.pem"
+  openssl x509 -req -in "$1.csr.pem" -CA intermediate.cert.pem -CAkey intermediate.key.pem -set_serial "$3" \
+    -days "$DAYS" -sha256 -extfile extensions.cnf -extensions "$2" -out "$1.cert.pem"
+  cat "$1.cert.pem" ca-chain.pem > "$1.fullchain.pem"
+}
+issue station-001 usr_cert 0x1001
+issue tls-server tls_cert 0x1002
+issue station-002 usr_cert 0x1003
+issue ocsp-responder ocsp 0x1004
+
+# Revoke station-002 and publish the CRL the way generate_crl.sh does
+: > index.txt
+echo 1000 > crlnumber
+openssl ca -config extensions.cnf -valid station-001.cert.pem
+openssl ca -config extensions.cnf -valid tls-server.cert.pem
+openssl ca -config extensions.cnf -revoke station-002.cert.pem -crl_reason keyCompromise
+openssl ca -config extensions.cnf -gencrl -out intermediate.crl.pem
+
+# What start_ocsp.sh's responder answers for each station
+for station in station-001 station-002; do
+  openssl ocsp -issuer intermediate.cert.pem -cert "$station.cert.pem" -reqout "$station.ocsp-request.der"
+  openssl ocsp -index index.txt -rsigner ocsp-responder.cert.pem -rkey ocsp-responder.key.pem \
+    -CA intermediate.cert.pem -ndays "$DAYS" -reqin "$station.ocsp-request.der" -respout "$station.ocsp.der"
+done
+
+# A chain that breaks its upper intermediate's pathlen:0 by putting another CA below it:
+# root -> Test Constrained CA -> Test Nested CA -> station-003
+openssl genrsa -out constrained.key.pem 2048
+openssl req -new -config extensions.cnf -subj "/CN=Test Constrained CA" \
+  -key constrained.key.pem -out constrained.csr.pem
+openssl x509 -req -in constrained.csr.pem -CA root.cert.pem -CAkey root.key.pem -set_serial 0x2000 \
+  -days "$DAYS" -sha256 -extfile extensions.cnf -extensions v3_intermediate_ca -out constrained.cert.pem
+openssl genrsa -out nested.key.pem 2048
+openssl req -new -config extensions.cnf -subj "/CN=Test Nested CA" -key nested.key.pem -out nested.csr.pem
+openssl x509 -req -in nested.csr.pem -CA constrained.cert.pem -CAkey constrained.key.pem -set_serial 0x2001 \
+  -days "$DAYS" -sha256 -extfile extensions.cnf -extensions v3_nested_ca -out nested.cert.pem
+openssl genrsa -out station-003.key.pem 2048
+openssl req -new -config extensions.cnf -subj "/CN=station-003" -key station-003.key.pem -out station-003.csr.pem
+openssl x509 -req -in station-003.csr.pem -CA nested.cert.pem -CAkey nested.key.pem -set_serial 0x2002 \
+  -days "$DAYS" -sha256 -extfile extensions.cnf -extensions usr_cert -out station-003.cert.pem
+cat station-003.cert.pem nested.cert.pem constrained.cert.pem root.cert.pem > station-003.fullchain.pem
+
+rm -f ./*.csr.pem ./*.ocsp-request.der index.txt.* crlnumber*
+rm -f ocsp-responder.fullchain.pem
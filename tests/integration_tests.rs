@@ -1,6 +1,6 @@
 use secure_disk_erasure::core::{StorageDevice, DeviceType, EraseMode, device_manager::DeviceManager, advanced::AdvancedWipeEngine};
 use secure_disk_erasure::error::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
 #[tokio::test]
@@ -25,6 +25,8 @@ async fn test_device_filtering() {
         supports_secure_erase: true,
         supports_trim: false,
         hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
     };
     
     let ssd_device = StorageDevice {
@@ -37,6 +39,8 @@ async fn test_device_filtering() {
         supports_secure_erase: true,
         supports_trim: true,
         hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
     };
     
     device_manager.devices.push(hdd_device);
@@ -66,7 +70,7 @@ async fn test_advanced_wipe_engine_creation() {
     let wipe_engine = AdvancedWipeEngine::new();
     assert!(wipe_engine.verify_after_wipe);
     assert!(wipe_engine.generate_hash);
-    assert_eq!(wipe_engine.max_retries, 3);
+    assert_eq!(wipe_engine.retry_policy.max_retries, 3);
 }
 
 #[tokio::test]
@@ -81,6 +85,8 @@ async fn test_wipe_result_creation() {
         supports_secure_erase: true,
         supports_trim: false,
         hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
     };
     
     let start_time = std::time::SystemTime::now();
@@ -89,12 +95,24 @@ async fn test_wipe_result_creation() {
     let result = secure_disk_erasure::core::WipeResult {
         device: device.clone(),
         mode: EraseMode::Full,
+        method: None,
         start_time,
         end_time,
         duration_seconds: 60,
         bytes_written: device.size,
         verification_passed: true,
+        verification: None,
+        expected_contents: Default::default(),
+        unwritable_ranges: Vec::new(),
+        unwritable_bytes: 0,
+        hidden_areas: Vec::new(),
+        hardware_commands: Vec::new(),
+        discards: Vec::new(),
+        crypto_erases: Vec::new(),
+        safety_overrides: Vec::new(),
         errors: Vec::new(),
+        passes: Vec::new(),
+        interruptions: Vec::new(),
     };
     
     assert_eq!(result.mode, EraseMode::Full);
@@ -134,6 +152,8 @@ async fn test_device_serialization() {
         supports_secure_erase: true,
         supports_trim: false,
         hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
     };
     
     let json = serde_json::to_string(&device).unwrap();
@@ -205,3 +225,1113 @@ async fn test_progress_bar() {
     
     progress_bar.finish();
 }
+
+#[tokio::test]
+async fn test_block_device_overwrite_image_file() {
+    use secure_disk_erasure::core::block_io::BlockDevice;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("disk.img");
+    // Deliberately not a multiple of the buffer or block size
+    let size = 3 * 1024 * 1024 + 100;
+    std::fs::write(&image_path, vec![0x5Au8; size]).unwrap();
+    
+    let mut target = BlockDevice::open_for_write(&image_path, 0).unwrap();
+    assert_eq!(target.size, size as u64);
+    assert!(!target.is_block_device);
+    
+    let written = target.overwrite(1024 * 1024, |_, buf| buf.fill(0xC3)).unwrap();
+    assert_eq!(written, size as u64);
+    
+    let data = std::fs::read(&image_path).unwrap();
+    assert_eq!(data.len(), size);
+    assert!(data.iter().all(|&b| b == 0xC3));
+}
+
+#[tokio::test]
+async fn test_quick_wipe_overwrites_image_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("usb.img");
+    let size = 2 * 1024 * 1024;
+    std::fs::write(&image_path, vec![0xA5u8; size]).unwrap();
+    
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "Loop Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::USB,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let wipe_engine = AdvancedWipeEngine::new();
+    wipe_engine.secure_erase_with_verification(&device, EraseMode::Quick).await.unwrap();
+    
+    let data = std::fs::read(&image_path).unwrap();
+    assert!(data.iter().all(|&b| b == 0));
+}
+
+#[tokio::test]
+async fn test_random_pattern_regenerates_at_any_offset() {
+    use secure_disk_erasure::core::advanced::PatternSource;
+    
+    let pattern = PatternSource::random();
+    let mut whole = vec![0u8; 4096];
+    pattern.fill(0, &mut whole);
+    assert!(whole.iter().any(|&b| b != 0));
+    
+    // Unaligned sub-ranges must match the stream produced from offset zero
+    for &(offset, len) in &[(1usize, 7usize), (3, 100), (512, 512), (1021, 2000)] {
+        let mut part = vec![0u8; len];
+        pattern.fill(offset as u64, &mut part);
+        assert_eq!(&part[..], &whole[offset..offset + len]);
+    }
+    
+    let json = serde_json::to_string(&pattern).unwrap();
+    let restored: PatternSource = serde_json::from_str(&json).unwrap();
+    assert_eq!(pattern, restored);
+}
+
+#[tokio::test]
+async fn test_pass_schedules() {
+    use secure_disk_erasure::core::advanced::{PassSchedule, PatternSource};
+    
+    assert_eq!(PassSchedule::Dod3Pass.passes().len(), 3);
+    assert_eq!(PassSchedule::Dod7Pass.passes().len(), 7);
+    
+    let gutmann = PassSchedule::Gutmann.passes();
+    assert_eq!(gutmann.len(), 35);
+    assert_eq!(gutmann[6], PatternSource::Repeating { bytes: vec![0x92, 0x49, 0x24] });
+    
+    let mut buf = [0u8; 4];
+    gutmann[6].fill(1, &mut buf);
+    assert_eq!(buf, [0x49, 0x24, 0x92, 0x49]);
+    
+    assert_eq!(PassSchedule::from_name("DoD-7"), Some(PassSchedule::Dod7Pass));
+    assert_eq!(PassSchedule::from_name("unknown"), None);
+}
+
+#[tokio::test]
+async fn test_full_wipe_records_regenerable_passes() {
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("hdd.img");
+    let size = 1024 * 1024;
+    std::fs::write(&image_path, vec![0x11u8; size]).unwrap();
+    
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "HDD Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let wipe_engine = AdvancedWipeEngine::new();
+    let result = wipe_engine.secure_erase_with_verification(&device, EraseMode::Full).await.unwrap();
+    assert_eq!(result.passes.len(), 3);
+    
+    let mut expected = vec![0u8; size];
+    result.passes.last().unwrap().pattern.fill(0, &mut expected);
+    assert_eq!(std::fs::read(&image_path).unwrap(), expected);
+    
+    // Accounting reflects what the passes measured, not the device size
+    assert_eq!(result.bytes_written, 3 * size as u64);
+    for pass in &result.passes {
+        assert_eq!(pass.stats.bytes_written, size as u64);
+        assert!(pass.finished_at >= pass.started_at);
+        assert!(pass.throughput_bytes_per_sec > 0.0);
+        assert!(pass.stats.peak_bytes_per_sec >= pass.throughput_bytes_per_sec);
+        assert!(pass.stats.errors.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_builtin_methods_cover_erase_modes() {
+    use secure_disk_erasure::core::methods::{MethodRegistry, WipeStep, HardwareCommand};
+    
+    let registry = MethodRegistry::builtin();
+    for mode in [EraseMode::Quick, EraseMode::Full, EraseMode::Advanced] {
+        assert!(registry.resolve(&mode).is_ok());
+    }
+    
+    let advanced = registry.get("advanced").unwrap();
+    assert!(matches!(
+        advanced.steps_for(&DeviceType::NVMe)[0],
+        WipeStep::HardwareCommand { command: HardwareCommand::NvmeCryptoErase, .. }
+    ));
+    assert!(matches!(
+        advanced.steps_for(&DeviceType::SAS)[0],
+        WipeStep::HardwareCommand { command: HardwareCommand::ScsiSanitizeCryptoErase, .. }
+    ));
+    assert_eq!(advanced.planned_passes(&DeviceType::USB), 7);
+    assert_eq!(registry.get("GUTMANN").unwrap().planned_passes(&DeviceType::HDD), 35);
+}
+
+#[tokio::test]
+async fn test_custom_method_from_file() {
+    use secure_disk_erasure::core::methods::MethodRegistry;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let methods_path = temp_dir.path().join("methods.toml");
+    std::fs::write(&methods_path, r#"
+[[method]]
+name = "nist-clear"
+description = "Single random pass with verification"
+compliance_level = "NIST 800-88 Clear"
+steps = [
+    { type = "overwrite", pattern = { kind = "repeating", bytes = [222, 173] } },
+    { type = "verify" },
+]
+"#).unwrap();
+    
+    let mut registry = MethodRegistry::builtin();
+    registry.load_path(temp_dir.path()).unwrap();
+    let method = registry.get("nist-clear").unwrap().clone();
+    assert!(method.has_verify_step(&DeviceType::SSD));
+    
+    let image_path = temp_dir.path().join("disk.img");
+    let size = 64 * 1024;
+    std::fs::write(&image_path, vec![0u8; size]).unwrap();
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::Unknown,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let result = AdvancedWipeEngine::new().secure_erase_with_method(&device, &method).await.unwrap();
+    assert_eq!(result.mode, EraseMode::Custom("nist-clear".to_string()));
+    assert_eq!(result.method.as_ref(), Some(&method));
+    assert_eq!(result.passes.len(), 1);
+    
+    let data = std::fs::read(&image_path).unwrap();
+    assert!(data.chunks(2).all(|pair| pair == [0xDE, 0xAD]));
+}
+
+#[tokio::test]
+async fn test_full_readback_reports_mismatched_lbas() {
+    use secure_disk_erasure::core::LbaRange;
+    use secure_disk_erasure::core::advanced::PatternSource;
+    use secure_disk_erasure::core::verification::{verify_target, VerificationMode};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("disk.img");
+    let size = 1024 * 1024 + 512;
+    let pattern = PatternSource::random();
+    let mut data = vec![0u8; size];
+    pattern.fill(0, &mut data);
+    std::fs::write(&image_path, &data).unwrap();
+    
+    let report = verify_target(&image_path, 0, &VerificationMode::Full, &pattern, 64 * 1024).unwrap();
+    assert!(report.passed);
+    assert_eq!(report.bytes_checked, size as u64);
+    assert_eq!(report.blocks_checked, 2049);
+    
+    // Corrupt LBA 3 and LBAs 100-102
+    data[3 * 512 + 17] ^= 0xFF;
+    for byte in &mut data[100 * 512..103 * 512 - 1] {
+        *byte ^= 0x01;
+    }
+    std::fs::write(&image_path, &data).unwrap();
+    
+    let report = verify_target(&image_path, 0, &VerificationMode::Full, &pattern, 64 * 1024).unwrap();
+    assert!(!report.passed);
+    assert_eq!(report.mismatched_blocks, 4);
+    assert_eq!(report.mismatched_ranges, vec![
+        LbaRange { start_lba: 3, count: 1 },
+        LbaRange { start_lba: 100, count: 3 },
+    ]);
+}
+
+#[tokio::test]
+async fn test_verify_step_compares_against_final_pass() {
+    use secure_disk_erasure::core::methods::MethodRegistry;
+    use secure_disk_erasure::core::verification::VerificationMode;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("hdd.img");
+    let size = 2 * 1024 * 1024;
+    std::fs::write(&image_path, vec![0x42u8; size]).unwrap();
+    
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "HDD Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let mut registry = MethodRegistry::new();
+    registry.load_toml(r#"
+[[method]]
+name = "random-verify"
+steps = [
+    { type = "schedule", schedule = "random" },
+    { type = "verify", mode = { kind = "representative", subranges = 8, percent = 5.0 } },
+]
+"#).unwrap();
+    let method = registry.get("random-verify").unwrap().clone();
+    
+    let mut wipe_engine = AdvancedWipeEngine::new();
+    wipe_engine.verification_mode = VerificationMode::Full;
+    let result = wipe_engine.secure_erase_with_method(&device, &method).await.unwrap();
+    
+    let report = result.verification.unwrap();
+    assert!(result.verification_passed);
+    assert_eq!(report.mode, VerificationMode::Representative { subranges: 8, percent: 5.0 });
+    assert_eq!(report.expected_pattern, result.passes[0].pattern);
+    assert!(report.bytes_checked > 0 && report.bytes_checked < size as u64);
+}
+
+#[tokio::test]
+async fn test_wipe_reports_progress_events() {
+    use secure_disk_erasure::core::progress::{progress_channel, WipePhase};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("hdd.img");
+    let size = 1024 * 1024;
+    std::fs::write(&image_path, vec![0x77u8; size]).unwrap();
+    
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "HDD Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let (reporter, mut receiver) = progress_channel();
+    let wipe_engine = AdvancedWipeEngine::new().with_progress(reporter);
+    wipe_engine.secure_erase_with_verification(&device, EraseMode::Full).await.unwrap();
+    drop(wipe_engine);
+    
+    let mut events = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        events.push(event);
+    }
+    
+    // Every overwrite pass finishes with a complete event
+    for pass in 1..=3 {
+        assert!(events.iter().any(|e| e.phase == WipePhase::Overwrite
+            && e.pass == pass && e.total_passes == 3 && e.bytes_done == size as u64));
+    }
+    let last = events.last().unwrap();
+    assert_eq!(last.phase, WipePhase::Verify);
+    assert_eq!(last.bytes_done, last.bytes_total);
+    assert_eq!(last.eta_seconds, Some(0));
+}
+
+#[tokio::test]
+async fn test_cancelled_wipe_resumes_from_journal() {
+    use secure_disk_erasure::core::checkpoint::{CancellationToken, Checkpoint};
+    use secure_disk_erasure::core::methods::MethodRegistry;
+    use secure_disk_erasure::error::SecureEraseError;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("hdd.img");
+    let size = 1024 * 1024;
+    std::fs::write(&image_path, vec![0x33u8; size]).unwrap();
+    
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "HDD Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: Some("WD-TEST123".to_string()),
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let mut registry = MethodRegistry::new();
+    registry.load_toml(r#"
+[[method]]
+name = "two-pass"
+steps = [
+    { type = "overwrite", pattern = { kind = "fixed", byte = 17 } },
+    { type = "overwrite", pattern = { kind = "fixed", byte = 34 } },
+]
+"#).unwrap();
+    let method = registry.get("two-pass").unwrap().clone();
+    let journal_path = Checkpoint::journal_path(temp_dir.path(), &device);
+    
+    // A cancelled wipe stops before writing and leaves its journal behind
+    let token = CancellationToken::new();
+    token.cancel();
+    let mut wipe_engine = AdvancedWipeEngine::new()
+        .with_cancellation(token)
+        .with_journal(journal_path.clone());
+    wipe_engine.verify_after_wipe = false;
+    let result = wipe_engine.secure_erase_with_method(&device, &method).await;
+    assert!(matches!(result, Err(SecureEraseError::Cancelled)));
+    
+    // Pretend the first pass finished and the second stopped halfway
+    let mut checkpoint = Checkpoint::load(&journal_path).unwrap();
+    assert_eq!(checkpoint.device_serial.as_deref(), Some("WD-TEST123"));
+    assert_eq!(checkpoint.steps_completed, 0);
+    checkpoint.passes.push(secure_disk_erasure::core::PassRecord {
+        pass_number: 1,
+        pattern: secure_disk_erasure::core::advanced::PatternSource::Fixed { byte: 17 },
+        started_at: std::time::SystemTime::now(),
+        finished_at: std::time::SystemTime::now(),
+        throughput_bytes_per_sec: 0.0,
+        stats: secure_disk_erasure::core::PassStats { bytes_written: size as u64, ..Default::default() },
+    });
+    checkpoint.steps_completed = 1;
+    checkpoint.current_pass = Some(secure_disk_erasure::core::checkpoint::PassProgress {
+        pass_number: 2,
+        pattern: secure_disk_erasure::core::advanced::PatternSource::Fixed { byte: 34 },
+        offset: 512 * 1024,
+        started_at: std::time::SystemTime::now(),
+        stats: secure_disk_erasure::core::PassStats { bytes_written: 512 * 1024, ..Default::default() },
+    });
+    checkpoint.save(&journal_path).unwrap();
+    
+    let mut wipe_engine = AdvancedWipeEngine::new().with_journal(journal_path.clone());
+    wipe_engine.verify_after_wipe = false;
+    let result = wipe_engine.resume_wipe(&device, Checkpoint::load(&journal_path).unwrap()).await.unwrap();
+    
+    assert_eq!(result.passes.len(), 2);
+    assert_eq!(result.interruptions.len(), 1);
+    assert_eq!(result.interruptions[0].pass_number, 2);
+    assert_eq!(result.interruptions[0].offset, 512 * 1024);
+    assert!(!journal_path.exists());
+    
+    // The second pass counts the bytes written before and after the interruption
+    assert_eq!(result.passes[1].stats.bytes_written, size as u64);
+    assert_eq!(result.bytes_written, 2 * size as u64);
+    
+    // Only the uncommitted half of the second pass was rewritten
+    let data = std::fs::read(&image_path).unwrap();
+    assert!(data[..512 * 1024].iter().all(|&b| b == 0x33));
+    assert!(data[512 * 1024..].iter().all(|&b| b == 0x22));
+}
+
+#[tokio::test]
+async fn test_batch_wipe_isolates_failed_device() {
+    use secure_disk_erasure::core::batch::{run_batch, BatchJob, BatchOptions, BatchStatus, BatchSummary};
+    use secure_disk_erasure::core::methods::MethodRegistry;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let size = 256 * 1024;
+    let image = |name: &str, serial: &str| {
+        let path = temp_dir.path().join(name);
+        std::fs::write(&path, vec![0x5Au8; size]).unwrap();
+        StorageDevice {
+            path,
+            name: name.to_string(),
+            size: size as u64,
+            device_type: DeviceType::HDD,
+            model: None,
+            serial: Some(serial.to_string()),
+            supports_secure_erase: false,
+            supports_trim: false,
+            hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: Default::default(),
+        }
+    };
+    let first = image("first.img", "SER-1");
+    let second = image("second.img", "SER-2");
+    let mut missing = image("missing.img", "SER-3");
+    missing.path = temp_dir.path().join("no-such-dir").join("missing.img");
+    
+    let method = MethodRegistry::builtin().get("quick").cloned().unwrap();
+    let jobs = vec![first.clone(), missing, second.clone()].into_iter().map(|device| BatchJob {
+        engine: AdvancedWipeEngine::new().with_journal(temp_dir.path().join(format!("{}.json", device.name))),
+        device,
+        resume: None,
+    }).collect();
+    let options = BatchOptions { max_concurrent: 2, bandwidth_limit: None };
+    let outcomes = run_batch(jobs, &method, &options).await;
+    
+    // Outcomes keep job order and the bad device does not stop the others
+    let statuses: Vec<_> = outcomes.iter().map(|o| o.status).collect();
+    assert_eq!(statuses, vec![BatchStatus::Succeeded, BatchStatus::Failed, BatchStatus::Succeeded]);
+    assert!(outcomes[1].error.is_some());
+    for device in [&first, &second] {
+        assert!(std::fs::read(&device.path).unwrap().iter().all(|&b| b == 0));
+    }
+    
+    let summary = BatchSummary::new(&method, std::time::SystemTime::now(), &outcomes);
+    assert_eq!((summary.total, summary.succeeded, summary.failed, summary.cancelled), (3, 2, 1, 0));
+    assert_eq!(summary.devices[2].serial.as_deref(), Some("SER-2"));
+}
+
+#[tokio::test]
+async fn test_readback_skips_unwritable_ranges() {
+    use secure_disk_erasure::core::{LbaRange, SanitizationStatus};
+    use secure_disk_erasure::core::advanced::PatternSource;
+    use secure_disk_erasure::core::verification::{verify_target_with_progress, VerificationMode};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("bad.img");
+    // Blocks 8..12 kept their old contents, as if the drive refused the writes
+    let mut data = vec![0u8; 64 * 1024];
+    data[8 * 512..12 * 512].fill(0xEE);
+    std::fs::write(&image_path, &data).unwrap();
+    
+    let zeros = PatternSource::Fixed { byte: 0 };
+    let unwritable = vec![LbaRange { start_lba: 8, count: 4 }];
+    let report = verify_target_with_progress(
+        &image_path, 0, &VerificationMode::Full, &zeros, &unwritable, 16 * 1024, |_, _| Ok(()),
+    ).unwrap();
+    assert!(report.passed);
+    assert_eq!(report.bytes_checked, data.len() as u64 - 4 * 512);
+    assert_eq!(report.excluded_ranges, unwritable);
+    
+    let device = StorageDevice {
+        path: image_path,
+        name: "Bad Image".to_string(),
+        size: data.len() as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    let mut result = secure_disk_erasure::core::WipeResult {
+        device,
+        mode: EraseMode::Quick,
+        method: None,
+        start_time: std::time::SystemTime::now(),
+        end_time: std::time::SystemTime::now(),
+        duration_seconds: 1,
+        bytes_written: data.len() as u64 - 4 * 512,
+        verification_passed: report.passed,
+        verification: Some(report),
+        expected_contents: Default::default(),
+        unwritable_ranges: unwritable,
+        unwritable_bytes: 4 * 512,
+        hidden_areas: Vec::new(),
+        hardware_commands: Vec::new(),
+        discards: Vec::new(),
+        crypto_erases: Vec::new(),
+        safety_overrides: Vec::new(),
+        errors: Vec::new(),
+        passes: Vec::new(),
+        interruptions: Vec::new(),
+    };
+    assert_eq!(result.sanitization_status(), SanitizationStatus::Partial);
+    result.unwritable_ranges.clear();
+    assert_eq!(result.sanitization_status(), SanitizationStatus::Complete);
+}
+
+#[tokio::test]
+async fn test_unrestored_hidden_area_is_not_reported_wiped() {
+    use secure_disk_erasure::core::{HiddenArea, HiddenAreaType};
+    use secure_disk_erasure::core::hidden_areas::HiddenAreaRestore;
+    use secure_disk_erasure::core::methods::{MethodRegistry, WipeStep};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("hpa.img");
+    let size = 1024 * 1024;
+    std::fs::write(&image_path, vec![0x42u8; size]).unwrap();
+    
+    // An image file has no max address to lift, so the restore cannot succeed
+    let device = StorageDevice {
+        path: image_path,
+        name: "HPA Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: vec![HiddenArea {
+            area_type: HiddenAreaType::HPA,
+            start_lba: 2048,
+            size: 512,
+            description: "Host Protected Area".to_string(),
+        }],
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let method = MethodRegistry::builtin().get("quick").unwrap()
+        .with_hidden_area_restore(HiddenAreaRestore::Temporary);
+    assert_eq!(method.steps[0], WipeStep::RestoreHiddenAreas { mode: HiddenAreaRestore::Temporary });
+    assert_eq!(method.planned_passes(&DeviceType::HDD), 1);
+    
+    let result = AdvancedWipeEngine::new().secure_erase_with_method(&device, &method).await.unwrap();
+    assert_eq!(result.bytes_written, size as u64);
+    assert_eq!(result.hidden_areas.len(), 1);
+    let status = &result.hidden_areas[0];
+    assert!(!status.restored);
+    assert!(!status.wiped);
+    assert!(status.attempted_at.is_some());
+    assert!(status.note.is_some());
+}
+
+#[tokio::test]
+async fn test_nvme_command_on_non_nvme_target_fails() {
+    use secure_disk_erasure::core::methods::{HardwareCommand, WipeMethod, WipeStep};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("disk.img");
+    std::fs::write(&image_path, vec![0xAAu8; 4096]).unwrap();
+    let device = StorageDevice {
+        path: image_path,
+        name: "Image".to_string(),
+        size: 4096,
+        device_type: DeviceType::NVMe,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    let method: WipeMethod = toml::from_str(r#"
+name = "nvme-sanitize"
+description = "Sanitize crypto erase"
+steps = [{ type = "hardware_command", command = "nvme_sanitize_crypto_erase" }]
+"#).unwrap();
+    assert!(matches!(
+        method.steps[0],
+        WipeStep::HardwareCommand { command: HardwareCommand::NvmeSanitizeCryptoErase, .. }
+    ));
+    
+    // An image file does not answer NVMe admin commands, so nothing may be reported as erased
+    assert!(AdvancedWipeEngine::new().secure_erase_with_method(&device, &method).await.is_err());
+}
+
+#[tokio::test]
+async fn test_mounted_device_needs_override_recorded_in_result() {
+    use secure_disk_erasure::core::{DeviceLayout, Partition};
+    use secure_disk_erasure::core::methods::MethodRegistry;
+    use secure_disk_erasure::core::safety::{self, AllowList, Hazard, SafetyOptions, SystemState};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("mounted.img");
+    let size = 1024 * 1024;
+    std::fs::write(&image_path, vec![0x42u8; size]).unwrap();
+    
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "Mounted Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: Some("IMG-0001".to_string()),
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: DeviceLayout {
+            mountpoints: Vec::new(),
+            partitions: vec![Partition {
+                name: "mounted1".to_string(),
+                path: temp_dir.path().join("mounted1"),
+                number: 1,
+                start_sector: 2048,
+                size: 512 * 1024,
+                mountpoints: vec![PathBuf::from("/mnt/scratch")],
+                holders: Vec::new(),
+            }],
+            holders: Vec::new(),
+            system_volumes: Vec::new(),
+        },
+    };
+    
+    // Refused by default, and --yes alone is not enough
+    let system = SystemState::default();
+    assert!(safety::preflight(&device, &system, &SafetyOptions::default()).is_err());
+    let options = SafetyOptions {
+        assume_yes: true,
+        allow_list: Some(AllowList::parse("IMG-0001\n")),
+        allow_in_use: false,
+    };
+    assert!(safety::preflight(&device, &system, &options).is_err());
+    
+    let options = SafetyOptions { allow_in_use: true, ..options };
+    let clearance = safety::preflight(&device, &system, &options).unwrap();
+    assert!(clearance.confirm_with.is_none());
+    assert_eq!(clearance.overrides.len(), 2);
+    assert_eq!(clearance.overrides[0].hazard, Some(Hazard::Mounted { mountpoint: PathBuf::from("/mnt/scratch") }));
+    
+    let method = MethodRegistry::builtin().get("quick").unwrap().clone();
+    let result = AdvancedWipeEngine::new()
+        .with_safety_overrides(clearance.overrides)
+        .secure_erase_with_method(&device, &method).await.unwrap();
+    assert_eq!(result.safety_overrides.len(), 2);
+    assert!(result.safety_overrides[1].hazard.is_none());
+}
+
+#[tokio::test]
+async fn test_dry_run_plans_without_writing() {
+    use secure_disk_erasure::core::methods::MethodRegistry;
+    use secure_disk_erasure::error::SecureEraseError;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("dry_run.img");
+    let size = 1024 * 1024;
+    std::fs::write(&image_path, vec![0x42u8; size]).unwrap();
+    
+    let device = StorageDevice {
+        path: image_path.clone(),
+        name: "Dry Run Image".to_string(),
+        size: size as u64,
+        device_type: DeviceType::HDD,
+        model: None,
+        serial: None,
+        supports_secure_erase: false,
+        supports_trim: false,
+        hidden_areas: Vec::new(),
+        attributes: Default::default(),
+        layout: Default::default(),
+    };
+    
+    let method = MethodRegistry::builtin().get("dod-3").unwrap().clone();
+    let engine = AdvancedWipeEngine::new().with_dry_run(true);
+    let plan = engine.plan(&device, &method);
+    assert_eq!(plan.passes, 3);
+    assert_eq!(plan.bytes_to_write, 3 * size as u64);
+    
+    let result = engine.secure_erase_with_method(&device, &method).await;
+    assert!(matches!(result, Err(SecureEraseError::DryRun(_))));
+    assert!(std::fs::read(&image_path).unwrap().iter().all(|&b| b == 0x42));
+}
+
+#[tokio::test]
+async fn test_pipeline_with_fake_probe_runs_whole_flow() {
+    use secure_disk_erasure::core::DeviceLayout;
+    use secure_disk_erasure::core::methods::MethodRegistry;
+    use secure_disk_erasure::core::pipeline::WipePipeline;
+    use secure_disk_erasure::core::probe::FakeProbe;
+    use secure_disk_erasure::core::safety::{SafetyOptions, SystemState};
+    use secure_disk_erasure::core::verification::VerificationMode;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let size = 1024 * 1024;
+    let image = |name: &str, mountpoints: Vec<PathBuf>| {
+        let path = temp_dir.path().join(name);
+        std::fs::write(&path, vec![0x42u8; size]).unwrap();
+        StorageDevice {
+            path,
+            name: name.to_string(),
+            size: size as u64,
+            device_type: DeviceType::HDD,
+            model: Some("Image".to_string()),
+            serial: Some(format!("SER-{}", name)),
+            supports_secure_erase: false,
+            supports_trim: false,
+            hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: DeviceLayout { mountpoints, ..Default::default() },
+        }
+    };
+    let spare = image("spare.img", Vec::new());
+    let root = image("root.img", vec![PathBuf::from("/")]);
+    let pipeline = WipePipeline::new(
+        Box::new(FakeProbe::new(vec![spare.clone(), root.clone()])),
+        SystemState::default(),
+    );
+    
+    assert_eq!(pipeline.select(&[&root.path], Some("model=Image")).unwrap().len(), 2);
+    assert!(pipeline.find(&temp_dir.path().join("missing.img")).is_err());
+    
+    // The system disk is refused, the spare disk needs its serial number typed
+    assert!(pipeline.preflight(&root, &SafetyOptions::default()).is_err());
+    let device = pipeline.find(&spare.path).unwrap();
+    let clearance = pipeline.preflight(&device, &SafetyOptions::default()).unwrap();
+    assert_eq!(clearance.confirm_with.as_deref(), Some("SER-spare.img"));
+    
+    let method = MethodRegistry::builtin().get("quick").unwrap().clone();
+    assert_eq!(pipeline.plan(&AdvancedWipeEngine::new(), &device, &method).passes, 1);
+    let mut configured = AdvancedWipeEngine::new();
+    configured.verification_mode = VerificationMode::Full;
+    let dod = MethodRegistry::builtin().get("dod-3").unwrap().clone();
+    assert!(pipeline.plan(&configured, &device, &dod).steps.iter().any(|step| step.action == "Verify: Full read-back"));
+    let result = pipeline.wipe(AdvancedWipeEngine::new(), &device, &method, clearance, None).await.unwrap();
+    assert!(result.verification_passed);
+    assert!(std::fs::read(&spare.path).unwrap().iter().all(|&b| b == 0));
+    assert!(std::fs::read(&root.path).unwrap().iter().all(|&b| b == 0x42));
+}
+
+/// Result of a quick wipe of a 4 KiB image at `device_path`
+fn image_wipe_result(device_path: PathBuf) -> secure_disk_erasure::core::WipeResult {
+    let now = std::time::SystemTime::now();
+    secure_disk_erasure::core::WipeResult {
+        device: StorageDevice {
+            path: device_path,
+            name: "Image \"disk\" €".to_string(),
+            size: 4096,
+            device_type: DeviceType::SSD,
+            model: Some("Image".to_string()),
+            serial: Some("SER-1".to_string()),
+            supports_secure_erase: false,
+            supports_trim: false,
+            hidden_areas: Vec::new(),
+            attributes: Default::default(),
+            layout: Default::default(),
+        },
+        mode: EraseMode::Quick,
+        method: None,
+        start_time: now,
+        end_time: now,
+        duration_seconds: 1,
+        bytes_written: 4096,
+        verification_passed: true,
+        verification: None,
+        expected_contents: Default::default(),
+        unwritable_ranges: Vec::new(),
+        unwritable_bytes: 0,
+        hidden_areas: Vec::new(),
+        hardware_commands: Vec::new(),
+        discards: Vec::new(),
+        crypto_erases: Vec::new(),
+        safety_overrides: Vec::new(),
+        errors: Vec::new(),
+        passes: Vec::new(),
+        interruptions: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_certificates_round_trip_every_schema() {
+    use secure_disk_erasure::certificates::{self, enhanced::EnhancedCertificateGenerator, signing::Schema, verifier::CertificateVerifier};
+    use secure_disk_erasure::crypto::{generate_key_pair, KeyFormat, SignatureAlgorithm};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let (private_key, public_key) = generate_key_pair(temp_dir.path(), SignatureAlgorithm::Ed25519, KeyFormat::Pem, None).await.unwrap();
+    let device_path = temp_dir.path().join("disk.img");
+    std::fs::write(&device_path, vec![0u8; 4096]).unwrap();
+    let result = image_wipe_result(device_path);
+    
+    let basic = temp_dir.path().join("basic.json");
+    certificates::generate_certificate(&result, &basic, &temp_dir.path().join("basic.pdf"), &private_key).await.unwrap();
+    let enhanced = temp_dir.path().join("enhanced.json");
+    EnhancedCertificateGenerator::new("Station".to_string(), "Org".to_string())
+        .with_ocsp_url("http://ocsp.example".to_string())
+        .generate_enhanced_certificate(&result, &enhanced, &temp_dir.path().join("enhanced.pdf"), &private_key)
+        .await.unwrap();
+    
+    let verifier = CertificateVerifier::new().with_public_key(public_key.to_string_lossy().to_string());
+    for (path, schema) in [(&basic, Schema::Basic), (&enhanced, Schema::Enhanced)] {
+        let json: serde_json::Value = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(json["version"], schema.version());
+        assert!(certificates::verify_certificate(path, Some(&public_key)).await.unwrap());
+        let verified = verifier.verify_certificate(path).await.unwrap();
+        assert!(verified.signature_valid && verified.hash_valid, "{:?}", verified.errors);
+        
+        // Re-serialized compactly the certificate still verifies
+        let compact = temp_dir.path().join("compact.json");
+        std::fs::write(&compact, serde_json::to_string(&json).unwrap()).unwrap();
+        assert!(certificates::verify_certificate(&compact, Some(&public_key)).await.unwrap());
+        
+        // Any edit outside the envelope breaks it
+        let mut tampered = json.clone();
+        tampered["device_info"]["serial"] = serde_json::Value::from("SER-2");
+        std::fs::write(&compact, serde_json::to_string(&tampered).unwrap()).unwrap();
+        assert!(!certificates::verify_certificate(&compact, Some(&public_key)).await.unwrap());
+        assert!(!verifier.verify_certificate(&compact).await.unwrap().is_valid);
+        
+        // Certificates written before canonical payloads are refused, not misreported
+        let mut legacy = json.clone();
+        legacy["version"] = serde_json::Value::from(if schema == Schema::Basic { "1.0" } else { "2.0" });
+        std::fs::write(&compact, serde_json::to_string(&legacy).unwrap()).unwrap();
+        assert!(certificates::verify_certificate(&compact, Some(&public_key)).await.is_err());
+        let refused = verifier.verify_certificate(&compact).await.unwrap();
+        assert!(!refused.is_valid && refused.errors[0].contains("predates"));
+    }
+}
+
+#[tokio::test]
+async fn test_station_certificate_chains_to_trust_anchor() {
+    use secure_disk_erasure::certificates::{enhanced::EnhancedCertificateGenerator, verifier::CertificateVerifier};
+    use secure_disk_erasure::crypto::x509::StationChain;
+    
+    let pki = Path::new("tests/fixtures/pki");
+    let temp_dir = TempDir::new().unwrap();
+    let device_path = temp_dir.path().join("disk.img");
+    std::fs::write(&device_path, vec![0u8; 4096]).unwrap();
+    let result = image_wipe_result(device_path);
+    let station = StationChain::from_pem(&std::fs::read_to_string(pki.join("station-001.fullchain.pem")).unwrap()).unwrap();
+    
+    let certificate = temp_dir.path().join("station.json");
+    EnhancedCertificateGenerator::new("Station".to_string(), "Org".to_string())
+        .with_station_chain(station.clone())
+        .generate_enhanced_certificate(&result, &certificate, &temp_dir.path().join("station.pdf"), &pki.join("station-001.key.pem"))
+        .await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&certificate).unwrap()).unwrap();
+    assert_eq!(json["signature"]["algorithm"], "RSA-PKCS1-SHA256");
+    assert_eq!(json["pki"]["ocsp_url"], "http://127.0.0.1:8888");
+    assert!(json["pki"]["station_certificate_pem"].as_str().unwrap().contains("BEGIN CERTIFICATE"));
+    
+    // The embedded station certificate carries the key; the anchor decides whether it is trusted
+    let anchor = pki.join("root.cert.pem").to_string_lossy().to_string();
+    let verified = CertificateVerifier::new().with_trust_anchors(anchor).verify_certificate(&certificate).await.unwrap();
+    assert!(verified.is_valid && verified.chain_valid, "{:?}", verified.errors);
+    assert_eq!(verified.verification_details.certificate_path, vec!["CN=station-001", "CN=Test Issuing CA", "CN=Test Root CA"]);
+    
+    let untrusted = CertificateVerifier::new().verify_certificate(&certificate).await.unwrap();
+    assert!(untrusted.signature_valid && !untrusted.chain_valid && !untrusted.is_valid);
+    
+    // Anchors of another PKI do not vouch for the station
+    let foreign_anchor = pki.join("tls-server.cert.pem").to_string_lossy().to_string();
+    let refused = CertificateVerifier::new().with_trust_anchors(foreign_anchor).verify_certificate(&certificate).await.unwrap();
+    assert!(refused.signature_valid && !refused.chain_valid && !refused.is_valid);
+    
+    // The key must belong to the station certificate
+    let (other_key, _) = secure_disk_erasure::crypto::generate_key_pair(
+        temp_dir.path(), secure_disk_erasure::crypto::SignatureAlgorithm::Ed25519, secure_disk_erasure::crypto::KeyFormat::Pem, None,
+    ).await.unwrap();
+    assert!(EnhancedCertificateGenerator::new("Station".to_string(), "Org".to_string())
+        .with_station_chain(station)
+        .generate_enhanced_certificate(&result, &certificate, &temp_dir.path().join("station.pdf"), &other_key)
+        .await.is_err());
+}
+
+#[tokio::test]
+async fn test_crl_revokes_station_certificate() {
+    use secure_disk_erasure::certificates::{enhanced::EnhancedCertificateGenerator, verifier::CertificateVerifier};
+    use secure_disk_erasure::crypto::x509::StationChain;
+    
+    let pki = Path::new("tests/fixtures/pki");
+    let temp_dir = TempDir::new().unwrap();
+    let device_path = temp_dir.path().join("disk.img");
+    std::fs::write(&device_path, vec![0u8; 4096]).unwrap();
+    let result = image_wipe_result(device_path);
+    let sign = |station: &str| {
+        let certificate = temp_dir.path().join(format!("{}.json", station));
+        let chain = StationChain::from_pem(&std::fs::read_to_string(pki.join(format!("{}.fullchain.pem", station))).unwrap()).unwrap();
+        let key = pki.join(format!("{}.key.pem", station));
+        let pdf = temp_dir.path().join(format!("{}.pdf", station));
+        let result = &result;
+        async move {
+            EnhancedCertificateGenerator::new("Station".to_string(), "Org".to_string())
+                .with_station_chain(chain)
+                .generate_enhanced_certificate(result, &certificate, &pdf, &key)
+                .await.unwrap();
+            certificate
+        }
+    };
+    let good = sign("station-001").await;
+    let revoked = sign("station-002").await;
+    
+    let anchor = pki.join("root.cert.pem").to_string_lossy().to_string();
+    let crl = pki.join("intermediate.crl.pem").to_string_lossy().to_string();
+    let verifier = CertificateVerifier::new().with_trust_anchors(anchor.clone()).with_crl_file(crl);
+    
+    let verified = verifier.verify_certificate(&good).await.unwrap();
+    assert!(verified.is_valid && verified.verification_details.crl_checked, "{:?}", verified.errors);
+    assert!(verified.verification_details.crl_this_update.is_some() && verified.verification_details.crl_next_update.is_some());
+    
+    let refused = verifier.verify_certificate(&revoked).await.unwrap();
+    assert!(refused.signature_valid && refused.chain_valid && refused.revoked && !refused.is_valid);
+    
+    // A cached copy is found by the file name at the end of PKIInfo.crl_url
+    let cache = temp_dir.path().join("crl-cache");
+    std::fs::create_dir(&cache).unwrap();
+    std::fs::copy(pki.join("intermediate.crl.pem"), cache.join("intermediate.crl.pem")).unwrap();
+    let cached = CertificateVerifier::new().with_trust_anchors(anchor.clone()).with_crl_cache(cache.to_string_lossy().to_string());
+    assert!(cached.verify_certificate(&revoked).await.unwrap().revoked);
+    
+    // Without a CRL, revocation is reported as unchecked rather than passed
+    let unchecked = CertificateVerifier::new().with_trust_anchors(anchor).verify_certificate(&revoked).await.unwrap();
+    assert!(!unchecked.verification_details.crl_checked && unchecked.warnings.iter().any(|w| w.contains("Revocation not checked")));
+}
+
+/// `openssl ocsp` answering from the fixture CA database, as `pki/scripts/start_ocsp.sh` runs it
+struct OcspResponder(std::process::Child);
+
+impl OcspResponder {
+    fn start(pki: &Path, port: u16) -> Option<Self> {
+        let child = std::process::Command::new("openssl")
+            .args(["ocsp", "-index", "index.txt", "-port"]).arg(port.to_string())
+            .args(["-rsigner", "ocsp-responder.cert.pem", "-rkey", "ocsp-responder.key.pem", "-CA", "intermediate.cert.pem", "-nmin", "5"])
+            .current_dir(pki)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn().ok()?;
+        Some(Self(child))
+    }
+}
+
+impl Drop for OcspResponder {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[tokio::test]
+async fn test_ocsp_status_is_fetched_cached_and_stapled() {
+    use secure_disk_erasure::certificates::{enhanced::EnhancedCertificateGenerator, verifier::CertificateVerifier};
+    use secure_disk_erasure::crypto::{ocsp::OcspClient, x509::StationChain};
+    
+    let pki = Path::new("tests/fixtures/pki");
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let url = format!("http://127.0.0.1:{}", port);
+    let responder = OcspResponder::start(pki, port);
+    
+    // The responder serves one connection at a time and exits on a malformed request, so wait for a real answer
+    let station = StationChain::from_pem(&std::fs::read_to_string(pki.join("station-001.fullchain.pem")).unwrap()).unwrap();
+    let issuer = station.issuer().unwrap().unwrap().to_vec();
+    let mut ready = false;
+    for _ in 0..50 {
+        if responder.is_none() { break; }
+        if OcspClient::new().fetch(&url, &station.leaf, &issuer).await.is_ok() { ready = true; break; }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    if !ready {
+        eprintln!("openssl ocsp responder unavailable; skipping");
+        return;
+    }
+    
+    let temp_dir = TempDir::new().unwrap();
+    let device_path = temp_dir.path().join("disk.img");
+    std::fs::write(&device_path, vec![0u8; 4096]).unwrap();
+    let result = image_wipe_result(device_path);
+    let sign = |station: &str, name: &str, stapling: bool| {
+        let certificate = temp_dir.path().join(format!("{}.json", name));
+        let pdf = temp_dir.path().join(format!("{}.pdf", name));
+        let chain = StationChain::from_pem(&std::fs::read_to_string(pki.join(format!("{}.fullchain.pem", station))).unwrap()).unwrap();
+        let key = pki.join(format!("{}.key.pem", station));
+        let mut generator = EnhancedCertificateGenerator::new("Station".to_string(), "Org".to_string())
+            .with_station_chain(chain)
+            .with_ocsp_url(url.clone());
+        if stapling {
+            generator = generator.with_ocsp_stapling(OcspClient::new());
+        }
+        let result = &result;
+        async move { generator.generate_enhanced_certificate(result, &certificate, &pdf, &key).await.map(|_| certificate) }
+    };
+    let good = sign("station-001", "good", false).await.unwrap();
+    let revoked = sign("station-002", "revoked", false).await.unwrap();
+    let stapled = sign("station-001", "stapled", true).await.unwrap();
+    
+    // A revoked station may not sign at all once its status is asked for
+    assert!(sign("station-002", "refused", true).await.is_err());
+    
+    let anchor = pki.join("root.cert.pem").to_string_lossy().to_string();
+    let cache = temp_dir.path().join("ocsp-cache").to_string_lossy().to_string();
+    let online = CertificateVerifier::new().with_trust_anchors(anchor.clone()).with_ocsp_cache(cache.clone());
+    
+    let verified = online.verify_certificate(&good).await.unwrap();
+    assert!(verified.is_valid && verified.verification_details.ocsp_checked, "{:?}", verified.errors);
+    assert_eq!(verified.verification_details.ocsp_status.as_deref(), Some("Good"));
+    assert!(!verified.verification_details.ocsp_stapled && verified.verification_details.ocsp_next_update.is_some());
+    
+    let refused = online.verify_certificate(&revoked).await.unwrap();
+    assert!(refused.signature_valid && refused.chain_valid && refused.revoked && !refused.is_valid);
+    drop(responder);
+    
+    // With the responder gone, the stapled and cached responses still answer
+    let offline = CertificateVerifier::new().with_trust_anchors(anchor.clone()).with_ocsp_online(false);
+    let verified = offline.verify_certificate(&stapled).await.unwrap();
+    assert!(verified.is_valid && verified.verification_details.ocsp_stapled, "{:?}", verified.errors);
+    assert_eq!(verified.verification_details.ocsp_status.as_deref(), Some("Good"));
+    
+    let cached = CertificateVerifier::new().with_trust_anchors(anchor).with_ocsp_online(false).with_ocsp_cache(cache)
+        .verify_certificate(&revoked).await.unwrap();
+    assert!(cached.revoked && cached.verification_details.ocsp_checked);
+    
+    let unchecked = offline.verify_certificate(&good).await.unwrap();
+    assert!(!unchecked.verification_details.ocsp_checked && unchecked.warnings.iter().any(|w| w.contains("OCSP not checked")));
+}
+
+#[tokio::test]
+async fn test_generated_keys_are_standard_and_sign_certificates() {
+    use secure_disk_erasure::certificates::{enhanced::EnhancedCertificateGenerator, verifier::CertificateVerifier};
+    use secure_disk_erasure::crypto::{generate_key_pair, load_private_key, load_public_key, KeyFormat, SignatureAlgorithm};
+    
+    let temp_dir = TempDir::new().unwrap();
+    let device_path = temp_dir.path().join("disk.img");
+    std::fs::write(&device_path, vec![0u8; 4096]).unwrap();
+    let result = image_wipe_result(device_path);
+    let passphrase_file = temp_dir.path().join("passphrase");
+    std::fs::write(&passphrase_file, "correct horse").unwrap();
+    let openssl = std::process::Command::new("openssl").arg("version").output().is_ok();
+    
+    for (algorithm, format) in [(SignatureAlgorithm::Ed25519, KeyFormat::Pem), (SignatureAlgorithm::EcdsaP256Sha256, KeyFormat::Der)] {
+        let dir = temp_dir.path().join(algorithm.key_type());
+        std::fs::create_dir(&dir).unwrap();
+        let (private_key, public_key) = generate_key_pair(&dir, algorithm, format, Some("correct horse")).await.unwrap();
+        let key = load_private_key(&private_key, Some("correct horse")).await.unwrap();
+        assert_eq!(key.public_key(), load_public_key(&public_key).await.unwrap());
+        
+        // OpenSSL reads both files as PKCS#8 and SubjectPublicKeyInfo
+        if openssl {
+            let form = if format == KeyFormat::Der { "DER" } else { "PEM" };
+            let private = std::process::Command::new("openssl")
+                .args(["pkey", "-noout", "-inform", form, "-in"]).arg(&private_key)
+                .arg("-passin").arg(format!("file:{}", passphrase_file.display()))
+                .output().unwrap();
+            assert!(private.status.success(), "{}", String::from_utf8_lossy(&private.stderr));
+            let public = std::process::Command::new("openssl")
+                .args(["pkey", "-pubin", "-noout", "-inform", form, "-in"]).arg(&public_key)
+                .output().unwrap();
+            assert!(public.status.success(), "{}", String::from_utf8_lossy(&public.stderr));
+        }
+        
+        let certificate = dir.join("certificate.json");
+        EnhancedCertificateGenerator::new("Station".to_string(), "Org".to_string())
+            .with_key_passphrase("correct horse".to_string())
+            .generate_enhanced_certificate(&result, &certificate, &dir.join("certificate.pdf"), &private_key)
+            .await.unwrap();
+        let verified = CertificateVerifier::new().with_public_key(public_key.to_string_lossy().to_string()).verify_certificate(&certificate).await.unwrap();
+        assert!(verified.signature_valid, "{:?}", verified.errors);
+    }
+}
+
+#[tokio::test]
+async fn test_certificate_marks_verification_not_applicable_without_a_pattern() {
+    use secure_disk_erasure::certificates::enhanced::EnhancedCertificateGenerator;
+    use secure_disk_erasure::core::methods::ExpectedContents;
+    
+    let pki = Path::new("tests/fixtures/pki");
+    let temp_dir = TempDir::new().unwrap();
+    let device_path = temp_dir.path().join("disk.img");
+    std::fs::write(&device_path, vec![0u8; 4096]).unwrap();
+    
+    // A discard on a drive that does not say what discarded blocks read back as
+    let mut result = image_wipe_result(device_path);
+    result.expected_contents = ExpectedContents::Indeterminate;
+    let certificate = temp_dir.path().join("discard.json");
+    EnhancedCertificateGenerator::new("Station".to_string(), "Org".to_string())
+        .generate_enhanced_certificate(&result, &certificate, &temp_dir.path().join("discard.pdf"), &pki.join("station-001.key.pem"))
+        .await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&certificate).unwrap()).unwrap();
+    assert!(json["verification"]["verification_method"].as_str().unwrap().starts_with("Not applicable"));
+    let audit = json["compliance"]["audit_trail"].as_array().unwrap();
+    assert!(audit.iter().any(|entry| entry["action"] == "Verification Performed" && entry["result"] == "Not applicable"));
+}